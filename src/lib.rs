@@ -0,0 +1,4027 @@
+//! WAV 响度曲线分析核心：曲线解析、A/B 对比统计、快速扫描与导出。
+//! 不依赖 GUI，可在无界面环境中调用、单独测试或嵌入其他程序；`main.rs` 是基于 eframe 的前端。
+//! 日志通过 [`LogSink`] 输出，批处理工具可接到自己的日志系统。
+//! 只使用分析库时可关闭默认的 `gui` 特性，不引入界面依赖。
+//!
+//! ```no_run
+//! use wav_lufs_curve::{compare, parse_wav, AnalysisSettings, CompareOptions, NoLog};
+//!
+//! let settings = AnalysisSettings::default();
+//! let a = parse_wav("master_a.wav".into(), &settings, &NoLog, &mut |_| {})?;
+//! let b = parse_wav("master_b.wav".into(), &settings, &NoLog, &mut |_| {})?;
+//! let result = compare(&a, &b, &CompareOptions::default()).ok_or("no overlap")?;
+//! println!("mean {:+.2} dB, std {:.3} dB, r {:.3}", result.mean_diff, result.std_dev, result.correlation_coefficient);
+//! # Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+//! ```
+#![warn(missing_docs)]
+
+use chrono::{DateTime, Local, NaiveDate, SecondsFormat};
+use hound::WavReader;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::error::Error;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::cell::Cell;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+// std::time::Instant 在浏览器 (wasm32) 中不可用
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
+
+pub mod metrics;
+#[cfg(test)]
+mod testutil;
+
+use metrics::{AnalysisContext, MetricRegistry, MetricValue, AVERAGE_METRIC};
+
+// --- 核心数据结构 ---
+
+/// 当前的序列化格式版本。保存曲线、对比结果等数据时用 [`Versioned`] 包裹，读取旧文件时据此判断：
+///
+/// - 1：曲线只有名称、点、时长、平均电平、最响/最安静窗口和来源路径；对比结果只有差值统计和差值曲线
+/// - 2：增加频段、EBU 响度曲线、标签、分析参数等字段，以及对比的偏移、电平匹配和 Bland-Altman 点
+///
+/// 新增字段一律带 `#[serde(default)]`，旧文件缺少的字段取默认值
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// 带格式版本号的序列化外壳；没有版本号的数据按版本 1 读取
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct Versioned<T> {
+    /// 写入时的格式版本 (见 [`SCHEMA_VERSION`])
+    #[serde(default = "first_schema_version")]
+    pub schema_version: u32,
+    /// 保存的数据
+    pub data: T,
+}
+
+impl<T> Versioned<T> {
+    /// 以当前格式版本包裹
+    pub fn new(data: T) -> Self {
+        Self { schema_version: SCHEMA_VERSION, data }
+    }
+}
+
+fn first_schema_version() -> u32 {
+    1
+}
+
+/// 一个文件的响度曲线及其统计
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AudioCurve {
+    /// 显示名称 (默认为文件名，可重命名)
+    pub name: String,
+    /// (时间, dBFS)
+    pub points: Vec<[f64; 2]>,
+    /// 时长 (秒)：WAV 为文件头声明的长度，CSV 为最后一个点的时间
+    pub duration: f64,
+    /// 平均电平 (默认为各窗口的功率平均，见 [`AverageMode`])，用于计算归一化偏移
+    pub average_dbfs: f64,
+    /// 最响窗口 (时间, dBFS)
+    pub loudest: Option<[f64; 2]>,
+    /// 最安静的非静音窗口 (时间, dBFS)
+    pub quietest: Option<[f64; 2]>,
+    /// 各频段 (见 BAND_SPLIT_HZ) 的 (时间, dBFS) 曲线；CSV 导入的曲线为空
+    #[serde(default)]
+    pub bands: Vec<Vec<[f64; 2]>>,
+    /// 源 WAV 文件路径 (用于回放)；CSV 导入的曲线没有音频
+    pub source: Option<PathBuf>,
+    /// 单文件图表中始终显示原始电平 (覆盖全局的 LevelDisplay)
+    #[serde(default)]
+    pub show_raw: bool,
+    /// 处于底噪 (SILENCE_FLOOR_DBFS) 的静音窗口，不计入最安静窗口统计
+    #[serde(default)]
+    pub silence_gating: WindowGating,
+    /// 来源文件信息 (文件详情窗口使用)；内存中构造的曲线为 None
+    #[serde(default)]
+    pub details: Option<SourceDetails>,
+    /// 用户添加的自由标签 (例如 "v2")，显示在图例和列表中
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// BS.1770 积分响度 / 真峰值 / 响度范围，用于交付规格检查；CSV 导入的曲线为 None
+    #[serde(default)]
+    pub loudness: Option<LoudnessMeasurement>,
+    /// EBU 瞬时 (400ms) 响度曲线 (时间, LUFS)，与积分响度在同一遍读取中计算；CSV 导入的曲线为空
+    #[serde(default)]
+    pub momentary: Vec<[f64; 2]>,
+    /// EBU 短期 (3s) 响度曲线 (时间, LUFS)
+    #[serde(default)]
+    pub short_term: Vec<[f64; 2]>,
+    /// 从开头累计的门限积分响度 (时间, LUFS)，每秒一点，用于观察积分响度何时稳定
+    #[serde(default)]
+    pub integrated: Vec<[f64; 2]>,
+    /// 计算曲线时使用的分析参数；CSV 导入和内存中构造的曲线为 None
+    #[serde(default)]
+    pub analysis: Option<AnalysisSettings>,
+    /// 分析时由 [`MetricRegistry`] 计算的指标 (按名称)；内存中构造的曲线为空
+    #[serde(default)]
+    pub metrics: BTreeMap<String, MetricValue>,
+    /// 加载时按文件名规则选中的分析配置 (见 [`AnalysisProfile`])；使用全局分析参数时为 None
+    #[serde(default)]
+    pub profile: Option<String>,
+    /// 加载时逐帧累积的解码样本哈希 (见 [`ContentHasher`])，用于发现重复加载的同一内容；CSV 导入的曲线为 None
+    #[serde(default)]
+    pub sample_hash: Option<u64>,
+}
+
+/// 按 BS.1770 / EBU Tech 3342 测得的整体响度指标
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct LoudnessMeasurement {
+    /// 门限积分响度 (LUFS)
+    pub integrated_lufs: f64,
+    /// 4 倍过采样真峰值 (dBTP)
+    pub true_peak_dbtp: f64,
+    /// 节目短于一个 3 秒短期块时无法计算
+    pub loudness_range_lu: Option<f64>,
+}
+
+/// 曲线来源文件的信息
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SourceDetails {
+    /// 来源文件路径 (URL 加载时为 URL)
+    pub path: PathBuf,
+    /// 文件大小 (字节)
+    pub file_size: u64,
+    /// 仅 WAV 来源有值
+    pub wav: Option<WavDetails>,
+    /// 加载各阶段的耗时；会话中保存的旧曲线为 0
+    #[serde(default)]
+    pub timing: LoadTiming,
+}
+
+/// 加载一个文件各阶段的耗时 (秒)，用于吞吐量统计
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct LoadTiming {
+    /// 读取并解码样本 (含逐帧的计权、分频和响度计量)；CSV 为读取和解析
+    pub decode_sec: f64,
+    /// 加窗计算曲线点、频段曲线、响度曲线和指标；CSV 为 0
+    pub windowing_sec: f64,
+}
+
+/// WAV 格式及分析参数
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct WavDetails {
+    /// 文件头中的格式
+    #[serde(with = "WavSpecDef")]
+    pub spec: hound::WavSpec,
+    /// 实际解码的样本数 (所有声道合计，下混前)
+    pub decoded_samples: u64,
+    /// RMS 窗口长度 (秒)
+    pub window_sec: f64,
+    /// 相邻窗口的步进 (秒)
+    pub step_sec: f64,
+    /// 最后一个完整窗口之后不足一个步进、未计入曲线的时长 (秒)
+    #[serde(default)]
+    pub unanalyzed_tail_sec: f64,
+}
+
+// hound 的类型没有实现 serde，按字段镜像序列化
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "hound::WavSpec")]
+struct WavSpecDef {
+    channels: u16,
+    sample_rate: u32,
+    bits_per_sample: u16,
+    #[serde(with = "SampleFormatDef")]
+    sample_format: hound::SampleFormat,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(remote = "hound::SampleFormat")]
+enum SampleFormatDef {
+    Float,
+    Int,
+}
+
+impl SourceDetails {
+    fn new(path: &Path, wav: Option<WavDetails>, timing: LoadTiming) -> Self {
+        let file_size = std::fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        Self { path: path.to_path_buf(), file_size, wav, timing }
+    }
+}
+
+/// 统计时被门限或静音排除的窗口数，显示为 "排除数/总数"
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub struct WindowGating {
+    /// 被排除的窗口数
+    pub excluded: usize,
+    /// 窗口总数
+    pub total: usize,
+}
+
+impl std::fmt::Display for WindowGating {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.excluded, self.total)
+    }
+}
+
+/// 流式内容哈希 (按 64 位字的 FNV-1a)：逐个样本累积，不需要保留样本，加载时几乎不增加耗时
+#[derive(Clone, Copy, Debug)]
+pub struct ContentHasher(u64);
+
+impl Default for ContentHasher {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl ContentHasher {
+    /// 累积一个 64 位字
+    pub fn write_u64(&mut self, word: u64) {
+        self.0 = (self.0 ^ word).wrapping_mul(0x0000_0100_0000_01b3);
+    }
+
+    /// 累积一个样本 (按位)
+    pub fn write_f64(&mut self, value: f64) {
+        self.write_u64(value.to_bits());
+    }
+
+    /// 当前的哈希值
+    pub fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// 每条曲线与之内容相同 (见 [`AudioCurve::content_hash`]) 的最早一条曲线的下标；没有更早的重复时为 None
+pub fn find_duplicates(curves: &[AudioCurve]) -> Vec<Option<usize>> {
+    let mut first_seen: HashMap<u64, usize> = HashMap::new();
+    curves.iter().enumerate()
+        .map(|(index, curve)| {
+            let first = *first_seen.entry(curve.content_hash()).or_insert(index);
+            (first != index).then_some(first)
+        })
+        .collect()
+}
+
+/// calculate_rms_dbfs 对静音窗口返回的底噪值
+pub const SILENCE_FLOOR_DBFS: f64 = -120.0;
+
+impl AudioCurve {
+    /// 由曲线点构造，并计算最响/最安静窗口及静音窗口数
+    pub fn new(name: String, points: Vec<[f64; 2]>, duration: f64, average_dbfs: f64) -> Self {
+        let loudest = points.iter().copied().max_by(|a, b| a[1].total_cmp(&b[1]));
+        let quietest = points.iter().copied()
+            .filter(|p| p[1] > SILENCE_FLOOR_DBFS)
+            .min_by(|a, b| a[1].total_cmp(&b[1]));
+        let silence_gating = WindowGating {
+            excluded: points.iter().filter(|p| p[1] <= SILENCE_FLOOR_DBFS).count(),
+            total: points.len(),
+        };
+        Self { name, points, duration, average_dbfs, loudest, quietest, bands: Vec::new(), source: None, show_raw: false, silence_gating, details: None, tags: Vec::new(), loudness: None, momentary: Vec::new(), short_term: Vec::new(), integrated: Vec::new(), analysis: None, metrics: BTreeMap::new(), profile: None, sample_hash: None }
+    }
+
+    /// 内容哈希：WAV 曲线为解码样本的哈希，没有样本哈希的曲线 (CSV 导入) 退回曲线点的哈希
+    pub fn content_hash(&self) -> u64 {
+        self.sample_hash.unwrap_or_else(|| {
+            let mut hasher = ContentHasher::default();
+            for &[t, v] in &self.points {
+                hasher.write_f64(t);
+                hasher.write_f64(v);
+            }
+            hasher.finish()
+        })
+    }
+
+    /// 按名称取指标
+    pub fn metric(&self, name: &str) -> Option<MetricValue> {
+        self.metrics.get(name).copied()
+    }
+
+    /// 只按曲线点运行内置指标 (没有音频的 CSV 曲线)，并按结果更新平均电平
+    pub fn measure_points(&mut self, analysis: &AnalysisSettings) {
+        self.metrics = MetricRegistry::builtin().run(&AnalysisContext::from_points(&self.points, analysis));
+        self.average_dbfs = self.metric(AVERAGE_METRIC).and_then(|m| m.value()).unwrap_or(SILENCE_FLOOR_DBFS);
+    }
+
+    /// 图例等处显示的名称：有标签时附加 "[标签, ...]"
+    pub fn display_label(&self) -> String {
+        if self.tags.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} [{}]", self.name, self.tags.join(", "))
+        }
+    }
+
+    /// 来源文件名 (重命名前的名称)；内存中构造的曲线为当前名称
+    pub fn file_name(&self) -> String {
+        self.details.as_ref().map(|d| &d.path).or(self.source.as_ref())
+            .and_then(|p| p.file_name())
+            .map_or_else(|| self.name.clone(), |n| n.to_string_lossy().to_string())
+    }
+
+    /// 分析时跳过的时间段 (开头 / 结尾)，图表中画成阴影；没有跳过，或跳过后不剩音频而分析了整个文件时为空
+    pub fn excluded_ranges(&self) -> Vec<[f64; 2]> {
+        let Some(analysis) = self.analysis.as_ref().filter(|a| a.trims_range()) else { return Vec::new() };
+        let [start, end] = analysis.analysis_range(self.duration);
+        if end <= start {
+            return Vec::new();
+        }
+        [[0.0, start], [end, self.duration]].into_iter().filter(|r| r[1] > r[0]).collect()
+    }
+
+    /// 按归一化方式把曲线移动到 `target` 所需的增益 (dB)
+    pub fn normalization_gain(&self, mode: NormalizeMode, target: f64) -> f64 {
+        match mode {
+            NormalizeMode::Average => target - self.average_dbfs,
+            // 没有任何窗口时退回平均电平
+            NormalizeMode::Peak => target - self.loudest.map_or(self.average_dbfs, |p| p[1]),
+        }
+    }
+}
+
+/// 归一化时对齐到目标的电平
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+pub enum NormalizeMode {
+    /// 平均 dBFS 对齐到目标 (LUFS 式归一化)
+    #[default]
+    Average,
+    /// 最响窗口 (`loudest`) 对齐到目标，例如 -1 dBFS
+    Peak,
+}
+
+/// 两条曲线逐点对比 (A - B) 的统计结果
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ComparisonResult {
+    /// 差值均值 (dB)
+    pub mean_diff: f64,
+    /// 差值标准差 (dB)，一致性判定的依据
+    pub std_dev: f64,
+    /// 最大差值 (dB)
+    pub max_diff: f64,
+    /// 最小差值 (dB)
+    pub min_diff: f64,
+    /// Pearson r
+    pub correlation_coefficient: f64,
+    /// T-stat for mean difference vs target
+    pub t_statistic: f64,
+    /// 实际参与对比的时间跨度 / 较长曲线的时长 (0.0 - 1.0)；版本 1 的结果没有记录，按完全重叠读取
+    #[serde(default = "full_overlap")]
+    pub overlap_ratio: f64,
+    /// 对比前 Track B 的时间平移 (秒)
+    #[serde(default)]
+    pub b_offset: f64,
+    /// 电平匹配时减去的平均电平差 (A - B)；此时其余差值统计为去除电平差后的动态差异
+    #[serde(default)]
+    pub level_offset: Option<f64>,
+    /// 两条曲线的点间隔或起点不同，已在点较疏一条的时间点上插值后配对
+    #[serde(default)]
+    pub resampled: bool,
+    /// (时间, 差值)
+    pub diff_points: Vec<[f64; 2]>,
+    /// Bland-Altman 图的点：(A 与 B 的均值, 差值)
+    #[serde(default)]
+    pub agreement_points: Vec<[f64; 2]>,
+    /// 配对后 Track A 的电平 (与 diff_points 一一对应，电平匹配时为去除平均电平后的值)；
+    /// 只在对比时要求保留 ([`CompareOptions::keep_aligned`]) 才生成，否则为空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub a_vals: Vec<f64>,
+    /// 配对后 Track B 的电平，同 a_vals
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub b_vals: Vec<f64>,
+}
+
+/// 差值偏离目标超过阈值的一段时间 (秒，Track A 的时间轴)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DiffRegion {
+    /// 第一个超出阈值的点
+    pub start: f64,
+    /// 最后一个超出阈值的点
+    pub end: f64,
+    /// 区间内偏离目标最大的点的时间
+    pub peak_time: f64,
+    /// 该点的差值 (dB)
+    pub peak_diff: f64,
+}
+
+/// 超出阈值的两段之间的间隔短于该值 (秒) 时合并为一个区间，避免一处差异被个别点拆成多段
+pub const DIFF_REGION_MERGE_GAP_SEC: f64 = 0.5;
+
+fn full_overlap() -> f64 {
+    1.0
+}
+
+/// Bland-Altman 一致性界限 (平均差 ± 1.96 倍标准差) 的系数
+pub const LIMITS_OF_AGREEMENT_Z: f64 = 1.96;
+
+impl ComparisonResult {
+    /// Bland-Altman 一致性界限 (下限, 上限)
+    pub fn limits_of_agreement(&self) -> (f64, f64) {
+        let half_width = LIMITS_OF_AGREEMENT_Z * self.std_dev;
+        (self.mean_diff - half_width, self.mean_diff + half_width)
+    }
+
+    /// 以 target_diff 为目标均差的 t 统计量。只用到已算好的均值、标准差和点数，
+    /// 目标改变时不必重新逐点对比
+    pub fn t_statistic_for(&self, target_diff: f64) -> f64 {
+        calculate_t_statistic(self.mean_diff - target_diff, self.std_dev, self.diff_points.len())
+    }
+
+    /// 配对后的逐点数据 (Track A 的时间, A, B, 差值)，即统计量所用的数据，电平匹配时为减去各自平均电平后的值。
+    /// 保留了 a_vals / b_vals 时直接取用，否则由差值点和 Bland-Altman 点还原 (A = 均值 + 差值 / 2)
+    pub fn aligned_pairs(&self) -> Box<dyn Iterator<Item = [f64; 4]> + '_> {
+        if self.has_aligned_values() {
+            Box::new(self.diff_points.iter().zip(self.a_vals.iter().zip(&self.b_vals)).map(|(&[time, diff], (&a, &b))| [time, a, b, diff]))
+        } else {
+            Box::new(self.diff_points.iter().zip(&self.agreement_points).map(|(&[time, diff], &[mean, _])| [time, mean + diff / 2.0, mean - diff / 2.0, diff]))
+        }
+    }
+
+    /// 是否保留了配对后的 a_vals / b_vals
+    pub fn has_aligned_values(&self) -> bool {
+        !self.a_vals.is_empty() && self.a_vals.len() == self.diff_points.len() && self.b_vals.len() == self.diff_points.len()
+    }
+
+    /// 给定置信度下 t 检验的双侧临界值，自由度为配对点数 - 1
+    pub fn t_critical_value(&self, confidence: f64) -> f64 {
+        t_critical_value(confidence, self.diff_points.len().saturating_sub(1))
+    }
+
+    /// 在给定置信度下检验原假设 H0: 均差 = target_diff。返回 true 表示均差与目标显著不同。
+    /// 只由已算好的统计量得出，置信度或目标改变时不必重新对比
+    pub fn differs_from_target(&self, target_diff: f64, confidence: f64) -> bool {
+        self.t_statistic_for(target_diff).abs() > self.t_critical_value(confidence)
+    }
+
+    /// 相关系数的 Fisher z 检验 (见 [`correlation_test`])，样本数为配对的点数
+    pub fn correlation_test(&self, min_r: f64, confidence: f64) -> Option<CorrelationTest> {
+        correlation_test(self.correlation_coefficient, self.diff_points.len(), min_r, confidence)
+    }
+
+    /// 差值与目标差值相差不超过 tolerance_db 的窗口比例 (0.0 - 1.0)，
+    /// 即 |差值 - 目标| 的分布中 tolerance_db 所处的百分位
+    pub fn within_tolerance(&self, target_diff: f64, tolerance_db: f64) -> f64 {
+        if self.diff_points.is_empty() {
+            return 0.0;
+        }
+        let within = self.diff_points.iter().filter(|p| (p[1] - target_diff).abs() <= tolerance_db).count();
+        within as f64 / self.diff_points.len() as f64
+    }
+
+    /// 差值偏离目标最大的 count 个区间，按偏离程度从大到小排列。
+    /// 区间为 |差值 - 目标| 连续超过 threshold_db 的一段 (间隔短于 DIFF_REGION_MERGE_GAP_SEC 的两段合并)，
+    /// 峰值取区间内偏离最大的点
+    pub fn error_regions(&self, target_diff: f64, threshold_db: f64, count: usize) -> Vec<DiffRegion> {
+        let mut regions: Vec<DiffRegion> = Vec::new();
+        for &[time, diff] in &self.diff_points {
+            if (diff - target_diff).abs() <= threshold_db {
+                continue;
+            }
+            match regions.last_mut() {
+                Some(region) if time - region.end < DIFF_REGION_MERGE_GAP_SEC => {
+                    region.end = time;
+                    if (diff - target_diff).abs() > (region.peak_diff - target_diff).abs() {
+                        (region.peak_time, region.peak_diff) = (time, diff);
+                    }
+                }
+                _ => regions.push(DiffRegion { start: time, end: time, peak_time: time, peak_diff: diff }),
+            }
+        }
+        regions.sort_by(|a, b| (b.peak_diff - target_diff).abs().total_cmp(&(a.peak_diff - target_diff).abs()));
+        regions.truncate(count);
+        regions
+    }
+
+    /// 一致性评分 (0 - 100)，公式见 ConsistencyWeights
+    pub fn consistency_score(&self, weights: &ConsistencyWeights, target_diff: f64) -> f64 {
+        let total = weights.correlation + weights.spread + weights.within_tolerance;
+        if total <= 0.0 {
+            return 0.0;
+        }
+        let [correlation, spread, within] = weights.components(self, target_diff);
+        100.0 * (weights.correlation * correlation + weights.spread * spread + weights.within_tolerance * within) / total
+    }
+}
+
+/// 一致性评分的权重与容差。评分为三个 0-1 分量的加权平均乘以 100：
+///
+/// - 相关：max(r, 0)，只看曲线形状
+/// - 离散：1 / (1 + σ / 容差)，差值标准差等于容差时为 0.5
+/// - 容差内比例：|差值 - 目标差值| ≤ 容差的窗口比例
+///
+/// 权重只按相对大小起作用，全部为 0 时评分为 0
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct ConsistencyWeights {
+    /// 相关系数 r 的权重
+    pub correlation: f64,
+    /// 差值离散度 (标准差) 的权重
+    pub spread: f64,
+    /// 容差内点比例的权重
+    pub within_tolerance: f64,
+    /// 容差 (dB)：与目标均差相差不超过该值的点计入容差内
+    pub tolerance_db: f64,
+}
+
+impl Default for ConsistencyWeights {
+    fn default() -> Self {
+        Self { correlation: 0.3, spread: 0.3, within_tolerance: 0.4, tolerance_db: 1.0 }
+    }
+}
+
+impl ConsistencyWeights {
+    /// 评分的三个分量 [相关, 离散, 容差内比例]，均在 0-1 之间
+    pub fn components(&self, result: &ComparisonResult, target_diff: f64) -> [f64; 3] {
+        let tolerance = self.tolerance_db.max(f64::EPSILON);
+        let correlation = if result.correlation_coefficient.is_finite() { result.correlation_coefficient.max(0.0) } else { 0.0 };
+        [correlation, 1.0 / (1.0 + result.std_dev / tolerance), result.within_tolerance(target_diff, tolerance)]
+    }
+}
+
+/// 多声道 WAV 在加窗前如何合并声道
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+pub enum DownmixMode {
+    /// 旧行为：直接对交错样本求 RMS (相当于各声道功率平均)
+    Interleaved,
+    /// 按下混矩阵合成单声道：立体声每声道使用可调增益 (默认 -3 dB)，5.1/7.1 使用 ITU-R BS.775 系数
+    #[default]
+    Matrix,
+    /// 按 ITU-R BS.1770 对各声道能量加权求和：环绕声道 +1.5 dB，LFE 不计入
+    Bs1770,
+}
+
+// WAVE_FORMAT_EXTENSIBLE 中 dwChannelMask 的扬声器位
+const SPEAKER_FRONT_LEFT: u32 = 0x1;
+const SPEAKER_FRONT_RIGHT: u32 = 0x2;
+const SPEAKER_FRONT_CENTER: u32 = 0x4;
+const SPEAKER_LOW_FREQUENCY: u32 = 0x8;
+// 后置/侧置环绕 (BL, BR, BC, SL, SR)
+const SPEAKER_SURROUNDS: u32 = 0x10 | 0x20 | 0x100 | 0x200 | 0x400;
+
+/// BS.1770 中环绕声道的能量权重 (+1.5 dB)
+const BS1770_SURROUND_WEIGHT: f64 = 1.41;
+
+/// 没有声道掩码时按声道数推断的常见布局 (单声道、立体声、5.1、7.1)
+fn default_channel_mask(channels: usize) -> Option<u32> {
+    match channels {
+        1 => Some(SPEAKER_FRONT_CENTER),
+        2 => Some(0x3),
+        6 => Some(0x3F),
+        8 => Some(0x63F),
+        _ => None,
+    }
+}
+
+/// 每个声道对应的扬声器位 (声道顺序即掩码中从低到高的置位顺序)；无法确定的声道为 0
+pub fn channel_speakers(channels: usize, mask: Option<u32>) -> Vec<u32> {
+    let mask = mask.filter(|&m| m != 0).or_else(|| default_channel_mask(channels)).unwrap_or(0);
+    let mut bits = (0..32).map(|b| 1u32 << b).filter(|bit| mask & bit != 0);
+    (0..channels).map(|_| bits.next().unwrap_or(0)).collect()
+}
+
+/// BS.1770 各声道能量权重：环绕 1.41，LFE 0，其余 (含未知声道) 1.0
+pub fn bs1770_channel_weights(speakers: &[u32]) -> Vec<f64> {
+    speakers.iter().map(|&speaker| {
+        if speaker == SPEAKER_LOW_FREQUENCY {
+            0.0
+        } else if speaker & SPEAKER_SURROUNDS != 0 {
+            BS1770_SURROUND_WEIGHT
+        } else {
+            1.0
+        }
+    }).collect()
+}
+
+/// 从 WAV 的 fmt 块读取 WAVE_FORMAT_EXTENSIBLE 声道掩码 (hound 不提供此字段)
+pub fn read_wav_channel_mask(path: &Path) -> Option<u32> {
+    read_wav_channel_mask_from(std::io::BufReader::new(File::open(path).ok()?))
+}
+
+/// 与 [`read_wav_channel_mask`] 相同，但从任意可定位的读取器 (如内存中的 WAV) 读取
+pub fn read_wav_channel_mask_from(mut file: impl std::io::Read + std::io::Seek) -> Option<u32> {
+    use std::io::SeekFrom;
+
+    let mut header = [0u8; 12];
+    file.read_exact(&mut header).ok()?;
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return None;
+    }
+
+    let mut chunk_header = [0u8; 8];
+    while file.read_exact(&mut chunk_header).is_ok() {
+        let size = u32::from_le_bytes(chunk_header[4..8].try_into().ok()?) as usize;
+        if &chunk_header[0..4] == b"fmt " {
+            let mut fmt = vec![0u8; size];
+            file.read_exact(&mut fmt).ok()?;
+            let format_tag = u16::from_le_bytes(fmt.get(0..2)?.try_into().ok()?);
+            if format_tag != 0xFFFE || size < 24 {
+                return None;
+            }
+            return Some(u32::from_le_bytes(fmt[20..24].try_into().ok()?));
+        }
+        // 块按偶数字节对齐
+        file.seek(SeekFrom::Current((size + (size & 1)) as i64)).ok()?;
+    }
+    None
+}
+
+/// 影响曲线计算结果的分析参数 (加载时传入工作线程)
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+#[serde(default)]
+pub struct AnalysisSettings {
+    /// 多声道文件的声道合并方式
+    pub downmix: DownmixMode,
+    /// 立体声下混时每个声道的增益 (dB)
+    pub stereo_downmix_db: f32,
+    /// 通过内存映射直接读取 data 块 (不影响结果，只影响读取速度)；映射失败时退回 WavReader
+    pub use_mmap: bool,
+    /// RMS 窗口长度 (秒)
+    pub window_sec: f64,
+    /// 相邻窗口的步进 (秒)
+    pub step_sec: f64,
+    /// 计算窗口电平前对样本施加的频率计权 (主曲线和频段曲线)
+    pub weighting: CurveWeighting,
+    /// 低于此电平 (dBFS) 的窗口记为静音 (SILENCE_FLOOR_DBFS)；默认 -180 dBFS 即只有数字静音算作静音
+    pub noise_floor_dbfs: f64,
+    /// 平均电平的计算方式
+    pub average: AverageMode,
+    /// 分析时跳过开头的秒数 (如提示音、预备拍)，这段不产生曲线点，不计入任何统计和归一化
+    pub skip_head_sec: f64,
+    /// 分析时跳过结尾的秒数
+    pub skip_tail_sec: f64,
+}
+
+impl Default for AnalysisSettings {
+    fn default() -> Self {
+        Self {
+            downmix: DownmixMode::default(),
+            stereo_downmix_db: -3.0,
+            use_mmap: true,
+            window_sec: 0.4,
+            step_sec: 0.1,
+            weighting: CurveWeighting::default(),
+            noise_floor_dbfs: -180.0,
+            average: AverageMode::default(),
+            skip_head_sec: 0.0,
+            skip_tail_sec: 0.0,
+        }
+    }
+}
+
+/// 曲线电平计算前的频率计权
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+pub enum CurveWeighting {
+    /// 不计权，窗口电平为 dBFS RMS
+    #[default]
+    Flat,
+    /// BS.1770 K 计权 (高架预滤波 + RLB 高通)，电平更接近响度表读数
+    K,
+    /// 实验性：按 ISO 226:2003 等响曲线计权 (1 kHz 为 0 dB)，得到假定重放响度级下的"感知响度"曲线，
+    /// 既不是 dBFS 也不是 LUFS
+    EqualLoudness {
+        /// 假定的重放响度级 (phon)，限制在 ISO 226 的有效范围 EQUAL_LOUDNESS_PHON_RANGE 内
+        phon: f64,
+    },
+}
+
+/// ISO 226:2003 等响曲线的有效响度级范围 (phon)
+pub const EQUAL_LOUDNESS_PHON_RANGE: std::ops::RangeInclusive<f64> = 20.0..=90.0;
+
+impl CurveWeighting {
+    /// 选择等响计权时的默认重放响度级 (phon)
+    pub const DEFAULT_PHON: f64 = 60.0;
+
+    /// 每个声道的计权滤波器链；不计权时为 None
+    fn filters(self, sample_rate: f64) -> Option<Vec<Biquad>> {
+        match self {
+            CurveWeighting::Flat => None,
+            CurveWeighting::K => Some(k_weighting_filters(sample_rate).to_vec()),
+            CurveWeighting::EqualLoudness { phon } => Some(equal_loudness_filters(sample_rate, phon)),
+        }
+    }
+}
+
+/// 由各窗口电平得出平均电平的方式
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+pub enum AverageMode {
+    /// 换算为线性功率取平均后再换回 dB，与能量意义上的平均响度一致
+    #[default]
+    Power,
+    /// 旧版本的行为：直接平均各窗口的 dB 值。动态大的素材会比功率平均低几 dB
+    DbMean,
+}
+
+impl AverageMode {
+    /// 各点电平 (dB) 的平均值；没有点时为 SILENCE_FLOOR_DBFS
+    pub fn average(self, points: &[[f64; 2]]) -> f64 {
+        if points.is_empty() {
+            return SILENCE_FLOOR_DBFS;
+        }
+        let n = points.len() as f64;
+        match self {
+            AverageMode::Power => {
+                let power = points.iter().map(|p| 10f64.powf(p[1] / 10.0)).sum::<f64>() / n;
+                (10.0 * power.log10()).max(SILENCE_FLOOR_DBFS)
+            }
+            AverageMode::DbMean => points.iter().map(|p| p[1]).sum::<f64>() / n,
+        }
+    }
+}
+
+impl AnalysisSettings {
+    /// 两组参数计算出的曲线是否相同 (只影响读取方式的 use_mmap 不算)
+    pub fn same_results(&self, other: &Self) -> bool {
+        *self == Self { use_mmap: self.use_mmap, ..other.clone() }
+    }
+
+    /// 时长为 duration 的文件实际分析的时间范围 [开始, 结束] (秒)；跳过的时长超过文件长度时为空范围
+    pub fn analysis_range(&self, duration: f64) -> [f64; 2] {
+        let start = self.skip_head_sec.clamp(0.0, duration);
+        [start, (duration - self.skip_tail_sec.max(0.0)).max(start)]
+    }
+
+    /// 是否跳过了开头或结尾
+    pub fn trims_range(&self) -> bool {
+        self.skip_head_sec > 0.0 || self.skip_tail_sec > 0.0
+    }
+
+    /// Matrix 模式下每个声道的下混系数：L/R -3 dB，C 0 dB，环绕 -6 dB，LFE 丢弃；
+    /// 立体声使用可调增益，无法识别的声道按等功率分配
+    pub fn downmix_coefficients(&self, speakers: &[u32]) -> Vec<f64> {
+        if speakers.len() == 2 {
+            return vec![10f64.powf(self.stereo_downmix_db as f64 / 20.0); 2];
+        }
+        let unknown = 1.0 / (speakers.len() as f64).sqrt();
+        speakers.iter().map(|&speaker| match speaker {
+            SPEAKER_FRONT_LEFT | SPEAKER_FRONT_RIGHT => std::f64::consts::FRAC_1_SQRT_2,
+            SPEAKER_FRONT_CENTER => 1.0,
+            SPEAKER_LOW_FREQUENCY => 0.0,
+            s if s & SPEAKER_SURROUNDS != 0 => 0.5,
+            _ => unknown,
+        }).collect()
+    }
+}
+
+// --- 分析配置 ---
+
+/// 命名的分析配置：不同类型的素材 (对白分轨、音乐母带、测量设备导出的 CSV) 需要不同的分析参数，
+/// 打开文件时按 [`ProfileRule`] 自动选用
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+#[serde(default)]
+pub struct AnalysisProfile {
+    /// 配置名称 (规则按名称引用，记录在 [`AudioCurve::profile`])
+    pub name: String,
+    /// 分析 WAV 时使用的参数，CSV 曲线也按它计算统计
+    pub analysis: AnalysisSettings,
+    /// CSV 的列布局；None 时自动识别
+    pub csv_layout: Option<CsvLayout>,
+}
+
+/// 文件名规则：文件名匹配 `pattern` (`*` 匹配任意多个字符，`?` 匹配一个字符，不区分大小写) 时使用名为 `profile` 的配置
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default)]
+pub struct ProfileRule {
+    /// 文件名通配符，如 `*_dx.wav`
+    pub pattern: String,
+    /// 配置名称
+    pub profile: String,
+}
+
+/// 文件名是否匹配通配符 (`*` / `?`，不区分大小写)
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.to_lowercase().chars().collect();
+    let name: Vec<char> = name.to_lowercase().chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 '*' 的位置及它当前匹配到的文件名位置，失配时让它多吞一个字符再试
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 按规则顺序为文件选择配置：第一条匹配文件名、且引用的配置存在的规则生效；没有匹配时为 None (使用全局参数)
+pub fn select_profile<'a>(path: &Path, rules: &[ProfileRule], profiles: &'a [AnalysisProfile]) -> Option<&'a AnalysisProfile> {
+    let name = path.file_name()?.to_string_lossy();
+    rules.iter()
+        .filter(|rule| wildcard_match(&rule.pattern, &name))
+        .find_map(|rule| profiles.iter().find(|profile| profile.name == rule.profile))
+}
+
+// --- 合成参考曲线 ---
+
+/// 不来自文件的参考曲线，用于检查一条曲线是否保持在某个电平或目标包络附近
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
+pub enum SyntheticReference {
+    /// 恒定电平 (dBFS)
+    Constant(f64),
+    /// 断点 (时间, dBFS)，相邻断点之间线性插值，第一个之前和最后一个之后保持端点的电平
+    Breakpoints(Vec<[f64; 2]>),
+}
+
+impl Default for SyntheticReference {
+    fn default() -> Self {
+        Self::Constant(-20.0)
+    }
+}
+
+impl SyntheticReference {
+    /// 时间 time (秒) 处的电平；没有断点时为 None
+    pub fn level_at(&self, time: f64) -> Option<f64> {
+        match self {
+            Self::Constant(level) => Some(*level),
+            Self::Breakpoints(points) => {
+                let (first, last) = (points.first()?, points.last()?);
+                if time <= first[0] {
+                    Some(first[1])
+                } else if time >= last[0] {
+                    Some(last[1])
+                } else {
+                    interpolate_at(points, time)
+                }
+            }
+        }
+    }
+
+    /// 在 template 的各点时间上生成曲线 (步进与 template 相同，可直接逐点对比)，统计按 template 的分析参数计算。
+    /// 没有断点时为 None
+    pub fn curve_like(&self, template: &AudioCurve, name: String) -> Option<AudioCurve> {
+        self.level_at(0.0)?;
+        let points = template.points.iter().filter_map(|p| Some([p[0], self.level_at(p[0])?])).collect();
+        let mut curve = AudioCurve::new(name, points, template.duration, SILENCE_FLOOR_DBFS);
+        curve.measure_points(template.analysis.as_ref().unwrap_or(&AnalysisSettings::default()));
+        // 统计沿用 template 的分析参数，也沿用其分析配置，对比时不提示两侧配置不同
+        curve.profile = template.profile.clone();
+        Some(curve)
+    }
+
+    /// 按时间排序断点，去掉时间不是有限值的断点
+    pub fn normalize(&mut self) {
+        if let Self::Breakpoints(points) = self {
+            points.retain(|p| p[0].is_finite() && p[1].is_finite());
+            points.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        }
+    }
+}
+
+// --- 日志系统 ---
+
+/// 一条日志
+pub struct LogEntry {
+    /// 写入缓冲区时分配的序号，单调递增，被淘汰或清空的条目不会复用
+    pub seq: u64,
+    /// 本地时间
+    pub timestamp: DateTime<Local>,
+    /// 写入这条日志的后台任务 (见 set_log_task_id)；界面线程为 None
+    pub task_id: Option<usize>,
+    /// 日志内容
+    pub message: String,
+    /// 日志级别
+    pub level: LogLevel,
+}
+
+impl LogEntry {
+    /// 以当前本地时间和当前线程的任务 ID 创建；序号在写入 LogBuffer 时分配
+    pub fn new(level: LogLevel, message: String) -> Self {
+        let task_id = LOG_TASK_ID.with(|id| id.get());
+        Self { seq: 0, timestamp: Local::now(), task_id, message, level }
+    }
+
+    /// 日志文件中的一行：ISO 8601 时间、级别、任务 ID (如有) 和内容；内容中的换行转义为 \n
+    pub fn file_line(&self) -> String {
+        let task = self.task_id.map_or(String::new(), |id| format!("[task {}] ", id));
+        let level = format!("{:?}", self.level).to_uppercase();
+        format!("{} {:<7} {}{}", self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, false), level, task, self.message.replace('\n', "\\n"))
+    }
+}
+
+thread_local! {
+    static LOG_TASK_ID: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// 把当前线程之后写入的日志标记为属于该后台任务 (None 取消标记)
+pub fn set_log_task_id(task_id: Option<usize>) {
+    LOG_TASK_ID.with(|id| id.set(task_id));
+}
+
+/// 日志级别，控制台可按级别过滤
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum LogLevel {
+    /// 一般信息
+    Info,
+    /// 错误
+    Error,
+    /// 调试信息
+    Debug,
+    /// 命令行输入或操作
+    Command,
+}
+
+/// 默认保留的日志条数
+pub const DEFAULT_LOG_CAPACITY: usize = 5000;
+
+/// 固定容量的日志环形缓冲区 (最旧在前)：写满后每写入一条淘汰最旧的一条，
+/// 并记录累计淘汰数，控制台据此提示丢失了多少条
+pub struct LogBuffer {
+    entries: VecDeque<LogEntry>,
+    capacity: usize,
+    next_seq: u64,
+    dropped: u64,
+    mirror: Option<LogFileMirror>,
+}
+
+impl LogBuffer {
+    /// 最多保留 capacity 条 (至少 1 条)
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self { entries: VecDeque::new(), capacity: capacity.max(1), next_seq: 0, dropped: 0, mirror: None }
+    }
+
+    /// 写入一条日志并分配序号；超出容量时淘汰最旧的条目
+    pub fn push(&mut self, mut entry: LogEntry) {
+        entry.seq = self.next_seq;
+        self.next_seq += 1;
+        // 写入线程已退出 (例如无法创建文件) 时停止镜像
+        if let Some(mirror) = &self.mirror && mirror.sender.send(entry.file_line()).is_err() {
+            self.mirror = None;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// 当前容量
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 修改容量；缩小时立即淘汰多出的最旧条目
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        while self.entries.len() > self.capacity {
+            self.entries.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    /// 因容量限制被淘汰的累计条数 (不含手动清空的条目)
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// 清空缓冲区；序号继续递增，淘汰计数归零
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.dropped = 0;
+    }
+
+    /// 之后写入的每条日志同时交给日志文件写入线程；None 停止镜像 (写入线程写完剩余日志后退出)
+    pub fn set_mirror(&mut self, mirror: Option<LogFileMirror>) {
+        self.mirror = mirror;
+    }
+
+    /// 停止镜像并等待已写入的日志落盘 (退出前调用，set_mirror(None) 不等待写入线程)
+    pub fn close_mirror(&mut self) {
+        if let Some(mirror) = self.mirror.take() {
+            mirror.close();
+        }
+    }
+
+    /// 当前的日志文件镜像
+    pub fn mirror(&self) -> Option<&LogFileMirror> {
+        self.mirror.as_ref()
+    }
+
+    /// 保留的条目数
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// 是否没有条目
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// 按时间顺序 (最旧在前) 遍历
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LogEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+}
+
+/// 单个日志文件的默认大小上限 (字节)
+pub const LOG_FILE_MAX_BYTES: u64 = 10 * 1024 * 1024;
+/// 日志文件保留的天数，更早的文件在轮换时删除
+const LOG_FILE_KEEP_DAYS: u64 = 7;
+const LOG_FILE_PREFIX: &str = "wav_lufs_curve-";
+
+/// 把日志镜像到磁盘：每天一个文件 (wav_lufs_curve-YYYY-MM-DD.log)，超过大小上限时把当前文件
+/// 改名为 .1.log 备份后重新开始。写文件在专用线程中进行，Logger::log 只向通道发送一行文本，
+/// 磁盘延迟不会阻塞调用方
+pub struct LogFileMirror {
+    sender: mpsc::Sender<String>,
+    dir: PathBuf,
+    handle: thread::JoinHandle<()>,
+}
+
+impl LogFileMirror {
+    /// 创建目录并启动写入线程
+    pub fn start(dir: PathBuf, max_bytes: u64) -> std::io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        let (sender, receiver) = mpsc::channel::<String>();
+        let thread_dir = dir.clone();
+        let handle = thread::Builder::new()
+            .name("log-file".to_string())
+            .spawn(move || write_log_file(&thread_dir, max_bytes.max(1), receiver))?;
+        Ok(Self { sender, dir, handle })
+    }
+
+    /// 停止镜像：关闭通道并等待写入线程写完剩余的日志
+    pub fn close(self) {
+        let Self { sender, handle, .. } = self;
+        drop(sender);
+        handle.join().ok();
+    }
+
+    /// 日志目录
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// 今天正在写入的日志文件
+    pub fn current_path(&self) -> PathBuf {
+        log_file_path(&self.dir, Local::now().date_naive())
+    }
+}
+
+fn log_file_path(dir: &Path, date: NaiveDate) -> PathBuf {
+    dir.join(format!("{}{}.log", LOG_FILE_PREFIX, date.format("%Y-%m-%d")))
+}
+
+/// 日志文件写入线程：逐批取出通道中的行，每批写完后 flush，进程崩溃时最多丢失正在写的一批。
+/// 所有发送端 (LogFileMirror) 被丢弃后退出
+fn write_log_file(dir: &Path, max_bytes: u64, receiver: mpsc::Receiver<String>) {
+    use std::io::Write;
+    let open = |date: NaiveDate| -> Option<(NaiveDate, std::io::BufWriter<File>, u64)> {
+        let file = std::fs::OpenOptions::new().create(true).append(true).open(log_file_path(dir, date)).ok()?;
+        let size = file.metadata().map_or(0, |m| m.len());
+        Some((date, std::io::BufWriter::new(file), size))
+    };
+    let mut current: Option<(NaiveDate, std::io::BufWriter<File>, u64)> = None;
+
+    while let Ok(first) = receiver.recv() {
+        for line in std::iter::once(first).chain(receiver.try_iter()) {
+            // 跨过午夜时换到新一天的文件，并删除过期的文件
+            let today = Local::now().date_naive();
+            if current.as_ref().is_none_or(|(date, _, _)| *date != today) {
+                if let Some((_, writer, _)) = current.as_mut() {
+                    writer.flush().ok();
+                }
+                remove_expired_log_files(dir, today);
+                current = open(today);
+            }
+            // 超过大小上限：当前文件改名为 .1.log (覆盖上一个备份) 后重新开始
+            let len = line.len() as u64 + 1;
+            let full = current.as_mut().filter(|(_, _, size)| *size > 0 && *size + len > max_bytes);
+            if let Some((date, writer, _)) = full {
+                writer.flush().ok();
+                let (date, path) = (*date, log_file_path(dir, *date));
+                std::fs::rename(&path, path.with_extension("1.log")).ok();
+                current = open(date);
+            }
+            if let Some((_, writer, size)) = current.as_mut() && writeln!(writer, "{}", line).is_ok() {
+                *size += len;
+            }
+        }
+        if let Some((_, writer, _)) = current.as_mut() {
+            writer.flush().ok();
+        }
+    }
+}
+
+/// 删除早于 LOG_FILE_KEEP_DAYS 天的日志文件 (含 .1.log 备份)
+fn remove_expired_log_files(dir: &Path, today: NaiveDate) {
+    let Ok(entries) = std::fs::read_dir(dir) else { return };
+    let Some(oldest) = today.checked_sub_days(chrono::Days::new(LOG_FILE_KEEP_DAYS)) else { return };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(date) = name.to_str()
+            .and_then(|name| name.strip_prefix(LOG_FILE_PREFIX))
+            .and_then(|rest| rest.get(..10))
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+        else { continue };
+        if date < oldest {
+            std::fs::remove_file(entry.path()).ok();
+        }
+    }
+}
+
+/// 线程安全的日志收集器；克隆 `entries` 即可在工作线程中写入同一份日志
+pub struct Logger {
+    /// 共享的日志缓冲区
+    pub entries: Arc<Mutex<LogBuffer>>,
+}
+
+impl Default for Logger {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Logger {
+    /// 空日志，容量为 DEFAULT_LOG_CAPACITY
+    pub fn new() -> Self {
+        Self { entries: Arc::new(Mutex::new(LogBuffer::with_capacity(DEFAULT_LOG_CAPACITY))) }
+    }
+
+    /// 记录一条日志，线程安全
+    pub fn log(&self, level: LogLevel, message: String) {
+        let entry = LogEntry::new(level, message);
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.push(entry);
+        }
+    }
+}
+
+/// 日志输出接口：解析、导出等函数只通过它报告进度和错误，不依赖界面的日志缓冲区
+pub trait LogSink {
+    /// 记录一条日志
+    fn log(&self, level: LogLevel, message: String);
+}
+
+impl LogSink for Logger {
+    fn log(&self, level: LogLevel, message: String) {
+        Logger::log(self, level, message);
+    }
+}
+
+/// 丢弃所有日志的输出端
+pub struct NoLog;
+
+impl LogSink for NoLog {
+    fn log(&self, _level: LogLevel, _message: String) {}
+}
+
+/// 辅助函数，方便记录日志
+pub fn log_info(logger: &dyn LogSink, msg: &str) {
+    logger.log(LogLevel::Info, msg.to_string());
+}
+
+/// 记录错误
+pub fn log_error(logger: &dyn LogSink, msg: &str) {
+    logger.log(LogLevel::Error, msg.to_string());
+}
+
+/// 记录调试信息
+pub fn log_debug(logger: &dyn LogSink, msg: &str) {
+    logger.log(LogLevel::Debug, msg.to_string());
+}
+
+/// 记录命令行输入或用户操作
+pub fn log_command(logger: &dyn LogSink, msg: &str) {
+    logger.log(LogLevel::Command, msg.to_string());
+}
+
+// --- 音频处理逻辑 (更新: 增加 Logger 参数) ---
+
+/// 样本的 RMS 电平 (dBFS)；静音返回 SILENCE_FLOOR_DBFS
+pub fn calculate_rms_dbfs(samples: &[f64]) -> f64 {
+    if samples.is_empty() { return SILENCE_FLOOR_DBFS; }
+    let squared_sum: f64 = samples.iter().map(|s| s * s).sum();
+    let rms = (squared_sum / samples.len() as f64).sqrt();
+    if rms < 1e-9 { SILENCE_FLOOR_DBFS } else { 20.0 * rms.log10() }
+}
+
+/// 计算 Pearson 相关系数 (r)
+pub fn calculate_correlation(a_vals: &[f64], b_vals: &[f64], len: usize) -> f64 {
+    pearson_correlation(a_vals.iter().copied().zip(b_vals.iter().copied()).take(len))
+}
+
+/// 成对数据 (a, b) 的 Pearson 相关系数；遍历两次 (先求均值)，不需要先收集到数组
+fn pearson_correlation(pairs: impl Iterator<Item = (f64, f64)> + Clone) -> f64 {
+    let len = pairs.clone().count();
+    if len <= 1 { return 0.0; }
+
+    let (sum_a, sum_b) = pairs.clone().fold((0.0, 0.0), |(sa, sb), (a, b)| (sa + a, sb + b));
+    let mean_a = sum_a / len as f64;
+    let mean_b = sum_b / len as f64;
+
+    let mut numerator = 0.0;
+    let mut sum_sq_a = 0.0;
+    let mut sum_sq_b = 0.0;
+
+    for (a, b) in pairs {
+        let dev_a = a - mean_a;
+        let dev_b = b - mean_b;
+
+        numerator += dev_a * dev_b;
+        sum_sq_a += dev_a * dev_a;
+        sum_sq_b += dev_b * dev_b;
+    }
+
+    let denominator = (sum_sq_a * sum_sq_b).sqrt();
+
+    if denominator == 0.0 {
+        return 0.0;
+    }
+    numerator / denominator
+}
+
+/// 计算单样本 T 统计量 (检验均值差值是否为 0/C)
+/// mean_difference 应该传入 (实际均值差 - 目标差值)
+pub fn calculate_t_statistic(mean_difference: f64, std_dev: f64, n: usize) -> f64 {
+    if n <= 1 || std_dev.abs() < f64::EPSILON {
+        return 0.0;
+    }
+    // 标准误差 (SEM) = std_dev / sqrt(n)
+    let sem = std_dev / (n as f64).sqrt();
+
+    // T = (Mean - Target) / SEM
+    mean_difference / sem
+}
+
+/// 可选的置信度范围，超出范围的置信度按边界处理
+pub const CONFIDENCE_RANGE: std::ops::RangeInclusive<f64> = 0.5..=0.9999;
+
+/// 双侧检验的临界值 (标准正态分布，大样本近似)，置信度为 [`CONFIDENCE_RANGE`] 内任意值
+pub fn critical_value(confidence: f64) -> f64 {
+    let confidence = confidence.clamp(*CONFIDENCE_RANGE.start(), *CONFIDENCE_RANGE.end());
+    normal_quantile(0.5 + confidence / 2.0)
+}
+
+/// 自由度为 df 的 t 分布的双侧临界值：|t| 超过该值的概率为 1 - 置信度。
+/// 由 [`student_t_p_value`] 二分求得，df 为 0 时按 1 处理
+pub fn t_critical_value(confidence: f64, df: usize) -> f64 {
+    let confidence = confidence.clamp(*CONFIDENCE_RANGE.start(), *CONFIDENCE_RANGE.end());
+    let alpha = 1.0 - confidence;
+    let df = df.max(1);
+    // t 分布的尾部比正态分布厚，临界值不小于正态临界值；上界逐次加倍直到包住目标
+    let (mut low, mut high) = (critical_value(confidence), 2.0 * critical_value(confidence));
+    while student_t_p_value(high, df) > alpha {
+        low = high;
+        high *= 2.0;
+    }
+    for _ in 0..100 {
+        let mid = 0.5 * (low + high);
+        if student_t_p_value(mid, df) > alpha {
+            low = mid;
+        } else {
+            high = mid;
+        }
+        if high - low < 1e-10 * high {
+            break;
+        }
+    }
+    0.5 * (low + high)
+}
+
+/// 自由度为 df 的 t 统计量的双侧 p 值 P(|T| ≥ |t|)
+pub fn student_t_p_value(t: f64, df: usize) -> f64 {
+    let df = df.max(1) as f64;
+    regularized_incomplete_beta(df / (df + t * t), df / 2.0, 0.5)
+}
+
+/// 标准正态分布的分位数 (Acklam 有理逼近，相对误差约 1e-9)
+fn normal_quantile(p: f64) -> f64 {
+    const A: [f64; 6] = [-3.969683028665376e1, 2.209460984245205e2, -2.759285104469687e2, 1.38357751867269e2, -3.066479806614716e1, 2.506628277459239];
+    const B: [f64; 5] = [-5.447609879822406e1, 1.615858368580409e2, -1.556989798598866e2, 6.680131188771972e1, -1.328068155288572e1];
+    const C: [f64; 6] = [-7.784894002430293e-3, -3.223964580411365e-1, -2.400758277161838, -2.549732539343734, 4.374664141464968, 2.938163982698783];
+    const D: [f64; 4] = [7.784695709041462e-3, 3.224671290700398e-1, 2.445134137142996, 3.754408661907416];
+    const P_LOW: f64 = 0.02425;
+    let tail = |q: f64| {
+        (((((C[0] * q + C[1]) * q + C[2]) * q + C[3]) * q + C[4]) * q + C[5]) / ((((D[0] * q + D[1]) * q + D[2]) * q + D[3]) * q + 1.0)
+    };
+    if p < P_LOW {
+        tail((-2.0 * p.ln()).sqrt())
+    } else if p > 1.0 - P_LOW {
+        -tail((-2.0 * (1.0 - p).ln()).sqrt())
+    } else {
+        let q = p - 0.5;
+        let r = q * q;
+        (((((A[0] * r + A[1]) * r + A[2]) * r + A[3]) * r + A[4]) * r + A[5]) * q
+            / (((((B[0] * r + B[1]) * r + B[2]) * r + B[3]) * r + B[4]) * r + 1.0)
+    }
+}
+
+/// ln Γ(x)，x ≥ 0.5 (Lanczos 逼近，g = 7)
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFS: [f64; 9] = [
+        0.999_999_999_999_809_9, 676.520_368_121_885_1, -1_259.139_216_722_402_8, 771.323_428_777_653_1,
+        -176.615_029_162_140_6, 12.507_343_278_686_905, -0.138_571_095_265_720_12, 9.984_369_578_019_572e-6, 1.505_632_735_149_311_6e-7,
+    ];
+    let x = x - 1.0;
+    let series = COEFFS[0] + COEFFS[1..].iter().enumerate().map(|(i, c)| c / (x + i as f64 + 1.0)).sum::<f64>();
+    let t = x + 7.5;
+    0.5 * (2.0 * std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + series.ln()
+}
+
+/// 正则化不完全 Beta 函数 I_x(a, b)，用连分式 (修正的 Lentz 算法) 计算
+fn regularized_incomplete_beta(x: f64, a: f64, b: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // 连分式在 x < (a + 1) / (a + b + 2) 时收敛快，否则用 I_x(a, b) = 1 - I_{1-x}(b, a)
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(x, a, b) / a
+    } else {
+        1.0 - front * beta_continued_fraction(1.0 - x, b, a) / b
+    }
+}
+
+fn beta_continued_fraction(x: f64, a: f64, b: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    let guard = |v: f64| if v.abs() < TINY { TINY } else { v };
+    let mut c = 1.0;
+    let mut d = 1.0 / guard(1.0 - (a + b) * x / (a + 1.0));
+    let mut h = d;
+    for m in 1..=300 {
+        let m = m as f64;
+        // 偶数项与奇数项
+        let even = m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m));
+        d = 1.0 / guard(1.0 + even * d);
+        c = guard(1.0 + even / c);
+        h *= d * c;
+        let odd = -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0));
+        d = 1.0 / guard(1.0 + odd * d);
+        c = guard(1.0 + odd / c);
+        let step = d * c;
+        h *= step;
+        if (step - 1.0).abs() < 1e-15 {
+            break;
+        }
+    }
+    h
+}
+
+/// Pearson r 的检验结果
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CorrelationTest {
+    /// 给定置信度下 r 的置信区间 (下限, 上限)
+    pub interval: (f64, f64),
+    /// r 显著高于最低可接受值 (置信区间下限高于该值)
+    pub passes: bool,
+}
+
+/// 按 Fisher z 变换 (z = atanh r，标准误 1/√(n-3)) 求 r 的置信区间，并检验 r 是否显著高于 min_r：
+/// 区间下限高于 min_r 时通过，相当于显著性水平为 (1 - 置信度) / 2 的单侧检验 H0: ρ ≤ min_r。
+/// 点数不超过 3 或 r 无法计算 (如曲线为常数) 时为 None
+pub fn correlation_test(r: f64, n: usize, min_r: f64, confidence: f64) -> Option<CorrelationTest> {
+    if n <= 3 || !r.is_finite() {
+        return None;
+    }
+    let z = r.clamp(-1.0, 1.0).atanh();
+    let half_width = critical_value(confidence) / ((n - 3) as f64).sqrt();
+    let interval = ((z - half_width).tanh(), (z + half_width).tanh());
+    Some(CorrelationTest { interval, passes: interval.0 > min_r })
+}
+
+/// 两条曲线允许的最大时长差 (秒)，超过则无法逐点对比
+pub const MAX_DURATION_DIFF_SEC: f64 = 2.0;
+
+/// 检查两条曲线的时长是否足够接近以进行逐点对比
+pub fn durations_comparable(a: &AudioCurve, b: &AudioCurve) -> bool {
+    (a.duration - b.duration).abs() <= MAX_DURATION_DIFF_SEC
+}
+
+/// 逐点对比两条曲线并计算统计量 (纯计算，不涉及 UI 状态，可在工作线程中调用)
+/// target_c 为 T 检验的目标平均差值 (A - B)；keep_aligned 为 true 时在结果中保留配对后的 a_vals / b_vals。
+/// 按索引配对，要求两条曲线的时间基相同 ([`same_time_base`])；否则用 [`compare`] 重采样后对比
+pub fn compare_curves(a: &AudioCurve, b: &AudioCurve, target_c: f64, keep_aligned: bool) -> ComparisonResult {
+    // 1. 计算差值和收集原始数据点
+    let len = std::cmp::min(a.points.len(), b.points.len());
+    let pairs = a.points[..len].iter().zip(&b.points[..len]);
+    let mut diff_vals = Vec::with_capacity(len);
+    let mut diff_points = Vec::with_capacity(len);
+    let mut agreement_points = Vec::with_capacity(len);
+
+    for (pa, pb) in pairs.clone() {
+        let diff = pa[1] - pb[1];
+        diff_vals.push(diff);
+        diff_points.push([pa[0], diff]);
+        agreement_points.push([(pa[1] + pb[1]) / 2.0, diff]);
+    }
+    let (a_vals, b_vals) = if keep_aligned {
+        pairs.clone().map(|(pa, pb)| (pa[1], pb[1])).unzip()
+    } else {
+        (Vec::new(), Vec::new())
+    };
+
+    // 2. 统计
+    let mean = diff_vals.iter().sum::<f64>() / len as f64;
+    let variance: f64 = diff_vals.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (len as f64 - 1.0).max(1.0);
+    let std_dev = variance.sqrt();
+    let max_diff = diff_vals.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
+    let min_diff = diff_vals.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+
+    let correlation_coefficient = pearson_correlation(pairs.map(|(pa, pb)| (pa[1], pb[1])));
+
+    // T 统计量检验 (实际平均差值 - 目标平均差值) 是否显著不为 0
+    let t_statistic = calculate_t_statistic(mean - target_c, std_dev, len);
+
+    // 逐点对比只覆盖到较短一侧的最后一个点，计算其占较长曲线 (到最后一个点) 的比例
+    let longest = curve_end(a).max(curve_end(b));
+    let overlap_ratio = if len == 0 {
+        0.0
+    } else if longest <= 0.0 {
+        1.0
+    } else {
+        (a.points[len - 1][0].min(b.points[len - 1][0]) / longest).clamp(0.0, 1.0)
+    };
+
+    ComparisonResult {
+        mean_diff: mean,
+        std_dev,
+        max_diff,
+        min_diff,
+        correlation_coefficient,
+        t_statistic,
+        overlap_ratio,
+        b_offset: 0.0,
+        level_offset: None,
+        resampled: false,
+        diff_points,
+        agreement_points,
+        a_vals,
+        b_vals,
+    }
+}
+
+/// 曲线在时间 t 处的线性插值；超出曲线时间范围时返回 None
+pub fn interpolate_at(points: &[[f64; 2]], t: f64) -> Option<f64> {
+    let (first, last) = (points.first()?, points.last()?);
+    if t < first[0] || t > last[0] {
+        return None;
+    }
+    let i = points.partition_point(|p| p[0] < t);
+    if i == 0 {
+        return Some(first[1]);
+    }
+    let (p0, p1) = (points[i - 1], points[i]);
+    let span = p1[0] - p0[0];
+    Some(if span <= 0.0 { p1[1] } else { p0[1] + (p1[1] - p0[1]) * (t - p0[0]) / span })
+}
+
+/// 曲线最后一个点的时间；WAV 曲线的时长包含最后一个窗口中心之后的半个窗口，重叠比例按点计算
+fn curve_end(curve: &AudioCurve) -> f64 {
+    curve.points.last().map_or(curve.duration, |p| p[0])
+}
+
+/// 一处响度跳变：within_sec 之内电平变化超过阈值 (如剪辑点前后增益不一致)
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoudnessJump {
+    /// 变化前的点的时间 (秒)
+    pub start: f64,
+    /// 变化后的点的时间 (秒)
+    pub end: f64,
+    /// 电平变化 (dB)，正值为变响
+    pub change_db: f64,
+}
+
+/// 扫描曲线点中的响度跳变：每个点与其后 within_sec 之内的点比较，变化不小于 threshold_db 的一对点为候选；
+/// 时间上重叠且方向相同的候选合并为一个事件，取变化最大的一对。处于底噪 (SILENCE_FLOOR_DBFS) 的静音点不参与比较。
+/// 只用已有的曲线点，修改阈值后重新扫描不需要重新读取音频
+pub fn find_loudness_jumps(points: &[[f64; 2]], threshold_db: f64, within_sec: f64) -> Vec<LoudnessJump> {
+    let audible: Vec<[f64; 2]> = points.iter().copied().filter(|p| p[1] > SILENCE_FLOOR_DBFS).collect();
+    let mut jumps: Vec<LoudnessJump> = Vec::new();
+    // 最后一个事件的全部候选覆盖到的时间，用于判断新候选是否与它重叠
+    let mut covered_until = f64::NEG_INFINITY;
+    for (i, &[start, before]) in audible.iter().enumerate() {
+        for &[end, after] in audible[i + 1..].iter().take_while(|p| p[0] - start <= within_sec) {
+            let change_db = after - before;
+            if change_db.abs() < threshold_db {
+                continue;
+            }
+            match jumps.last_mut() {
+                Some(last) if start <= covered_until && last.change_db.signum() == change_db.signum() => {
+                    // 变化相同时取时间跨度最短的一对，更接近实际的剪辑点
+                    let larger = change_db.abs() - last.change_db.abs();
+                    if larger > 1e-9 || (larger.abs() <= 1e-9 && end - start < last.end - last.start) {
+                        *last = LoudnessJump { start, end, change_db };
+                    }
+                    covered_until = covered_until.max(end);
+                }
+                _ => {
+                    jumps.push(LoudnessJump { start, end, change_db });
+                    covered_until = end;
+                }
+            }
+        }
+    }
+    jumps
+}
+
+/// 相邻点时间间隔的中位数 (秒)；少于两个点时返回 None
+pub fn median_spacing(points: &[[f64; 2]]) -> Option<f64> {
+    let mut gaps: Vec<f64> = points.windows(2).map(|w| w[1][0] - w[0][0]).collect();
+    if gaps.is_empty() {
+        return None;
+    }
+    gaps.sort_by(f64::total_cmp);
+    Some(gaps[gaps.len() / 2])
+}
+
+/// 两条曲线的时间基是否相同：点间隔中位数和第一个点的时间都相差不超过间隔的 1%。
+/// 例如 0.5s 步进导出的 CSV 与 0.1s 步进的 WAV 曲线不能按索引配对
+pub fn same_time_base(a: &AudioCurve, b: &AudioCurve) -> bool {
+    match (median_spacing(&a.points), median_spacing(&b.points), a.points.first(), b.points.first()) {
+        (Some(spacing_a), Some(spacing_b), Some(first_a), Some(first_b)) => {
+            let tolerance = 0.01 * spacing_a.max(spacing_b);
+            (spacing_a - spacing_b).abs() <= tolerance && (first_a[0] - first_b[0]).abs() <= tolerance
+        }
+        // 少于两个点，没有可比较的间隔
+        _ => true,
+    }
+}
+
+/// 减去曲线自身的平均电平，用于对比前的电平匹配 (只保留对比需要的点)
+pub fn level_matched(curve: &AudioCurve) -> AudioCurve {
+    let points = curve.points.iter().map(|p| [p[0], p[1] - curve.average_dbfs]).collect();
+    AudioCurve::new(curve.name.clone(), points, curve.duration, 0.0)
+}
+
+/// 将 Track B 在时间轴上平移 offset 秒后再对比 (正值表示 B 向后移)。
+/// 一条曲线在另一条 (点较疏的一条) 的各时间点上线性插值，只保留两者重叠的部分；
+/// offset 为 0 且时间基相同时等同于 compare_curves
+pub fn compare_curves_with_offset(a: &AudioCurve, b: &AudioCurve, offset: f64, target_c: f64, keep_aligned: bool) -> ComparisonResult {
+    let resampled = !same_time_base(a, b);
+    if offset == 0.0 && !resampled {
+        return compare_curves(a, b, target_c, keep_aligned);
+    }
+
+    // 插值到较密的网格上会让同一段数据被重复计数，因此以较疏的一条为网格；时间一律为 A 的时间轴
+    let b_coarser = match (median_spacing(&a.points), median_spacing(&b.points)) {
+        (Some(spacing_a), Some(spacing_b)) => spacing_b > spacing_a * 1.01,
+        _ => false,
+    };
+    let (a_points, b_points): (Vec<[f64; 2]>, Vec<[f64; 2]>) = if b_coarser {
+        b.points.iter()
+            .filter_map(|p| {
+                let t = p[0] + offset;
+                interpolate_at(&a.points, t).map(|v| ([t, v], [t, p[1]]))
+            })
+            .unzip()
+    } else {
+        a.points.iter()
+            .filter_map(|p| interpolate_at(&b.points, p[0] - offset).map(|v| (*p, [p[0], v])))
+            .unzip()
+    };
+    let span = match (a_points.first(), a_points.last()) {
+        (Some(first), Some(last)) => last[0] - first[0],
+        _ => 0.0,
+    };
+    let a_aligned = AudioCurve::new(a.name.clone(), a_points, a.duration, a.average_dbfs);
+    let b_aligned = AudioCurve::new(b.name.clone(), b_points, b.duration, b.average_dbfs);
+
+    let mut result = compare_curves(&a_aligned, &b_aligned, target_c, keep_aligned);
+    // 重叠部分不再从 0 秒开始，按实际跨度重新计算
+    let longest = curve_end(a).max(curve_end(b));
+    result.overlap_ratio = if longest <= 0.0 { 1.0 } else { (span / longest).clamp(0.0, 1.0) };
+    result.b_offset = offset;
+    result.resampled = resampled;
+    result
+}
+
+/// [`compare`] 的选项
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CompareOptions {
+    /// 对比前 Track B 的时间平移 (秒)
+    pub offset: f64,
+    /// t 检验的目标均差 (dB)
+    pub target_diff: f64,
+    /// 先各自减去平均电平，只比较动态差异
+    pub level_match: bool,
+    /// 在结果中保留配对后的 a_vals / b_vals (导出、逐点分析用)；批量对比等只需要统计量时不保留以节省内存
+    pub keep_aligned: bool,
+}
+
+/// 对比两条曲线 (A - B)：按选项平移 Track B 并可先做电平匹配。不检查时长，调用前可用 [`durations_comparable`] 判断。
+/// 平移后两条曲线没有重叠的点 (如 offset 超过较短曲线的时长) 时没有可统计的差值，返回 None
+pub fn compare(a: &AudioCurve, b: &AudioCurve, options: &CompareOptions) -> Option<ComparisonResult> {
+    let result = if options.level_match {
+        let mut result = compare_curves_with_offset(&level_matched(a), &level_matched(b), options.offset, options.target_diff, options.keep_aligned);
+        result.level_offset = Some(a.average_dbfs - b.average_dbfs);
+        result
+    } else {
+        compare_curves_with_offset(a, b, options.offset, options.target_diff, options.keep_aligned)
+    };
+    (!result.diff_points.is_empty()).then_some(result)
+}
+
+
+/// 按 WAV 格式规范逐个读取样本 (交错排列) 并归一化到 [-1.0, 1.0]，对每个样本调用 f。
+/// 支持 16/24/32-bit PCM 和 32-bit Float；不支持的格式返回错误信息。
+/// 按帧 (每个声道一个样本) 遍历 WAV，末尾不完整的帧会被丢弃
+pub fn for_each_wav_frame<R: std::io::Read>(reader: &mut WavReader<R>, mut f: impl FnMut(&[f64])) -> Result<(), AnalyzerError> {
+    try_for_each_wav_frame(reader, |frame| {
+        f(frame);
+        Ok(())
+    })
+}
+
+/// 与 for_each_wav_frame 相同，f 返回错误时停止读取并返回该错误
+fn try_for_each_wav_frame<R: std::io::Read>(reader: &mut WavReader<R>, mut f: impl FnMut(&[f64]) -> Result<(), AnalyzerError>) -> Result<(), AnalyzerError> {
+    let channels = reader.spec().channels as usize;
+    let mut frame = vec![0.0; channels];
+    let mut filled = 0;
+    try_for_each_wav_sample(reader, |s| {
+        frame[filled] = s;
+        filled += 1;
+        if filled < channels {
+            return Ok(());
+        }
+        filled = 0;
+        f(&frame)
+    })
+}
+
+/// 内存映射的 WAV 文件及其 data 块在映射中的字节范围
+#[cfg(not(target_arch = "wasm32"))]
+pub struct MappedWav {
+    map: memmap2::Mmap,
+    data: std::ops::Range<usize>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MappedWav {
+    /// 映射文件并定位 data 块；data 块长度必须恰好为 `sample_count` 个紧密排列的样本，
+    /// 否则 (被截断、24-bit 放在 32-bit 容器中等) 返回 None，由调用方退回 WavReader
+    pub fn open(path: &Path, spec: hound::WavSpec, sample_count: usize) -> Option<Self> {
+        let file = File::open(path).ok()?;
+        // SAFETY: 映射只读；解析期间文件被其他进程修改时只会读到错误的样本值
+        let map = unsafe { memmap2::Mmap::map(&file) }.ok()?;
+        if map.get(0..4)? != b"RIFF" || map.get(8..12)? != b"WAVE" {
+            return None;
+        }
+        let mut pos = 12;
+        while let Some(chunk_header) = map.get(pos..pos + 8) {
+            let size = u32::from_le_bytes(chunk_header[4..8].try_into().ok()?) as usize;
+            if &chunk_header[0..4] == b"data" {
+                let bytes = spec.bits_per_sample.div_ceil(8) as usize;
+                let data = pos + 8..pos + 8 + size;
+                return (size == sample_count * bytes && data.end <= map.len()).then_some(Self { map, data });
+            }
+            // 块按偶数字节对齐
+            pos += 8 + size + (size & 1);
+        }
+        None
+    }
+
+    /// 与 for_each_wav_frame 相同，但直接从映射的字节解析样本
+    pub fn for_each_frame(&self, spec: hound::WavSpec, mut f: impl FnMut(&[f64])) -> Result<(), AnalyzerError> {
+        self.try_for_each_frame(spec, |frame| {
+            f(frame);
+            Ok(())
+        })
+    }
+
+    /// 与 for_each_frame 相同，f 返回错误时停止解析并返回该错误
+    fn try_for_each_frame(&self, spec: hound::WavSpec, mut f: impl FnMut(&[f64]) -> Result<(), AnalyzerError>) -> Result<(), AnalyzerError> {
+        let data = &self.map[self.data.clone()];
+        let channels = spec.channels as usize;
+        let decode: fn(&[u8]) -> f64 = match (spec.sample_format, spec.bits_per_sample) {
+            (hound::SampleFormat::Int, 16) => |b| i16::from_le_bytes([b[0], b[1]]) as f64 / (1u32 << 15) as f64,
+            // 24-bit 放到 i32 高位再算术右移，完成符号扩展
+            (hound::SampleFormat::Int, 24) => |b| (i32::from_le_bytes([0, b[0], b[1], b[2]]) >> 8) as f64 / (1u32 << 23) as f64,
+            (hound::SampleFormat::Int, 32) => |b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64 / (1u64 << 31) as f64,
+            (hound::SampleFormat::Float, 32) => |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64,
+            _ => {
+                return Err(AnalyzerError::UnsupportedFormat { format: spec.sample_format, bits: spec.bits_per_sample });
+            }
+        };
+        let bytes = spec.bits_per_sample.div_ceil(8) as usize;
+        let mut frame = vec![0.0; channels];
+        for block in data.chunks_exact(bytes * channels) {
+            for (sample, b) in frame.iter_mut().zip(block.chunks_exact(bytes)) {
+                *sample = decode(b);
+            }
+            f(&frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// 浏览器中没有内存映射：不存在任何值，解析总是退回 WavReader
+#[cfg(target_arch = "wasm32")]
+pub enum MappedWav {}
+
+#[cfg(target_arch = "wasm32")]
+impl MappedWav {
+    /// 总是返回 None
+    pub fn open(_path: &Path, _spec: hound::WavSpec, _sample_count: usize) -> Option<Self> {
+        None
+    }
+
+    /// 不可能被调用 (没有 MappedWav 的值)
+    pub fn for_each_frame(&self, _spec: hound::WavSpec, _f: impl FnMut(&[f64])) -> Result<(), AnalyzerError> {
+        match *self {}
+    }
+
+    fn try_for_each_frame(&self, _spec: hound::WavSpec, _f: impl FnMut(&[f64]) -> Result<(), AnalyzerError>) -> Result<(), AnalyzerError> {
+        match *self {}
+    }
+}
+
+/// 逐个读取样本并归一化到 [-1, 1] (交错顺序，不区分声道)
+pub fn for_each_wav_sample<R: std::io::Read>(reader: &mut WavReader<R>, mut f: impl FnMut(f64)) -> Result<(), AnalyzerError> {
+    try_for_each_wav_sample(reader, |s| {
+        f(s);
+        Ok(())
+    })
+}
+
+/// 与 for_each_wav_sample 相同，并按已读样本数报告进度；`progress` 被取消时停止读取并返回 Cancelled
+pub fn for_each_wav_sample_with_progress<R: std::io::Read>(reader: &mut WavReader<R>, progress: &mut dyn Progress, mut f: impl FnMut(f64)) -> Result<(), AnalyzerError> {
+    let total = reader.len() as usize;
+    let report_every = (total / PROGRESS_STEPS).max(1);
+    let mut read = 0;
+    try_for_each_wav_sample(reader, |s| {
+        f(s);
+        read += 1;
+        if read % report_every == 0 {
+            report_progress(progress, read as f32 / total as f32)?;
+        }
+        Ok(())
+    })?;
+    progress.report(1.0);
+    Ok(())
+}
+
+/// 读取样本时的错误：data 块比头部声明的短 (文件被截断) 时不是读写故障，而是文件本身已损坏。
+/// hound 把读到文件末尾报告为 ErrorKind::Other ("Failed to read enough bytes.")
+fn sample_error(err: hound::Error) -> AnalyzerError {
+    match err {
+        hound::Error::IoError(err) if matches!(err.kind(), std::io::ErrorKind::UnexpectedEof | std::io::ErrorKind::Other) => {
+            AnalyzerError::DecodeFailed(format!("data chunk is truncated ({})", err))
+        }
+        other => other.into(),
+    }
+}
+
+/// 与 for_each_wav_sample 相同，f 返回错误时停止读取并返回该错误；无法解码的样本 (如被截断的 data 块) 返回错误
+fn try_for_each_wav_sample<R: std::io::Read>(reader: &mut WavReader<R>, mut f: impl FnMut(f64) -> Result<(), AnalyzerError>) -> Result<(), AnalyzerError> {
+    let spec = reader.spec();
+    match (spec.sample_format, spec.bits_per_sample) {
+        // 16-bit Integer PCM (Read as i16, max value is 2^15)
+        (hound::SampleFormat::Int, 16) => {
+            let max_val = 1u32 << 15;
+            reader.samples::<i16>()
+                .try_for_each(|s| f(s.map_err(sample_error)? as f64 / max_val as f64))?;
+        }
+        // 24-bit Integer PCM (Read as i32, max value is 2^23)
+        (hound::SampleFormat::Int, 24) => {
+            let max_val = 1u32 << 23;
+            reader.samples::<i32>()
+                .try_for_each(|s| f(s.map_err(sample_error)? as f64 / max_val as f64))?;
+        }
+        // 32-bit Integer PCM (Read as i32, max value is 2^31)
+        (hound::SampleFormat::Int, 32) => {
+            let max_val = 1u64 << 31;
+            reader.samples::<i32>()
+                .try_for_each(|s| f(s.map_err(sample_error)? as f64 / max_val as f64))?;
+        }
+        // 32-bit Float (Read as f32, already normalized [-1.0, 1.0])
+        (hound::SampleFormat::Float, 32) => {
+            reader.samples::<f32>()
+                .try_for_each(|s| f(s.map_err(sample_error)? as f64))?;
+        }
+        // Fallback for unsupported formats
+        _ => {
+            return Err(AnalyzerError::UnsupportedFormat { format: spec.sample_format, bits: spec.bits_per_sample });
+        }
+    }
+    Ok(())
+}
+
+/// 解析、加载曲线时的错误。Display 为简短的英文技术描述，界面按变体给出本地化说明和处理建议
+#[derive(Debug)]
+pub enum AnalyzerError {
+    /// 不支持的 WAV 采样格式 (支持 16/24/32-bit PCM 和 32-bit float)
+    UnsupportedFormat {
+        /// 采样格式
+        format: hound::SampleFormat,
+        /// 位深
+        bits: u16,
+    },
+    /// WAV 有合法的文件头但没有任何音频帧 (只有文件头，或 data 块为空/被截断)
+    EmptyFile,
+    /// 文件头或样本数据损坏，无法解码
+    DecodeFailed(String),
+    /// CSV 中没有任何一行能按列布局解析出时间和电平；`line` 为第一处出错的行号 (从 1 开始)
+    CsvSchema {
+        /// 出错的行号
+        line: usize,
+        /// 出错原因
+        detail: String,
+    },
+    /// 读取文件失败
+    Io(std::io::Error),
+    /// 从 URL 下载失败 (HTTP 错误状态、连接失败等)
+    Download(String),
+    /// 下载的内容不是音频 (如 HTML 登录页)；内容为服务器给出的 Content-Type
+    NotAudio(String),
+    /// 任务被取消
+    Cancelled,
+}
+
+impl std::fmt::Display for AnalyzerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AnalyzerError::UnsupportedFormat { format, bits } => write!(f, "unsupported WAV format: {}-bit {:?}", bits, format),
+            AnalyzerError::EmptyFile => write!(f, "file contains no audio data"),
+            AnalyzerError::DecodeFailed(detail) => write!(f, "cannot decode file: {}", detail),
+            AnalyzerError::CsvSchema { line, detail } => write!(f, "no usable rows in CSV (line {}: {})", line, detail),
+            AnalyzerError::Io(err) => err.fmt(f),
+            AnalyzerError::Download(detail) => write!(f, "download failed: {}", detail),
+            AnalyzerError::NotAudio(content_type) => write!(f, "server did not return audio (Content-Type: {})", content_type),
+            AnalyzerError::Cancelled => write!(f, "cancelled"),
+        }
+    }
+}
+
+impl Error for AnalyzerError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AnalyzerError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// io::Error 无法序列化：保存系统错误码 (用于恢复 ErrorKind 和 is_retryable 判断) 及错误文本
+#[derive(Serialize, Deserialize)]
+enum AnalyzerErrorRepr {
+    UnsupportedFormat {
+        #[serde(with = "SampleFormatDef")]
+        format: hound::SampleFormat,
+        bits: u16,
+    },
+    EmptyFile,
+    DecodeFailed(String),
+    CsvSchema { line: usize, detail: String },
+    Io { message: String, raw_os_error: Option<i32> },
+    Download(String),
+    NotAudio(String),
+    Cancelled,
+}
+
+impl Serialize for AnalyzerError {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let repr = match self {
+            AnalyzerError::UnsupportedFormat { format, bits } => AnalyzerErrorRepr::UnsupportedFormat { format: *format, bits: *bits },
+            AnalyzerError::EmptyFile => AnalyzerErrorRepr::EmptyFile,
+            AnalyzerError::DecodeFailed(detail) => AnalyzerErrorRepr::DecodeFailed(detail.clone()),
+            AnalyzerError::CsvSchema { line, detail } => AnalyzerErrorRepr::CsvSchema { line: *line, detail: detail.clone() },
+            AnalyzerError::Io(err) => AnalyzerErrorRepr::Io { message: err.to_string(), raw_os_error: err.raw_os_error() },
+            AnalyzerError::Download(detail) => AnalyzerErrorRepr::Download(detail.clone()),
+            AnalyzerError::NotAudio(content_type) => AnalyzerErrorRepr::NotAudio(content_type.clone()),
+            AnalyzerError::Cancelled => AnalyzerErrorRepr::Cancelled,
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for AnalyzerError {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Ok(match AnalyzerErrorRepr::deserialize(deserializer)? {
+            AnalyzerErrorRepr::UnsupportedFormat { format, bits } => AnalyzerError::UnsupportedFormat { format, bits },
+            AnalyzerErrorRepr::EmptyFile => AnalyzerError::EmptyFile,
+            AnalyzerErrorRepr::DecodeFailed(detail) => AnalyzerError::DecodeFailed(detail),
+            AnalyzerErrorRepr::CsvSchema { line, detail } => AnalyzerError::CsvSchema { line, detail },
+            AnalyzerErrorRepr::Io { raw_os_error: Some(code), .. } => AnalyzerError::Io(std::io::Error::from_raw_os_error(code)),
+            AnalyzerErrorRepr::Io { message, raw_os_error: None } => AnalyzerError::Io(std::io::Error::other(message)),
+            AnalyzerErrorRepr::Download(detail) => AnalyzerError::Download(detail),
+            AnalyzerErrorRepr::NotAudio(content_type) => AnalyzerError::NotAudio(content_type),
+            AnalyzerErrorRepr::Cancelled => AnalyzerError::Cancelled,
+        })
+    }
+}
+
+impl From<std::io::Error> for AnalyzerError {
+    fn from(err: std::io::Error) -> Self {
+        AnalyzerError::Io(err)
+    }
+}
+
+impl From<hound::Error> for AnalyzerError {
+    fn from(err: hound::Error) -> Self {
+        match err {
+            hound::Error::IoError(err) => AnalyzerError::Io(err),
+            other => AnalyzerError::DecodeFailed(other.to_string()),
+        }
+    }
+}
+
+impl AnalyzerError {
+    /// 文件被其他程序占用或暂时无权限，稍后重试可能成功
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, AnalyzerError::Io(err) if is_file_in_use(err) || err.kind() == std::io::ErrorKind::PermissionDenied)
+    }
+}
+
+/// 路径的文件名，用作曲线名称和日志；没有文件名的路径 (如 `..`、`/`) 显示完整路径
+pub fn file_display_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_else(|| path.display().to_string())
+}
+
+/// 解析期间进度回调的调用次数上限 (约每 1% 一次)
+const PROGRESS_STEPS: usize = 100;
+
+/// 解析进度的接收端。`FnMut(f32)` 闭包可直接使用；
+/// 可取消的调用方实现 `is_cancelled`，解析在下一次报告进度时返回 [`AnalyzerError::Cancelled`]
+pub trait Progress {
+    /// 报告进度 (0.0 - 1.0)
+    fn report(&mut self, fraction: f32);
+    /// 调用方是否已取消解析
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl<F: FnMut(f32)> Progress for F {
+    fn report(&mut self, fraction: f32) {
+        self(fraction);
+    }
+}
+
+/// 报告进度；调用方已取消时返回 Cancelled，由解析流程向上传递
+fn report_progress(progress: &mut dyn Progress, fraction: f32) -> Result<(), AnalyzerError> {
+    if progress.is_cancelled() {
+        return Err(AnalyzerError::Cancelled);
+    }
+    progress.report(fraction);
+    Ok(())
+}
+
+/// 【已修复】解析 WAV 文件，支持 16/24/32-bit PCM 和 32-bit Float 格式。
+/// `progress` 随解析推进以 0.0 - 1.0 调用；不需要进度时传入 `&mut |_| {}`
+pub fn parse_wav(path: PathBuf, analysis: &AnalysisSettings, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    parse_wav_with_metrics(path, analysis, &MetricRegistry::builtin(), logger, progress)
+}
+
+/// 与 [`parse_wav`] 相同，但运行给定的指标列表 (应包含内置的平均电平指标，否则平均电平为 SILENCE_FLOOR_DBFS)
+pub fn parse_wav_with_metrics(path: PathBuf, analysis: &AnalysisSettings, metrics: &MetricRegistry, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    let filename = file_display_name(&path);
+    log_info(logger, &format!("▶️ 开始解析 WAV 文件: {}", filename));
+
+    let reader = WavReader::open(&path)?;
+    let mapped = if analysis.use_mmap { MappedWav::open(&path, reader.spec(), reader.len() as usize) } else { None };
+    if analysis.use_mmap && mapped.is_none() {
+        log_debug(logger, "内存映射不可用，使用 WavReader 读取样本");
+    }
+    let input = WavInput { name: filename, reader, channel_mask: read_wav_channel_mask(&path), mapped };
+    let (mut curve, wav_details, timing) = analyze_wav(input, analysis, metrics, logger, progress)?;
+    curve.details = Some(SourceDetails::new(&path, Some(wav_details), timing));
+    curve.source = Some(path);
+    Ok(curve)
+}
+
+/// 解析内存中的 WAV 数据 (如浏览器文件选择器或拖放得到的字节，没有可打开的路径)。
+/// `name` 作为曲线名称；结果与从文件解析相同，但没有来源路径，不能重新分析
+pub fn parse_wav_bytes(name: &str, bytes: &[u8], analysis: &AnalysisSettings, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    log_info(logger, &format!("▶️ 开始解析 WAV 数据: {} ({} 字节)", name, bytes.len()));
+
+    let reader = WavReader::new(std::io::Cursor::new(bytes))?;
+    let channel_mask = read_wav_channel_mask_from(std::io::Cursor::new(bytes));
+    let input = WavInput { name: name.to_string(), reader, channel_mask, mapped: None };
+    let (mut curve, wav_details, timing) = analyze_wav(input, analysis, &MetricRegistry::builtin(), logger, progress)?;
+    curve.details = Some(SourceDetails { path: PathBuf::from(name), file_size: bytes.len() as u64, wav: Some(wav_details), timing });
+    Ok(curve)
+}
+
+/// 待解析的 WAV：文件或内存中的数据
+struct WavInput<R> {
+    name: String,
+    reader: WavReader<R>,
+    channel_mask: Option<u32>,
+    // 可用内存映射时直接从映射的字节解析样本
+    mapped: Option<MappedWav>,
+}
+
+/// WAV 解析流程：读取样本、计算曲线、频段、响度计量和指标。
+/// 来源相关的字段 (details、source) 由调用方填写
+fn analyze_wav<R: std::io::Read>(input: WavInput<R>, analysis: &AnalysisSettings, metrics: &MetricRegistry, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<(AudioCurve, WavDetails, LoadTiming), AnalyzerError> {
+    let WavInput { name: filename, mut reader, channel_mask, mapped } = input;
+    let spec = reader.spec();
+    // 文件头声明的每声道帧数，即文件时长
+    let header_frames = reader.duration();
+
+    log_debug(logger, &format!("WAV Spec: Rate={}Hz, Channels={}, Bits={}, Format={:?}", spec.sample_rate, spec.channels, spec.bits_per_sample, spec.sample_format));
+
+    // 只有文件头的 WAV 在读取样本前直接报告，不进入加窗流程
+    if reader.len() == 0 || spec.channels == 0 {
+        log_error(logger, &format!("⚠️ WAV 文件没有音频数据: {}", filename));
+        return Err(AnalyzerError::EmptyFile);
+    }
+
+    // 根据 WAV 文件的格式规范读取并归一化样本；启用下混时在此阶段逐帧合成单声道。
+    // 同时逐帧计算各频段能量 (与主曲线使用相同的声道合并方式)，供多频段视图使用
+    let file_channels = spec.channels as usize;
+    let downmix = file_channels > 1 && analysis.downmix != DownmixMode::Interleaved;
+    let speakers = channel_speakers(file_channels, channel_mask);
+    let weights = bs1770_channel_weights(&speakers);
+    let coeffs = analysis.downmix_coefficients(&speakers);
+    match analysis.downmix {
+        _ if !downmix => {}
+        DownmixMode::Bs1770 => log_debug(logger, &format!("声道布局: {:?}, BS.1770 权重: {:?}", speakers, weights)),
+        _ => log_debug(logger, &format!("声道布局: {:?}, 下混矩阵: {:?}", speakers, coeffs)),
+    }
+
+    let frame_count = reader.len() as usize / file_channels.max(1);
+    let mut samples: Vec<f64> = Vec::with_capacity(if downmix { frame_count } else { frame_count * file_channels });
+    let mut band_energies: Vec<[f64; BAND_COUNT]> = Vec::with_capacity(frame_count);
+    let splitter = BandSplitter::new(spec.sample_rate as f64);
+    let mut splitters = vec![splitter; file_channels];
+    // 交付规格检查用的 BS.1770 计量 (始终使用原始声道，与下混方式无关)
+    let mut meter = LoudnessMeter::new(spec.sample_rate, weights.clone());
+    // 曲线计权滤波器 (每个声道一组)，计量器使用自己的 K 计权，不受此设置影响
+    let mut curve_filters = analysis.weighting.filters(spec.sample_rate as f64).map(|chain| vec![chain; file_channels]);
+    let mut weighted_frame = vec![0.0; file_channels];
+    // 分析范围 (帧)：跳过开头 / 结尾后什么都不剩时忽略该设置，分析整个文件
+    let sample_rate = spec.sample_rate as usize;
+    let [range_start_sec, range_end_sec] = analysis.analysis_range(frame_count as f64 / sample_rate.max(1) as f64);
+    let mut range = (range_start_sec * sample_rate as f64).round() as usize..(range_end_sec * sample_rate as f64).round() as usize;
+    if range.is_empty() {
+        if analysis.trims_range() {
+            log_error(logger, &format!("⚠️ {} 跳过开头 {:.2}s / 结尾 {:.2}s 后没有剩余音频，分析整个文件", filename, analysis.skip_head_sec, analysis.skip_tail_sec));
+        }
+        range = 0..frame_count;
+    } else if analysis.trims_range() {
+        log_info(logger, &format!("ℹ️ 分析范围 {:.2}s - {:.2}s", range_start_sec, range_end_sec));
+    }
+    // 读取样本占解析耗时的绝大部分，按已读帧数报告 0 - 90% 的进度
+    let report_every = (frame_count / PROGRESS_STEPS).max(1);
+    let mut frames_read = 0;
+
+    // 解码样本的内容哈希 (计权前，与分析参数无关)，格式不同的相同样本不算重复
+    let mut content_hasher = ContentHasher::default();
+    content_hasher.write_u64(spec.sample_rate as u64);
+    content_hasher.write_u64(spec.channels as u64);
+
+    let read_started = Instant::now();
+    let mut on_frame = |frame: &[f64]| {
+        frames_read += 1;
+        frame.iter().for_each(|&s| content_hasher.write_f64(s));
+        if frames_read % report_every == 0 {
+            report_progress(progress, 0.9 * frames_read as f32 / frame_count.max(1) as f32)?;
+        }
+        // 整体响度只计量分析范围内的帧
+        if range.contains(&(frames_read - 1)) {
+            meter.push_frame(frame);
+        }
+        let frame: &[f64] = match &mut curve_filters {
+            Some(filters) => {
+                for ((y, x), chain) in weighted_frame.iter_mut().zip(frame).zip(filters.iter_mut()) {
+                    *y = chain.iter_mut().fold(*x, |v, filter| filter.process(v));
+                }
+                &weighted_frame
+            }
+            None => frame,
+        };
+        let mut energy = [0.0; BAND_COUNT];
+        match analysis.downmix {
+            _ if !downmix => {
+                // 与交错 RMS 一致：各声道功率取平均
+                samples.extend_from_slice(frame);
+                for (x, splitter) in frame.iter().zip(splitters.iter_mut()) {
+                    for (e, y) in energy.iter_mut().zip(splitter.process(*x)) {
+                        *e += y * y / file_channels as f64;
+                    }
+                }
+            }
+            DownmixMode::Bs1770 => {
+                // 每帧保存加权能量和的平方根：后续 RMS 只用到平方，因此窗口 RMS 即为加权能量
+                let total: f64 = frame.iter().zip(&weights).map(|(s, w)| w * s * s).sum();
+                samples.push(total.sqrt());
+                for ((x, w), splitter) in frame.iter().zip(&weights).zip(splitters.iter_mut()) {
+                    for (e, y) in energy.iter_mut().zip(splitter.process(*x)) {
+                        *e += w * y * y;
+                    }
+                }
+            }
+            _ => {
+                let mono: f64 = frame.iter().zip(&coeffs).map(|(s, c)| s * c).sum();
+                samples.push(mono);
+                for (e, y) in energy.iter_mut().zip(splitters[0].process(mono)) {
+                    *e = y * y;
+                }
+            }
+        }
+        band_energies.push(energy);
+        Ok(())
+    };
+    let read_result = match &mapped {
+        Some(mapped) => mapped.try_for_each_frame(spec, &mut on_frame),
+        None => try_for_each_wav_frame(&mut reader, &mut on_frame),
+    };
+    let decode_sec = read_started.elapsed().as_secs_f64();
+    log_debug(logger, &format!("读取样本耗时 {:.1} ms ({})", decode_sec * 1000.0, if mapped.is_some() { "mmap" } else { "WavReader" }));
+    let windowing_started = Instant::now();
+    if let Err(err) = read_result {
+        match err {
+            AnalyzerError::Cancelled => log_info(logger, &format!("⏹️ 已取消解析: {}", filename)),
+            _ => log_error(logger, &format!("❌ {}: {}", filename, err)),
+        }
+        return Err(err);
+    }
+
+    // 文件头声明了样本但 data 块被截断时，读取后仍可能为空
+    if samples.is_empty() {
+        log_error(logger, &format!("⚠️ WAV 文件没有可读取的样本: {}", filename));
+        return Err(AnalyzerError::EmptyFile);
+    }
+    log_debug(logger, &format!("总样本数: {}", samples.len()));
+
+    let (window_sec, step_sec) = (analysis.window_sec, analysis.step_sec);
+    let channels = if downmix { 1 } else { file_channels };
+
+    if sample_rate == 0 {
+        log_error(logger, "⚠️ 文件头中的采样率为 0，无法计算时间轴。");
+        return Err(AnalyzerError::DecodeFailed("sample rate is 0 Hz".to_string()));
+    }
+
+    // 窗口和步进以帧 (每声道一个样本) 计；不下混时每帧在 samples 中占 channels 个交错样本。
+    // 采样率极低时按秒换算可能不足一帧，至少取 1 帧
+    let mut window_size = (window_sec * sample_rate as f64) as usize;
+    let step_size = ((step_sec * sample_rate as f64) as usize).max(1);
+    if window_size == 0 {
+        log_debug(logger, &format!("采样率 {} Hz 下窗口不足一帧，按 1 帧计算", sample_rate));
+        window_size = 1;
+    }
+
+    // 文件头声明的帧数多于实际读到的帧数 (data 块被截断) 时，分析范围截到实际长度
+    let total_frames = samples.len() / channels;
+    let range = range.start.min(total_frames)..range.end.min(total_frames);
+    let range = if range.is_empty() { 0..total_frames } else { range };
+
+    // 短于一个窗口的文件 (如 UI 音效) 整个文件 (分析范围) 作为一个窗口，得到位于中点的单个点
+    if range.len() < window_size {
+        log_info(logger, &format!(
+            "ℹ️ {} 时长 {:.3}s 短于分析窗口 {:.3}s，整个文件按一个窗口计算",
+            filename, range.len() as f64 / sample_rate as f64, window_sec
+        ));
+        window_size = range.len();
+    }
+
+    let mut points = Vec::new();
+    let mut start = range.start;
+    while start + window_size <= range.end {
+        let window = &samples[start * channels..(start + window_size) * channels];
+        let db = match calculate_rms_dbfs(window) {
+            db if db < analysis.noise_floor_dbfs => SILENCE_FLOOR_DBFS,
+            db => db,
+        };
+        points.push([window_center_sec(start, window_size, sample_rate), db]);
+        start += step_size;
+    }
+
+    // 最后一个完整窗口之后剩余的帧不足一个步进，不单独成窗；记录其时长而不是静默丢弃
+    let analyzed_end = if points.is_empty() { range.start } else { start - step_size + window_size };
+    let unanalyzed_tail_sec = (range.end - analyzed_end) as f64 / sample_rate as f64;
+    if unanalyzed_tail_sec > 0.0 {
+        log_debug(logger, &format!("末尾 {:.3}s 不足一个步进，未计入曲线", unanalyzed_tail_sec));
+    }
+    let wav_details = WavDetails { spec, decoded_samples: (frames_read * file_channels) as u64, window_sec, step_sec, unanalyzed_tail_sec };
+
+    // 时长按文件头的帧数计算，而不是最后一个窗口的中心
+    let duration = header_frames as f64 / sample_rate as f64;
+    let context = AnalysisContext::with_samples(&points, analysis, spec, &samples[range.start * channels..range.end * channels], channels);
+    let metric_values = metrics.run(&context);
+    let average_dbfs = metric_values.get(AVERAGE_METRIC).and_then(|m| m.value()).unwrap_or(SILENCE_FLOOR_DBFS);
+
+    log_info(logger, &format!("✅ 文件解析完成: {} (Duration: {:.2}s, Points: {})", filename, duration, points.len()));
+
+    let mut curve = AudioCurve::new(filename, points, duration, average_dbfs);
+    log_silence_gating(logger, &curve);
+    curve.bands = band_curves(&band_energies[range.clone()], range.start, sample_rate, window_size, step_size);
+    curve.loudness = Some(meter.measurement());
+    // 计量器从分析范围的起点开始计时，换回文件时间
+    let range_start_sec = range.start as f64 / sample_rate as f64;
+    let to_file_time = |curve: Vec<[f64; 2]>| curve.into_iter().map(|[t, v]| [t + range_start_sec, v]).collect();
+    curve.momentary = to_file_time(meter.momentary_curve());
+    curve.short_term = to_file_time(meter.short_term_curve());
+    curve.integrated = to_file_time(meter.integrated_curve());
+    curve.analysis = Some(analysis.clone());
+    curve.metrics = metric_values;
+    curve.sample_hash = Some(content_hasher.finish());
+    let timing = LoadTiming { decode_sec, windowing_sec: windowing_started.elapsed().as_secs_f64() };
+    log_debug(logger, &format!("加窗及指标耗时 {:.1} ms", timing.windowing_sec * 1000.0));
+    progress.report(1.0);
+    Ok((curve, wav_details, timing))
+}
+
+/// 从 start 帧开始、长 window_size 帧的窗口中心时间 (秒)，与声道数无关
+fn window_center_sec(start: usize, window_size: usize, sample_rate: usize) -> f64 {
+    (start as f64 + window_size as f64 / 2.0) / sample_rate as f64
+}
+
+/// CSV 时间列的单位
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CsvTimeUnit {
+    #[default]
+    /// 秒
+    Seconds,
+    /// 毫秒
+    Milliseconds,
+}
+
+/// CSV 响度日志的列布局：其他工具导出的日志列顺序不同，且常带元数据行和额外列
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct CsvLayout {
+    /// 字段分隔符
+    pub delimiter: u8,
+    /// 表头前跳过的元数据行数
+    pub skip_lines: usize,
+    /// 元数据行之后的第一行是否为表头
+    pub has_header: bool,
+    /// 时间列 (从 0 开始)
+    pub time_column: usize,
+    /// 电平列 (从 0 开始)
+    pub value_column: usize,
+    /// 时间列为数字时的单位
+    pub time_unit: CsvTimeUnit,
+}
+
+impl Default for CsvLayout {
+    /// 本程序导出的布局：逗号分隔，第一行为表头，时间 (秒) 在第 1 列，电平在第 2 列
+    fn default() -> Self {
+        Self { delimiter: b',', skip_lines: 0, has_header: true, time_column: 0, value_column: 1, time_unit: CsvTimeUnit::Seconds }
+    }
+}
+
+/// CSV 文件开头的预览：自动识别的布局、表头 (无表头时为空字符串) 及前几行数据
+#[derive(Clone, Debug)]
+pub struct CsvPreview {
+    /// 自动识别的布局
+    pub layout: CsvLayout,
+    /// 各列名称
+    pub headers: Vec<String>,
+    /// 表头之后的前几行数据
+    pub rows: Vec<Vec<String>>,
+}
+
+/// 识别分隔符和元数据行时读取的行数
+const CSV_SNIFF_LINES: usize = 64;
+
+/// 按列名识别时间列和电平列 (如 "Timecode"、"Short-term LUFS")；识别不出时为第 1、2 列
+pub fn detect_csv_columns(headers: &[String]) -> (usize, usize, CsvTimeUnit) {
+    let lower: Vec<String> = headers.iter().map(|h| h.trim().to_lowercase()).collect();
+    let time_column = ["time", "时间", "position", "sec"].iter()
+        .find_map(|kw| lower.iter().position(|h| h.contains(kw)))
+        .unwrap_or(0);
+    // 按关键词优先级选第一列，LUFS 优先于 dBFS 等泛称
+    let value_column = ["lufs", "loudness", "响度", "dbfs", "db", "level", "value"].iter()
+        .find_map(|kw| lower.iter().enumerate().position(|(i, h)| i != time_column && h.contains(kw)))
+        .unwrap_or(if time_column == 0 { 1 } else { 0 });
+    let time_unit = match lower.get(time_column) {
+        Some(h) if h.contains("(ms)") || h.contains("msec") || h.ends_with(" ms") || h == "ms" => CsvTimeUnit::Milliseconds,
+        _ => CsvTimeUnit::Seconds,
+    };
+    (time_column, value_column, time_unit)
+}
+
+/// 读取 CSV 开头：按字段数识别分隔符 (逗号/分号/制表符)，字段数不足的前导行视为元数据，
+/// 首个完整行全部不是数字时作为表头，再按列名识别时间列和电平列
+pub fn preview_csv(path: &Path, max_rows: usize) -> Result<CsvPreview, AnalyzerError> {
+    preview_csv_from(std::io::BufReader::new(File::open(path)?), max_rows)
+}
+
+/// 与 [`preview_csv`] 相同，但预览内存中的 CSV 数据 (浏览器中选择的文件)
+pub fn preview_csv_bytes(bytes: &[u8], max_rows: usize) -> Result<CsvPreview, AnalyzerError> {
+    preview_csv_from(bytes, max_rows)
+}
+
+fn preview_csv_from(reader: impl std::io::BufRead, max_rows: usize) -> Result<CsvPreview, AnalyzerError> {
+    let mut lines: Vec<String> = reader.lines().take(CSV_SNIFF_LINES).collect::<Result<_, _>>()?;
+    if let Some(first) = lines.first_mut() {
+        *first = first.trim_start_matches('\u{feff}').to_string();
+    }
+
+    let split = |delimiter: u8| -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .delimiter(delimiter)
+            .from_reader(lines.join("\n").as_bytes())
+            .records()
+            .filter_map(Result::ok)
+            .map(|r| r.iter().map(|f| f.trim().to_string()).collect())
+            .collect()
+    };
+    let width = |records: &[Vec<String>]| records.iter().map(Vec::len).max().unwrap_or(0);
+    let (delimiter, records) = [b',', b';', b'\t'].into_iter()
+        .map(|d| (d, split(d)))
+        .max_by_key(|(_, records)| width(records))
+        .unwrap_or((b',', Vec::new()));
+
+    let columns = width(&records);
+    let skip_lines = records.iter().position(|r| r.len() == columns).unwrap_or(0);
+    let has_header = records.get(skip_lines).is_some_and(|r| r.iter().all(|f| f.parse::<f64>().is_err()));
+    let headers = match (has_header, records.get(skip_lines)) {
+        (true, Some(row)) => row.clone(),
+        _ => vec![String::new(); columns],
+    };
+    let (time_column, value_column, time_unit) = if has_header { detect_csv_columns(&headers) } else { (0, 1, CsvTimeUnit::Seconds) };
+    let rows = records.into_iter().skip(skip_lines + has_header as usize).take(max_rows).collect();
+
+    Ok(CsvPreview {
+        layout: CsvLayout { delimiter, skip_lines, has_header, time_column, value_column, time_unit },
+        headers,
+        rows,
+    })
+}
+
+/// 解析时间单元格：秒数或 [hh:]mm:ss[.fff] 时码
+pub fn parse_csv_time(cell: &str, unit: CsvTimeUnit) -> Option<f64> {
+    let cell = cell.trim();
+    if cell.contains(':') {
+        let mut seconds = 0.0;
+        for part in cell.split(':') {
+            seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+        }
+        return Some(seconds);
+    }
+    let value = cell.parse::<f64>().ok()?;
+    Some(match unit {
+        CsvTimeUnit::Seconds => value,
+        CsvTimeUnit::Milliseconds => value / 1000.0,
+    })
+}
+
+/// 解析电平单元格：允许带单位后缀 (如 "-23.1 LUFS")，"-inf" 记为底噪
+fn parse_csv_level(cell: &str) -> Option<f64> {
+    let number = cell.split_whitespace().next()?;
+    match number.parse::<f64>() {
+        Ok(v) if v.is_finite() => Some(v),
+        Ok(v) if v < 0.0 => Some(SILENCE_FLOOR_DBFS),
+        _ => None,
+    }
+}
+
+/// 解析 CSV 文件 (自动识别列布局)；`progress` 按已读取的字节比例以 0.0 - 1.0 调用
+pub fn parse_csv(path: PathBuf, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    let preview = preview_csv(&path, 0)?;
+    parse_csv_with_layout(path, &preview.layout, logger, progress)
+}
+
+/// 按指定列布局解析 CSV 文件；无法解析的行记录日志后跳过，没有任何可用行时返回 CsvSchema
+pub fn parse_csv_with_layout(path: PathBuf, layout: &CsvLayout, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    let filename = file_display_name(&path);
+    log_info(logger, &format!("▶️ 开始解析 CSV 文件: {}", filename));
+
+    let file = File::open(&path)?;
+    let file_len = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let (mut curve, timing) = analyze_csv(filename, file, file_len, layout, logger, progress)?;
+    curve.details = Some(SourceDetails::new(&path, None, timing));
+    Ok(curve)
+}
+
+/// 按指定列布局解析内存中的 CSV 数据 (浏览器中选择的文件)；`name` 作为曲线名称，结果没有来源路径
+pub fn parse_csv_bytes(name: &str, bytes: &[u8], layout: &CsvLayout, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    log_info(logger, &format!("▶️ 开始解析 CSV 数据: {} ({} 字节)", name, bytes.len()));
+    let (mut curve, timing) = analyze_csv(name.to_string(), bytes, bytes.len() as u64, layout, logger, progress)?;
+    curve.details = Some(SourceDetails { path: PathBuf::from(name), file_size: bytes.len() as u64, wav: None, timing });
+    Ok(curve)
+}
+
+/// CSV 解析流程；`len` 为数据的字节数，用于报告进度。来源相关的字段由调用方填写
+fn analyze_csv(filename: String, input: impl std::io::Read, len: u64, layout: &CsvLayout, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<(AudioCurve, LoadTiming), AnalyzerError> {
+    log_debug(logger, &format!("CSV 布局: {:?}", layout));
+    let started = Instant::now();
+    let file_len = len.max(1);
+    let report_every = (file_len / PROGRESS_STEPS as u64).max(1);
+    let mut next_report = report_every;
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(layout.delimiter)
+        .from_reader(input);
+    let mut points = Vec::new();
+    let needed = layout.time_column.max(layout.value_column) + 1;
+    // 第一处无法解析的行 (行号, 原因)，没有任何可用行时作为错误返回
+    let mut first_problem: Option<(usize, String)> = None;
+
+    for (index, result) in rdr.records().enumerate().skip(layout.skip_lines + layout.has_header as usize) {
+        let line_num = index + 1;
+        if let Some(byte) = result.as_ref().ok().and_then(|r| r.position()).map(|p| p.byte())
+            && byte >= next_report
+        {
+            if let Err(err) = report_progress(progress, byte as f32 / file_len as f32) {
+                log_info(logger, &format!("⏹️ 已取消解析: {}", filename));
+                return Err(err);
+            }
+            next_report = byte + report_every;
+        }
+        let record = match result {
+            Ok(r) => r,
+            Err(e) => {
+                log_error(logger, &format!("CSV 读取错误 (Line {}): {}", line_num, e));
+                first_problem.get_or_insert((line_num, e.to_string()));
+                continue;
+            }
+        };
+
+        if record.len() >= needed {
+            let Some(t) = parse_csv_time(&record[layout.time_column], layout.time_unit) else {
+                log_error(logger, &format!("CSV 格式错误 (Time, Line {}): {:?}", line_num, &record[layout.time_column]));
+                first_problem.get_or_insert((line_num, format!("time {:?}", &record[layout.time_column])));
+                continue;
+            };
+            let Some(v) = parse_csv_level(&record[layout.value_column]) else {
+                log_error(logger, &format!("CSV 格式错误 (Value, Line {}): {:?}", line_num, &record[layout.value_column]));
+                first_problem.get_or_insert((line_num, format!("value {:?}", &record[layout.value_column])));
+                continue;
+            };
+            points.push([t, v]);
+        } else {
+            log_error(logger, &format!("CSV 格式错误 (列数不足 {}, Line {}): {:?}", needed, line_num, record));
+            first_problem.get_or_insert((line_num, format!("{} column(s), {} needed", record.len(), needed)));
+        }
+    }
+    if points.is_empty() {
+        return Err(match first_problem {
+            Some((line, detail)) => AnalyzerError::CsvSchema { line, detail },
+            None => AnalyzerError::EmptyFile,
+        });
+    }
+    let duration = points.last().map(|p| p[0]).unwrap_or(0.0);
+
+    log_info(logger, &format!("✅ CSV 解析完成: {} (Duration: {:.2}s, Points: {})", filename, duration, points.len()));
+
+    let mut curve = AudioCurve::new(filename, points, duration, SILENCE_FLOOR_DBFS);
+    // 指标 (含平均电平) 按默认分析参数计算；load_file 会按实际的分析参数重新计算
+    curve.measure_points(&AnalysisSettings::default());
+    log_silence_gating(logger, &curve);
+    progress.report(1.0);
+    Ok((curve, LoadTiming { decode_sec: started.elapsed().as_secs_f64(), windowing_sec: 0.0 }))
+}
+
+/// 记录被当作静音排除的窗口数，便于确认没有把真实节目内容当作静音丢弃
+pub fn log_silence_gating(logger: &dyn LogSink, curve: &AudioCurve) {
+    let gating = curve.silence_gating;
+    if gating.excluded > 0 {
+        log_info(logger, &format!("静音窗口 {} (不计入最安静窗口统计): {}", gating, curve.name));
+    }
+}
+
+
+/// 按扩展名加载 WAV 或 CSV (CSV 自动识别列布局)
+pub fn load_file(path: PathBuf, analysis: &AnalysisSettings, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    if let Some(ext) = path.extension()
+        && ext == "csv"
+    {
+        let mut curve = parse_csv(path, logger, progress)?;
+        curve.measure_points(analysis);
+        return Ok(curve);
+    }
+    parse_wav(path, analysis, logger, progress)
+}
+
+/// 与 [`load_file`] 相同，但解析内存中的数据 (浏览器中选择的文件)；按 `name` 的扩展名区分 CSV 和 WAV
+pub fn load_bytes(name: &str, bytes: &[u8], analysis: &AnalysisSettings, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    if Path::new(name).extension().is_some_and(|ext| ext == "csv") {
+        let preview = preview_csv_bytes(bytes, 0)?;
+        let mut curve = parse_csv_bytes(name, bytes, &preview.layout, logger, progress)?;
+        curve.measure_points(analysis);
+        return Ok(curve);
+    }
+    parse_wav_bytes(name, bytes, analysis, logger, progress)
+}
+
+/// 文件被占用时的重试间隔 (毫秒)，逐次退避
+const LOAD_RETRY_DELAYS_MS: [u64; 3] = [200, 500, 1000];
+
+/// 判断 IO 错误是否因为文件被其他程序占用 (Windows 共享/锁冲突)
+pub fn is_file_in_use(io_err: &std::io::Error) -> bool {
+    // ERROR_SHARING_VIOLATION = 32, ERROR_LOCK_VIOLATION = 33
+    cfg!(windows) && matches!(io_err.raw_os_error(), Some(32) | Some(33))
+}
+
+/// 加载文件；遇到文件被占用或暂时无权限时按退避间隔重试，每次重试都记录日志
+pub fn load_file_with_retry(path: PathBuf, analysis: &AnalysisSettings, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    retry_load(&path, logger, || load_file(path.clone(), analysis, logger, progress))
+}
+
+/// 按分析配置加载 WAV 或 CSV (CSV 使用配置的列布局，未设置时自动识别)，曲线记录配置名称；
+/// 与 [`load_file_with_retry`] 一样在文件被占用时重试
+pub fn load_file_with_profile(path: PathBuf, profile: &AnalysisProfile, logger: &dyn LogSink, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    let mut curve = retry_load(&path, logger, || match profile.csv_layout {
+        Some(layout) if path.extension().is_some_and(|ext| ext == "csv") => {
+            let mut curve = parse_csv_with_layout(path.clone(), &layout, logger, progress)?;
+            curve.measure_points(&profile.analysis);
+            Ok(curve)
+        }
+        _ => load_file(path.clone(), &profile.analysis, logger, progress),
+    })?;
+    curve.profile = Some(profile.name.clone());
+    Ok(curve)
+}
+
+/// 反复调用 load，直到成功或遇到不可重试的错误 / 用完重试次数
+fn retry_load(path: &Path, logger: &dyn LogSink, mut load: impl FnMut() -> Result<AudioCurve, AnalyzerError>) -> Result<AudioCurve, AnalyzerError> {
+    let filename = file_display_name(path);
+    let mut attempt = 0;
+    loop {
+        match load() {
+            Ok(curve) => return Ok(curve),
+            Err(e) => {
+                if !e.is_retryable() || attempt >= LOAD_RETRY_DELAYS_MS.len() {
+                    return Err(e);
+                }
+                let delay = LOAD_RETRY_DELAYS_MS[attempt];
+                attempt += 1;
+                log_debug(logger, &format!(
+                    "⏳ 文件暂时无法打开 ({}): {}，{}ms 后重试 ({}/{})",
+                    filename, e, delay, attempt, LOAD_RETRY_DELAYS_MS.len()
+                ));
+                thread::sleep(Duration::from_millis(delay));
+            }
+        }
+    }
+}
+
+// --- 快速扫描：积分响度 (ITU-R BS.1770) 与真峰值 ---
+
+/// 二阶 IIR 滤波器 (Direct Form I)
+#[derive(Clone, Copy, Debug, Default)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3], // a[0] 恒为 1
+    x1: f64, x2: f64, y1: f64, y2: f64,
+}
+
+impl Biquad {
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b[0] * x + self.b[1] * self.x1 + self.b[2] * self.x2
+            - self.a[1] * self.y1 - self.a[2] * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    /// 二阶巴特沃斯低通 (RBJ cookbook, Q = 1/√2)
+    fn low_pass(sample_rate: f64, f0: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * f0 / sample_rate;
+        let alpha = w0.sin() * std::f64::consts::FRAC_1_SQRT_2;
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        Self {
+            b: [(1.0 - cos) / 2.0 / a0, (1.0 - cos) / a0, (1.0 - cos) / 2.0 / a0],
+            a: [1.0, -2.0 * cos / a0, (1.0 - alpha) / a0],
+            ..Default::default()
+        }
+    }
+
+    /// 二阶巴特沃斯高通 (RBJ cookbook, Q = 1/√2)
+    fn high_pass(sample_rate: f64, f0: f64) -> Self {
+        let w0 = 2.0 * std::f64::consts::PI * f0 / sample_rate;
+        let alpha = w0.sin() * std::f64::consts::FRAC_1_SQRT_2;
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha;
+        Self {
+            b: [(1.0 + cos) / 2.0 / a0, -(1.0 + cos) / a0, (1.0 + cos) / 2.0 / a0],
+            a: [1.0, -2.0 * cos / a0, (1.0 - alpha) / a0],
+            ..Default::default()
+        }
+    }
+
+    /// 峰值均衡 (RBJ cookbook)：f0 处增益 gain_db，远离 f0 时为 0 dB
+    fn peaking(sample_rate: f64, f0: f64, q: f64, gain_db: f64) -> Self {
+        let a = 10f64.powf(gain_db / 40.0);
+        let w0 = 2.0 * std::f64::consts::PI * f0 / sample_rate;
+        let alpha = w0.sin() / (2.0 * q);
+        let cos = w0.cos();
+        let a0 = 1.0 + alpha / a;
+        Self {
+            b: [(1.0 + alpha * a) / a0, -2.0 * cos / a0, (1.0 - alpha * a) / a0],
+            a: [1.0, -2.0 * cos / a0, (1.0 - alpha / a) / a0],
+            ..Default::default()
+        }
+    }
+
+    /// 频率 f 处的幅度响应 (dB)
+    fn response_db(&self, sample_rate: f64, f: f64) -> f64 {
+        let w = 2.0 * std::f64::consts::PI * f / sample_rate;
+        // H(e^jw) = (b0 + b1 z^-1 + b2 z^-2) / (1 + a1 z^-1 + a2 z^-2)，分别求实部和虚部
+        let (c1, s1, c2, s2) = (w.cos(), w.sin(), (2.0 * w).cos(), (2.0 * w).sin());
+        let power = |[k0, k1, k2]: [f64; 3]| (k0 + k1 * c1 + k2 * c2).powi(2) + (k1 * s1 + k2 * s2).powi(2);
+        10.0 * (power(self.b) / power(self.a)).log10()
+    }
+}
+
+/// ISO 226:2003 表 1：1/3 倍频程频率 (Hz) 及各频率的指数 αf、幅度响应 LU (dB)、听阈 Tf (dB)
+const ISO226_FREQ_HZ: [f64; 29] = [
+    20.0, 25.0, 31.5, 40.0, 50.0, 63.0, 80.0, 100.0, 125.0, 160.0, 200.0, 250.0, 315.0, 400.0, 500.0,
+    630.0, 800.0, 1000.0, 1250.0, 1600.0, 2000.0, 2500.0, 3150.0, 4000.0, 5000.0, 6300.0, 8000.0, 10000.0, 12500.0,
+];
+const ISO226_ALPHA: [f64; 29] = [
+    0.532, 0.506, 0.480, 0.455, 0.432, 0.409, 0.387, 0.367, 0.349, 0.330, 0.315, 0.301, 0.288, 0.276, 0.267,
+    0.259, 0.253, 0.250, 0.246, 0.244, 0.243, 0.243, 0.243, 0.242, 0.242, 0.245, 0.254, 0.271, 0.301,
+];
+const ISO226_LU: [f64; 29] = [
+    -31.6, -27.2, -23.0, -19.1, -15.9, -13.0, -10.3, -8.1, -6.2, -4.5, -3.1, -2.0, -1.1, -0.4, 0.0,
+    0.3, 0.5, 0.0, -2.7, -4.1, -1.0, 1.7, 2.5, 1.2, -2.1, -7.1, -11.2, -10.7, -3.1,
+];
+const ISO226_TF: [f64; 29] = [
+    78.5, 68.7, 59.5, 51.1, 44.0, 37.5, 31.5, 26.5, 22.1, 17.9, 14.4, 11.4, 8.6, 6.2, 4.4,
+    3.0, 2.2, 2.4, 3.5, 1.7, -1.3, -4.2, -6.0, -5.4, -1.5, 6.0, 12.6, 13.9, 12.3,
+];
+/// ISO226_FREQ_HZ 中 1 kHz 的下标
+const ISO226_1KHZ: usize = 17;
+
+/// 响度级为 phon 时，表 1 第 index 个频率的纯音需要的声压级 (dB SPL)，ISO 226:2003 公式 (1)
+fn iso226_spl(index: usize, phon: f64) -> f64 {
+    let (alpha, lu, tf) = (ISO226_ALPHA[index], ISO226_LU[index], ISO226_TF[index]);
+    let af = 4.47e-3 * (10f64.powf(0.025 * phon) - 1.15) + (0.4 * 10f64.powf((tf + lu) / 10.0 - 9.0)).powf(alpha);
+    10.0 / alpha * af.log10() - lu + 94.0
+}
+
+/// 等响计权：各频率相对 1 kHz 少需要 (正) 或多需要 (负) 的声压级 (dB)，即反转的等响曲线
+fn equal_loudness_weights(phon: f64) -> [f64; 29] {
+    let phon = phon.clamp(*EQUAL_LOUDNESS_PHON_RANGE.start(), *EQUAL_LOUDNESS_PHON_RANGE.end());
+    let reference = iso226_spl(ISO226_1KHZ, phon);
+    std::array::from_fn(|i| reference - iso226_spl(i, phon))
+}
+
+/// 等响计权滤波器的 Q (约 1 倍频程带宽)：更窄时相邻频率之间起伏大，更宽时拟合发散
+const EQUAL_LOUDNESS_Q: f64 = 1.5;
+/// 拟合等响计权时的修正次数
+const EQUAL_LOUDNESS_FIT_ITERATIONS: usize = 10;
+
+/// 等响计权滤波器链：在 ISO 226 表中每个低于 0.45 倍采样率的频率放一个峰值滤波器，
+/// 使级联的响应在这些频率上等于等响计权 (12.5 kHz 以上不计权)。
+/// 相邻滤波器互相影响，先按各滤波器增益 10 dB 时在各频率的响应建立 (近似线性的) 影响矩阵解出增益，
+/// 再按实际级联响应的误差迭代修正。在 31.5 Hz - 12.5 kHz 内与曲线的偏差约在 1 dB 以内
+fn equal_loudness_filters(sample_rate: f64, phon: f64) -> Vec<Biquad> {
+    let weights = equal_loudness_weights(phon);
+    let bands: Vec<usize> = (0..ISO226_FREQ_HZ.len()).filter(|&i| ISO226_FREQ_HZ[i] < 0.45 * sample_rate).collect();
+    let design = |gains: &[f64]| -> Vec<Biquad> {
+        bands.iter().zip(gains).map(|(&i, &gain)| Biquad::peaking(sample_rate, ISO226_FREQ_HZ[i], EQUAL_LOUDNESS_Q, gain)).collect()
+    };
+    const UNIT_GAIN_DB: f64 = 10.0;
+    let unit = design(&vec![UNIT_GAIN_DB; bands.len()]);
+    let influence: Vec<Vec<f64>> = bands.iter()
+        .map(|&row| unit.iter().map(|filter| filter.response_db(sample_rate, ISO226_FREQ_HZ[row]) / UNIT_GAIN_DB).collect())
+        .collect();
+
+    let mut gains = solve_linear(&influence, &bands.iter().map(|&i| weights[i]).collect::<Vec<_>>());
+    for _ in 0..EQUAL_LOUDNESS_FIT_ITERATIONS {
+        let filters = design(&gains);
+        let errors: Vec<f64> = bands.iter()
+            .map(|&i| weights[i] - filters.iter().map(|f| f.response_db(sample_rate, ISO226_FREQ_HZ[i])).sum::<f64>())
+            .collect();
+        for (gain, correction) in gains.iter_mut().zip(solve_linear(&influence, &errors)) {
+            *gain += correction;
+        }
+    }
+    design(&gains)
+}
+
+/// 高斯消元 (列主元) 解方程组 matrix · x = rhs；matrix 为方阵
+fn solve_linear(matrix: &[Vec<f64>], rhs: &[f64]) -> Vec<f64> {
+    let n = rhs.len();
+    let mut rows: Vec<Vec<f64>> = matrix.iter().zip(rhs).map(|(row, &b)| row.iter().copied().chain([b]).collect()).collect();
+    for col in 0..n {
+        let pivot = (col..n).max_by(|&a, &b| rows[a][col].abs().total_cmp(&rows[b][col].abs())).unwrap_or(col);
+        rows.swap(col, pivot);
+        let pivot_row = rows[col].clone();
+        if pivot_row[col] == 0.0 {
+            continue;
+        }
+        for row in rows.iter_mut().enumerate().filter(|(i, _)| *i != col).map(|(_, row)| row) {
+            let factor = row[col] / pivot_row[col];
+            for (x, p) in row[col..].iter_mut().zip(&pivot_row[col..]) {
+                *x -= factor * p;
+            }
+        }
+    }
+    (0..n).map(|i| if rows[i][i] != 0.0 { rows[i][n] / rows[i][i] } else { 0.0 }).collect()
+}
+
+/// 多频段视图的分频点 (Hz)：低频 < 250，中频 250–4000，高频 > 4000
+const BAND_SPLIT_HZ: [f64; 2] = [250.0, 4000.0];
+/// 多频段视图的频段数 (低/中/高)
+pub const BAND_COUNT: usize = 3;
+
+/// 单声道三段分频器：低频/高频各串联两级二阶滤波 (24 dB/oct)，中频为高通 + 低通
+#[derive(Clone, Copy)]
+struct BandSplitter {
+    low: [Biquad; 2],
+    mid: [Biquad; 2],
+    high: [Biquad; 2],
+}
+
+impl BandSplitter {
+    fn new(sample_rate: f64) -> Self {
+        let [low_edge, high_edge] = BAND_SPLIT_HZ;
+        // 分频点不能超过奈奎斯特频率
+        let high_edge = high_edge.min(sample_rate * 0.45);
+        Self {
+            low: [Biquad::low_pass(sample_rate, low_edge); 2],
+            mid: [Biquad::high_pass(sample_rate, low_edge), Biquad::low_pass(sample_rate, high_edge)],
+            high: [Biquad::high_pass(sample_rate, high_edge); 2],
+        }
+    }
+
+    fn process(&mut self, x: f64) -> [f64; BAND_COUNT] {
+        let run = |[first, second]: &mut [Biquad; 2]| second.process(first.process(x));
+        [run(&mut self.low), run(&mut self.mid), run(&mut self.high)]
+    }
+}
+
+/// 将逐帧的频段能量按与主曲线相同的窗口/步进转换为 (时间, dBFS) 曲线；`first_frame` 为切片首帧在文件中的位置
+fn band_curves(frame_energies: &[[f64; BAND_COUNT]], first_frame: usize, sample_rate: usize, window_size: usize, step_size: usize) -> Vec<Vec<[f64; 2]>> {
+    let mut bands = vec![Vec::new(); BAND_COUNT];
+    let mut i = 0;
+    while i + window_size <= frame_energies.len() {
+        let time = window_center_sec(first_frame + i, window_size, sample_rate);
+        for (band, points) in bands.iter_mut().enumerate() {
+            let mean_energy = frame_energies[i..i + window_size].iter().map(|e| e[band]).sum::<f64>() / window_size as f64;
+            let db = if mean_energy < 1e-18 { SILENCE_FLOOR_DBFS } else { 10.0 * mean_energy.log10() };
+            points.push([time, db]);
+        }
+        i += step_size;
+    }
+    bands
+}
+
+/// 根据采样率计算 K 计权的两级滤波器 (高架预滤波 + RLB 高通)，系数公式同 libebur128
+fn k_weighting_filters(sample_rate: f64) -> [Biquad; 2] {
+    use std::f64::consts::PI;
+
+    let f0 = 1681.974450955533;
+    let g = 3.999843853973347;
+    let q = 0.7071752369554196;
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(g / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    let f0 = 38.13547087602444;
+    let q = 0.5003270373238773;
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+        ..Default::default()
+    };
+
+    [shelf, high_pass]
+}
+
+/// 真峰值检测的过采样倍数及每相位抽头数
+const TRUE_PEAK_OVERSAMPLE: usize = 4;
+const TRUE_PEAK_TAPS_PER_PHASE: usize = 12;
+
+/// 4 倍过采样插值滤波器的多相系数 (Hann 窗 sinc)
+fn true_peak_phases() -> [[f64; TRUE_PEAK_TAPS_PER_PHASE]; TRUE_PEAK_OVERSAMPLE] {
+    let n = TRUE_PEAK_OVERSAMPLE * TRUE_PEAK_TAPS_PER_PHASE;
+    let center = (n - 1) as f64 / 2.0;
+    let mut phases = [[0.0; TRUE_PEAK_TAPS_PER_PHASE]; TRUE_PEAK_OVERSAMPLE];
+    for i in 0..n {
+        let x = (i as f64 - center) / TRUE_PEAK_OVERSAMPLE as f64;
+        let sinc = if x.abs() < 1e-12 { 1.0 } else { (std::f64::consts::PI * x).sin() / (std::f64::consts::PI * x) };
+        let window = 0.5 - 0.5 * (2.0 * std::f64::consts::PI * i as f64 / (n - 1) as f64).cos();
+        phases[i % TRUE_PEAK_OVERSAMPLE][i / TRUE_PEAK_OVERSAMPLE] = sinc * window;
+    }
+    phases
+}
+
+/// BS.1770 两级门限后的平均块能量及保留的块数：先去掉低于 -70 LUFS 的块，
+/// 再去掉低于其均值 -10 LU 的块；全部被排除时为 None。
+/// 门限换算成能量比较，累计计算收敛曲线时不必对每块取对数
+fn gated_mean_energy(blocks: &[f64]) -> Option<(f64, usize)> {
+    let lufs_to_energy = |lufs: f64| 10f64.powf((lufs + 0.691) / 10.0);
+    let mean_above = |threshold: f64| -> Option<(f64, usize)> {
+        let (sum, count) = blocks.iter()
+            .filter(|&&e| e > threshold)
+            .fold((0.0, 0usize), |(sum, count), &e| (sum + e, count + 1));
+        (count > 0).then(|| (sum / count as f64, count))
+    };
+    let absolute = lufs_to_energy(-70.0);
+    let (abs_gated, _) = mean_above(absolute)?;
+    // 相对门限 = 绝对门限后的均值 -10 LU，即能量的 1/10
+    mean_above((abs_gated / 10.0).max(absolute))
+}
+
+/// 流式积分响度/真峰值计量器：逐帧输入样本，只保留 100ms 子块能量，不保存曲线点
+pub struct LoudnessMeter {
+    channels: usize,
+    weights: Vec<f64>, // BS.1770 声道权重
+    filters: Vec<[Biquad; 2]>,
+    subblock_frames: usize,
+    frames_in_subblock: usize,
+    subblock_energy: f64,
+    subblocks: Vec<f64>, // 每个 100ms 子块的 (各声道加权均方和)
+    phases: [[f64; TRUE_PEAK_TAPS_PER_PHASE]; TRUE_PEAK_OVERSAMPLE],
+    history: Vec<[f64; TRUE_PEAK_TAPS_PER_PHASE]>, // 每声道最近的样本 (最新在前)
+    peak: f64,
+}
+
+impl LoudnessMeter {
+    /// `weights` 为各声道的 BS.1770 权重，其长度即声道数
+    pub fn new(sample_rate: u32, weights: Vec<f64>) -> Self {
+        let sample_rate = sample_rate as f64;
+        let channels = weights.len();
+        Self {
+            channels,
+            weights,
+            filters: vec![k_weighting_filters(sample_rate); channels],
+            subblock_frames: ((sample_rate * 0.1).round() as usize).max(1),
+            frames_in_subblock: 0,
+            subblock_energy: 0.0,
+            subblocks: Vec::new(),
+            phases: true_peak_phases(),
+            history: vec![[0.0; TRUE_PEAK_TAPS_PER_PHASE]; channels],
+            peak: 0.0,
+        }
+    }
+
+    /// 输入一帧 (每个声道一个样本)
+    pub fn push_frame(&mut self, frame: &[f64]) {
+        for (ch, &x) in frame.iter().enumerate().take(self.channels) {
+            // K 计权能量
+            let [shelf, high_pass] = &mut self.filters[ch];
+            let y = high_pass.process(shelf.process(x));
+            self.subblock_energy += self.weights[ch] * y * y;
+
+            // 真峰值：原始样本峰值 + 多相插值峰值
+            let history = &mut self.history[ch];
+            history.copy_within(0..TRUE_PEAK_TAPS_PER_PHASE - 1, 1);
+            history[0] = x;
+            self.peak = self.peak.max(x.abs());
+            for phase in &self.phases {
+                let interpolated: f64 = phase.iter().zip(history.iter()).map(|(h, s)| h * s).sum();
+                self.peak = self.peak.max(interpolated.abs());
+            }
+        }
+
+        self.frames_in_subblock += 1;
+        if self.frames_in_subblock == self.subblock_frames {
+            self.subblocks.push(self.subblock_energy / self.subblock_frames as f64);
+            self.subblock_energy = 0.0;
+            self.frames_in_subblock = 0;
+        }
+    }
+
+    /// 门限积分响度 (LUFS，400ms 块、75% 重叠，-70 LUFS 绝对 + -10 LU 相对门限) 及被门限排除的块数
+    pub fn integrated_lufs(&self) -> (f64, WindowGating) {
+        let blocks = self.gating_blocks();
+        let all_gated = WindowGating { excluded: blocks.len(), total: blocks.len() };
+        match gated_mean_energy(&blocks) {
+            Some((energy, kept)) => (-0.691 + 10.0 * energy.log10(), WindowGating { excluded: blocks.len() - kept, total: blocks.len() }),
+            None => (f64::NEG_INFINITY, all_gated),
+        }
+    }
+
+    /// 400ms 门限块的能量 (75% 重叠，即每个 100ms 子块起一块)
+    fn gating_blocks(&self) -> Vec<f64> {
+        self.subblocks.windows(4)
+            .map(|w| w.iter().sum::<f64>() / 4.0)
+            .collect()
+    }
+
+    /// 积分响度随时间的收敛过程 (时间, LUFS)：每秒一点 (最后一点为整个节目)，
+    /// 值为从开头到该时刻的门限积分响度，最后一点等于 integrated_lufs。
+    /// 全部被门限排除时记为 SILENCE_FLOOR_DBFS，节目短于一个 400ms 块时为空
+    pub fn integrated_curve(&self) -> Vec<[f64; 2]> {
+        let blocks = self.gating_blocks();
+        let mut ends: Vec<usize> = (10..self.subblocks.len()).step_by(10).collect();
+        if !blocks.is_empty() {
+            ends.push(self.subblocks.len());
+        }
+        ends.into_iter()
+            .map(|end| {
+                let lufs = gated_mean_energy(&blocks[..end - 3]).map_or(SILENCE_FLOOR_DBFS, |(energy, _)| -0.691 + 10.0 * energy.log10());
+                [end as f64 * 0.1, lufs.max(SILENCE_FLOOR_DBFS)]
+            })
+            .collect()
+    }
+
+    /// 响度范围 (LU，EBU Tech 3342)：3 秒短期块 (100ms 步进)，-70 LUFS 绝对门限 + -20 LU 相对门限，
+    /// 取门限后短期响度的 10% 与 95% 分位数之差；不足一个短期块时返回 None
+    pub fn loudness_range(&self) -> Option<f64> {
+        let to_lufs = |energy: f64| -0.691 + 10.0 * energy.log10();
+        let blocks: Vec<f64> = self.subblocks.windows(30)
+            .map(|w| w.iter().sum::<f64>() / 30.0)
+            .filter(|&e| to_lufs(e) > -70.0)
+            .collect();
+        if blocks.is_empty() {
+            return None;
+        }
+        let relative_threshold = to_lufs(blocks.iter().sum::<f64>() / blocks.len() as f64) - 20.0;
+        let mut levels: Vec<f64> = blocks.into_iter().map(to_lufs).filter(|&l| l > relative_threshold).collect();
+        levels.sort_by(f64::total_cmp);
+        let percentile = |p: f64| levels[((levels.len() - 1) as f64 * p).round() as usize];
+        Some(percentile(0.95) - percentile(0.10))
+    }
+
+    /// 每 100ms 一点的滑动窗口响度 (LUFS)，窗口为 `subblocks` 个子块；时间取窗口中心，与 RMS 曲线对齐。
+    /// 静音窗口记为 SILENCE_FLOOR_DBFS，节目短于一个窗口时为空
+    fn sliding_loudness(&self, subblocks: usize) -> Vec<[f64; 2]> {
+        self.subblocks.windows(subblocks)
+            .enumerate()
+            .map(|(i, w)| {
+                let energy = w.iter().sum::<f64>() / subblocks as f64;
+                let lufs = if energy > 0.0 { -0.691 + 10.0 * energy.log10() } else { SILENCE_FLOOR_DBFS };
+                [(i as f64 + subblocks as f64 / 2.0) * 0.1, lufs.max(SILENCE_FLOOR_DBFS)]
+            })
+            .collect()
+    }
+
+    /// EBU 瞬时响度 (M)：400ms 窗口
+    pub fn momentary_curve(&self) -> Vec<[f64; 2]> {
+        self.sliding_loudness(4)
+    }
+
+    /// EBU 短期响度 (S)：3s 窗口
+    pub fn short_term_curve(&self) -> Vec<[f64; 2]> {
+        self.sliding_loudness(30)
+    }
+
+    /// 积分响度、真峰值及响度范围
+    pub fn measurement(&self) -> LoudnessMeasurement {
+        LoudnessMeasurement {
+            integrated_lufs: self.integrated_lufs().0,
+            true_peak_dbtp: self.true_peak_dbtp(),
+            loudness_range_lu: self.loudness_range(),
+        }
+    }
+
+    /// 真峰值 (dBTP)；全静音时为负无穷
+    pub fn true_peak_dbtp(&self) -> f64 {
+        if self.peak <= 0.0 { f64::NEG_INFINITY } else { 20.0 * self.peak.log10() }
+    }
+}
+
+/// 快速扫描单个 WAV：流式计算积分响度 (LUFS) 和真峰值 (dBTP)，不生成曲线。
+/// `progress` 被取消时在下一次报告进度时返回 Cancelled
+pub fn quick_scan_wav(path: &std::path::Path, progress: &mut dyn Progress) -> Result<(f64, f64, WindowGating), AnalyzerError> {
+    let mut reader = WavReader::open(path)?;
+    let spec = reader.spec();
+    let speakers = channel_speakers(spec.channels as usize, read_wav_channel_mask(path));
+    let mut meter = LoudnessMeter::new(spec.sample_rate, bs1770_channel_weights(&speakers));
+
+    let frame_count = reader.duration() as usize;
+    let report_every = (frame_count / PROGRESS_STEPS).max(1);
+    let mut frames_read = 0;
+    try_for_each_wav_frame(&mut reader, |frame| {
+        meter.push_frame(frame);
+        frames_read += 1;
+        if frames_read % report_every == 0 {
+            report_progress(progress, frames_read as f32 / frame_count as f32)?;
+        }
+        Ok(())
+    })?;
+    progress.report(1.0);
+
+    let (lufs, gating) = meter.integrated_lufs();
+    Ok((lufs, meter.true_peak_dbtp(), gating))
+}
+
+/// CSV 导出选项
+#[derive(Clone, Copy, Debug)]
+pub struct CsvExportOptions {
+    /// 归一化列使用的归一化方式
+    pub normalize_mode: NormalizeMode,
+    /// 归一化列的目标电平 (dBFS)
+    pub target: f64,
+    /// 按固定间隔 (秒) 插值重采样；None 时写出分析得到的原始点
+    pub resample_interval: Option<f64>,
+}
+
+/// 在 interval 的整数倍时刻对曲线线性插值，只保留曲线时间范围内的时刻
+pub fn resample_uniform(points: &[[f64; 2]], interval: f64) -> Vec<[f64; 2]> {
+    let (Some(first), Some(last)) = (points.first(), points.last()) else {
+        return Vec::new();
+    };
+    if interval <= 0.0 {
+        return points.to_vec();
+    }
+    let start = (first[0] / interval).ceil() as i64;
+    let end = (last[0] / interval).floor() as i64;
+    (start..=end)
+        .filter_map(|k| {
+            let t = k as f64 * interval;
+            // 浮点误差可能让最后一个时刻略超出曲线末尾
+            interpolate_at(points, t.min(last[0])).map(|v| [t, v])
+        })
+        .collect()
+}
+
+/// 导出文件记录的分析参数 (名称, 值)，使导出的数据可以追溯和复现
+pub fn analysis_metadata(analysis: &AnalysisSettings) -> Vec<(&'static str, String)> {
+    vec![
+        ("window_sec", analysis.window_sec.to_string()),
+        ("step_sec", analysis.step_sec.to_string()),
+        ("weighting", format!("{:?}", analysis.weighting)),
+        ("noise_floor_dbfs", analysis.noise_floor_dbfs.to_string()),
+        ("downmix", format!("{:?}", analysis.downmix)),
+        ("stereo_downmix_db", analysis.stereo_downmix_db.to_string()),
+        ("average", format!("{:?}", analysis.average)),
+        ("skip_head_sec", analysis.skip_head_sec.to_string()),
+        ("skip_tail_sec", analysis.skip_tail_sec.to_string()),
+    ]
+}
+
+/// 导出曲线时记录的来源信息：生成程序、源文件、格式、分析配置与参数及静音门限排除的窗口数。
+/// CSV 导入的曲线没有分析参数和格式，只记录已知的部分
+pub fn curve_export_metadata(curve: &AudioCurve) -> Vec<(&'static str, String)> {
+    let mut meta = vec![("generator", format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")))];
+    if let Some(details) = &curve.details {
+        meta.push(("source", details.path.file_name().unwrap_or_default().to_string_lossy().to_string()));
+        if let Some(wav) = &details.wav {
+            meta.push(("sample_rate", wav.spec.sample_rate.to_string()));
+            meta.push(("channels", wav.spec.channels.to_string()));
+            meta.push(("bits_per_sample", format!("{} {:?}", wav.spec.bits_per_sample, wav.spec.sample_format)));
+        }
+    }
+    if let Some(profile) = &curve.profile {
+        meta.push(("profile", profile.clone()));
+    }
+    if let Some(analysis) = &curve.analysis {
+        meta.extend(analysis_metadata(analysis));
+    }
+    meta.push(("silence_gated_windows", format!("{}/{}", curve.silence_gating.excluded, curve.silence_gating.total)));
+    meta
+}
+
+/// 将 AudioCurve 写入指定 CSV 路径 (不弹出对话框，供控制台 `export` 使用)
+pub fn write_curve_csv(curve: &AudioCurve, options: &CsvExportOptions, path: &Path, logger: &dyn LogSink) -> Result<(), Box<dyn Error + Send + Sync>> {
+    log_info(logger, &format!("▶️ 导出数据到: {}", path.display()));
+    write_curve_csv_to(curve, options, File::create(path)?, logger)?;
+    log_info(logger, &format!("✅ CSV 文件导出成功: {}", file_display_name(path)));
+    Ok(())
+}
+
+/// 与 [`write_curve_csv`] 相同，但写入任意输出 (如浏览器中作为下载的内存缓冲区)
+pub fn write_curve_csv_to(curve: &AudioCurve, options: &CsvExportOptions, output: impl std::io::Write, logger: &dyn LogSink) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(output);
+
+    // 表头之前逐行写出来源信息和曲线的指标 ("# 名称", 值)；字段数少于数据行，重新导入时作为元数据跳过
+    for (name, value) in curve_export_metadata(curve) {
+        wtr.write_record([format!("# {}", name), value])?;
+    }
+    for (name, value) in &curve.metrics {
+        wtr.write_record([format!("# {}", name), value.value().map_or_else(String::new, |v| format!("{:.6}", v))])?;
+    }
+
+    // 写入表头
+    wtr.write_record(["Time (s)", "Loudness (dBFS)", "Normalized Loudness (dBFS)"])?;
+
+    // 计算偏移量
+    let offset_val = curve.normalization_gain(options.normalize_mode, options.target);
+    log_debug(logger, &format!("应用归一化偏移量: {:.2} dB", offset_val));
+
+    let resampled = options.resample_interval.map(|interval| {
+        log_debug(logger, &format!("按 {:.3}s 间隔重采样导出", interval));
+        resample_uniform(&curve.points, interval)
+    });
+    let points = resampled.as_deref().unwrap_or(&curve.points);
+
+    // 写入数据点
+    for point in points {
+        let normalized_db = point[1] + offset_val;
+        wtr.write_record(&[
+            // 时间和原始响度保留 6 位小数，保证重新导入后与原曲线逐点对齐
+            format!("{:.6}", point[0]),      // Time
+            format!("{:.6}", point[1]),      // Raw dBFS
+            format!("{:.2}", normalized_db), // Normalized dBFS
+        ])?;
+    }
+
+    wtr.flush()?;
+    Ok(())
+}
+
+/// 导出对比的逐点数据表 (时间, A, B, 差值)，供在表格软件中自行统计；表头之前写出两条曲线的名称及对齐方式
+pub fn write_comparison_table(result: &ComparisonResult, a_name: &str, b_name: &str, path: &Path, logger: &dyn LogSink) -> Result<(), Box<dyn Error + Send + Sync>> {
+    log_info(logger, &format!("▶️ 导出对比数据表到: {}", path.display()));
+    let mut wtr = csv::WriterBuilder::new().flexible(true).from_path(path)?;
+    wtr.write_record(["# track_a", a_name])?;
+    wtr.write_record(["# track_b", b_name])?;
+    wtr.write_record(["# b_offset_sec".to_string(), result.b_offset.to_string()])?;
+    wtr.write_record(["# resampled".to_string(), result.resampled.to_string()])?;
+    // 电平匹配时 A / B 列为减去各自平均电平后的值
+    let unit = match result.level_offset {
+        Some(offset) => {
+            wtr.write_record(["# level_offset_db".to_string(), format!("{:.6}", offset)])?;
+            "dB, level-matched"
+        }
+        None => "dBFS",
+    };
+    wtr.write_record(["Time (s)".to_string(), format!("A ({})", unit), format!("B ({})", unit), "Diff (dB)".to_string()])?;
+    for row in result.aligned_pairs() {
+        wtr.write_record(row.map(|v| format!("{:.6}", v)))?;
+    }
+    wtr.flush()?;
+    log_info(logger, &format!("✅ 对比数据表导出成功 ({} 行)", result.diff_points.len()));
+    Ok(())
+}
+
+/// 将 WAV 乘以给定增益 (dB) 后按原格式写入 `target`，返回超出满刻度的样本数。
+/// 整数 PCM 中这些样本被削波；浮点 WAV 可以表示满刻度以上的值，按原值写出。
+/// `progress` 被取消时删除写到一半的文件并返回 Cancelled
+pub fn write_gain_adjusted_wav(source: &Path, target: &Path, gain_db: f64, progress: &mut dyn Progress) -> Result<usize, Box<dyn Error + Send + Sync>> {
+    let mut reader = WavReader::open(source)?;
+    let spec = reader.spec();
+    let gain = 10f64.powf(gain_db / 20.0);
+    let full_scale = (1u64 << (spec.bits_per_sample.max(1) - 1)) as f64;
+
+    let mut writer = hound::WavWriter::create(target, spec)?;
+    let mut clipped = 0;
+    let mut write_error = None;
+    let read_result = for_each_wav_sample_with_progress(&mut reader, progress, |s| {
+        if write_error.is_some() {
+            return;
+        }
+        let y = s * gain;
+        if y.abs() > 1.0 {
+            clipped += 1;
+        }
+        let result = match spec.sample_format {
+            hound::SampleFormat::Float => writer.write_sample(y as f32),
+            hound::SampleFormat::Int => writer.write_sample((y * full_scale).round().clamp(-full_scale, full_scale - 1.0) as i32),
+        };
+        if let Err(e) = result {
+            write_error = Some(e);
+        }
+    });
+
+    let result: Result<(), Box<dyn Error + Send + Sync>> = match (read_result, write_error) {
+        (Err(e), _) => Err(e.into()),
+        (_, Some(e)) => Err(e.into()),
+        _ => Ok(()),
+    };
+    if let Err(e) = result {
+        drop(writer);
+        let _ = std::fs::remove_file(target); // 不留下不完整的文件
+        return Err(e);
+    }
+    writer.finalize()?;
+    Ok(clipped)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::{write_wav, Signal, TempDir, ALL_FORMATS};
+
+    fn test_logger() -> Logger {
+        Logger::new()
+    }
+
+    #[test]
+    fn log_buffer_evicts_oldest_entries_and_keeps_sequence_numbers() {
+        let mut buffer = LogBuffer::with_capacity(3);
+        for i in 0..5 {
+            buffer.push(LogEntry::new(LogLevel::Info, i.to_string()));
+        }
+        // 容量 3：最旧的两条被淘汰，序号不因淘汰而改变
+        let kept: Vec<(u64, &str)> = buffer.iter().map(|e| (e.seq, e.message.as_str())).collect();
+        assert_eq!(kept, [(2, "2"), (3, "3"), (4, "4")]);
+        assert_eq!(buffer.dropped(), 2);
+
+        buffer.set_capacity(1);
+        assert_eq!((buffer.len(), buffer.iter().next().unwrap().seq, buffer.dropped()), (1, 4, 4));
+
+        // 清空后淘汰计数归零，序号继续递增
+        buffer.clear();
+        buffer.push(LogEntry::new(LogLevel::Error, "after clear".to_string()));
+        assert_eq!((buffer.iter().next().unwrap().seq, buffer.dropped()), (5, 0));
+    }
+
+    #[test]
+    fn log_file_mirror_writes_one_line_per_entry_and_rotates_by_size() {
+        let dir = TempDir::new("log_file");
+        let logs = dir.join("logs");
+        let logger = Logger::new();
+        logger.entries.lock().unwrap().set_mirror(Some(LogFileMirror::start(logs.clone(), 300).unwrap()));
+        let path = logger.entries.lock().unwrap().mirror().unwrap().current_path();
+        set_log_task_id(Some(7));
+        log_info(&logger, "first\nsecond");
+        set_log_task_id(None);
+        for i in 0..10 {
+            log_error(&logger, &format!("entry {}", i));
+        }
+        // 关闭镜像时等待写入线程写完剩余日志
+        logger.entries.lock().unwrap().close_mirror();
+
+        let backup = path.with_extension("1.log");
+        let text = std::fs::read_to_string(&backup).unwrap() + &std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 11, "{:?}", lines);
+        assert!(std::fs::metadata(&path).unwrap().len() <= 300);
+        // ISO 时间、级别、任务 ID 及转义后的换行
+        let first: Vec<&str> = lines[0].splitn(3, ' ').collect();
+        assert!(DateTime::parse_from_rfc3339(first[0]).is_ok(), "{}", first[0]);
+        assert_eq!(first[1..], ["INFO", "   [task 7] first\\nsecond"]);
+        assert!(lines[10].ends_with("ERROR   entry 9"));
+    }
+
+    #[test]
+    fn csv_export_round_trip_matches_wav_curve() {
+        let dir = TempDir::new("roundtrip");
+        let wav_path = dir.join("roundtrip.wav");
+        let csv_path = dir.join("roundtrip.csv");
+        // 采样率选用步进无法整除的 11025 Hz 以检验时间精度；阶跃让各窗口电平不同
+        write_wav(&wav_path, Signal::Step { first_dbfs: -6.0, second_dbfs: -20.0, switch_sec: 2.3 }, ALL_FORMATS[0], 11025, 1, 5.0);
+
+        let logger = test_logger();
+        let original = parse_wav(wav_path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+        let options = CsvExportOptions { normalize_mode: NormalizeMode::Average, target: -23.0, resample_interval: None };
+        write_curve_csv(&original, &options, &csv_path, &logger).unwrap();
+        let reimported = parse_csv(csv_path, &logger, &mut |_| {}).unwrap();
+
+        assert_eq!(original.points.len(), reimported.points.len());
+        for (a, b) in original.points.iter().zip(&reimported.points) {
+            assert!((a[0] - b[0]).abs() < 1e-6, "time {} vs {}", a[0], b[0]);
+            assert!((a[1] - b[1]).abs() < 1e-6, "level {} vs {}", a[1], b[1]);
+        }
+        assert!((original.average_dbfs - reimported.average_dbfs).abs() < 1e-6);
+        // WAV 时长来自文件头；CSV 只有各点时间，时长为最后一个点
+        assert!((original.duration - 5.0).abs() < 1e-9);
+        assert!((reimported.duration - original.points.last().unwrap()[0]).abs() < 1e-6);
+
+        // 与原曲线对比应当没有差异
+        let result = compare_curves(&original, &reimported, 0.0, false);
+        assert!(result.mean_diff.abs() < 1e-6);
+        assert!(result.std_dev < 1e-6);
+        assert!((result.overlap_ratio - 1.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn cancelling_stops_reading_at_the_next_progress_report() {
+        /// 第一次报告进度后即取消
+        #[derive(Default)]
+        struct CancelAfterFirstReport(Vec<f32>);
+        impl Progress for CancelAfterFirstReport {
+            fn report(&mut self, fraction: f32) {
+                self.0.push(fraction);
+            }
+            fn is_cancelled(&self) -> bool {
+                !self.0.is_empty()
+            }
+        }
+
+        let dir = TempDir::new("cancel");
+        let wav_path = dir.join("cancel.wav");
+        let csv_path = dir.join("cancel.csv");
+        write_wav(&wav_path, Signal::Sine { freq: 440.0, peak_dbfs: -6.0 }, ALL_FORMATS[0], 11025, 1, 5.0);
+        let logger = test_logger();
+        let curve = parse_wav(wav_path.clone(), &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+        let options = CsvExportOptions { normalize_mode: NormalizeMode::Average, target: -23.0, resample_interval: None };
+        write_curve_csv(&curve, &options, &csv_path, &logger).unwrap();
+
+        let scanned = quick_scan_wav(&wav_path, &mut CancelAfterFirstReport::default());
+        assert!(matches!(scanned, Err(AnalyzerError::Cancelled)), "{:?}", scanned);
+        // 取消写出时不留下写到一半的文件
+        let gain_path = dir.join("gain.wav");
+        let written = write_gain_adjusted_wav(&wav_path, &gain_path, 3.0, &mut CancelAfterFirstReport::default());
+        assert!(written.is_err_and(|e| matches!(e.downcast_ref(), Some(AnalyzerError::Cancelled))));
+        assert!(!gain_path.exists());
+
+        for result in [
+            parse_wav(wav_path, &AnalysisSettings::default(), &logger, &mut CancelAfterFirstReport::default()),
+            parse_csv(csv_path, &logger, &mut CancelAfterFirstReport::default()),
+        ] {
+            assert!(matches!(result, Err(AnalyzerError::Cancelled)), "{:?}", result.map(|c| c.name));
+        }
+    }
+
+    #[test]
+    fn gain_above_full_scale_clips_pcm_but_not_float() {
+        let dir = TempDir::new("gain");
+        for format in [(hound::SampleFormat::Int, 16), (hound::SampleFormat::Float, 32)] {
+            let (source, target) = (dir.join("source.wav"), dir.join("gain.wav"));
+            write_wav(&source, Signal::Sine { freq: 440.0, peak_dbfs: -6.0 }, format, 8000, 1, 0.5);
+            let over = write_gain_adjusted_wav(&source, &target, 12.0, &mut |_| {}).unwrap();
+            assert!(over > 0, "{:?}", format);
+
+            let mut peak: f64 = 0.0;
+            for_each_wav_sample(&mut WavReader::open(&target).unwrap(), |s| peak = peak.max(s.abs())).unwrap();
+            match format.0 {
+                hound::SampleFormat::Int => assert!(peak <= 1.0, "{}", peak),
+                // -6 dBFS + 12 dB ≈ +6 dBFS，浮点保留超出满刻度的部分
+                hound::SampleFormat::Float => assert!((peak - 10f64.powf(6.0 / 20.0)).abs() < 0.01, "{}", peak),
+            }
+        }
+    }
+
+    #[test]
+    fn csv_export_metadata_records_source_and_analysis() {
+        let dir = TempDir::new("csv_metadata");
+        let (wav_path, csv_path) = (dir.join("tone.wav"), dir.join("tone.csv"));
+        write_wav(&wav_path, Signal::Sine { freq: 440.0, peak_dbfs: -6.0 }, ALL_FORMATS[1], 11025, 2, 2.0);
+
+        let logger = test_logger();
+        let analysis = AnalysisSettings { window_sec: 0.8, ..AnalysisSettings::default() };
+        let original = parse_wav(wav_path, &analysis, &logger, &mut |_| {}).unwrap();
+        let options = CsvExportOptions { normalize_mode: NormalizeMode::Average, target: -23.0, resample_interval: None };
+        write_curve_csv(&original, &options, &csv_path, &logger).unwrap();
+
+        // 表头之前记录了源文件、格式和分析参数
+        let text = std::fs::read_to_string(&csv_path).unwrap();
+        for line in ["# source,tone.wav", "# sample_rate,11025", "# channels,2", "# bits_per_sample,24 Int", "# window_sec,0.8"] {
+            assert!(text.lines().any(|l| l == line), "missing {:?} in\n{}", line, text);
+        }
+        // 元数据行不影响重新导入
+        let reimported = parse_csv(csv_path, &logger, &mut |_| {}).unwrap();
+        assert_eq!(reimported.points.len(), original.points.len());
+    }
+
+    #[test]
+    fn momentary_and_short_term_follow_a_steady_tone() {
+        let mut meter = LoudnessMeter::new(48000, vec![1.0]);
+        for i in 0..48000 * 5 {
+            meter.push_frame(&[0.1 * (2.0 * std::f64::consts::PI * 1000.0 * i as f64 / 48000.0).sin()]);
+        }
+        let (momentary, short_term) = (meter.momentary_curve(), meter.short_term_curve());
+        // 50 个 100ms 子块：400ms 窗口 47 点，3s 窗口 21 点，时间为窗口中心
+        assert_eq!((momentary.len(), short_term.len()), (47, 21));
+        assert!((momentary[0][0] - 0.2).abs() < 1e-9 && (short_term[0][0] - 1.5).abs() < 1e-9);
+        // 稳态信号的瞬时 / 短期响度都等于积分响度
+        let integrated = meter.integrated_lufs().0;
+        for p in momentary.iter().skip(1).chain(&short_term) {
+            assert!((p[1] - integrated).abs() < 0.05, "{} vs {}", p[1], integrated);
+        }
+        // 累计积分响度每秒一点，最后一点为整个节目
+        let running = meter.integrated_curve();
+        assert_eq!(running.iter().map(|p| p[0]).collect::<Vec<_>>(), [1.0, 2.0, 3.0, 4.0, 5.0]);
+        assert_eq!(running[4][1], integrated);
+        assert!(running.iter().all(|p| (p[1] - integrated).abs() < 0.05));
+    }
+
+    #[test]
+    fn csv_layout_is_detected_from_metadata_and_column_names() {
+        let dir = TempDir::new("csv_layout");
+        let path = dir.join("meter_log.csv");
+        std::fs::write(&path, "\u{feff}Program: mix v3\nIntegrated: -23.0 LUFS\nIndex;Timecode;Short-term LUFS;True Peak\n\
+            1;00:00:00.100;-24.5;-3.0\n2;00:00:00.200;-inf;-3.1\n3;00:01:00.000;-22.0 LUFS;-2.9\n").unwrap();
+
+        let preview = preview_csv(&path, 10).unwrap();
+        assert_eq!(preview.layout, CsvLayout { delimiter: b';', skip_lines: 2, has_header: true, time_column: 1, value_column: 2, time_unit: CsvTimeUnit::Seconds });
+        assert_eq!(preview.rows.len(), 3);
+
+        let curve = parse_csv(path, &test_logger(), &mut |_| {}).unwrap();
+        assert_eq!(curve.points, vec![[0.1, -24.5], [0.2, SILENCE_FLOOR_DBFS], [60.0, -22.0]]);
+        assert_eq!(parse_csv_time("1500", CsvTimeUnit::Milliseconds), Some(1.5));
+    }
+
+    #[test]
+    fn profile_rules_pick_the_first_matching_profile() {
+        assert!(wildcard_match("*_dx.wav", "Reel1_DX.wav"));
+        assert!(wildcard_match("mix_v?.*", "mix_v3.wav"));
+        assert!(!wildcard_match("*_dx.wav", "reel1_dx.wav.bak"));
+        assert!(!wildcard_match("mix_v?.wav", "mix_v10.wav"));
+
+        let rig = AnalysisProfile {
+            name: "Rig".into(),
+            analysis: AnalysisSettings { window_sec: 1.0, ..AnalysisSettings::default() },
+            csv_layout: Some(CsvLayout { has_header: false, time_column: 1, value_column: 0, ..CsvLayout::default() }),
+        };
+        let profiles = vec![rig];
+        // 引用不存在的配置的规则被跳过
+        let rules = vec![
+            ProfileRule { pattern: "*.csv".into(), profile: "Missing".into() },
+            ProfileRule { pattern: "rig_*".into(), profile: "Rig".into() },
+        ];
+        assert!(select_profile(Path::new("mix.wav"), &rules, &profiles).is_none());
+        let profile = select_profile(Path::new("/logs/rig_01.csv"), &rules, &profiles).unwrap();
+
+        let dir = TempDir::new("profile_csv");
+        let path = dir.join("rig_01.csv");
+        std::fs::write(&path, "-20.0,0.0\n-22.0,1.0\n-24.0,2.0\n").unwrap();
+        let curve = load_file_with_profile(path, profile, &test_logger(), &mut |_| {}).unwrap();
+        assert_eq!(curve.points, vec![[0.0, -20.0], [1.0, -22.0], [2.0, -24.0]]);
+        assert_eq!(curve.profile.as_deref(), Some("Rig"));
+        assert!(curve_export_metadata(&curve).contains(&("profile", "Rig".to_string())));
+    }
+
+    #[test]
+    fn statistics_helpers_match_closed_forms() {
+        // 满幅正弦的 RMS 为 1/√2 (约 -3.01 dBFS)，静音为底噪
+        let sine: Vec<f64> = (0..4800).map(|i| (2.0 * std::f64::consts::PI * i as f64 / 48.0).sin()).collect();
+        assert!((calculate_rms_dbfs(&sine) + 3.0103).abs() < 1e-3);
+        assert_eq!(calculate_rms_dbfs(&[0.0; 16]), SILENCE_FLOOR_DBFS);
+
+        let a = [1.0, 2.0, 3.0, 4.0];
+        let inverted: Vec<f64> = a.iter().map(|v| 10.0 - 2.0 * v).collect();
+        assert!((calculate_correlation(&a, &a, a.len()) - 1.0).abs() < 1e-12);
+        assert!((calculate_correlation(&a, &inverted, a.len()) + 1.0).abs() < 1e-12);
+        assert!((calculate_t_statistic(0.5, 2.0, 16) - 1.0).abs() < 1e-12);
+
+        // r = 0.95、100 点时 95% 区间约为 [0.926, 0.966]
+        let test = correlation_test(0.95, 100, 0.9, 0.95).unwrap();
+        assert!((test.interval.0 - 0.9265).abs() < 1e-4 && (test.interval.1 - 0.9661).abs() < 1e-4);
+        assert!(test.passes);
+        assert!(!correlation_test(0.95, 100, 0.93, 0.95).unwrap().passes);
+        assert_eq!(correlation_test(1.0, 50, 0.9, 0.99).unwrap().interval, (1.0, 1.0));
+        assert!(correlation_test(0.95, 3, 0.9, 0.95).is_none());
+
+        // 任意置信度的临界值与统计表一致；自由度很大时 t 分布趋于正态
+        assert!((critical_value(0.95) - 1.959964).abs() < 1e-5);
+        assert!((critical_value(0.999) - 3.290527).abs() < 1e-5);
+        assert!((t_critical_value(0.95, 1) - 12.7062).abs() < 1e-3);
+        assert!((t_critical_value(0.95, 10) - 2.228139).abs() < 1e-5);
+        assert!((t_critical_value(0.999, 5) - 6.868827).abs() < 1e-5);
+        assert!((t_critical_value(0.99, 100_000) - critical_value(0.99)).abs() < 1e-3);
+        assert!((student_t_p_value(2.228139, 10) - 0.05).abs() < 1e-6);
+    }
+
+    #[test]
+    fn equal_loudness_filters_follow_the_iso226_contour() {
+        // 60 phon 时 1 kHz 需要 60 dB SPL，100 Hz 约 78.6 dB，3150 Hz 约 56.4 dB
+        assert!((iso226_spl(ISO226_1KHZ, 60.0) - 60.0).abs() < 0.05);
+        let weights = equal_loudness_weights(60.0);
+        assert!((weights[7] + 18.64).abs() < 0.05 && (weights[22] - 3.59).abs() < 0.05);
+
+        for sample_rate in [11025.0, 48000.0] {
+            let filters = equal_loudness_filters(sample_rate, 60.0);
+            let response = |f: f64| filters.iter().map(|filter| filter.response_db(sample_rate, f)).sum::<f64>();
+            for (i, &f) in ISO226_FREQ_HZ.iter().enumerate().skip(2).filter(|(_, f)| **f < 0.45 * sample_rate) {
+                assert!((response(f) - weights[i]).abs() < 0.5, "{} Hz @ {}: {} vs {}", f, sample_rate, response(f), weights[i]);
+            }
+        }
+    }
+
+    #[test]
+    fn loudness_jumps_are_merged_per_edit_point() {
+        // 每 0.1 秒一点：1 秒处在两步内升高 8 dB，2 秒处一步降低 7 dB，3 秒处进入静音 (不算跳变)
+        let level = |t: f64| if t < 0.95 { -30.0 } else if t < 1.05 { -26.0 } else if t < 1.95 { -22.0 } else if t < 2.95 { -29.0 } else { SILENCE_FLOOR_DBFS };
+        let points: Vec<[f64; 2]> = (0..40).map(|i| i as f64 * 0.1).map(|t| [t, level(t)]).collect();
+
+        let jumps = find_loudness_jumps(&points, 6.0, 0.5);
+        assert_eq!(jumps.len(), 2, "{:?}", jumps);
+        assert!((jumps[0].change_db - 8.0).abs() < 1e-9 && jumps[0].start < 1.0 && jumps[0].end > 1.0);
+        assert!((jumps[1].change_db + 7.0).abs() < 1e-9 && (jumps[1].start - 1.9).abs() < 1e-9 && (jumps[1].end - 2.0).abs() < 1e-9);
+        // 调高阈值后只剩变化更大的一处
+        assert_eq!(find_loudness_jumps(&points, 7.5, 0.5).len(), 1);
+        assert!(find_loudness_jumps(&points, 6.0, 0.05).is_empty());
+    }
+
+    #[test]
+    fn synthetic_references_follow_the_template_hop() {
+        let template = AudioCurve::new("stem".into(), (0..50).map(|i| [0.2 + i as f64 * 0.1, -20.0]).collect(), 5.0, -20.0);
+
+        let constant = SyntheticReference::Constant(-18.0).curve_like(&template, "ref".into()).unwrap();
+        assert_eq!(constant.points.len(), template.points.len());
+        assert!(constant.points.iter().zip(&template.points).all(|(c, t)| c[0] == t[0] && c[1] == -18.0));
+        assert!((constant.average_dbfs + 18.0).abs() < 1e-9);
+
+        // 1 秒到 3 秒从 -30 升到 -10，两端保持端点电平
+        let mut ramp = SyntheticReference::Breakpoints(vec![[3.0, -10.0], [1.0, -30.0]]);
+        ramp.normalize();
+        assert_eq!(ramp.level_at(0.5), Some(-30.0));
+        assert!((ramp.level_at(2.0).unwrap() + 20.0).abs() < 1e-9);
+        assert_eq!(ramp.level_at(4.0), Some(-10.0));
+        assert!(SyntheticReference::Breakpoints(Vec::new()).curve_like(&template, "empty".into()).is_none());
+    }
+
+    /// 振幅 4 dB 的正弦包络，及整体低 3 dB 的同一包络
+    fn level_pair() -> (AudioCurve, AudioCurve) {
+        let points: Vec<[f64; 2]> = (0..100).map(|i| [i as f64 * 0.1, -20.0 + (i as f64 * 0.2).sin() * 4.0]).collect();
+        let quieter = points.iter().map(|p| [p[0], p[1] - 3.0]).collect();
+        (AudioCurve::new("a".to_string(), points, 9.9, -20.0), AudioCurve::new("b".to_string(), quieter, 9.9, -23.0))
+    }
+
+    #[test]
+    fn compare_with_level_match_reports_only_dynamics() {
+        let (a, quieter) = level_pair();
+        let plain = compare(&a, &quieter, &CompareOptions::default()).unwrap();
+        assert!((plain.mean_diff - 3.0).abs() < 1e-9 && plain.level_offset.is_none());
+
+        let matched = compare(&a, &quieter, &CompareOptions { level_match: true, ..CompareOptions::default() }).unwrap();
+        assert!(matched.mean_diff.abs() < 1e-9 && matched.std_dev < 1e-9);
+        assert_eq!(matched.level_offset, Some(3.0));
+    }
+
+    #[test]
+    fn offset_beyond_the_curve_duration_has_no_comparison() {
+        let (a, quieter) = level_pair();
+        for offset in [12.0, -12.0] {
+            assert!(compare(&a, &quieter, &CompareOptions { offset, ..CompareOptions::default() }).is_none(), "offset {}", offset);
+            assert!(compare(&a, &quieter, &CompareOptions { offset, level_match: true, ..CompareOptions::default() }).is_none());
+        }
+        // 部分重叠时仍有结果
+        let partial = compare(&a, &quieter, &CompareOptions { offset: 5.0, ..CompareOptions::default() }).unwrap();
+        assert!(partial.mean_diff.is_finite() && partial.overlap_ratio < 1.0);
+    }
+
+    #[test]
+    fn t_test_is_evaluated_against_any_target_from_stored_statistics() {
+        let (a, quieter) = level_pair();
+        // 改变目标差值只需重算 t 统计量
+        let plain = compare(&a, &quieter, &CompareOptions::default()).unwrap();
+        let retargeted = compare(&a, &quieter, &CompareOptions { target_diff: 2.5, ..CompareOptions::default() }).unwrap();
+        assert!((plain.t_statistic_for(2.5) - retargeted.t_statistic).abs() < 1e-9);
+
+        // 差值约 3 dB ± 0.1 dB：与 0 dB 显著不同，与 3 dB 无显著差异
+        let jittered = AudioCurve::new("c".to_string(), a.points.iter().enumerate().map(|(i, p)| [p[0], p[1] - 3.0 + 0.1 * (i as f64 * 1.7).sin()]).collect(), 9.9, -23.0);
+        let noisy = compare(&a, &jittered, &CompareOptions::default()).unwrap();
+        assert!(noisy.differs_from_target(0.0, 0.99) && !noisy.differs_from_target(noisy.mean_diff, 0.90));
+    }
+
+    #[test]
+    fn aligned_values_are_kept_only_when_requested() {
+        let (a, quieter) = level_pair();
+        let plain = compare(&a, &quieter, &CompareOptions::default()).unwrap();
+        assert!(!plain.has_aligned_values() && plain.a_vals.is_empty() && plain.b_vals.is_empty());
+
+        let kept = compare(&a, &quieter, &CompareOptions { keep_aligned: true, ..CompareOptions::default() }).unwrap();
+        assert!(kept.has_aligned_values());
+        assert_eq!(kept.a_vals, a.points.iter().map(|p| p[1]).collect::<Vec<_>>());
+        assert_eq!(kept.b_vals, quieter.points.iter().map(|p| p[1]).collect::<Vec<_>>());
+        // 保留与否不影响统计量
+        assert_eq!((kept.mean_diff, kept.std_dev, kept.correlation_coefficient), (plain.mean_diff, plain.std_dev, plain.correlation_coefficient));
+    }
+
+    #[test]
+    fn aligned_pairs_restore_both_levels_with_or_without_kept_values() {
+        let (a, quieter) = level_pair();
+        let plain = compare(&a, &quieter, &CompareOptions::default()).unwrap();
+        let kept = compare(&a, &quieter, &CompareOptions { keep_aligned: true, ..CompareOptions::default() }).unwrap();
+        // 导出用的配对数据：未保留时由差值点和 Bland-Altman 点还原，与保留的原始电平一致
+        for result in [&plain, &kept] {
+            assert_eq!(result.aligned_pairs().count(), a.points.len());
+            for (row, (pa, pb)) in result.aligned_pairs().zip(a.points.iter().zip(&quieter.points)) {
+                assert!((row[0] - pa[0]).abs() < 1e-12 && (row[1] - pa[1]).abs() < 1e-9 && (row[2] - pb[1]).abs() < 1e-9 && (row[3] - 3.0).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn curves_with_different_point_spacing_are_resampled() {
+        // 同一条响度曲线：A 为 0.5s 步进导出的 CSV，B 为 0.1s 步进的 WAV 分析结果
+        let level = |t: f64| -20.0 + 2.0 * (t * 0.7).sin();
+        let coarse: Vec<[f64; 2]> = (0..20).map(|i| [0.2 + i as f64 * 0.5, level(0.2 + i as f64 * 0.5)]).collect();
+        let fine: Vec<[f64; 2]> = (0..96).map(|i| [0.2 + i as f64 * 0.1, level(0.2 + i as f64 * 0.1)]).collect();
+        let a = AudioCurve::new("a".to_string(), coarse, 9.7, -20.0);
+        let b = AudioCurve::new("b".to_string(), fine, 9.7, -20.0);
+        assert_eq!(median_spacing(&a.points), Some(0.5));
+        assert!(!same_time_base(&a, &b));
+
+        // 按索引配对会得到明显的差值；重采样后只剩线性插值误差，且按较疏的 A 配对
+        assert!(compare_curves(&a, &b, 0.0, false).std_dev > 0.5);
+        let result = compare(&a, &b, &CompareOptions::default()).unwrap();
+        assert!(result.resampled && result.diff_points.len() == 20);
+        assert!(result.mean_diff.abs() < 0.01 && result.std_dev < 0.01, "mean {} std {}", result.mean_diff, result.std_dev);
+
+        // 交换 A / B 时以 B 为网格，差值取反
+        let swapped = compare(&b, &a, &CompareOptions::default()).unwrap();
+        assert_eq!(swapped.diff_points.len(), 20);
+        assert!((swapped.mean_diff + result.mean_diff).abs() < 1e-9);
+    }
+
+    #[test]
+    fn consistency_score_combines_components() {
+        let points: Vec<[f64; 2]> = (0..200).map(|i| [i as f64 * 0.1, -20.0 + (i as f64 * 0.3).sin() * 6.0]).collect();
+        let a = AudioCurve::new("a".to_string(), points.clone(), 20.0, -20.0);
+        let louder = AudioCurve::new("b".to_string(), points.iter().map(|p| [p[0], p[1] + 3.0]).collect(), 20.0, -17.0);
+        let weights = ConsistencyWeights::default();
+
+        // 完全相同：三个分量都为 1
+        let same = compare_curves(&a, &a.clone(), 0.0, false);
+        assert!((same.consistency_score(&weights, 0.0) - 100.0).abs() < 1e-9);
+
+        // 整体差 3 dB：形状和离散度满分，但没有窗口落在目标 0 dB ±1 dB 以内
+        let shifted = compare_curves(&a, &louder, 0.0, false);
+        assert_eq!(shifted.within_tolerance(0.0, 1.0), 0.0);
+        assert!((shifted.consistency_score(&weights, 0.0) - 60.0).abs() < 1e-6);
+        // 以 -3 dB 为目标差值时又是满分
+        assert!((shifted.consistency_score(&weights, -3.0) - 100.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn full_scale_wav_is_0_dbfs_in_every_bit_depth() {
+        let dir = TempDir::new("bitdepth");
+        let logger = test_logger();
+
+        for (sample_format, bits) in ALL_FORMATS {
+            let path = dir.join(&format!("full_scale_{:?}_{}.wav", sample_format, bits));
+            write_wav(&path, Signal::Square { freq: 1000.0, peak_dbfs: 0.0 }, (sample_format, bits), 8000, 1, 2.0);
+            let curve = parse_wav(path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            assert!(!curve.points.is_empty());
+            for p in &curve.points {
+                assert!(p[1].abs() < 0.01, "{:?} {}-bit: window at {}s is {} dBFS", sample_format, bits, p[0], p[1]);
+            }
+            assert!(curve.average_dbfs.abs() < 0.01, "{:?} {}-bit: average {} dBFS", sample_format, bits, curve.average_dbfs);
+        }
+    }
+
+    #[test]
+    fn mmap_and_reader_decode_the_same_curve_in_every_format() {
+        let dir = TempDir::new("mmap");
+        let logger = test_logger();
+        let without_mmap = AnalysisSettings { use_mmap: false, ..AnalysisSettings::default() };
+
+        for format in ALL_FORMATS {
+            let path = dir.join(&format!("noise_{:?}_{}.wav", format.0, format.1));
+            write_wav(&path, Signal::WhiteNoise { peak_dbfs: -6.0 }, format, 8000, 2, 2.0);
+            let mapped = parse_wav(path.clone(), &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            let reader_curve = parse_wav(path, &without_mmap, &logger, &mut |_| {}).unwrap();
+            assert!(!mapped.points.is_empty());
+            assert_eq!(mapped.points, reader_curve.points, "{:?} {}-bit: mmap and reader curves differ", format.0, format.1);
+        }
+    }
+
+    /// 内存映射与 WavReader 的读取耗时对比 (10 分钟 48 kHz 24-bit 立体声，约 170 MB)，各取 3 次中最快的一次。
+    /// 耗时与机器有关，默认不运行：cargo test --release --lib -- --ignored mmap_reading_benchmark --nocapture
+    #[test]
+    #[ignore]
+    fn mmap_reading_benchmark() {
+        let dir = TempDir::new("mmap_bench");
+        let path = dir.join("long.wav");
+        write_wav(&path, Signal::WhiteNoise { peak_dbfs: -6.0 }, (hound::SampleFormat::Int, 24), 48000, 2, 600.0);
+        let best_of_3 = |use_mmap: bool| {
+            let analysis = AnalysisSettings { use_mmap, ..AnalysisSettings::default() };
+            (0..3)
+                .map(|_| {
+                    let started = Instant::now();
+                    parse_wav(path.clone(), &analysis, &NoLog, &mut |_| {}).unwrap();
+                    started.elapsed()
+                })
+                .min()
+                .unwrap()
+        };
+        let (mapped, reader) = (best_of_3(true), best_of_3(false));
+        println!("parse_wav: mmap {:.2?}, WavReader {:.2?} ({:.2}x)", mapped, reader, reader.as_secs_f64() / mapped.as_secs_f64());
+        assert!(mapped < reader, "mmap {:?} is not faster than WavReader {:?}", mapped, reader);
+    }
+
+    #[test]
+    fn in_memory_wav_matches_the_file_curve() {
+        let dir = TempDir::new("bytes");
+        let logger = test_logger();
+
+        for format in ALL_FORMATS {
+            let path = dir.join(&format!("step_{:?}_{}.wav", format.0, format.1));
+            write_wav(&path, Signal::Step { first_dbfs: -6.0, second_dbfs: -18.0, switch_sec: 1.0 }, format, 8000, 2, 2.0);
+            let curve = parse_wav(path.clone(), &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            // 浏览器中没有文件路径，从内存解析的结果必须与按路径解析一致
+            let bytes = std::fs::read(&path).unwrap();
+            let bytes_curve = parse_wav_bytes("step.wav", &bytes, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            assert_eq!(curve.points, bytes_curve.points, "{:?} {}-bit: file and in-memory curves differ", format.0, format.1);
+            assert_eq!(bytes_curve.name, "step.wav");
+            assert!(bytes_curve.source.is_none());
+        }
+    }
+
+    #[test]
+    fn in_memory_csv_matches_the_file_curve() {
+        let dir = TempDir::new("csv_bytes");
+        let wav_path = dir.join("tone.wav");
+        let csv_path = dir.join("tone.csv");
+        write_wav(&wav_path, Signal::Step { first_dbfs: -6.0, second_dbfs: -18.0, switch_sec: 1.0 }, ALL_FORMATS[0], 8000, 1, 2.0);
+        let logger = test_logger();
+        let analysis = AnalysisSettings::default();
+        let original = load_file(wav_path, &analysis, &logger, &mut |_| {}).unwrap();
+        let options = CsvExportOptions { normalize_mode: NormalizeMode::Average, target: -23.0, resample_interval: None };
+        let mut exported = Vec::new();
+        write_curve_csv_to(&original, &options, &mut exported, &logger).unwrap();
+        std::fs::write(&csv_path, &exported).unwrap();
+
+        // 浏览器中导出为下载、导入时只有文件内容：与按路径导入的结果一致
+        let file_curve = load_file(csv_path, &analysis, &logger, &mut |_| {}).unwrap();
+        let bytes_curve = load_bytes("tone.csv", &exported, &analysis, &logger, &mut |_| {}).unwrap();
+        assert_eq!(file_curve.points, bytes_curve.points);
+        assert_eq!(file_curve.average_dbfs, bytes_curve.average_dbfs);
+        assert_eq!(bytes_curve.name, "tone.csv");
+        assert!(bytes_curve.source.is_none());
+        assert_eq!(bytes_curve.points.len(), original.points.len());
+    }
+
+    #[test]
+    fn paths_without_a_file_name_are_errors_not_panics() {
+        assert_eq!(file_display_name(Path::new("/music/mix.wav")), "mix.wav");
+        assert_eq!(file_display_name(Path::new("..")), "..");
+        for path in ["..", "/"] {
+            assert!(parse_wav(PathBuf::from(path), &AnalysisSettings::default(), &NoLog, &mut |_| {}).is_err());
+            assert!(parse_csv_with_layout(PathBuf::from(path), &CsvLayout::default(), &NoLog, &mut |_| {}).is_err());
+        }
+    }
+
+    #[test]
+    fn header_only_wav_reports_no_audio_data() {
+        let dir = TempDir::new("empty");
+        let path = dir.join("header_only.wav");
+        let spec = hound::WavSpec { channels: 2, sample_rate: 48000, bits_per_sample: 16, sample_format: hound::SampleFormat::Int };
+        hound::WavWriter::create(&path, spec).unwrap().finalize().unwrap();
+
+        let err = load_file_with_retry(path, &AnalysisSettings::default(), &test_logger(), &mut |_| {}).unwrap_err();
+        assert!(matches!(err, AnalyzerError::EmptyFile), "unexpected error: {}", err);
+    }
+
+    #[test]
+    fn truncated_data_chunk_is_a_decode_error_in_every_format() {
+        let dir = TempDir::new("truncated");
+        for (i, format) in ALL_FORMATS.iter().enumerate() {
+            let path = dir.join(&format!("truncated_{}.wav", i));
+            write_wav(&path, Signal::Sine { freq: 440.0, peak_dbfs: -6.0 }, *format, 8000, 2, 1.0);
+            // 去掉 data 块后半部分，头部声明的长度不变
+            let len = std::fs::metadata(&path).unwrap().len();
+            File::options().write(true).open(&path).unwrap().set_len(len / 2).unwrap();
+
+            let err = parse_wav(path.clone(), &AnalysisSettings::default(), &test_logger(), &mut |_| {}).unwrap_err();
+            assert!(matches!(err, AnalyzerError::DecodeFailed(_)), "{:?}: unexpected error: {}", format, err);
+            assert!(!err.is_retryable());
+            let scanned = quick_scan_wav(&path, &mut |_| {});
+            assert!(matches!(scanned, Err(AnalyzerError::DecodeFailed(_))), "{:?}: {:?}", format, scanned);
+        }
+    }
+
+    #[test]
+    fn synthetic_signals_have_expected_rms_in_every_format() {
+        let dir = TempDir::new("synthetic_rms");
+        let logger = test_logger();
+        let full_scale_sine = Signal::Sine { freq: 1000.0, peak_dbfs: 0.0 };
+
+        for format in ALL_FORMATS {
+            let path = dir.join(&format!("sine_{:?}_{}.wav", format.0, format.1));
+            write_wav(&path, full_scale_sine, format, 48000, 1, 2.0);
+            let curve = parse_wav(path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            // 满幅正弦的 RMS 为 1/√2，即 -3.01 dBFS
+            for p in &curve.points {
+                assert!((p[1] + 3.0103).abs() < 0.05, "{:?}: window at {}s is {} dBFS", format, p[0], p[1]);
+            }
+        }
+
+        let cases = [
+            (Signal::Silence, SILENCE_FLOOR_DBFS, 1e-9),
+            // 均匀白噪声 RMS = 峰值 / √3 (-4.77 dB)
+            (Signal::WhiteNoise { peak_dbfs: -6.0 }, -6.0 - 4.7712, 0.1),
+        ];
+        for (signal, expected, tolerance) in cases {
+            let path = dir.join("signal.wav");
+            write_wav(&path, signal, ALL_FORMATS[1], 48000, 1, 2.0);
+            let curve = parse_wav(path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            assert!((curve.average_dbfs - expected).abs() < tolerance, "{:?}: average {} dBFS", signal, curve.average_dbfs);
+        }
+    }
+
+    #[test]
+    fn window_and_step_determine_point_times() {
+        let dir = TempDir::new("window_step");
+        let path = dir.join("step.wav");
+        write_wav(&path, Signal::Step { first_dbfs: -10.0, second_dbfs: -30.0, switch_sec: 1.0 }, ALL_FORMATS[0], 48000, 1, 2.0);
+        let curve = parse_wav(path, &AnalysisSettings::default(), &test_logger(), &mut |_| {}).unwrap();
+
+        // 0.4s 窗口、0.1s 步进：2s 文件有 (2.0 - 0.4) / 0.1 + 1 = 17 个窗口，时间为窗口中心
+        assert_eq!(curve.points.len(), 17);
+        let timing = curve.details.as_ref().unwrap().timing;
+        assert!(timing.decode_sec > 0.0 && timing.windowing_sec > 0.0);
+        for (i, p) in curve.points.iter().enumerate() {
+            assert!((p[0] - (0.2 + 0.1 * i as f64)).abs() < 1e-9, "point {} at {}s", i, p[0]);
+        }
+        assert_eq!(curve.duration, 2.0);
+
+        // 完全落在阶跃一侧的窗口读到该侧的电平 (正弦 RMS 比峰值低 3.01 dB)
+        for p in &curve.points {
+            if p[0] + 0.2 <= 1.0 {
+                assert!((p[1] + 13.0103).abs() < 0.05, "window at {}s is {} dBFS", p[0], p[1]);
+            } else if p[0] - 0.2 >= 1.0 {
+                assert!((p[1] + 33.0103).abs() < 0.05, "window at {}s is {} dBFS", p[0], p[1]);
+            }
+        }
+    }
+
+    #[test]
+    fn duplicate_content_is_found_regardless_of_name_and_analysis() {
+        let dir = TempDir::new("duplicates");
+        let logger = test_logger();
+        let (original, copy, other) = (dir.join("mix.wav"), dir.join("mix_final.wav"), dir.join("other.wav"));
+        write_wav(&original, Signal::Sine { freq: 1000.0, peak_dbfs: -6.0 }, ALL_FORMATS[0], 48000, 2, 1.0);
+        std::fs::copy(&original, &copy).unwrap();
+        write_wav(&other, Signal::Sine { freq: 1000.0, peak_dbfs: -7.0 }, ALL_FORMATS[0], 48000, 2, 1.0);
+
+        // 样本哈希在计权之前累积，分析参数不同的同一内容仍是重复
+        let short_windows = AnalysisSettings { window_sec: 0.2, weighting: CurveWeighting::K, ..AnalysisSettings::default() };
+        let curves = vec![
+            parse_wav(original, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap(),
+            parse_wav(other, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap(),
+            parse_wav(copy, &short_windows, &logger, &mut |_| {}).unwrap(),
+        ];
+        assert_eq!(find_duplicates(&curves), [None, None, Some(0)]);
+
+        // 没有样本哈希的曲线 (CSV) 按曲线点判断
+        let points = vec![[0.0, -20.0], [0.1, -21.0]];
+        let csv = |name: &str, points: Vec<[f64; 2]>| AudioCurve::new(name.into(), points, 0.1, -20.5);
+        let imported = [csv("a.csv", points.clone()), csv("b.csv", vec![[0.0, -20.0], [0.1, -22.0]]), csv("c.csv", points)];
+        assert_eq!(find_duplicates(&imported), [None, None, Some(0)]);
+    }
+
+    #[test]
+    fn analysis_settings_control_windows_weighting_and_floor() {
+        let dir = TempDir::new("analysis_settings");
+        let path = dir.join("step.wav");
+        write_wav(&path, Signal::Step { first_dbfs: -10.0, second_dbfs: -30.0, switch_sec: 1.0 }, ALL_FORMATS[0], 48000, 1, 2.0);
+        let logger = test_logger();
+        let parse = |analysis: &AnalysisSettings| parse_wav(path.clone(), analysis, &logger, &mut |_| {}).unwrap();
+
+        let coarse = AnalysisSettings { window_sec: 0.5, step_sec: 0.25, ..AnalysisSettings::default() };
+        let curve = parse(&coarse);
+        assert_eq!(curve.points.len(), 7);
+        assert_eq!(curve.points[0][0], 0.25);
+        assert!(curve.analysis.as_ref().is_some_and(|a| a.same_results(&coarse)));
+        assert!(!coarse.same_results(&AnalysisSettings::default()));
+        assert!(coarse.same_results(&AnalysisSettings { use_mmap: false, ..coarse.clone() }));
+
+        // 1 kHz 处 K 计权增益约 +0.69 dB
+        let k = parse(&AnalysisSettings { weighting: CurveWeighting::K, ..AnalysisSettings::default() });
+        assert!((k.points[2][1] - (-13.0103 + 0.69)).abs() < 0.05, "K-weighted {} dB", k.points[2][1]);
+        // 等响计权以 1 kHz 为 0 dB
+        let perceived = parse(&AnalysisSettings { weighting: CurveWeighting::EqualLoudness { phon: 60.0 }, ..AnalysisSettings::default() });
+        assert!((perceived.points[2][1] - (-13.0103)).abs() < 0.2, "ISO 226 weighted {} dB", perceived.points[2][1]);
+
+        // -33 dBFS RMS 的后半段低于 -25 dBFS 门限，记为静音
+        let gated = parse(&AnalysisSettings { noise_floor_dbfs: -25.0, ..AnalysisSettings::default() });
+        assert_eq!(gated.points.last().map(|p| p[1]), Some(SILENCE_FLOOR_DBFS));
+        assert!(gated.points[0][1] > -25.0);
+
+        // 跳过前 1 秒：只剩 -30 dB 峰值 (-33 dBFS RMS) 的后半段，窗口、统计和 EBU 曲线都不含前半段
+        let tail_only = parse(&AnalysisSettings { skip_head_sec: 1.0, ..AnalysisSettings::default() });
+        assert_eq!(tail_only.points.len(), 7);
+        assert!((tail_only.points[0][0] - 1.2).abs() < 1e-9 && (tail_only.points[6][0] - 1.8).abs() < 1e-9);
+        assert!((tail_only.average_dbfs - (-33.0103)).abs() < 0.05, "{}", tail_only.average_dbfs);
+        assert!((tail_only.momentary[0][0] - 1.2).abs() < 1e-9);
+        assert!(tail_only.loudness.unwrap().integrated_lufs < -30.0);
+        assert_eq!(tail_only.excluded_ranges(), vec![[0.0, 1.0]]);
+        // 跳过的时长超过文件长度时分析整个文件
+        let everything = parse(&AnalysisSettings { skip_head_sec: 1.5, skip_tail_sec: 1.5, ..AnalysisSettings::default() });
+        assert_eq!(everything.points.len(), 17);
+        assert!(everything.excluded_ranges().is_empty());
+    }
+
+    #[test]
+    fn average_is_a_power_mean_unless_legacy_mode_is_selected() {
+        // -10 dB 与 -30 dB 各占一半：功率平均 ≈ -13.0 dB，dB 值平均为 -20 dB
+        let points = [[0.0, -10.0], [1.0, -30.0]];
+        assert!((AverageMode::Power.average(&points) - 10.0 * ((0.1 + 0.001) / 2.0f64).log10()).abs() < 1e-9);
+        assert_eq!(AverageMode::DbMean.average(&points), -20.0);
+        assert_eq!(AverageMode::Power.average(&[[0.0, -23.0]; 4]), -23.0);
+        assert_eq!(AverageMode::Power.average(&[]), SILENCE_FLOOR_DBFS);
+    }
+
+    #[test]
+    fn stereo_and_mono_windows_share_times_and_duration() {
+        let dir = TempDir::new("mono_stereo");
+        let signal = Signal::Step { first_dbfs: -6.0, second_dbfs: -18.0, switch_sec: 0.75 };
+        let logger = test_logger();
+        // 2.05s：最后一个完整窗口之后剩 50ms 未分析
+        let parse = |channels: u16, downmix: DownmixMode| {
+            let path = dir.join(&format!("step_{}ch.wav", channels));
+            write_wav(&path, signal, ALL_FORMATS[0], 44100, channels, 2.05);
+            parse_wav(path, &AnalysisSettings { downmix, ..AnalysisSettings::default() }, &logger, &mut |_| {}).unwrap()
+        };
+        let mono = parse(1, DownmixMode::default());
+        for downmix in [DownmixMode::Interleaved, DownmixMode::default()] {
+            let stereo = parse(2, downmix);
+            assert_eq!(mono.points.len(), stereo.points.len(), "{:?}", downmix);
+            for (m, s) in mono.points.iter().zip(&stereo.points) {
+                assert!((m[0] - s[0]).abs() < 1e-12, "{:?}: mono {}s vs stereo {}s", downmix, m[0], s[0]);
+            }
+            assert!((stereo.duration - 2.05).abs() < 1e-9, "{:?}: duration {}", downmix, stereo.duration);
+            let tail = stereo.details.and_then(|d| d.wav).map(|w| w.unanalyzed_tail_sec).unwrap();
+            assert!((tail - 0.05).abs() < 0.001, "{:?}: tail {}", downmix, tail);
+        }
+        assert_eq!(mono.points[0][0], 0.2);
+    }
+
+    #[test]
+    fn files_shorter_than_a_window_yield_one_point() {
+        let dir = TempDir::new("short_files");
+        let logger = test_logger();
+        let sine = Signal::Sine { freq: 1000.0, peak_dbfs: -6.0 };
+        // 0.05s / 0.3s 短于 0.4s 窗口，0.4s 恰好一个窗口
+        for seconds in [0.05, 0.3, 0.4] {
+            let path = dir.join(&format!("short_{}.wav", seconds));
+            write_wav(&path, sine, ALL_FORMATS[0], 48000, 1, seconds);
+            let curve = parse_wav(path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+            assert_eq!(curve.points.len(), 1, "{}s", seconds);
+            assert!((curve.points[0][0] - seconds / 2.0).abs() < 1e-9, "{}s: point at {}", seconds, curve.points[0][0]);
+            assert!((curve.points[0][1] + 9.0103).abs() < 0.1, "{}s: {} dBFS", seconds, curve.points[0][1]);
+            assert!((curve.duration - seconds).abs() < 1e-9 && (curve.average_dbfs - curve.points[0][1]).abs() < 1e-9);
+        }
+
+        // 8 Hz 采样率下 0.1s 步进不足一帧，按 1 帧步进 (窗口 3 帧，16 帧共 14 个窗口)
+        let path = dir.join("8hz.wav");
+        write_wav(&path, Signal::WhiteNoise { peak_dbfs: -6.0 }, ALL_FORMATS[0], 8, 1, 2.0);
+        let curve = parse_wav(path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+        assert_eq!(curve.points.len(), 14);
+        assert!((curve.points[1][0] - curve.points[0][0] - 0.125).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unsupported_sample_format_is_reported() {
+        let dir = TempDir::new("unsupported");
+        let path = dir.join("8bit.wav");
+        write_wav(&path, Signal::Sine { freq: 440.0, peak_dbfs: -6.0 }, (hound::SampleFormat::Int, 8), 8000, 1, 1.0);
+
+        let err = parse_wav(path, &AnalysisSettings::default(), &test_logger(), &mut |_| {}).unwrap_err();
+        assert!(matches!(err, AnalyzerError::UnsupportedFormat { format: hound::SampleFormat::Int, bits: 8 }), "unexpected error: {}", err);
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn csv_export_keeps_curve_metrics() {
+        let dir = TempDir::new("csv_metrics");
+        let (wav_path, csv_path) = (dir.join("noise.wav"), dir.join("noise.csv"));
+        write_wav(&wav_path, Signal::WhiteNoise { peak_dbfs: -12.0 }, ALL_FORMATS[3], 44100, 2, 3.0);
+
+        let logger = test_logger();
+        let original = parse_wav(wav_path, &AnalysisSettings::default(), &logger, &mut |_| {}).unwrap();
+        let options = CsvExportOptions { normalize_mode: NormalizeMode::Average, target: -23.0, resample_interval: None };
+        write_curve_csv(&original, &options, &csv_path, &logger).unwrap();
+        let reimported = parse_csv(csv_path.clone(), &logger, &mut |_| {}).unwrap();
+
+        // 指标写在表头之前，重新导入时作为元数据跳过；按曲线点重新计算的指标与原曲线一致
+        assert!(std::fs::read_to_string(&csv_path).unwrap().lines().any(|line| line.starts_with("# average_dbfs,")));
+        assert_eq!(original.metric(AVERAGE_METRIC), Some(MetricValue::Dbfs(original.average_dbfs)));
+        assert_eq!(original.metrics.keys().collect::<Vec<_>>(), reimported.metrics.keys().collect::<Vec<_>>());
+        for (name, value) in &original.metrics {
+            assert!((value.value().unwrap() - reimported.metrics[name].value().unwrap()).abs() < 1e-6, "{}", name);
+        }
+    }
+
+    #[test]
+    fn error_regions_are_runs_beyond_the_threshold_ranked_by_peak() {
+        // 每 0.1s 一点；B 在 2.0-2.3s 比 A 低 2 dB (峰值 3 dB)，在 6.0s 附近高 5 dB，其余只差 0.2 dB
+        let a: Vec<[f64; 2]> = (0..100).map(|i| [i as f64 * 0.1, -20.0]).collect();
+        let b: Vec<[f64; 2]> = a.iter().map(|&[t, level]| {
+            let bump = match (t * 10.0).round() as i32 {
+                20 | 21 | 23 => -2.0,
+                22 => -3.0,
+                59..=61 => 5.0,
+                _ => 0.2,
+            };
+            [t, level + bump]
+        }).collect();
+        let result = compare_curves(&AudioCurve::new("a".into(), a, 10.0, -20.0), &AudioCurve::new("b".into(), b, 10.0, -20.0), 0.0, false);
+
+        let regions = result.error_regions(0.0, 1.0, 5);
+        assert_eq!(regions.len(), 2);
+        // 偏离最大的 (A - B = -5 dB) 排在前面
+        assert!((regions[0].start - 5.9).abs() < 1e-9 && (regions[0].end - 6.1).abs() < 1e-9);
+        assert!((regions[0].peak_diff + 5.0).abs() < 1e-9);
+        assert!((regions[1].start - 2.0).abs() < 1e-9 && (regions[1].end - 2.3).abs() < 1e-9);
+        assert!((regions[1].peak_time - 2.2).abs() < 1e-9 && (regions[1].peak_diff - 3.0).abs() < 1e-9);
+        assert_eq!(result.error_regions(0.0, 1.0, 1).len(), 1);
+        // 目标差值为 -0.2 dB 时其余点都在容差内，结果不变
+        assert_eq!(result.error_regions(-0.2, 1.0, 5).len(), 2);
+    }
+
+    #[test]
+    fn curves_and_results_round_trip_through_serde_and_version_1_files_still_load() {
+        let dir = TempDir::new("serde");
+        let (a_path, b_path) = (dir.join("a.wav"), dir.join("b.wav"));
+        write_wav(&a_path, Signal::Step { first_dbfs: -12.0, second_dbfs: -24.0, switch_sec: 1.0 }, ALL_FORMATS[0], 8000, 2, 2.0);
+        write_wav(&b_path, Signal::Sine { freq: 440.0, peak_dbfs: -18.0 }, ALL_FORMATS[3], 8000, 1, 2.0);
+        let analysis = AnalysisSettings::default();
+        let a = parse_wav(a_path, &analysis, &NoLog, &mut |_| {}).unwrap();
+        let b = parse_wav(b_path, &analysis, &NoLog, &mut |_| {}).unwrap();
+        let result = compare(&a, &b, &CompareOptions { offset: 0.1, target_diff: 0.0, level_match: true, keep_aligned: false }).unwrap();
+
+        let text = ron::to_string(&Versioned::new(a.clone())).unwrap();
+        let curve: Versioned<AudioCurve> = ron::from_str(&text).unwrap();
+        assert_eq!(curve.schema_version, SCHEMA_VERSION);
+        assert_eq!(curve.data, a);
+        let text = ron::to_string(&Versioned::new(result.clone())).unwrap();
+        assert_eq!(ron::from_str::<Versioned<ComparisonResult>>(&text).unwrap().data, result);
+
+        // 无法序列化的 io::Error 按系统错误码恢复，重试判断不变
+        let error = AnalyzerError::Io(std::io::Error::from_raw_os_error(13));
+        let restored: AnalyzerError = ron::from_str(&ron::to_string(&error).unwrap()).unwrap();
+        assert_eq!((restored.to_string(), restored.is_retryable()), (error.to_string(), error.is_retryable()));
+
+        // 版本 1 的文件：新增字段取默认值
+        let old: Versioned<AudioCurve> = ron::from_str(include_str!("../tests/fixtures/curve_v1.ron")).unwrap();
+        assert_eq!(old.schema_version, 1);
+        assert_eq!((old.data.name.as_str(), old.data.points.len(), old.data.loudest), ("mix_v1.wav", 4, Some([0.3, -18.25])));
+        assert_eq!(old.data.source.as_deref(), Some(Path::new("/music/mix_v1.wav")));
+        assert!(old.data.bands.is_empty() && old.data.tags.is_empty() && old.data.analysis.is_none() && old.data.loudness.is_none());
+        // 没有版本号的数据按版本 1 读取；缺少重叠比例的旧对比结果视为完全重叠
+        let unversioned: Versioned<ComparisonResult> = ron::from_str(
+            "(data: (mean_diff: 0.5, std_dev: 1.0, max_diff: 2.0, min_diff: -1.0, correlation_coefficient: 0.9, t_statistic: 3.0, diff_points: [(0.2, 0.5)]))",
+        ).unwrap();
+        assert_eq!((unversioned.schema_version, unversioned.data.overlap_ratio, unversioned.data.level_offset), (1, 1.0, None));
+    }
+}
@@ -1,1174 +1,7816 @@
 use eframe::egui;
-use egui_plot::{Legend, Line, Plot, PlotPoints};
+use egui_extras::{Column, TableBuilder};
+use egui_plot::{Legend, Line, MarkerShape, Plot, PlotBounds, PlotPoint, PlotPoints, Points};
 use hound::WavReader;
+#[cfg(not(target_arch = "wasm32"))]
+use notify::Watcher;
+#[cfg(not(target_arch = "wasm32"))]
 use rfd::FileDialog;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
 use std::error::Error;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use std::thread;
 use std::sync::mpsc;
 use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 use chrono::Local;
-use csv;
+use serde::{Deserialize, Serialize};
+use wav_lufs_curve::metrics::{AVERAGE_METRIC, PEAK_WINDOW_METRIC};
+use wav_lufs_curve::{
+    AudioCurve, AnalysisSettings, AnalysisProfile, ProfileRule, select_profile, AverageMode,
+    CurveWeighting, DownmixMode, LoadTiming, LoudnessMeasurement, BAND_COUNT,
+    EQUAL_LOUDNESS_PHON_RANGE, SILENCE_FLOOR_DBFS, SCHEMA_VERSION,
+    load_file_with_profile, load_file_with_retry, file_display_name, is_file_in_use, AnalyzerError,
+    Progress, for_each_wav_sample_with_progress, quick_scan_wav,
+    parse_csv, parse_csv_with_layout, preview_csv, CsvLayout, CsvPreview, CsvTimeUnit,
+    CsvExportOptions, NormalizeMode, write_curve_csv, analysis_metadata, write_comparison_table,
+    write_gain_adjusted_wav,
+    compare, CompareOptions, ComparisonResult, ConsistencyWeights, correlation_test,
+    durations_comparable, interpolate_at, median_spacing, CONFIDENCE_RANGE, LIMITS_OF_AGREEMENT_Z,
+    find_duplicates, find_loudness_jumps, LoudnessJump, SyntheticReference,
+    LogBuffer, LogEntry, LogFileMirror, LogLevel, Logger, DEFAULT_LOG_CAPACITY, log_command,
+    log_debug, log_error, log_info, set_log_task_id,
+};
+#[cfg(not(target_arch = "wasm32"))]
+use wav_lufs_curve::{LOG_FILE_MAX_BYTES, Versioned};
+#[cfg(target_arch = "wasm32")]
+use wav_lufs_curve::{load_bytes, write_curve_csv_to};
 
 // --- 语言和国际化结构 ---
 
-/// 定义支持的语言
+/// 界面语言，对应内置或 `locales/` 目录中的语言文件代码 (如 "zh"、"en")
 #[derive(PartialEq, Clone, Copy, Debug)]
-enum Language {
-    Chinese,
-    English,
+struct Language(&'static str);
+
+impl Language {
+    const CHINESE: Language = Language("zh");
+    const ENGLISH: Language = Language("en");
+
+    /// 找到的所有语言，按代码排序
+    fn available() -> impl Iterator<Item = Language> {
+        locale_registry().locales.iter().map(|locale| Language(locale.code.as_str()))
+    }
+
+    /// 语言文件中自己的名称，用于语言下拉框
+    fn display_name(self) -> &'static str {
+        locale_registry().lookup(self, "language_name")
+    }
+
+    /// 字符串中含中日韩文字时需要加载系统 CJK 字体
+    fn needs_cjk_font(self) -> bool {
+        locale_registry().find(self).is_some_and(|locale| locale.needs_cjk_font)
+    }
+}
+
+/// 编译时打包的语言文件；可执行文件旁的 `locales/` 目录中的同名文件会覆盖其中的键
+const BUNDLED_LOCALES: &[(&str, &str)] = &[
+    ("en", include_str!("../locales/en.json")),
+    ("zh", include_str!("../locales/zh.json")),
+    ("ja", include_str!("../locales/ja.json")),
+    ("ko", include_str!("../locales/ko.json")),
+];
+
+/// 一个语言文件：键 → 文本
+struct Locale {
+    code: String,
+    strings: HashMap<String, String>,
+    needs_cjk_font: bool,
+}
+
+/// 启动时发现的全部语言文件
+struct LocaleRegistry {
+    locales: Vec<Locale>,
+    // 无法读取或解析的语言文件，启动时写入日志
+    errors: Vec<String>,
+}
+
+impl LocaleRegistry {
+    /// 加载内置语言文件，再合并可执行文件旁 `locales/*.json` 中的语言
+    fn discover() -> Self {
+        let mut registry = LocaleRegistry { locales: Vec::new(), errors: Vec::new() };
+        for (code, text) in BUNDLED_LOCALES {
+            match parse_locale_json(text) {
+                Ok(strings) => registry.insert(code, strings),
+                Err(e) => registry.errors.push(format!("内置语言文件 {}.json: {}", code, e)),
+            }
+        }
+
+        let dir = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|p| p.join("locales")));
+        if let Some(dir) = dir && let Ok(entries) = std::fs::read_dir(&dir) {
+            let mut paths: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")))
+                .collect();
+            paths.sort();
+            for path in paths {
+                let Some(code) = path.file_stem().and_then(|stem| stem.to_str()) else { continue };
+                match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| parse_locale_json(&text)) {
+                    Ok(strings) => registry.insert(code, strings),
+                    Err(e) => registry.errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+
+        registry.locales.sort_by(|a, b| a.code.cmp(&b.code));
+        registry
+    }
+
+    /// 新增语言，或把键合并进同代码的已有语言
+    fn insert(&mut self, code: &str, strings: HashMap<String, String>) {
+        let index = match self.locales.iter().position(|locale| locale.code == code) {
+            Some(index) => {
+                self.locales[index].strings.extend(strings);
+                index
+            }
+            None => {
+                self.locales.push(Locale { code: code.to_string(), strings, needs_cjk_font: false });
+                self.locales.len() - 1
+            }
+        };
+        let locale = &mut self.locales[index];
+        locale.needs_cjk_font = locale.strings.values().any(|text| text.chars().any(is_cjk_char));
+    }
+
+    fn find(&self, lang: Language) -> Option<&Locale> {
+        self.locales.iter().find(|locale| locale.code == lang.0)
+    }
+
+    /// 查找键对应的文本：缺失时回退到英文，英文也缺失时显示键名
+    fn lookup(&'static self, lang: Language, key: &'static str) -> &'static str {
+        [lang, Language::ENGLISH].into_iter()
+            .find_map(|lang| self.find(lang)?.strings.get(key))
+            .map_or(key, String::as_str)
+    }
+}
+
+fn locale_registry() -> &'static LocaleRegistry {
+    static REGISTRY: std::sync::OnceLock<LocaleRegistry> = std::sync::OnceLock::new();
+    REGISTRY.get_or_init(LocaleRegistry::discover)
 }
 
-/// 包含所有 UI 文本的结构体
-struct Lang {
+/// 中日韩文字 (汉字、假名、谚文及全角标点)
+fn is_cjk_char(c: char) -> bool {
+    matches!(c as u32,
+        0x1100..=0x11FF | 0x3000..=0x30FF | 0x3130..=0x318F | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF | 0xAC00..=0xD7AF | 0xF900..=0xFAFF | 0xFF00..=0xFFEF)
+}
+
+/// 解析 `{ "key": "text", ... }` 形式的语言文件 (值必须都是字符串)
+fn parse_locale_json(text: &str) -> Result<HashMap<String, String>, String> {
+    serde_json::from_str(text.trim_start_matches('\u{feff}')).map_err(|e| e.to_string())
+}
+
+/// 由字段列表生成 `Lang` 结构体和按键名加载的 `Lang::load`；键名即字段名
+macro_rules! define_lang {
+    ($($field:ident,)*) => {
+        /// 包含所有 UI 文本的结构体
+        struct Lang {
+            $($field: &'static str,)*
+            band_names: [&'static str; BAND_COUNT],
+        }
+
+        impl Lang {
+            /// 语言文件中的全部键
+            #[cfg(test)]
+            const KEYS: &[&str] = &[$(stringify!($field),)* "band_low", "band_mid", "band_high"];
+
+            /// 根据语言加载字符串，缺失的键回退到英文
+            fn load(lang: Language) -> Self {
+                let registry = locale_registry();
+                Lang {
+                    $($field: registry.lookup(lang, stringify!($field)),)*
+                    band_names: ["band_low", "band_mid", "band_high"].map(|key| registry.lookup(lang, key)),
+                }
+            }
+        }
+    };
+}
+
+define_lang! {
     // ⭐ 新增：导航栏/全局 UI 文本
-    nav_lang_label: &'static str,
-    nav_zh_label: &'static str,
-    nav_en_label: &'static str,
-    nav_help_btn: &'static str,
+    nav_lang_label,
+    nav_help_btn,
+    nav_help_hint,
+    nav_theme_label,
+    theme_system,
+    theme_dark,
+    theme_light,
+    nav_time_label,
+    time_format_seconds,
+    time_format_minsec_hint,
+    ui_zoom_label,
+    ui_zoom_hint,
 
     // 菜单/模式
-    mode_single: &'static str,
-    mode_compare: &'static str,
-    mode_console: &'static str, // 控制台模式
+    mode_single,
+    mode_compare,
+    mode_batch,
+    mode_console, // 控制台模式
+    mode_live,
+    live_unavailable,
+    live_device_label,
+    live_default_device,
+    live_refresh_devices,
+    live_devices_failed_fmt,
+    live_start_btn,
+    live_stop_btn,
+    live_open_failed_fmt,
+    live_freeze,
+    live_freeze_hint,
+    live_retain_label,
+    live_retain_hint,
+    live_status_fmt,
+    live_level_fmt,
+    live_dropped_fmt,
+    live_add_btn,
+    live_added_fmt,
+    live_empty,
 
     // 状态栏
-    status_loading: &'static str,
-    status_ready: &'static str,
+    status_loading,
+    status_ready,
+    status_progress_fmt,
+    status_done_fmt,
+    recent_errors_btn_fmt,
+    recent_errors_title,
+    recent_errors_clear,
 
     // 单文件模式
-    single_heading: &'static str,
-    single_open_btn: &'static str,
-    single_clear_btn: &'static str,
-    single_empty_label: &'static str,
-    single_y_label: &'static str,
-    single_x_label: &'static str,
-    debug_end_loading: &'static str,
+    single_heading,
+    single_open_btn,
+    url_open_btn,
+    url_load_btn,
+    csv_import_open_btn,
+    csv_import_open_hint,
+    csv_import_title,
+    csv_import_detected_fmt,
+    csv_import_time_column,
+    csv_import_value_column,
+    csv_import_time_unit,
+    csv_import_unit_seconds,
+    csv_import_unit_ms,
+    csv_import_timecode_hint,
+    csv_import_btn,
+    csv_import_same_column,
+    csv_import_cancel,
+    url_err_not_audio_fmt,
+    web_unavailable_hint,
+    single_clear_btn,
+    duplicate_remove_btn,
+    duplicate_remove_hint,
+    duplicate_removed_fmt,
+    duplicate_loaded_fmt,
+    duplicate_badge_fmt,
+    single_empty_label,
+    single_y_label,
+    single_y_label_perceived,
+    single_x_label,
+    single_x_label_minsec,
+    debug_end_loading,
 
     // 归一化和导出
-    export_csv_btn: &'static str,
-    normalize_label: &'static str,
-    normalize_apply: &'static str,
+    export_csv_btn,
+    export_all_btn,
+    export_resample_label,
+    export_resample_hint,
+    single_table_toggle,
+    stats_col_name,
+    stats_col_duration,
+    stats_col_avg,
+    stats_col_loudest,
+    stats_col_quietest,
+    stats_col_points,
+    normalize_label,
+    normalize_mode_label,
+    normalize_mode_average,
+    normalize_mode_peak,
+    normalize_peak_hint,
+    normalize_peak_label,
+    normalize_apply,
+    normalize_apply_hint,
+    normalize_preview_title,
+    normalize_col_current,
+    normalize_col_gain,
+    normalize_col_result,
+    normalize_col_loudest,
+    normalize_preview_note,
+    single_show_markers,
+    single_hide_silence,
+    single_hide_silence_hint,
+    zones_label,
+    zones_hint,
+    zones_green,
+    zones_yellow,
+    zones_red,
+    zones_follow_target,
+    zones_follow_target_hint,
+    track_style_menu,
+    track_colors_label,
+    track_colors_colorblind,
+    track_colors_classic,
+    track_colors_custom,
+    track_style_solid,
+    track_style_dashed,
+    track_style_dotted,
+    single_target_line_fmt,
+    single_focus_hint,
+    single_focus_label,
+    single_focus_avg,
+    single_focus_loudest,
+    single_focus_quietest,
+    single_focus_duration,
+    single_focus_points,
+    single_focus_silent_fmt,
+    curve_rename_hint,
+    tag_add_hint,
+    tag_chip_hint,
+    tag_filter_label,
+    tag_filter_clear,
+    draw_order_title,
+    draw_order_hint,
+    draw_order_front_hint,
+    draw_order_move_top,
+    draw_order_move_bottom,
+    draw_order_up_hint,
+    draw_order_down_hint,
+    draw_order_pin_hint,
+    draw_order_pin,
+    draw_order_unpin,
+    details_title,
+    details_hint,
+    details_path,
+    details_size,
+    details_sample_rate,
+    details_channels,
+    details_format,
+    details_decoded,
+    details_window,
+    details_tail,
+    details_silent,
+    details_na,
+    delivery_title,
+    delivery_integrated_label,
+    delivery_true_peak_label,
+    delivery_lra_label,
+    delivery_summary_fmt,
+    delivery_headline_fmt,
+    delivery_headline_hint,
+    delivery_failures_only,
+    delivery_failures_clear,
+    delivery_col_integrated,
+    delivery_col_true_peak,
+    delivery_col_lra,
+    delivery_col_result,
+    delivery_pass,
+    delivery_fail,
+    delivery_not_checked,
+    playback_play,
+    playback_pause,
+    playback_stop,
+    playback_seek_hint,
+    playback_slider_hint,
+    playback_no_audio,
+    playback_unavailable,
+    playback_cursor,
+    measure_hint,
+    value_copied_fmt,
+    measure_clear,
+    measure_copy,
+    y_axis_label,
+    y_fit_data,
+    y_fit_ignore_silence,
+    y_fit_ignore_silence_hint,
+    annotation_title,
+    annotation_hint,
+    annotation_add_title,
+    annotation_add,
+    annotation_jump,
+    annotation_delete,
+    annotation_export_audacity,
+    annotation_export_csv,
+    jumps_title,
+    jumps_threshold_label,
+    jumps_window_label,
+    jumps_hint,
+    jumps_row_hint,
+    dialog_cancel,
+    clear_confirm_title,
+    clear_confirm_fmt,
+    clear_confirm_ok,
+    exit_confirm_title,
+    exit_confirm_fmt,
+    exit_confirm_ok,
+    exit_confirm_keep,
+    recovery_title,
+    recovery_body_fmt,
+    recovery_restore,
+    recovery_discard,
+    recovery_restored_fmt,
+    single_view_label,
+    single_view_overall,
+    single_view_bands,
+    single_view_bars,
+    bars_hint,
+    bars_y_label,
+    bars_target_fmt,
+    bars_unmeasured_fmt,
+    meter_momentary,
+    meter_momentary_hint,
+    meter_short_term,
+    meter_short_term_hint,
+    meter_integrated,
+    meter_integrated_hint,
+    level_display_label,
+    level_display_normalized,
+    level_display_raw,
+    level_display_both,
+    level_raw_override,
+    level_raw_override_hint,
+    downmix_label,
+    downmix_interleaved,
+    downmix_matrix,
+    downmix_bs1770,
+    downmix_stereo_gain,
+    downmix_hint,
+    analysis_mmap_label,
+    analysis_mmap_hint,
+    analysis_settings_title,
+    analysis_window_label,
+    analysis_step_label,
+    analysis_weighting_label,
+    analysis_weighting_flat,
+    analysis_weighting_k,
+    analysis_weighting_hint,
+    analysis_weighting_iso226,
+    analysis_weighting_iso226_fmt,
+    analysis_weighting_iso226_hint,
+    analysis_floor_label,
+    analysis_floor_hint,
+    analysis_skip_head_label,
+    analysis_skip_tail_label,
+    analysis_skip_hint,
+    analysis_db_mean_label,
+    analysis_db_mean_hint,
+    legend_avg_power,
+    legend_avg_db_mean,
+    analysis_stale_fmt,
+    analysis_stale_curve_hint,
+    analysis_reanalyze_btn,
+    analysis_reanalyze_hint,
+    analysis_reanalyze_skipped_fmt,
+    watch_all_label,
+    watch_all_hint,
+    watch_file_label,
+    watch_file_hint,
+    watch_reloaded_fmt,
+    profile_none,
+    profile_mixed_compare_fmt,
+    details_profile,
+    profile_editor_title,
+    profile_editor_hint,
+    profile_name_label,
+    profile_remove_btn,
+    profile_add_btn,
+    profile_default_name,
+    profile_csv_layout_label,
+    profile_csv_layout_hint,
+    profile_csv_delimiter,
+    profile_csv_tab,
+    profile_csv_skip_lines,
+    profile_csv_header,
+    profile_rules_label,
+    profile_rule_add_btn,
+    analysis_mixed_single_fmt,
+    analysis_mixed_compare,
 
     // 对比模式
-    compare_heading: &'static str,
-    compare_track_a_label: &'static str,
-    compare_track_b_label: &'static str,
-    compare_select_a: &'static str,
-    compare_select_b: &'static str,
-    compare_report_title: &'static str,
-    compare_plot_raw_label: &'static str,
-    compare_plot_diff_label: &'static str,
-    compare_empty_label: &'static str,
-    compare_conf_label: &'static str,
-    // compare_target_diff_label: &'static str, // (这个标签直接在 UI 中硬编码了)
+    compare_heading,
+    compare_track_a_label,
+    compare_track_b_label,
+    compare_select_a,
+    compare_select_b,
+    synthetic_title,
+    synthetic_hint,
+    synthetic_constant,
+    synthetic_breakpoints,
+    synthetic_add_point,
+    synthetic_import_csv,
+    synthetic_import_csv_hint,
+    synthetic_import_failed_fmt,
+    synthetic_use,
+    synthetic_stop,
+    synthetic_waiting_b,
+    synthetic_name_fmt,
+    synthetic_breakpoints_fmt,
+    synthetic_report_note,
+    compare_slot_loading_fmt,
+    compare_slot_cancel,
+    compare_slot_cancel_hint,
+    compare_slot_error_fmt,
+    compare_report_title,
+    compare_tracks_fmt,
+    compare_plot_raw_label,
+    compare_plot_diff_label,
+    compare_plot_bland_altman_label,
+    compare_plot_bland_altman_hint,
+    bland_altman_x_label,
+    bland_altman_y_label,
+    bland_altman_limits_fmt,
+    compare_empty_label,
+    compare_conf_label,
+    compare_export_table_btn,
+    compare_export_table_hint,
+    compare_conf_custom_hint,
+    compare_t_critical_fmt,
+    compare_threshold_label,
+    compare_high_match_threshold,
+    compare_huge_diff_threshold,
+    compare_target_diff_label,
+
+    // 批量对比模式
+    batch_heading,
+    batch_select_ref,
+    batch_select_folder,
+    batch_run_btn,
+    batch_export_btn,
+    batch_empty_label,
+    batch_progress_fmt,
+    batch_col_file,
+    batch_col_mean,
+    batch_col_std,
+    batch_col_corr,
+    batch_col_verdict,
 
     // 结果字符串格式
-    compare_err_duration_fmt: &'static str,
-    compare_avg_diff_fmt: &'static str,
-    compare_std_dev_fmt: &'static str,
-    compare_correlation_fmt: &'static str,
-    compare_t_stat_fmt: &'static str,
-    compare_t_test_significant: &'static str,
-    compare_t_test_not_significant: &'static str,
-
-    compare_max_diff_fmt: &'static str,
-    compare_min_diff_fmt: &'static str,
+    compare_err_duration_fmt,
+    compare_err_no_overlap_fmt,
+    compare_avg_diff_fmt,
+    compare_std_dev_fmt,
+    compare_correlation_fmt,
+    compare_metric_fmt,
+    compare_score_fmt,
+    compare_score_parts_fmt,
+    score_weights_title,
+    score_weight_correlation,
+    score_weight_spread,
+    score_weight_within,
+    score_tolerance_label,
+    score_weights_reset,
+    score_formula,
+    compare_t_stat_fmt,
+    compare_t_target_fmt,
+    compare_t_test_significant,
+    compare_t_test_not_significant,
+    compare_min_r_label,
+    compare_min_r_hint,
+    compare_r_interval_fmt,
+    compare_r_test_pass_fmt,
+    compare_r_test_fail_fmt,
+    compare_r_test_na,
+
+    compare_max_diff_fmt,
+    compare_min_diff_fmt,
+    error_regions_title_fmt,
+    error_regions_count_label,
+    error_regions_none,
+    error_regions_row_fmt,
+    error_regions_jump_hint,
+    compare_overlap_fmt,
+    compare_offset_label,
+    compare_offset_fmt,
+    compare_level_match,
+    compare_level_match_hint,
+    compare_level_offset_fmt,
+    compare_residual_note,
+    compare_resampled_note_fmt,
+    compare_partial_warning_fmt,
+    compare_min_overlap_label,
+
+    // 文件加载错误
+    load_failed_fmt,
+    load_err_not_found,
+    load_err_permission,
+    load_err_in_use,
+    load_err_no_audio,
+    load_err_unsupported_fmt,
+    load_err_decode_fmt,
+    load_err_csv_schema_fmt,
+    load_err_cancelled,
+    load_err_download_fmt,
+    load_hint_convert_pcm,
+    load_hint_reexport,
+    load_hint_csv_import,
+    load_hint_close_other,
 
     // 状态结果
-    compare_high_match: &'static str,
-    compare_mid_diff: &'static str,
-    compare_huge_diff: &'static str,
+    compare_high_match,
+    compare_mid_diff,
+    compare_huge_diff,
 
     // --- 新增：帮助文本/悬浮窗 ---
-    help_title: &'static str,
-    help_desc: &'static str,
-    help_monitor_title: &'static str,
-    help_console_title: &'static str,
-    help_cmd_list: &'static str,
-    help_cmd_kill: &'static str,
-    help_cmd_clear: &'static str,
-    help_cmd_quit: &'static str,
+    help_title,
+    help_desc,
+    help_monitor_title,
+    help_console_title,
+    help_cmd_list,
+    help_cmd_kill,
+    help_cmd_clear,
+    help_cmd_logcap,
+    help_cmd_logfile,
+    help_cmd_quit,
+    help_cmd_quickscan,
+    help_cmd_perf,
+    help_cmd_history,
+    help_cmd_help,
+    help_cmd_load,
+    help_cmd_loadurl,
+    help_cmd_load_ab,
+    help_cmd_compare,
+    help_cmd_export,
 
     // ⭐ 新增：控制台硬编码信息
-    console_cmd_hint_cn: &'static str,
-    console_cmd_label: &'static str,
-    help_monitor_desc: &'static str,
+    console_cmd_hint_cn,
+    console_cmd_label,
+    console_run_btn,
+    console_monitor_heading,
+    console_log_heading,
+    console_toggle_hint,
+    cmd_usage_fmt,
+    cmd_unclosed_quote_fmt,
+    cmd_kill_needs_id,
+    cmd_history_missing_fmt,
+    cmd_unknown_fmt,
+    cmd_curve_not_found_fmt,
+    cmd_tasks_header,
+    cmd_history_header,
+    cmd_help_header,
+    cmd_log_cleared,
+    cmd_logcap_current_fmt,
+    cmd_logcap_set_fmt,
+    cmd_logcap_invalid_fmt,
+    cmd_logfile_on_fmt,
+    cmd_logfile_off,
+    console_output_empty,
+    cmd_compare_pending,
+    cmd_compare_missing,
+    task_error_fmt,
+    folder_read_failed_fmt,
+    export_success_fmt,
+    export_failed_fmt,
+    export_all_done_fmt,
+    normalize_export_failed_fmt,
+    playback_decode_failed_fmt,
+    monitor_no_tasks,
+    perf_title,
+    perf_none,
+    perf_task_fmt,
+    perf_totals_fmt,
+    perf_throughput_fmt,
+    perf_bottleneck_decode_fmt,
+    perf_bottleneck_windowing_fmt,
+    perf_slowest_header,
+    task_reveal_hint,
+    task_curve_gone,
+    task_error_title,
+    task_retry,
+    task_retry_unavailable,
+    task_copy_error,
+    task_error_close,
+    quickscan_failed_fmt,
+    playback_open_failed_fmt,
+    log_search_hint,
+    log_autoscroll,
+    log_capacity_label,
+    log_capacity_hint,
+    log_file_label,
+    log_file_hint_fmt,
+    log_dropped_fmt,
+    help_monitor_desc,
+
+    // 缺少 CJK 字体时的横幅 (各语言均使用英文，因为此时 CJK 文字无法显示)
+    font_missing_banner,
 }
 
 impl Lang {
-    /// 根据语言加载字符串
-    fn load(lang: Language) -> Self {
-        match lang {
-            // 中文 (zh_CN)
-            Language::Chinese => Lang {
-                // ⭐ 新增：导航栏/全局 UI 文本
-                nav_lang_label: "语言:",
-                nav_zh_label: "中文",
-                nav_en_label: "English",
-                nav_help_btn: "❓ 帮助",
-
-                mode_single: "🎵 单机批处理模式",
-                mode_compare: "⚖️ AB 对比模式",
-                mode_console: "💻 控制台/日志",
-                status_loading: "正在处理音频数据，请稍候...",
-                status_ready: "就绪",
-                single_heading: "单文件/批处理分析",
-                single_open_btn: "📂 打开文件 (支持多选 WAV/CSV)",
-                single_clear_btn: "🗑️ 清空列表",
-                single_empty_label: "请加载文件以查看图表。",
-                single_y_label: "Loudness (dBFS)",
-                single_x_label: "Time (s)",
-                debug_end_loading: "⏹️ 结束加载 (Debug)",
-                export_csv_btn: "💾 导出为 CSV",
-                normalize_label: "LUFS 归一化目标 (平均 dBFS):",
-                normalize_apply: "应用归一化",
-                compare_heading: "A/B 动态一致性检验",
-                compare_track_a_label: "Track A (Ref):",
-                compare_track_b_label: "Track B (Target):",
-                compare_select_a: "📂 选择文件 A",
-                compare_select_b: "📂 选择文件 B",
-                compare_report_title: "分析报告",
-                compare_plot_raw_label: "响度曲线对比 (A vs B)",
-                compare_plot_diff_label: "差值稳定性 (Track A - Track B)",
-                compare_empty_label: "请加载两个文件以开始对比...",
-                compare_conf_label: "假设检验置信度:",
-                compare_err_duration_fmt: "❌ 时间差异过大 ({}s vs {}s)，无法进行逐点对比。",
-                compare_avg_diff_fmt: "平均差异: {} dB",
-                compare_std_dev_fmt: "动态标准差: {}",
-                compare_correlation_fmt: "动态相关系数 (r): {}",
-                compare_t_stat_fmt: "均值差值 T-统计量: {}",
-                compare_t_test_significant: "❌ 均值差值显著",
-                compare_t_test_not_significant: "✅ 均值差值不显著",
-                compare_max_diff_fmt: "最大差值: {} dB",
-                compare_min_diff_fmt: "最小差值: {} dB",
-                compare_high_match: "✅ 动态一致性极高",
-                compare_mid_diff: "⚠️ 动态存在差异",
-                compare_huge_diff: "❌ 动态差异巨大",
-
-                // 新增：帮助文本
-                help_title: "📊 WAV 动态分析器帮助",
-                help_desc: "本应用用于分析 WAV/CSV 文件的响度曲线 (LUFS/dBFS) 并进行归一化或动态一致性 (A/B) 比较。",
-                help_monitor_title: "进程监视器",
-                help_console_title: "控制台命令",
-                help_cmd_list: "显示当前所有正在运行或已完成的后台任务。",
-                help_cmd_kill: "发送终止信号给指定 ID 的任务。用法: kill <任务ID>",
-                help_cmd_clear: "清空控制台日志。",
-                help_cmd_quit: "发送关闭信号给工作池，准备退出应用。",
-
-                // ⭐ 新增：控制台硬编码信息
-                console_cmd_hint_cn: "可用命令: `tasks` (或 `list`) | `kill <ID>` | `clear` | `quit` (或 `exit`)",
-                console_cmd_label: "CMD >",
-                help_monitor_desc: "进程监视器（💻 控制台/日志模式）显示后台加载和分析任务的实时状态。",
-            },
-            // 英文 (en_US)
-            Language::English => Lang {
-                // ⭐ 新增：导航栏/全局 UI 文本
-                nav_lang_label: "Language:",
-                nav_zh_label: "Chinese",
-                nav_en_label: "English",
-                nav_help_btn: "❓ Help",
-
-                mode_single: "🎵 Single Batch Mode",
-                mode_compare: "⚖️ A/B Comparison Mode",
-                mode_console: "💻 Console/Log",
-                status_loading: "Processing audio data, please wait...",
-                status_ready: "Ready",
-                single_heading: "Single File / Batch Analysis",
-                single_open_btn: "📂 Open Files (WAV/CSV Multi-select)",
-                single_clear_btn: "🗑️ Clear List",
-                single_empty_label: "Please load files to view the plot.",
-                single_y_label: "Loudness (dBFS)",
-                single_x_label: "Time (s)",
-                debug_end_loading: "⏹️ End Loading (Debug)",
-                export_csv_btn: "💾 Export to CSV",
-                normalize_label: "LUFS Normalization Target (Avg dBFS):",
-                normalize_apply: "Apply Normalization",
-                compare_heading: "A/B Dynamic Consistency Check",
-                compare_track_a_label: "Track A (Ref):",
-                compare_track_b_label: "Track B (Target):",
-                compare_select_a: "📂 Select File A",
-                compare_select_b: "📂 Select File B",
-                compare_report_title: "Analysis Report",
-                compare_plot_raw_label: "Loudness Curve Comparison (A vs B)",
-                compare_plot_diff_label: "Difference Stability (Track A - Track B)",
-                compare_empty_label: "Please load two files to start comparison...",
-                compare_conf_label: "Hypothesis Test Confidence:",
-                compare_err_duration_fmt: "❌ Duration difference too large ({}s vs {}s), unable to perform point-by-point comparison.",
-                compare_avg_diff_fmt: "Average Difference: {} dB",
-                compare_std_dev_fmt: "Dynamic Std Dev: {}",
-                compare_correlation_fmt: "Dynamic Correlation (r): {}",
-                compare_t_stat_fmt: "Mean Diff T-Statistic: {}",
-                compare_t_test_significant: "❌ Mean Difference is Significant",
-                compare_t_test_not_significant: "✅ Mean Difference is Not Significant",
-                compare_max_diff_fmt: "Max Difference: {} dB",
-                compare_min_diff_fmt: "Min Difference: {} dB",
-                compare_high_match: "✅ High Dynamic Consistency",
-                compare_mid_diff: "⚠️ Dynamic Differences Exist",
-                compare_huge_diff: "❌ Huge Dynamic Difference",
-
-                // 新增：帮助文本
-                help_title: "📊 WAV Dynamics Analyzer Help",
-                help_desc: "This application is used to analyze loudness curves (LUFS/dBFS) of WAV/CSV files and perform normalization or dynamic consistency (A/B) comparisons.",
-                help_monitor_title: "Process Monitor",
-                help_console_title: "Console Commands",
-                help_cmd_list: "Show all currently running or completed background tasks.",
-                help_cmd_kill: "Sends a termination signal to the task with the specified ID. Usage: kill <TaskID>",
-                help_cmd_clear: "Clear the console log.",
-                help_cmd_quit: "Sends a shutdown signal to the worker pool, preparing to exit the application.",
-
-                // ⭐ 新增：控制台硬编码信息
-                console_cmd_hint_cn: "Available commands: `tasks` (or `list`) | `kill <ID>` | `clear` | `quit` (or `exit`)",
-                console_cmd_label: "CMD >",
-                help_monitor_desc: "The process monitor (💻 Console/Log mode) shows the real-time status of background loading and analysis tasks.",
-            },
+    /// 格式化时长差异过大的错误信息
+    fn format_duration_error(&self, a_duration: f64, b_duration: f64) -> String {
+        self.compare_err_duration_fmt
+            .replacen("{}", &format!("{:.2}", a_duration), 1)
+            .replacen("{}", &format!("{:.2}", b_duration), 1)
+    }
+
+    /// Track B 平移后与 A 没有重叠时的错误提示
+    fn format_no_overlap_error(&self, offset: f64) -> String {
+        self.compare_err_no_overlap_fmt.replacen("{}", &format!("{:+.2}", offset), 1)
+    }
+
+    /// 判定结果对应的显示文本
+    fn verdict_label(&self, verdict: Verdict) -> &'static str {
+        match verdict {
+            Verdict::HighMatch => self.compare_high_match,
+            Verdict::MidDiff => self.compare_mid_diff,
+            Verdict::HugeDiff => self.compare_huge_diff,
+        }
+    }
+
+    /// 统计表列标题
+    fn stats_column_label(&self, column: StatsColumn) -> &'static str {
+        match column {
+            StatsColumn::Name => self.stats_col_name,
+            StatsColumn::Duration => self.stats_col_duration,
+            StatsColumn::Average => self.stats_col_avg,
+            StatsColumn::Loudest => self.stats_col_loudest,
+            StatsColumn::Quietest => self.stats_col_quietest,
+            StatsColumn::Points => self.stats_col_points,
         }
     }
 }
 
 
-// --- 核心数据结构 ---
+// --- 界面状态与设置 (曲线数据和分析函数见 lib.rs) ---
 
-#[derive(Clone, Debug)]
-struct AudioCurve {
-    name: String,
-    // (时间, dBFS)
-    points: Vec<[f64; 2]>,
-    duration: f64,
-    average_dbfs: f64, // 用于计算归一化偏移
+/// 界面主题选择
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum ThemeChoice {
+    #[default]
+    System,
+    Dark,
+    Light,
 }
 
-#[derive(Clone, Debug)]
-struct ComparisonResult {
-    mean_diff: f64,
-    std_dev: f64,
-    max_diff: f64,
-    min_diff: f64,
-    correlation_coefficient: f64, // Pearson r
-    t_statistic: f64,             // T-stat for mean difference vs target
-    // (时间, 差值)
-    diff_points: Vec<[f64; 2]>,
+impl ThemeChoice {
+    /// 解析为实际使用的 egui 主题 (System 无法探测时回退到深色)
+    fn resolve(self, ctx: &egui::Context) -> egui::Theme {
+        match self {
+            ThemeChoice::System => ctx.system_theme().unwrap_or(egui::Theme::Dark),
+            ThemeChoice::Dark => egui::Theme::Dark,
+            ThemeChoice::Light => egui::Theme::Light,
+        }
+    }
 }
 
-#[derive(PartialEq, Clone, Copy)]
-enum AppMode {
-    Single,
-    Compare,
-    Console,
+/// 对比图中 Track A / B 的配色方案
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum TrackColors {
+    /// Okabe-Ito 蓝 / 橙：红绿色盲可区分，也不会与红色的错误提示混淆
+    #[default]
+    ColorblindSafe,
+    /// 旧版的绿 / 红
+    Classic,
+    /// 用户自选，见 AppSettings::track_custom_colors
+    Custom,
 }
 
-// --- 日志系统 ---
-
-struct LogEntry {
-    time: String,
-    message: String,
-    level: LogLevel,
+/// 对比图中曲线的线型；配色接近时仍可靠线型区分两条曲线
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum TrackLineStyle {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
 }
 
-#[derive(PartialEq, Clone, Copy, Debug)]
-enum LogLevel {
-    Info,
-    Error,
-    Debug,
-    Command, // 命令行输入或操作
+impl TrackLineStyle {
+    fn plot_style(self) -> egui_plot::LineStyle {
+        match self {
+            TrackLineStyle::Solid => egui_plot::LineStyle::Solid,
+            TrackLineStyle::Dashed => egui_plot::LineStyle::Dashed { length: 10.0 },
+            TrackLineStyle::Dotted => egui_plot::LineStyle::Dotted { spacing: 4.0 },
+        }
+    }
 }
 
-struct Logger {
-    entries: Arc<Mutex<Vec<LogEntry>>>,
+/// 时间的显示格式 (图表横轴及界面中的时间字段)
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum TimeFormat {
+    #[default]
+    Seconds,
+    MinSec,
 }
 
-impl Logger {
-    fn new() -> Self {
-        Self { entries: Arc::new(Mutex::new(Vec::new())) }
+/// mm:ss 横轴刻度可选的间隔 (秒)，保证刻度落在整秒/整分钟上
+const TIME_GRID_STEPS: [f64; 16] = [0.1, 0.2, 0.5, 1.0, 2.0, 5.0, 10.0, 15.0, 30.0, 60.0, 120.0, 300.0, 600.0, 900.0, 1800.0, 3600.0];
+
+impl TimeFormat {
+    /// 按 decimals 位小数格式化秒数，如 `83.5s` 或 `1:23.5`
+    fn format(self, secs: f64, decimals: usize) -> String {
+        match self {
+            TimeFormat::Seconds => format!("{:.*}s", decimals, secs),
+            TimeFormat::MinSec => {
+                // 先按显示精度取整，避免出现 0:60
+                let scale = 10f64.powi(decimals as i32);
+                let total = (secs.abs() * scale).round() / scale;
+                let minutes = (total / 60.0).floor();
+                let rest = total - minutes * 60.0;
+                let width = if decimals > 0 { decimals + 3 } else { 2 };
+                let sign = if secs < 0.0 && total > 0.0 { "-" } else { "" };
+                format!("{}{}:{:0width$.decimals$}", sign, minutes, rest)
+            }
+        }
     }
 
-    /// 记录一条日志，线程安全
-    fn log(&self, level: LogLevel, message: String) {
-        let time = Local::now().format("%H:%M:%S").to_string();
-        let entry = LogEntry { time, message, level };
-        if let Ok(mut entries) = self.entries.lock() {
-            entries.push(entry);
-            // 限制日志条目数量
-            if entries.len() > 1000 {
-                entries.drain(0..500);
-            }
+    /// 让图表横轴按该格式显示时间；秒模式保持 egui_plot 默认的刻度和悬停标签
+    fn apply<'a>(self, plot: Plot<'a>) -> Plot<'a> {
+        match self {
+            TimeFormat::Seconds => plot,
+            TimeFormat::MinSec => plot
+                .x_grid_spacer(time_grid_spacer)
+                .x_axis_formatter(move |mark, _range| self.format(mark.value, time_decimals(mark.step_size)))
+                .label_formatter(move |name, point| {
+                    let text = format!("{}\n{:.2}", self.format(point.x, 2), point.y);
+                    if name.is_empty() { text } else { format!("{}\n{}", name, text) }
+                }),
         }
     }
 }
 
-// 辅助函数，方便记录日志
-fn log_info(logger: &Logger, msg: &str) {
-    logger.log(LogLevel::Info, msg.to_string());
+/// 刻度间隔对应的小数位数
+fn time_decimals(step: f64) -> usize {
+    if step >= 1.0 { 0 } else if step >= 0.1 { 1 } else { 2 }
 }
 
-fn log_error(logger: &Logger, msg: &str) {
-    logger.log(LogLevel::Error, msg.to_string());
+/// 以 TIME_GRID_STEPS 为间隔的横轴刻度：最小间隔及其后两级分别作为细/中/粗网格线
+fn time_grid_spacer(input: egui_plot::GridInput) -> Vec<egui_plot::GridMark> {
+    let first = TIME_GRID_STEPS.iter().position(|&s| s >= input.base_step_size).unwrap_or(TIME_GRID_STEPS.len() - 1);
+    let steps = &TIME_GRID_STEPS[first..(first + 3).min(TIME_GRID_STEPS.len())];
+    let step = steps[0];
+    let (min, max) = input.bounds;
+    let mut marks = Vec::new();
+    let mut i = (min / step).ceil() as i64;
+    while (i as f64) * step <= max {
+        let value = i as f64 * step;
+        // 该值能被整除的最大间隔决定网格线粗细
+        let step_size = steps.iter().rev()
+            .find(|&&s| ((value / s).round() * s - value).abs() < step * 1e-3)
+            .copied()
+            .unwrap_or(step);
+        marks.push(egui_plot::GridMark { value, step_size });
+        i += 1;
+    }
+    marks
 }
 
-fn log_debug(logger: &Logger, msg: &str) {
-    logger.log(LogLevel::Debug, msg.to_string());
+/// Alt+单击图表时把指针处的 (时间, 数值) 复制到剪贴板，返回复制的文本。
+/// series 非空时吸附到该时刻离指针最近的曲线并复制其插值，否则复制指针坐标
+fn copy_value_on_click(plot_ui: &egui_plot::PlotUi<'_>, series: &[CursorSeries], format_x: impl Fn(f64) -> String) -> Option<String> {
+    if !plot_ui.response().clicked() || !plot_ui.ctx().input(|i| i.modifiers.alt) {
+        return None;
+    }
+    let pointer = plot_ui.pointer_coordinate()?;
+    let nearest = series.iter()
+        .filter_map(|s| s.level_at(pointer.x).map(|v| (s.name, v)))
+        .min_by(|a, b| (a.1 - pointer.y).abs().total_cmp(&(b.1 - pointer.y).abs()));
+    let text = match nearest {
+        Some((name, value)) => format!("{}, {:.2} dB ({})", format_x(pointer.x), value, name),
+        None => format!("{}, {:.2} dB", format_x(pointer.x), pointer.y),
+    };
+    plot_ui.ctx().copy_text(text.clone());
+    Some(text)
 }
 
-fn log_command(logger: &Logger, msg: &str) {
-    logger.log(LogLevel::Command, msg.to_string());
+/// 按平均响度目标推算的默认分区边界 (绿区下沿, 黄区下沿, 红区下沿)：
+/// 目标以下 6 dB 内为绿区，目标以上 8 dB 内为黄区，再往上为红区
+fn zone_edges_for_target(target: f32) -> [f32; 3] {
+    [target - 6.0, target, target + 8.0]
 }
 
-// --- 任务管理结构 ---
-
-#[derive(Clone, Debug, PartialEq)]
-enum TaskState {
-    Waiting,
-    Running(f32), // 0.0 - 1.0 进度
-    Completed,
-    Killed,
-    Error(String),
+/// 红区向上延伸的高度；图表 Y 轴不自动适配，超出可见范围的部分不影响缩放
+const ZONE_CEILING_DB: f64 = 200.0;
+
+/// 在图表底层画出响度分区背景，须在曲线之前调用。
+/// 横向只覆盖 x 范围 (数据的时间范围)，避免撑大自动适配的横轴
+fn draw_loudness_zones(plot_ui: &mut egui_plot::PlotUi<'_>, edges: [f64; 3], x: [f64; 2], palette: &Palette) {
+    let bands = [
+        (edges[0], edges[1], palette.ok),
+        (edges[1], edges[2], palette.warn),
+        (edges[2], edges[2] + ZONE_CEILING_DB, palette.error),
+    ];
+    for (i, (low, high, color)) in bands.into_iter().enumerate() {
+        if high <= low {
+            continue;
+        }
+        let corners = vec![[x[0], low], [x[1], low], [x[1], high], [x[0], high]];
+        plot_ui.polygon(egui_plot::Polygon::new("", PlotPoints::new(corners))
+            .id(egui::Id::new(("loudness_zone", i)))
+            .fill_color(color.gamma_multiply(0.12))
+            .stroke(egui::Stroke::NONE)
+            .allow_hover(false));
+    }
 }
 
-#[derive(Clone, Debug)]
-struct AudioTask {
-    id: usize,
-    name: String,
-    state: TaskState,
+/// 分析时跳过的时间段 (见 AudioCurve::excluded_ranges) 画成与曲线同色的淡阴影，纵向铺满当前可见范围。
+/// time_shift 为曲线在图中的时间平移 (对比图的 Track B 偏移)
+fn draw_excluded_ranges(plot_ui: &mut egui_plot::PlotUi<'_>, curve: &AudioCurve, time_shift: f64, color: egui::Color32, id_salt: usize) {
+    let bounds = plot_ui.plot_bounds();
+    let (low, high) = (bounds.min()[1], bounds.max()[1]);
+    for (i, [start, end]) in curve.excluded_ranges().into_iter().enumerate() {
+        let (start, end) = (start + time_shift, end + time_shift);
+        let corners = vec![[start, low], [end, low], [end, high], [start, high]];
+        plot_ui.polygon(egui_plot::Polygon::new("", PlotPoints::new(corners))
+            .id(egui::Id::new(("excluded_range", id_salt, i)))
+            .fill_color(color.gamma_multiply(0.1))
+            .stroke(egui::Stroke::NONE)
+            .allow_hover(false));
+    }
 }
 
-// UI 线程发送给 WorkerPool 主线程的命令
-#[derive(Debug)]
-enum WorkerCommand {
-    Kill(usize), // 杀死指定 ID 的任务
-    Shutdown,    // 关闭所有 worker
+/// 与主题相关的配色方案，所有 UI/绘图颜色统一从这里取
+#[derive(Clone, Copy, Debug)]
+struct Palette {
+    // 绘图
+    track_a: egui::Color32,
+    track_b: egui::Color32,
+    diff_line: egui::Color32,
+    mean_line: egui::Color32,
+    zero_line: egui::Color32,
+    // 判定/状态
+    ok: egui::Color32,
+    warn: egui::Color32,
+    error: egui::Color32,
+    // 日志级别
+    log_info: egui::Color32,
+    log_debug: egui::Color32,
+    log_command: egui::Color32,
+    // 任务状态
+    task_waiting: egui::Color32,
+    task_completed: egui::Color32,
+    task_error: egui::Color32,
 }
 
-// Worker/Task 线程发送给 UI 线程的消息
-enum WorkerMessage {
-    Log(LogEntry),
-    UpdateTaskState(usize, TaskState),
-    NewCurve(AudioCurve, Option<char>), // 专门用于返回处理结果
+impl Palette {
+    /// 单文件模式中第 index 条曲线的颜色 (与 egui_plot 自动配色一致)，标记点使用同一颜色
+    fn curve_color(index: usize) -> egui::Color32 {
+        let golden_ratio = (5.0_f32.sqrt() - 1.0) / 2.0;
+        let h = index as f32 * golden_ratio;
+        egui::ecolor::Hsva::new(h.fract(), 0.85, 0.5, 1.0).into()
+    }
+
+    fn verdict_color(&self, verdict: Verdict) -> egui::Color32 {
+        match verdict {
+            Verdict::HighMatch => self.ok,
+            Verdict::MidDiff => self.warn,
+            Verdict::HugeDiff => self.error,
+        }
+    }
+
+    /// 按配色方案设置 Track A / B 的颜色；色盲友好方案在浅色主题下使用较深的蓝 / 朱红
+    fn set_track_colors(&mut self, theme: egui::Theme, colors: TrackColors, custom: [[u8; 3]; 2]) {
+        use egui::Color32;
+        let rgb = |[r, g, b]: [u8; 3]| Color32::from_rgb(r, g, b);
+        (self.track_a, self.track_b) = match (colors, theme) {
+            (TrackColors::ColorblindSafe, egui::Theme::Dark) => (Color32::from_rgb(86, 180, 233), Color32::from_rgb(230, 159, 0)),
+            (TrackColors::ColorblindSafe, egui::Theme::Light) => (Color32::from_rgb(0, 114, 178), Color32::from_rgb(213, 94, 0)),
+            (TrackColors::Classic, egui::Theme::Dark) => (Color32::GREEN, Color32::RED),
+            (TrackColors::Classic, egui::Theme::Light) => (Color32::from_rgb(0, 140, 0), Color32::from_rgb(200, 0, 0)),
+            (TrackColors::Custom, _) => (rgb(custom[0]), rgb(custom[1])),
+        };
+    }
+
+    fn for_theme(theme: egui::Theme) -> Self {
+        use egui::Color32;
+        match theme {
+            egui::Theme::Dark => Palette {
+                track_a: Color32::GREEN,
+                track_b: Color32::RED,
+                diff_line: Color32::from_rgb(0, 255, 255),
+                mean_line: Color32::GRAY,
+                zero_line: Color32::WHITE,
+                ok: Color32::GREEN,
+                warn: Color32::YELLOW,
+                error: Color32::RED,
+                log_info: Color32::LIGHT_GREEN,
+                log_debug: Color32::YELLOW,
+                log_command: Color32::LIGHT_BLUE,
+                task_waiting: Color32::GRAY,
+                task_completed: Color32::from_rgb(100, 150, 255),
+                task_error: Color32::from_rgb(200, 60, 60),
+            },
+            egui::Theme::Light => Palette {
+                track_a: Color32::from_rgb(0, 140, 0),
+                track_b: Color32::from_rgb(200, 0, 0),
+                diff_line: Color32::from_rgb(0, 120, 160),
+                mean_line: Color32::DARK_GRAY,
+                zero_line: Color32::BLACK,
+                ok: Color32::from_rgb(0, 130, 0),
+                warn: Color32::from_rgb(180, 120, 0),
+                error: Color32::from_rgb(190, 0, 0),
+                log_info: Color32::from_rgb(0, 120, 0),
+                log_debug: Color32::from_rgb(150, 110, 0),
+                log_command: Color32::from_rgb(0, 80, 180),
+                task_waiting: Color32::DARK_GRAY,
+                task_completed: Color32::BLUE,
+                task_error: Color32::DARK_RED,
+            },
+        }
+    }
 }
 
-struct WorkerPool {
-    tasks: Arc<Mutex<Vec<AudioTask>>>, // 共享任务列表
-    next_id: usize,
-    command_tx: mpsc::Sender<WorkerCommand>, // UI -> Worker 命令发送端
-    _worker_handle: thread::JoinHandle<()>,   // Worker 管理线程句柄
+/// 单文件模式统计表的列 (均可点击标题排序)
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum StatsColumn {
+    Name,
+    Duration,
+    Average,
+    Loudest,
+    Quietest,
+    Points,
 }
 
-impl WorkerPool {
-    fn new(ui_tx: mpsc::Sender<WorkerMessage>) -> Self {
-        let (command_tx, command_rx) = mpsc::channel();
-        let tasks = Arc::new(Mutex::new(Vec::<AudioTask>::new()));
-        let tasks_clone = tasks.clone();
-        let ui_tx_clone = ui_tx.clone();
+impl StatsColumn {
+    const ALL: [StatsColumn; 6] = [
+        StatsColumn::Name, StatsColumn::Duration, StatsColumn::Average,
+        StatsColumn::Loudest, StatsColumn::Quietest, StatsColumn::Points,
+    ];
+}
 
-        // 启动 WorkerPool 管理线程 (非阻塞)
-        let _worker_handle = thread::spawn(move || {
-            loop {
-                // 1. 检查来自 UI 的命令
-                match command_rx.try_recv() {
-                    Ok(WorkerCommand::Kill(id)) => {
-                        if let Ok(mut tasks_lock) = tasks_clone.lock() {
-                            if let Some(task) = tasks_lock.iter_mut().find(|t| t.id == id && t.state != TaskState::Completed && t.state != TaskState::Killed) {
-                                // 在任务列表中标记为 Killed
-                                task.state = TaskState::Killed;
-                                ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Killed)).unwrap_or_default();
-
-                                // 记录到日志
-                                ui_tx_clone.send(WorkerMessage::Log(LogEntry {
-                                    time: Local::now().format("%H:%M:%S").to_string(),
-                                    message: format!("Command: Task {} ({}) marked for kill. (Note: Actual thread termination is not guaranteed in std::thread)", id, task.name),
-                                    level: LogLevel::Command,
-                                })).unwrap_or_default();
-                            }
-                        }
-                    }
-                    Ok(WorkerCommand::Shutdown) => {
-                        ui_tx_clone.send(WorkerMessage::Log(LogEntry {
-                            time: Local::now().format("%H:%M:%S").to_string(),
-                            message: "WorkerPool received Shutdown command. Exiting.".to_string(),
-                            level: LogLevel::Debug,
-                        })).unwrap_or_default();
-                        break;
-                    }
-                    Err(mpsc::TryRecvError::Empty) => {
-                        // 无命令，继续
-                    }
-                    Err(mpsc::TryRecvError::Disconnected) => break, // 通道断开
-                }
+/// 单文件图表的绘制顺序：single_files 的下标，最后一个画在最上层，图例也按此顺序列出。
+/// 曲线颜色仍按加载顺序的下标分配，调整顺序不会改变颜色。
+/// 置顶的曲线始终排在其余曲线之上 (新加载的曲线也放在它们下面)
+#[derive(Default)]
+struct DrawOrder {
+    order: Vec<usize>,
+    pinned: BTreeSet<usize>,
+}
 
-                thread::sleep(Duration::from_millis(100));
+impl DrawOrder {
+    /// 去掉已不存在的下标，新曲线补到最上层 (置顶曲线之下)
+    fn sync(&mut self, len: usize) {
+        self.order.retain(|&i| i < len);
+        self.pinned.retain(|&i| i < len);
+        for i in 0..len {
+            if !self.order.contains(&i) {
+                self.order.push(i);
             }
-        });
-
-        Self {
-            tasks,
-            next_id: 1,
-            command_tx,
-            _worker_handle,
         }
+        self.keep_pinned_on_top();
     }
 
-    /// 启动一个后台任务
-    fn spawn_task<F>(&mut self, name: String, f: F, ui_tx: mpsc::Sender<WorkerMessage>, logger: &Logger)
-    where
-        F: FnOnce(usize, mpsc::Sender<WorkerMessage>, Arc<Mutex<Vec<LogEntry>>>) + Send + 'static,
-    {
-        let id = self.next_id;
-        self.next_id += 1;
-        let task_name = name.clone();
+    fn clear(&mut self) {
+        self.order.clear();
+        self.pinned.clear();
+    }
 
-        // 传递日志条目 Arc<Mutex<...>> 的克隆给工作线程
-        let logger_entries_clone = logger.entries.clone();
-        let ui_tx_clone = ui_tx.clone();
+    /// 曲线从列表中移除：去掉其下标，其后的下标依次减一
+    fn remove(&mut self, index: usize) {
+        let shift = |i: usize| if i > index { i - 1 } else { i };
+        self.order = self.order.iter().filter(|&&i| i != index).map(|&i| shift(i)).collect();
+        self.pinned = self.pinned.iter().filter(|&&i| i != index).map(|&i| shift(i)).collect();
+    }
 
-        // 1. 记录初始状态
-        log_info(logger, &format!("⚙️ 任务 {} 启动: {}", id, task_name));
+    /// 置顶的曲线移到最上层，各自保持原有的相对顺序
+    fn keep_pinned_on_top(&mut self) {
+        let pinned = &self.pinned;
+        self.order.sort_by_key(|i| pinned.contains(i));
+    }
 
-        let initial_task = AudioTask {
-            id,
-            name: task_name.clone(),
-            state: TaskState::Running(0.0),
-        };
+    fn is_pinned(&self, index: usize) -> bool {
+        self.pinned.contains(&index)
+    }
 
-        // 2. 启动实际工作线程
-        thread::spawn(move || {
-            ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Running(0.0))).unwrap_or_default();
+    /// 置顶或取消置顶；置顶的曲线放到最上层，取消后留在其余曲线的最上层
+    fn toggle_pin(&mut self, index: usize) {
+        if !self.pinned.remove(&index) {
+            self.pinned.insert(index);
+            self.order.retain(|&i| i != index);
+            self.order.push(index);
+        }
+        self.keep_pinned_on_top();
+    }
 
-            // 执行实际任务
-            f(id, ui_tx_clone.clone(), logger_entries_clone.clone());
+    /// 与上一层 (up 为 true) 或下一层的曲线交换位置
+    fn step(&mut self, index: usize, up: bool) {
+        let Some(position) = self.order.iter().position(|&i| i == index) else { return };
+        let other = if up { position + 1 } else { position.wrapping_sub(1) };
+        if other < self.order.len() {
+            self.order.swap(position, other);
+            self.keep_pinned_on_top();
+        }
+    }
 
-            // 任务完成，发送最终状态 (这里仅作为兜底，实际应在 f 中发送 Completed/Error/Killed)
-            ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Completed)).unwrap_or_default();
+    /// 按上次会话保存的顺序 (曲线键列表，最下层在前) 放入新加载的曲线；不在其中的曲线放到最上层
+    fn insert(&mut self, index: usize, keys: &[String], saved: &[String]) {
+        let rank = |i: usize| saved.iter().position(|k| *k == keys[i]);
+        let position = match rank(index) {
+            Some(r) => self.order.iter().position(|&i| rank(i).is_none_or(|other| other > r)).unwrap_or(self.order.len()),
+            None => self.order.len(),
+        };
+        self.order.insert(position, index);
+        self.keep_pinned_on_top();
+    }
 
-            let thread_logger = Logger { entries: logger_entries_clone };
-            log_info(&thread_logger, &format!("✔️ 任务 {} 完成: {}", id, task_name));
+    fn bring_to_front(&mut self, index: usize) {
+        self.order.retain(|&i| i != index);
+        self.order.push(index);
+        self.keep_pinned_on_top();
+    }
 
-        });
+    fn send_to_back(&mut self, index: usize) {
+        self.order.retain(|&i| i != index);
+        self.order.insert(0, index);
+        self.keep_pinned_on_top();
+    }
 
-        // 3. 存储任务信息
-        if let Ok(mut tasks_lock) = self.tasks.lock() {
-            tasks_lock.push(initial_task);
+    /// 拖放：把 index 放到 target 的上一层
+    fn move_above(&mut self, index: usize, target: usize) {
+        if index == target {
+            return;
         }
+        self.order.retain(|&i| i != index);
+        let position = self.order.iter().position(|&i| i == target).map_or(self.order.len(), |p| p + 1);
+        self.order.insert(position, index);
+        self.keep_pinned_on_top();
     }
 }
 
-
-// --- 音频处理逻辑 (更新: 增加 Logger 参数) ---
-
-fn calculate_rms_dbfs(samples: &[f64]) -> f64 {
-    if samples.is_empty() { return -120.0; }
-    let squared_sum: f64 = samples.iter().map(|s| s * s).sum();
-    let rms = (squared_sum / samples.len() as f64).sqrt();
-    if rms < 1e-9 { -120.0 } else { 20.0 * rms.log10() }
+/// 统计表的排序状态；排序后的行顺序会缓存，仅在排序方式或曲线列表变化时重算
+struct StatsTable {
+    sort: StatsColumn,
+    ascending: bool,
+    order: Vec<usize>,
+    // (排序列, 升序, 曲线列表版本)
+    order_key: Option<(StatsColumn, bool, u64)>,
+    // 下一帧要滚动到的曲线索引
+    scroll_to: Option<usize>,
 }
 
-/// 计算 Pearson 相关系数 (r)
-fn calculate_correlation(a_vals: &[f64], b_vals: &[f64], len: usize) -> f64 {
-    if len <= 1 { return 0.0; }
-
-    let mean_a = a_vals.iter().sum::<f64>() / len as f64;
-    let mean_b = b_vals.iter().sum::<f64>() / len as f64;
-
-    let mut numerator = 0.0;
-    let mut sum_sq_a = 0.0;
-    let mut sum_sq_b = 0.0;
-
-    for i in 0..len {
-        let dev_a = a_vals[i] - mean_a;
-        let dev_b = b_vals[i] - mean_b;
-
-        numerator += dev_a * dev_b;
-        sum_sq_a += dev_a * dev_a;
-        sum_sq_b += dev_b * dev_b;
+impl Default for StatsTable {
+    fn default() -> Self {
+        Self { sort: StatsColumn::Name, ascending: true, order: Vec::new(), order_key: None, scroll_to: None }
     }
+}
 
-    let denominator = (sum_sq_a * sum_sq_b).sqrt();
+impl StatsTable {
+    /// 点击列标题：同一列切换升降序，换列时默认升序
+    fn toggle_sort(&mut self, column: StatsColumn) {
+        if self.sort == column {
+            self.ascending = !self.ascending;
+        } else {
+            self.sort = column;
+            self.ascending = true;
+        }
+    }
 
-    if denominator == 0.0 {
-        return 0.0;
+    fn refresh(&mut self, curves: &[AudioCurve], revision: u64) {
+        let key = (self.sort, self.ascending, revision);
+        if self.order_key == Some(key) && self.order.len() == curves.len() {
+            return;
+        }
+        let level = |p: Option<[f64; 2]>| p.map_or(f64::NEG_INFINITY, |p| p[1]);
+        self.order.clear();
+        self.order.extend(0..curves.len());
+        self.order.sort_by(|&a, &b| {
+            let (a, b) = (&curves[a], &curves[b]);
+            let ordering = match self.sort {
+                StatsColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+                StatsColumn::Duration => a.duration.total_cmp(&b.duration),
+                StatsColumn::Average => a.average_dbfs.total_cmp(&b.average_dbfs),
+                StatsColumn::Loudest => level(a.loudest).total_cmp(&level(b.loudest)),
+                StatsColumn::Quietest => level(a.quietest).total_cmp(&level(b.quietest)),
+                StatsColumn::Points => a.points.len().cmp(&b.points.len()),
+            };
+            if self.ascending { ordering } else { ordering.reverse() }
+        });
+        self.order_key = Some(key);
     }
-    numerator / denominator
 }
 
-/// 计算单样本 T 统计量 (检验均值差值是否为 0/C)
-/// mean_difference 应该传入 (实际均值差 - 目标差值)
-fn calculate_t_statistic(mean_difference: f64, std_dev: f64, n: usize) -> f64 {
-    if n <= 1 || std_dev.abs() < f64::EPSILON {
-        return 0.0;
-    }
-    // 标准误差 (SEM) = std_dev / sqrt(n)
-    let sem = std_dev / (n as f64).sqrt();
+/// 图表上的测量光标
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CursorSlot {
+    A,
+    B,
+}
 
-    // T = (Mean - Target) / SEM
-    mean_difference / sem
+/// 一个图表上的两个测量光标 (保存绘图坐标的时间，缩放后位置不变)
+#[derive(Default)]
+struct MeasureCursors {
+    a: Option<f64>,
+    b: Option<f64>,
+    dragging: Option<CursorSlot>,
+    // 指针是否悬停在光标附近；下一帧据此禁用图表平移，使拖动作用于光标
+    hovered: bool,
 }
 
+/// 测量读数中的一条曲线：在 (t - time_shift) 处插值，读数加上 level_shift
+struct CursorSeries<'a> {
+    name: &'a str,
+    points: &'a [[f64; 2]],
+    time_shift: f64,
+    level_shift: f64,
+}
 
-/// 【已修复】解析 WAV 文件，支持 16/24/32-bit PCM 和 32-bit Float 格式。
-fn parse_wav(path: PathBuf, logger: &Logger) -> Result<AudioCurve, Box<dyn Error + Send + Sync>> {
-    let filename = path.file_name().unwrap().to_string_lossy().to_string();
-    log_info(logger, &format!("▶️ 开始解析 WAV 文件: {}", filename));
+impl CursorSeries<'_> {
+    fn level_at(&self, t: f64) -> Option<f64> {
+        interpolate_at(self.points, t - self.time_shift).map(|v| v + self.level_shift)
+    }
+}
 
-    let mut reader = WavReader::open(&path)?;
-    let spec = reader.spec();
+impl MeasureCursors {
+    /// 指针距光标多少像素以内可以拖动
+    const GRAB_DISTANCE: f32 = 6.0;
 
-    log_debug(logger, &format!("WAV Spec: Rate={}Hz, Channels={}, Bits={}, Format={:?}", spec.sample_rate, spec.channels, spec.bits_per_sample, spec.sample_format));
+    fn is_empty(&self) -> bool {
+        self.a.is_none() && self.b.is_none()
+    }
 
-    // 根据 WAV 文件的格式规范读取并归一化样本
-    let samples: Vec<f64> = match (spec.sample_format, spec.bits_per_sample) {
-        // 16-bit Integer PCM (Read as i16, max value is 2^15)
-        (hound::SampleFormat::Int, 16) => {
-            let max_val = 1u32 << 15;
-            reader.samples::<i16>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64 / max_val as f64)
-                .collect()
-        }
-        // 24-bit Integer PCM (Read as i32, max value is 2^23)
-        (hound::SampleFormat::Int, 24) => {
-            let max_val = 1u32 << 23;
-            reader.samples::<i32>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64 / max_val as f64)
-                .collect()
+    fn clear(&mut self) {
+        *self = Self::default();
+    }
+
+    /// 处理图表上的指针输入：单击放置 A，Shift+单击放置 B，拖动光标附近移动光标。
+    /// Ctrl/Cmd+单击和 Alt+单击留给调用方 (回放跳转、复制数值)，此处忽略
+    fn interact(&mut self, plot_ui: &egui_plot::PlotUi<'_>) {
+        let response = plot_ui.response();
+        let modifiers = plot_ui.ctx().input(|i| i.modifiers);
+        let pointer = plot_ui.pointer_coordinate();
+
+        let near = response.hover_pos().and_then(|pos| {
+            [(CursorSlot::A, self.a), (CursorSlot::B, self.b)].into_iter()
+                .filter_map(|(slot, x)| x.map(|x| (slot, (plot_ui.screen_from_plot(PlotPoint::new(x, 0.0)).x - pos.x).abs())))
+                .filter(|&(_, distance)| distance <= Self::GRAB_DISTANCE)
+                .min_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(slot, _)| slot)
+        });
+        // 多个图表共用同一对光标时，只由指针所在 (或正在拖动) 的图表更新
+        if response.hovered() || response.dragged() {
+            self.hovered = near.is_some() || self.dragging.is_some();
         }
-        // 32-bit Integer PCM (Read as i32, max value is 2^31)
-        (hound::SampleFormat::Int, 32) => {
-            let max_val = 1u64 << 31;
-            reader.samples::<i32>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64 / max_val as f64)
-                .collect()
+
+        if response.drag_started() {
+            self.dragging = near;
         }
-        // 32-bit Float (Read as f32, already normalized [-1.0, 1.0])
-        (hound::SampleFormat::Float, 32) => {
-            reader.samples::<f32>()
-                .filter_map(|s| s.ok())
-                .map(|s| s as f64)
-                .collect()
+        if response.dragged()
+            && let Some(slot) = self.dragging
+            && let Some(p) = pointer
+        {
+            self.set(slot, p.x);
         }
-        // Fallback for unsupported formats
-        _ => {
-            let msg = format!(
-                "❌ 不支持的 WAV 格式: Format={:?}, Bits={}",
-                spec.sample_format, spec.bits_per_sample
-            );
-            log_error(logger, &msg);
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::InvalidData,
-                msg,
-            )));
+        if response.drag_stopped() {
+            self.dragging = None;
         }
-    };
 
-    if samples.is_empty() {
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "WAV 文件没有可用的样本数据")));
+        if response.clicked()
+            && !modifiers.command
+            && !modifiers.alt
+            && let Some(p) = pointer
+        {
+            self.set(if modifiers.shift { CursorSlot::B } else { CursorSlot::A }, p.x);
+        }
     }
-    log_debug(logger, &format!("总样本数: {}", samples.len()));
-
-    let window_sec = 0.4;
-    let step_sec = 0.1;
-    let sample_rate = spec.sample_rate as usize;
-    let channels = spec.channels as usize;
 
-    let window_size = (window_sec * sample_rate as f64) as usize;
-    let step_size = (step_sec * sample_rate as f64) as usize;
-
-    if window_size * channels == 0 || step_size * channels == 0 {
-        log_error(logger, "⚠️ 窗口/步进尺寸计算为 0，跳过曲线生成。");
-        return Err(Box::new(std::io::Error::new(std::io::ErrorKind::InvalidData, "计算窗口大小错误")));
+    fn set(&mut self, slot: CursorSlot, x: f64) {
+        match slot {
+            CursorSlot::A => self.a = Some(x),
+            CursorSlot::B => self.b = Some(x),
+        }
     }
 
-    let mut points = Vec::new();
-    let mut dbfs_sum = 0.0;
-    let mut i = 0;
-    while i + window_size * channels <= samples.len() {
-        let window = &samples[i..i + window_size * channels];
-        let db = calculate_rms_dbfs(window);
-        let time = (i as f64 + (window_size * channels / 2) as f64) / (sample_rate * channels) as f64;
-        points.push([time, db]);
-        dbfs_sum += db;
-        i += step_size * channels;
+    fn draw(&self, plot_ui: &mut egui_plot::PlotUi<'_>, color: egui::Color32) {
+        for (label, x) in [("A", self.a), ("B", self.b)] {
+            if let Some(x) = x {
+                plot_ui.vline(egui_plot::VLine::new(label, x)
+                    .color(color)
+                    .style(egui_plot::LineStyle::Dashed { length: 4.0 })
+                    .width(1.0));
+            }
+        }
     }
 
-    let duration = points.last().map(|p| p[0]).unwrap_or(0.0);
-    let average_dbfs = if points.is_empty() { -120.0 } else { dbfs_sum / points.len() as f64 };
+    /// 读数文本：Δt 以及每条曲线在两个光标处的电平和差值 (B - A)
+    fn readout(&self, series: &[CursorSeries], time: TimeFormat) -> Vec<String> {
+        let mut lines = Vec::new();
+        if let (Some(a), Some(b)) = (self.a, self.b) {
+            lines.push(format!("A {} · B {} · Δt {}", time.format(a, 3), time.format(b, 3), time.format(b - a, 3)));
+        } else if let Some(x) = self.a.or(self.b) {
+            lines.push(format!("{} {}", if self.a.is_some() { "A" } else { "B" }, time.format(x, 3)));
+        }
+        let level = |v: Option<f64>| v.map_or_else(|| "-".to_string(), |v| format!("{:.2} dB", v));
+        for s in series {
+            let (la, lb) = (self.a.and_then(|t| s.level_at(t)), self.b.and_then(|t| s.level_at(t)));
+            let delta = match (la, lb) {
+                (Some(la), Some(lb)) => format!(" · Δ {:+.2} dB", lb - la),
+                _ => String::new(),
+            };
+            lines.push(format!("{}: A {} · B {}{}", s.name, level(la), level(lb), delta));
+        }
+        lines
+    }
 
-    log_info(logger, &format!("✅ 文件解析完成: {} (Duration: {:.2}s, Points: {})", filename, duration, points.len()));
+    /// 在图表上方显示读数及清除/复制按钮；没有光标时只显示操作提示
+    fn show_readout(&mut self, ui: &mut egui::Ui, lang: &Lang, series: &[CursorSeries], time: TimeFormat) {
+        if self.is_empty() {
+            ui.weak(lang.measure_hint); // I18N
+            return;
+        }
+        let lines = self.readout(series, time);
+        ui.horizontal(|ui| {
+            if ui.small_button(lang.measure_clear).clicked() { // I18N
+                self.clear();
+            }
+            if ui.small_button(lang.measure_copy).clicked() { // I18N
+                ui.ctx().copy_text(lines.join("\n"));
+            }
+            ui.weak(lang.measure_hint);
+        });
+        for line in &lines {
+            ui.monospace(line);
+        }
+    }
+}
 
-    Ok(AudioCurve { name: filename, points, duration, average_dbfs })
+/// 自动适配 Y 轴的方式
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum YFit {
+    /// 包含所有可见点
+    Data,
+    /// 取可见点的第 1–99 百分位，避免静音窗口把曲线压扁
+    IgnoreSilence,
 }
 
-/// 解析 CSV 文件
-fn parse_csv(path: PathBuf, logger: &Logger) -> Result<AudioCurve, Box<dyn Error + Send + Sync>> {
-    let filename = path.file_name().unwrap().to_string_lossy().to_string();
-    log_info(logger, &format!("▶️ 开始解析 CSV 文件: {}", filename));
+/// 图表的 Y 轴范围控件。只在输入框被修改或点击适配按钮的那一帧设置图表范围，不与用户的缩放冲突
+#[derive(Default)]
+struct YAxisControl {
+    apply: bool,
+    fit: Option<YFit>,
+}
 
-    let file = File::open(&path)?;
-    let mut rdr = csv::Reader::from_reader(file);
-    let mut points = Vec::new();
-    let mut dbfs_sum = 0.0;
-    let mut count = 0;
+impl YAxisControl {
+    fn ui(&mut self, ui: &mut egui::Ui, lang: &Lang, range: &mut [f64; 2]) {
+        ui.label(lang.y_axis_label); // I18N
+        let [min, max] = range;
+        let min_changed = ui.add(egui::DragValue::new(min).speed(0.5).range(-200.0..=*max - 1.0).suffix(" dB")).changed();
+        ui.label("…");
+        let max_changed = ui.add(egui::DragValue::new(max).speed(0.5).range(*min + 1.0..=60.0).suffix(" dB")).changed();
+        self.apply |= min_changed || max_changed;
+        if ui.button(lang.y_fit_data).clicked() { // I18N
+            self.fit = Some(YFit::Data);
+        }
+        if ui.button(lang.y_fit_ignore_silence).on_hover_text(lang.y_fit_ignore_silence_hint).clicked() { // I18N
+            self.fit = Some(YFit::IgnoreSilence);
+        }
+    }
 
-    for (line_num, result) in rdr.records().enumerate() {
-        let record = match result {
-            Ok(r) => r,
-            Err(e) => {
-                log_error(logger, &format!("CSV 读取错误 (Line {}): {}", line_num + 2, e));
-                continue;
-            }
-        };
+    /// 本帧要设置的 Y 轴范围 (仅在输入框刚被修改后)
+    fn take_apply(&mut self, range: [f64; 2]) -> Option<[f64; 2]> {
+        std::mem::take(&mut self.apply).then_some(range)
+    }
+}
 
-        if record.len() >= 2 {
-            let t = match record[0].parse::<f64>() {
-                Ok(v) => v,
-                Err(e) => {
-                    log_error(logger, &format!("CSV 格式错误 (Time, Line {}): {}", line_num + 2, e));
-                    continue;
-                }
-            };
-            let v = match record[1].parse::<f64>() {
-                Ok(v) => v,
-                Err(e) => {
-                    log_error(logger, &format!("CSV 格式错误 (Value, Line {}): {}", line_num + 2, e));
-                    continue;
-                }
-            };
-            points.push([t, v]);
-            dbfs_sum += v;
-            count += 1;
-        } else {
-            log_error(logger, &format!("CSV 格式错误 (列数不足 2, Line {}): {:?}", line_num + 2, record));
-        }
+/// 按 YFit 从可见点的电平计算 Y 轴范围，两端留出少量余量
+fn fit_y_range(mut values: Vec<f64>, fit: YFit) -> Option<[f64; 2]> {
+    if values.is_empty() {
+        return None;
     }
-    let duration = points.last().map(|p| p[0]).unwrap_or(0.0);
-    let average_dbfs = if count == 0 { -120.0 } else { dbfs_sum / count as f64 };
+    values.sort_by(|a, b| a.total_cmp(b));
+    let percentile = |q: f64| values[((values.len() - 1) as f64 * q).round() as usize];
+    let (lo, hi) = match fit {
+        YFit::Data => (values[0], values[values.len() - 1]),
+        YFit::IgnoreSilence => (percentile(0.01), percentile(0.99)),
+    };
+    let margin = ((hi - lo) * 0.05).max(1.0);
+    Some([lo - margin, hi + margin])
+}
 
-    log_info(logger, &format!("✅ CSV 解析完成: {} (Duration: {:.2}s, Points: {})", filename, duration, points.len()));
+/// x 范围内各条可见线上点的电平
+fn visible_levels(lines: &[&[PlotPoint]], x_range: std::ops::RangeInclusive<f64>) -> Vec<f64> {
+    lines.iter()
+        .flat_map(|points| points.iter())
+        .filter(|p| x_range.contains(&p.x))
+        .map(|p| p.y)
+        .collect()
+}
 
-    Ok(AudioCurve { name: filename, points, duration, average_dbfs })
+/// 用户在时间轴上附加到某条曲线的文本标注
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct Annotation {
+    time: f64,
+    text: String,
 }
 
+/// 曲线是否用与当前不同 (影响结果) 的分析参数计算；CSV 曲线没有分析参数，不会过期
+fn curve_is_stale(curve: &AudioCurve, settings: &AppSettings) -> bool {
+    let current = settings.analysis_for(curve.source.as_deref());
+    curve.analysis.as_ref().is_some_and(|used| !used.same_results(current))
+}
 
-fn load_file(path: PathBuf, logger: &Logger) -> Result<AudioCurve, Box<dyn Error + Send + Sync>> {
-    if let Some(ext) = path.extension() {
-        if ext == "csv" {
-            return parse_csv(path, logger);
+/// 曲线使用的各组不同 (影响结果) 的分析参数，按首次出现的顺序；CSV 曲线不计入
+fn distinct_analysis_settings<'a>(curves: impl IntoIterator<Item = &'a AudioCurve>) -> Vec<&'a AnalysisSettings> {
+    let mut distinct: Vec<&AnalysisSettings> = Vec::new();
+    for analysis in curves.into_iter().filter_map(|c| c.analysis.as_ref()) {
+        if !distinct.iter().any(|d| d.same_results(analysis)) {
+            distinct.push(analysis);
         }
     }
-    parse_wav(path, logger)
+    distinct
 }
 
-/// 导出 AudioCurve 数据到 CSV 文件
-fn export_to_csv(curve: &AudioCurve, target_lufs: f64, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let default_name = format!("{}.csv", curve.name.replace(".wav", "").replace(".csv", ""));
-
-    // 允许用户选择保存位置
-    let path = FileDialog::new()
-        .set_file_name(&default_name)
-        .add_filter("CSV File", &["csv"])
-        .save_file();
+/// 影响结果的分析参数控件 (窗口、计权、噪声门限、跳过范围、平均方式、下混)，全局参数和分析配置共用
+fn ui_analysis_params(ui: &mut egui::Ui, analysis: &mut AnalysisSettings, lang: &Lang, id_salt: &str) {
+    ui.horizontal(|ui| {
+        ui.label(lang.analysis_window_label); // I18N
+        ui.add(egui::DragValue::new(&mut analysis.window_sec).speed(0.01).range(0.05..=10.0).suffix(" s"));
+        ui.label(lang.analysis_step_label); // I18N
+        ui.add(egui::DragValue::new(&mut analysis.step_sec).speed(0.01).range(0.01..=analysis.window_sec).suffix(" s"));
+        ui.separator();
+        ui.label(lang.analysis_weighting_label); // I18N
+        ui.selectable_value(&mut analysis.weighting, CurveWeighting::Flat, lang.analysis_weighting_flat)
+            .on_hover_text(lang.analysis_weighting_hint);
+        ui.selectable_value(&mut analysis.weighting, CurveWeighting::K, lang.analysis_weighting_k)
+            .on_hover_text(lang.analysis_weighting_hint);
+        // 实验性的等响计权：选中后可调假定的重放响度级
+        let equal_loudness = matches!(analysis.weighting, CurveWeighting::EqualLoudness { .. });
+        if ui.selectable_label(equal_loudness, lang.analysis_weighting_iso226) // I18N
+            .on_hover_text(lang.analysis_weighting_iso226_hint)
+            .clicked()
+            && !equal_loudness
+        {
+            analysis.weighting = CurveWeighting::EqualLoudness { phon: CurveWeighting::DEFAULT_PHON };
+        }
+        if let CurveWeighting::EqualLoudness { phon } = &mut analysis.weighting {
+            ui.add(egui::DragValue::new(phon).speed(1.0).range(EQUAL_LOUDNESS_PHON_RANGE).suffix(" phon"));
+        }
+        ui.separator();
+        ui.label(lang.analysis_floor_label) // I18N
+            .on_hover_text(lang.analysis_floor_hint);
+        ui.add(egui::DragValue::new(&mut analysis.noise_floor_dbfs).speed(0.5).range(-180.0..=-20.0).suffix(" dBFS"));
+        ui.separator();
+        ui.label(lang.analysis_skip_head_label) // I18N
+            .on_hover_text(lang.analysis_skip_hint);
+        ui.add(egui::DragValue::new(&mut analysis.skip_head_sec).speed(0.1).range(0.0..=3600.0).suffix(" s"));
+        ui.label(lang.analysis_skip_tail_label); // I18N
+        ui.add(egui::DragValue::new(&mut analysis.skip_tail_sec).speed(0.1).range(0.0..=3600.0).suffix(" s"));
+        ui.separator();
+        let mut db_mean = analysis.average == AverageMode::DbMean;
+        if ui.checkbox(&mut db_mean, lang.analysis_db_mean_label) // I18N
+            .on_hover_text(lang.analysis_db_mean_hint)
+            .changed()
+        {
+            analysis.average = if db_mean { AverageMode::DbMean } else { AverageMode::Power };
+        }
+    });
+
+    // --- 声道下混设置 ---
+    ui.horizontal(|ui| {
+        ui.label(lang.downmix_label); // I18N
+        egui::ComboBox::from_id_salt(id_salt)
+            .selected_text(match analysis.downmix {
+                DownmixMode::Interleaved => lang.downmix_interleaved,
+                DownmixMode::Matrix => lang.downmix_matrix,
+                DownmixMode::Bs1770 => lang.downmix_bs1770,
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut analysis.downmix, DownmixMode::Interleaved, lang.downmix_interleaved);
+                ui.selectable_value(&mut analysis.downmix, DownmixMode::Matrix, lang.downmix_matrix);
+                ui.selectable_value(&mut analysis.downmix, DownmixMode::Bs1770, lang.downmix_bs1770);
+            });
+        ui.add_enabled_ui(analysis.downmix == DownmixMode::Matrix, |ui| {
+            ui.label(lang.downmix_stereo_gain);
+            ui.add(egui::DragValue::new(&mut analysis.stereo_downmix_db)
+                .speed(0.1)
+                .range(-12.0..=0.0)
+                .suffix(" dB")
+            );
+        });
+    });
+}
 
-    if let Some(path) = path {
-        log_info(logger, &format!("▶️ 导出数据到: {}", path.display()));
-        let file = File::create(&path)?;
-        let mut wtr = csv::Writer::from_writer(file);
+/// 分析配置中 CSV 列布局的控件 (列号从 1 开始显示)
+fn ui_csv_layout(ui: &mut egui::Ui, layout: &mut CsvLayout, lang: &Lang, id: usize) {
+    ui.horizontal(|ui| {
+        ui.label(lang.profile_csv_delimiter); // I18N
+        egui::ComboBox::from_id_salt(("profile_csv_delimiter", id))
+            .selected_text(match layout.delimiter {
+                b'\t' => lang.profile_csv_tab.to_string(),
+                d => (d as char).to_string(),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut layout.delimiter, b',', ",");
+                ui.selectable_value(&mut layout.delimiter, b';', ";");
+                ui.selectable_value(&mut layout.delimiter, b'\t', lang.profile_csv_tab);
+            });
+        ui.label(lang.profile_csv_skip_lines); // I18N
+        ui.add(egui::DragValue::new(&mut layout.skip_lines).range(0..=1000));
+        ui.checkbox(&mut layout.has_header, lang.profile_csv_header); // I18N
+        ui.separator();
+        for (label, column) in [(lang.csv_import_time_column, &mut layout.time_column), (lang.csv_import_value_column, &mut layout.value_column)] {
+            ui.label(label); // I18N
+            let mut number = *column + 1;
+            if ui.add(egui::DragValue::new(&mut number).range(1..=256)).changed() {
+                *column = number - 1;
+            }
+        }
+        ui.separator();
+        ui.label(lang.csv_import_time_unit); // I18N
+        ui.selectable_value(&mut layout.time_unit, CsvTimeUnit::Seconds, lang.csv_import_unit_seconds);
+        ui.selectable_value(&mut layout.time_unit, CsvTimeUnit::Milliseconds, lang.csv_import_unit_ms);
+    });
+}
 
-        // 写入表头
-        wtr.write_record(&["Time (s)", "Loudness (dBFS)", "Normalized Loudness (dBFS)"])?;
+/// 分析参数的一行摘要 (沿用分析参数面板的标签)
+fn analysis_summary(analysis: &AnalysisSettings, lang: &Lang) -> String {
+    let weighting = match analysis.weighting {
+        CurveWeighting::Flat => lang.analysis_weighting_flat.to_string(),
+        CurveWeighting::K => lang.analysis_weighting_k.to_string(),
+        CurveWeighting::EqualLoudness { phon } => lang.analysis_weighting_iso226_fmt.replacen("{}", &format!("{:.0}", phon), 1),
+    };
+    let downmix = match analysis.downmix {
+        DownmixMode::Interleaved => lang.downmix_interleaved,
+        DownmixMode::Matrix => lang.downmix_matrix,
+        DownmixMode::Bs1770 => lang.downmix_bs1770,
+    };
+    let mut summary = format!(
+        "{} {:.2} s  {} {:.2} s  {} {}  {} {:.0} dBFS  {} {}",
+        lang.analysis_window_label, analysis.window_sec,
+        lang.analysis_step_label, analysis.step_sec,
+        lang.analysis_weighting_label, weighting,
+        lang.analysis_floor_label, analysis.noise_floor_dbfs,
+        lang.downmix_label, downmix,
+    );
+    if analysis.trims_range() {
+        summary.push_str(&format!(
+            "  {} {:.2} s  {} {:.2} s",
+            lang.analysis_skip_head_label, analysis.skip_head_sec, lang.analysis_skip_tail_label, analysis.skip_tail_sec,
+        ));
+    }
+    if analysis.average == AverageMode::DbMean {
+        summary.push_str(&format!("  ({})", lang.analysis_db_mean_label));
+    }
+    summary
+}
 
-        // 计算偏移量
-        let offset_val = target_lufs - curve.average_dbfs;
-        log_debug(logger, &format!("应用归一化偏移量: {:.2} dB", offset_val));
+/// 标注、重命名及标签按曲线保存的键：WAV 用源文件路径，CSV 曲线用文件名 (不随重命名改变)
+fn curve_key(curve: &AudioCurve) -> String {
+    curve.source.as_ref().map_or_else(|| curve.file_name(), |p| p.display().to_string())
+}
 
-        // 写入数据点
-        for point in &curve.points {
-            let normalized_db = point[1] + offset_val;
-            wtr.write_record(&[
-                format!("{:.3}", point[0]),      // Time
-                format!("{:.2}", point[1]),      // Raw dBFS
-                format!("{:.2}", normalized_db), // Normalized dBFS
-            ])?;
-        }
+/// 曲线的自定义名称及标签，按 curve_key 随会话持久化，重新加载同一文件时恢复
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+struct CurveLabel {
+    // 与文件名不同时才保存
+    name: Option<String>,
+    tags: Vec<String>,
+}
 
-        wtr.flush()?;
-        log_info(logger, &format!("✅ CSV 文件导出成功: {}", path.file_name().unwrap_or_default().to_string_lossy()));
+impl CurveLabel {
+    /// 记录曲线当前的名称和标签；与文件名相同且没有标签时删除记录
+    fn store(labels: &mut BTreeMap<String, CurveLabel>, curve: &AudioCurve) {
+        let key = curve_key(curve);
+        let name = (curve.name != curve.file_name()).then(|| curve.name.clone());
+        if name.is_none() && curve.tags.is_empty() {
+            labels.remove(&key);
+        } else {
+            labels.insert(key, CurveLabel { name, tags: curve.tags.clone() });
+        }
     }
-    Ok(())
 }
 
+/// 曲线列表中正在进行的行内编辑
+struct CurveEdit {
+    key: String,
+    kind: CurveEditKind,
+    text: String,
+    // 编辑框出现的第一帧请求键盘焦点
+    focus_pending: bool,
+}
 
-// --- GUI 应用程序结构 ---
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum CurveEditKind {
+    Rename,
+    AddTag,
+}
 
-struct WavLufsApp {
-    mode: AppMode,
-    lang: Lang,
-    current_lang: Language,
+/// 标签的显示颜色：按文本哈希取色相，同一标签颜色固定
+fn tag_color(tag: &str) -> egui::Color32 {
+    let hash = tag.bytes().fold(0u32, |h, b| h.wrapping_mul(31).wrapping_add(b as u32));
+    egui::ecolor::Hsva::new((hash % 360) as f32 / 360.0, 0.35, 0.9, 1.0).into()
+}
 
-    // 全局日志系统
-    logger: Logger,
+/// 标签小块
+fn tag_chip(ui: &mut egui::Ui, tag: &str) -> egui::Response {
+    ui.add(egui::Button::new(egui::RichText::new(tag).small().color(egui::Color32::BLACK))
+        .fill(tag_color(tag))
+        .corner_radius(8.0)
+        .small())
+}
 
-    // 异步工作池
-    worker_pool: WorkerPool,
-    ui_tx: mpsc::Sender<WorkerMessage>,
-    ui_rx: mpsc::Receiver<WorkerMessage>, // Worker -> UI 消息接收端
+/// 导出文件名：去掉音频扩展名，并替换重命名中可能出现的路径分隔符
+fn export_file_stem(curve: &AudioCurve) -> String {
+    curve.name.replace(".wav", "").replace(".csv", "").replace(['/', '\\'], "_")
+}
 
-    // 命令行相关
-    cmd_input: String,
+/// 归一化 WAV 的文件名 (`<名称>_normalized.wav`)；不同文件夹中的同名文件依次加上 _2、_3 …，
+/// 避免同时运行的导出任务写入同一个文件 (按不区分大小写比较)
+fn normalized_file_names(sources: &[PathBuf]) -> Vec<String> {
+    let mut used = HashSet::new();
+    sources.iter()
+        .map(|source| {
+            let stem = source.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            let mut name = format!("{}_normalized.wav", stem);
+            let mut n = 2;
+            while !used.insert(name.to_lowercase()) {
+                name = format!("{}_normalized_{}.wav", stem, n);
+                n += 1;
+            }
+            name
+        })
+        .collect()
+}
 
-    // 单机模式数据
-    single_files: Arc<Mutex<Vec<AudioCurve>>>,
-    loading: bool,
-    error_msg: Option<String>,
-    target_lufs: f32,
-    show_help_popup: bool, // 新增：控制帮助悬浮窗
+/// 文件大小的可读形式，例如 "12.34 MiB (12939428 bytes)"
+fn format_file_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["bytes", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} bytes", bytes)
+    } else {
+        format!("{:.2} {} ({} bytes)", value, UNITS[unit], bytes)
+    }
+}
 
-    // 对比模式数据
-    compare_a: Option<AudioCurve>,
-    compare_b: Option<AudioCurve>,
-    compare_result: Option<ComparisonResult>,
-    confidence_level: f32,
-    // ⭐ 新增: 目标平均差值 (Target Mean Difference)
-    target_mean_diff: f32,
+/// 右键单击图表后等待输入文本的标注
+struct PendingAnnotation {
+    key: String,
+    curve_name: String,
+    time: f64,
+    text: String,
 }
 
-impl WavLufsApp {
-    fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        let current_lang = Language::Chinese;
-        let lang = Lang::load(current_lang);
+/// 标注导出格式
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum AnnotationFormat {
+    /// Audacity 标签轨：每行 "起点\t终点\t文本"
+    Audacity,
+    Csv,
+}
 
-        // --- 修正: 将字体配置逻辑移到 configure_fonts 并调用 ---
-        Self::configure_fonts(&cc.egui_ctx, current_lang);
-        // --- 字体配置结束 ---
+/// 置信度的百分数文字，去掉多余的小数 (0.95 -> "95"，0.999 -> "99.9")
+fn confidence_percent(confidence: f32) -> String {
+    let text = format!("{:.2}", confidence * 100.0);
+    text.trim_end_matches('0').trim_end_matches('.').to_string()
+}
 
-        // 显式关闭调试功能，避免显示 ID 冲突的调试信息
+/// 合成参考的简短描述 (曲线名称和报告中使用)
+fn synthetic_description(reference: &SyntheticReference, lang: &Lang) -> String {
+    match reference {
+        SyntheticReference::Constant(level) => format!("{:.1} dBFS", level),
+        SyntheticReference::Breakpoints(points) => lang.synthetic_breakpoints_fmt.replacen("{}", &points.len().to_string(), 1),
+    }
+}
 
-        // 在 egui 0.27 中，该功能已移至 Context 上的 set_debug_on_hover 方法。
+/// 单文件列表中内容重复的曲线 (见 find_duplicates)，曲线列表变化时才重新计算
+#[derive(Default)]
+struct DuplicateScan {
+    revision: Option<u64>,
+    // 每条曲线内容相同的最早一条曲线的下标
+    first: Vec<Option<usize>>,
+}
 
+impl DuplicateScan {
+    fn refresh(&mut self, curves: &[AudioCurve], revision: u64) {
+        if self.revision != Some(revision) || self.first.len() != curves.len() {
+            self.first = find_duplicates(curves);
+            self.revision = Some(revision);
+        }
+    }
 
-        let logger = Logger::new();
-        log_info(&logger, "✅ 应用启动成功。");
+    fn any(&self) -> bool {
+        self.first.iter().any(Option::is_some)
+    }
 
-        // --- 初始化 MPSC 通道和 WorkerPool ---
-        let (ui_tx, ui_rx) = mpsc::channel();
-        let worker_pool = WorkerPool::new(ui_tx.clone());
+    /// 与 index 内容相同的其他曲线
+    fn partners(&self, index: usize) -> Vec<usize> {
+        let root = |i: usize| self.first.get(i).copied().flatten().unwrap_or(i);
+        (0..self.first.len()).filter(|&i| i != index && root(i) == root(index)).collect()
+    }
+}
 
-        Self {
-            mode: AppMode::Single,
-            lang,
-            current_lang,
-            logger,
-            worker_pool,
-            ui_tx,
-            ui_rx,
-            cmd_input: String::new(),
-            single_files: Arc::new(Mutex::new(Vec::new())),
-            loading: false,
-            error_msg: None,
-            target_lufs: -23.0,
-            show_help_popup: false, // 默认关闭
-            compare_a: None,
-            compare_b: None,
-            compare_result: None,
-            confidence_level: 0.95,
-            // ⭐ 初始化目标差值为 0.0 (默认为检查绝对匹配)
-            target_mean_diff: 0.0,
+/// 内容与其他曲线相同时在名称旁显示的标记，悬停列出相同的曲线
+fn duplicate_badge(ui: &mut egui::Ui, scan: &DuplicateScan, curves: &[AudioCurve], index: usize, lang: &Lang, color: egui::Color32) {
+    let partners = scan.partners(index);
+    if !partners.is_empty() {
+        let names: Vec<&str> = partners.iter().map(|&i| curves[i].name.as_str()).collect();
+        ui.colored_label(color, "⧉").on_hover_text(lang.duplicate_badge_fmt.replacen("{}", &names.join(", "), 1)); // I18N
+    }
+}
+
+/// 单文件列表的响度跳变 (曲线下标, 跳变)，曲线列表或阈值变化时才用已有的曲线点重新扫描
+#[derive(Default)]
+struct JumpScan {
+    // (曲线列表版本, 阈值, 时间范围的位模式)
+    key: Option<(u64, u32, u32)>,
+    events: Vec<(usize, LoudnessJump)>,
+}
+
+impl JumpScan {
+    fn refresh(&mut self, curves: &[AudioCurve], revision: u64, threshold_db: f32, within_sec: f32) {
+        let key = (revision, threshold_db.to_bits(), within_sec.to_bits());
+        if self.key == Some(key) {
+            return;
         }
+        self.events = curves.iter().enumerate()
+            .flat_map(|(index, curve)| {
+                find_loudness_jumps(&curve.points, threshold_db as f64, within_sec as f64).into_iter().map(move |jump| (index, jump))
+            })
+            .collect();
+        self.key = Some(key);
     }
+}
 
-    // --- 新增: 字体配置方法 ---
-    /// 配置 egui 字体，根据当前语言加载中文字体
-    fn configure_fonts(ctx: &egui::Context, lang: Language) {
-        let mut fonts = egui::FontDefinitions::default();
+/// 单文件图表中各曲线的原始及归一化显示点，曲线列表或归一化目标变化时才重新计算
+#[derive(Default)]
+struct PlotCache {
+    curves: Vec<CachedCurve>,
+    // (曲线列表版本, 归一化方式, 目标电平的位模式)
+    key: Option<(u64, NormalizeMode, u64)>,
+}
 
-        if lang == Language::Chinese {
-            // 1. 加载中文字体 (假设项目中存在 chinese_font.ttf)
-            // 必须使用 .into() 兼容 egui::FontData::from_static
-            fonts.font_data.insert(
-                "chinese_font".to_owned(),
-                // ⚠️ 警告：因为我没有 chinese_font.ttf，所以这里假设它已在项目中
-                // 实际部署时，请确保 chinese_font.ttf 文件在 main.rs 同目录下。
-                // 否则编译会失败。
-                egui::FontData::from_static(include_bytes!("chinese_font.ttf")).into(),
-            );
+struct CachedCurve {
+    // 归一化偏移量：按归一化方式移动到目标所需的增益
+    offset: f64,
+    raw: Vec<PlotPoint>,
+    normalized: Vec<PlotPoint>,
+    raw_bands: Vec<Vec<PlotPoint>>,
+    normalized_bands: Vec<Vec<PlotPoint>>,
+    // 非静音 (高于 SILENCE_FLOOR_DBFS) 的连续点区间，隐藏底噪时只画这些区间
+    audible: Vec<std::ops::Range<usize>>,
+    // EBU 瞬时 / 短期 / 累计积分响度曲线，偏移沿用整体响度的归一化偏移
+    meters: [CachedSeries; 3],
+}
+
+/// 与主曲线点数不同的附加曲线的显示点及非静音区间
+struct CachedSeries {
+    raw: Vec<PlotPoint>,
+    normalized: Vec<PlotPoint>,
+    audible: Vec<std::ops::Range<usize>>,
+}
 
-            // 2. 设置字体为默认，将中文字体放在首位
-            fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap().insert(0, "chinese_font".to_owned());
-            fonts.families.get_mut(&egui::FontFamily::Monospace).unwrap().push("chinese_font".to_owned());
+impl CachedSeries {
+    fn segments(&self, normalized: bool, hide_silence: bool) -> Vec<&[PlotPoint]> {
+        let points = if normalized { &self.normalized } else { &self.raw };
+        if hide_silence {
+            self.audible.iter().map(|range| &points[range.clone()]).collect()
+        } else {
+            vec![points]
         }
-        // 如果是 English 模式，egui::FontDefinitions::default() 会确保使用默认字体
+    }
+}
 
-        // 3. 应用字体配置
-        ctx.set_fonts(fonts);
+/// 点序列中电平高于底噪的连续区间
+fn audible_ranges(points: &[[f64; 2]]) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (i, p) in points.iter().enumerate() {
+        match (p[1] > SILENCE_FLOOR_DBFS, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ranges.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
     }
-    // ----------------------------
+    if let Some(s) = start {
+        ranges.push(s..points.len());
+    }
+    ranges
+}
 
-    // 运行对比逻辑
-    fn run_comparison(&mut self) {
-        if let (Some(a), Some(b)) = (&self.compare_a, &self.compare_b) {
-            // 1. 检查时间长度
-            let duration_diff = (a.duration - b.duration).abs();
-            if duration_diff > 2.0 { // 容忍 2 秒误差
-                let a_fmt = format!("{:.2}", a.duration);
-                let b_fmt = format!("{:.2}", b.duration);
+impl CachedCurve {
+    /// 要画的线段：隐藏底噪时为各非静音区间 (点数与主曲线不同的频段曲线不拆分)，否则为整条线
+    fn segments<'a>(&self, points: &'a [PlotPoint], hide_silence: bool) -> Vec<&'a [PlotPoint]> {
+        if hide_silence && points.len() == self.raw.len() {
+            self.audible.iter().map(|range| &points[range.clone()]).collect()
+        } else {
+            vec![points]
+        }
+    }
+}
 
-                let final_err_msg = self.lang.compare_err_duration_fmt
-                    .replacen("{}", &a_fmt, 1)
-                    .replacen("{}", &b_fmt, 1);
+impl PlotCache {
+    fn refresh(&mut self, curves: &[AudioCurve], revision: u64, mode: NormalizeMode, target: f64) {
+        let key = (revision, mode, target.to_bits());
+        if self.key == Some(key) && self.curves.len() == curves.len() {
+            return;
+        }
+        let shift = |points: &[[f64; 2]], offset: f64| -> Vec<PlotPoint> {
+            points.iter().map(|p| PlotPoint::new(p[0], p[1] + offset)).collect()
+        };
+        self.curves = curves.iter()
+            .map(|curve| {
+                let offset = curve.normalization_gain(mode, target);
+                let series = |points: &[[f64; 2]]| CachedSeries {
+                    raw: shift(points, 0.0),
+                    normalized: shift(points, offset),
+                    audible: audible_ranges(points),
+                };
+                CachedCurve {
+                    offset,
+                    raw: shift(&curve.points, 0.0),
+                    normalized: shift(&curve.points, offset),
+                    raw_bands: curve.bands.iter().map(|band| shift(band, 0.0)).collect(),
+                    normalized_bands: curve.bands.iter().map(|band| shift(band, offset)).collect(),
+                    audible: audible_ranges(&curve.points),
+                    meters: [series(&curve.momentary), series(&curve.short_term), series(&curve.integrated)],
+                }
+            })
+            .collect();
+        self.key = Some(key);
+    }
+}
 
-                log_error(&self.logger, &format!("⚠️ 对比失败: {}", final_err_msg));
-                self.error_msg = Some(final_err_msg);
-                self.compare_result = None;
-                return;
-            }
+/// 对比图表的显示点 (Track A、按偏移移动后的 Track B、差值曲线、Bland-Altman 点)。
+/// 对比结果或插槽曲线变化 (AppState::compare_revision 递增) 后，下次绘制前重新生成；其余帧只借用，不再逐帧复制点
+#[derive(Default)]
+struct ComparePlotCache {
+    revision: Option<u64>,
+    track_a: Vec<PlotPoint>,
+    track_b: Vec<PlotPoint>,
+    diff: Vec<PlotPoint>,
+    agreement: Vec<PlotPoint>,
+}
 
-            // 2. 计算差值和收集原始数据点
-            let len = std::cmp::min(a.points.len(), b.points.len());
-            log_debug(&self.logger, &format!("对比点数: {}", len));
-            let mut diff_vals = Vec::new();
-            let mut diff_points = Vec::new();
-            let mut a_vals = Vec::new();
-            let mut b_vals = Vec::new();
-
-            for i in 0..len {
-                let diff = a.points[i][1] - b.points[i][1];
-                diff_vals.push(diff);
-                diff_points.push([a.points[i][0], diff]);
-                a_vals.push(a.points[i][1]);
-                b_vals.push(b.points[i][1]);
-            }
-
-            // 3. 统计
-            let mean = diff_vals.iter().sum::<f64>() / len as f64;
-            let variance: f64 = diff_vals.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (len as f64 - 1.0).max(1.0);
-            let std_dev = variance.sqrt();
-            let max_diff = diff_vals.iter().fold(f64::NEG_INFINITY, |a, &b| a.max(b));
-            let min_diff = diff_vals.iter().fold(f64::INFINITY, |a, &b| a.min(b));
-
-            // 4. 新增统计计算
-            let correlation_coefficient = calculate_correlation(&a_vals, &b_vals, len);
-
-            // ⭐ 修改 T 统计量的计算，使用目标差值作为检验的中心点
-            let target_c = self.target_mean_diff as f64;
-            // T 统计量现在检验 (实际平均差值 - 目标平均差值) 是否显著不为 0
-            let t_statistic = calculate_t_statistic(mean - target_c, std_dev, len);
-
-            log_info(&self.logger, &format!("✅ 对比完成。 Mean Diff: {:.2} dB, Std Dev: {:.4}", mean, std_dev));
-            log_debug(&self.logger, &format!("Correlation (r): {:.4}, T-Stat: {:.2}", correlation_coefficient, t_statistic));
-
-
-            self.compare_result = Some(ComparisonResult {
-                mean_diff: mean,
-                std_dev,
-                max_diff,
-                min_diff,
-                correlation_coefficient,
-                t_statistic,
-                diff_points,
-            });
-            self.error_msg = None;
-        } else {
-            log_error(&self.logger, "⚠️ 对比失败: 缺少 Track A 或 Track B。");
+impl ComparePlotCache {
+    fn refresh(&mut self, revision: u64, a: Option<&AudioCurve>, b: Option<&AudioCurve>, res: &ComparisonResult) {
+        if self.revision == Some(revision) {
+            return;
         }
+        let shift = |points: &[[f64; 2]], offset: f64| -> Vec<PlotPoint> {
+            points.iter().map(|p| PlotPoint::new(p[0] + offset, p[1])).collect()
+        };
+        self.track_a = a.map_or_else(Vec::new, |a| shift(&a.points, 0.0));
+        self.track_b = b.map_or_else(Vec::new, |b| shift(&b.points, res.b_offset));
+        self.diff = shift(&res.diff_points, 0.0);
+        self.agreement = shift(&res.agreement_points, 0.0);
+        self.revision = Some(revision);
     }
+}
 
+/// 决定逐点对比结果的输入：两个插槽的曲线来源和影响差值的选项。
+/// 目标差值、置信度和判定阈值只影响 t 检验与判定，不在键中，改变时复用上一次的结果
+#[derive(PartialEq, Clone, Debug)]
+struct ComparisonKey {
+    a: String,
+    b: String,
+    offset: f32,
+    level_match: bool,
+}
 
-    // 允许切换语言，同时更新 UI
-    // fn switch_language(&mut self, new_lang: Language, ctx: &egui::Context) {
-    //     if self.current_lang != new_lang {
-    //         log_info(&self.logger, &format!("切换语言到: {:?}", new_lang));
-    //         self.current_lang = new_lang;
-    //         self.lang = Lang::load(new_lang);
+/// 界面帧耗时统计，每隔 FRAME_STATS_INTERVAL 以 Debug 级别写入日志
+#[derive(Default)]
+struct FrameStats {
+    since: Option<Instant>,
+    frames: u32,
+    total: Duration,
+    longest: Duration,
+}
 
-    //         //  修正: 切换语言时重新配置字体
-    //         Self::configure_fonts(ctx, new_lang);
+const FRAME_STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+impl FrameStats {
+    fn record(&mut self, elapsed: Duration, logger: &Logger) {
+        let since = *self.since.get_or_insert_with(Instant::now);
+        self.frames += 1;
+        self.total += elapsed;
+        self.longest = self.longest.max(elapsed);
+        if since.elapsed() >= FRAME_STATS_INTERVAL {
+            log_debug(logger, &format!(
+                "帧耗时: 平均 {:.2} ms, 最长 {:.2} ms ({} 帧)",
+                self.total.as_secs_f64() * 1000.0 / self.frames as f64,
+                self.longest.as_secs_f64() * 1000.0,
+                self.frames
+            ));
+            *self = Self { since: Some(Instant::now()), ..Self::default() };
+        }
+    }
+}
 
-    //         ctx.request_repaint();
-    //     }
-    // }
+/// 单文件模式的图表视图
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum SingleView {
+    #[default]
+    Overall,
+    /// 每条曲线按频段拆成多条线叠加显示
+    Bands,
+    /// 各文件的积分响度按大小排列成条形图，文件多时比叠加的曲线更容易看出异常值
+    Bars,
 }
 
-impl eframe::App for WavLufsApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        // 全局样式微调
-        ctx.set_pixels_per_point(1.2);
+/// 对比模式下方图表：差值随时间变化，或 Bland-Altman 一致性图
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum CompareLowerPlot {
+    #[default]
+    Diff,
+    /// x 轴为 A、B 均值，y 轴为差值
+    BlandAltman,
+}
 
-        // --- 异步消息处理 (非阻塞循环) ---
-        while let Ok(msg) = self.ui_rx.try_recv() {
-            match msg {
-                WorkerMessage::Log(entry) => {
-                    if let Ok(mut entries) = self.logger.entries.lock() {
-                        entries.push(entry);
-                    }
-                    ctx.request_repaint();
-                }
-                WorkerMessage::UpdateTaskState(id, state) => {
-                    if let Ok(mut tasks) = self.worker_pool.tasks.lock() {
-                        if let Some(task) = tasks.iter_mut().find(|t| t.id == id) {
-                            task.state = state.clone();
-                            self.loading = tasks.iter().any(|t| matches!(t.state, TaskState::Running(_)) || t.state == TaskState::Waiting);
+/// 单文件图表显示原始电平还是按目标归一化后的电平
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, Default)]
+enum LevelDisplay {
+    #[default]
+    Normalized,
+    Raw,
+    /// 原始曲线画虚线，归一化曲线画实线
+    Both,
+}
 
-                            if let TaskState::Error(e) = state {
-                                self.error_msg = Some(format!("Task {} Error: {}", id, e));
-                            }
-                        }
-                    }
-                    ctx.request_repaint();
-                }
-                WorkerMessage::NewCurve(curve, slot_opt) => { // 修正: 接收 slot_opt
-                    if let Some(slot) = slot_opt {
-                        // 对比模式结果
-                        if slot == 'A' {
-                            self.compare_a = Some(curve);
-                        } else if slot == 'B' {
-                            self.compare_b = Some(curve);
-                        }
+/// 交付规格：积分响度目标 ± 容差、真峰值上限、响度范围上限 (默认按 EBU R128)
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(default)]
+struct DeliverySpec {
+    integrated_target: f32,
+    integrated_tolerance: f32,
+    max_true_peak: f32,
+    max_loudness_range: f32,
+}
 
-                        // 关键: 尝试运行对比 (必须在 UI 线程上)
-                        if self.compare_a.is_some() && self.compare_b.is_some() {
-                            self.run_comparison();
-                        }
-                    } else {
-                        // 单机模式结果
-                        if let AppMode::Single = self.mode {
-                            if let Ok(mut files) = self.single_files.lock() {
-                                files.push(curve);
-                            }
-                        }
-                    }
-                    ctx.request_repaint();
-                }
-            }
-        }
+impl Default for DeliverySpec {
+    fn default() -> Self {
+        Self { integrated_target: -23.0, integrated_tolerance: 1.0, max_true_peak: -1.0, max_loudness_range: 20.0 }
+    }
+}
 
-        // --- 顶部导航栏 (I18N & 语言选择) ---
-        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
-            ui.horizontal(|ui| {
-                // 模式选择
-                ui.selectable_value(&mut self.mode, AppMode::Single, self.lang.mode_single);
-                ui.selectable_value(&mut self.mode, AppMode::Compare, self.lang.mode_compare);
-                ui.selectable_value(&mut self.mode, AppMode::Console, self.lang.mode_console);
+/// 单个文件各项指标是否符合交付规格
+#[derive(Clone, Copy, Debug)]
+struct Compliance {
+    integrated: bool,
+    true_peak: bool,
+    loudness_range: bool,
+}
 
-                ui.separator();
+impl Compliance {
+    fn passed(&self) -> bool {
+        self.integrated && self.true_peak && self.loudness_range
+    }
+}
 
-                // 语言选择
-                // 修正：使用 I18N 字段替代硬编码的 "语言:"
-                ui.label(self.lang.nav_lang_label);
-                let old_lang = self.current_lang; // 记录旧语言
+impl DeliverySpec {
+    /// 节目过短无法计算响度范围时，该项视为通过
+    fn check(&self, m: &LoudnessMeasurement) -> Compliance {
+        Compliance {
+            integrated: (m.integrated_lufs - self.integrated_target as f64).abs() <= self.integrated_tolerance as f64,
+            true_peak: m.true_peak_dbtp <= self.max_true_peak as f64,
+            loudness_range: m.loudness_range_lu.is_none_or(|lra| lra <= self.max_loudness_range as f64),
+        }
+    }
 
-                // 中文选项 - 修正：使用 I18N 字段替代硬编码的 "中文"
-                ui.selectable_value(&mut self.current_lang, Language::Chinese, self.lang.nav_zh_label);
+    /// 有测量结果且未通过规格 (CSV 曲线没有音频，不算未通过)
+    fn fails(&self, curve: &AudioCurve) -> bool {
+        curve.loudness.is_some_and(|m| !self.check(&m).passed())
+    }
+}
 
-                // English 选项 - 修正：使用 I18N 字段替代硬编码的 "English"
-                ui.selectable_value(&mut self.current_lang, Language::English, self.lang.nav_en_label);
+/// 需要跨会话保存的用户设置 (通过 eframe::Storage 持久化)
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+struct AppSettings {
+    // 保存时的格式版本 (见 SCHEMA_VERSION)；没有此字段的旧设置按版本 1 读取，加载后更新为当前版本
+    #[serde(default = "first_settings_version")]
+    schema_version: u32,
+    // 对比判定阈值：std_dev < high_match_threshold 为一致，>= huge_diff_threshold 为巨大差异
+    high_match_threshold: f32,
+    huge_diff_threshold: f32,
+    theme: ThemeChoice,
+    // 对比时要求的最小时间重叠比例 (百分比)
+    min_overlap_percent: f32,
+    // 单文件图表上显示最响/最安静标记
+    show_extreme_markers: bool,
+    // 单文件图表不画处于底噪 (SILENCE_FLOOR_DBFS) 的静音窗口
+    hide_silence_floor: bool,
+    // 图表背景的响度分区 (绿/黄/红)；边界为 None 时按平均响度目标推算
+    show_loudness_zones: bool,
+    zone_edges: Option<[f32; 3]>,
+    single_view: SingleView,
+    // 整体视图中叠加 EBU 瞬时 (M) / 短期 (S) 响度曲线及累计积分响度 (I)
+    show_momentary: bool,
+    show_short_term: bool,
+    show_integrated: bool,
+    level_display: LevelDisplay,
+    show_stats_table: bool,
+    // 对比前减去各自的平均电平，只比较动态差异
+    compare_level_match: bool,
+    // 单文件 / 对比图表手动设置的 Y 轴范围 (dBFS)
+    single_y_range: [f64; 2],
+    compare_y_range: [f64; 2],
+    // 界面缩放 (相对系统显示缩放的倍数，UI_ZOOM_RANGE 之内)
+    ui_zoom: f32,
+    // 单文件模式按平均电平还是最响窗口归一化
+    normalize_mode: NormalizeMode,
+    compare_lower_plot: CompareLowerPlot,
+    // CSV 导出时按固定间隔 (秒) 重采样
+    export_resample: bool,
+    export_interval: f32,
+    delivery_spec: DeliverySpec,
+    // 控制台模式：进程监视器列宽度及两列是否显示
+    console_monitor_width: f32,
+    console_show_monitor: bool,
+    console_show_log: bool,
+    // 对比报告中一致性评分的权重及容差
+    consistency_weights: ConsistencyWeights,
+    time_format: TimeFormat,
+    analysis: AnalysisSettings,
+    // 已在日志中说明平均电平改为功率平均 (旧版本保存的设置首次加载时说明一次)
+    power_average_noted: bool,
+    // 对比模式偏差区间列表显示的区间数
+    error_region_count: usize,
+    // 日志环形缓冲区保留的条数 (控制台 logcap 命令或日志列中修改)
+    log_capacity: usize,
+    // 把日志同时写入按天轮换的日志文件；目录为 None 时使用平台数据目录下的 logs
+    log_file: bool,
+    log_file_dir: Option<PathBuf>,
+    // 对比原始曲线图中 Track A / B 的配色和线型
+    track_colors: TrackColors,
+    track_custom_colors: [[u8; 3]; 2],
+    track_styles: [TrackLineStyle; 2],
+    // 实时监视模式的输入设备 (None 为默认设备) 及曲线保留时长 (分钟)
+    live_device: Option<String>,
+    live_retain_minutes: f32,
+    // 所有已加载的文件在磁盘上变化后都自动重新分析 (关闭时只监视单独勾选的文件)
+    auto_reload_all: bool,
+    // 相关系数检验的最低可接受 r
+    min_correlation: f32,
+    // 响度跳变列表：jump_window_sec 之内变化超过 jump_threshold_db 的位置
+    jump_threshold_db: f32,
+    jump_window_sec: f32,
+    // 对比模式合成参考的编辑内容 (不论是否正在使用)
+    synthetic_reference: SyntheticReference,
+    // 命名的分析配置及按文件名选用配置的规则 (按顺序，第一条匹配的生效)
+    analysis_profiles: Vec<AnalysisProfile>,
+    profile_rules: Vec<ProfileRule>,
+}
 
-                // 修正语言切换逻辑：在 selectable_value 之外检查并重新加载
-                if self.current_lang != old_lang {
-                    log_info(&self.logger, &format!("切换语言到: {:?}", self.current_lang));
+/// 日志缓冲区容量允许的范围 (条)
+const LOG_CAPACITY_RANGE: std::ops::RangeInclusive<usize> = 100..=1_000_000;
 
-                    // 核心切换逻辑：重新加载语言数据和字体
-                    self.lang = Lang::load(self.current_lang);
-                    Self::configure_fonts(ctx, self.current_lang);
+/// 界面缩放允许的范围 (75% - 200%)
+const UI_ZOOM_RANGE: std::ops::RangeInclusive<f32> = 0.75..=2.0;
 
-                    // 由于 selectable_value 已经点击了，我们不需要 if clicked() 包装
-                    ui.ctx().request_repaint();
-                }
+fn first_settings_version() -> u32 {
+    1
+}
 
-                ui.separator();
+impl Default for AppSettings {
+    fn default() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            high_match_threshold: 1.0,
+            huge_diff_threshold: 3.0,
+            theme: ThemeChoice::default(),
+            min_overlap_percent: 90.0,
+            show_extreme_markers: true,
+            hide_silence_floor: false,
+            show_loudness_zones: false,
+            zone_edges: None,
+            single_view: SingleView::default(),
+            show_momentary: false,
+            show_short_term: false,
+            show_integrated: false,
+            level_display: LevelDisplay::default(),
+            show_stats_table: false,
+            compare_level_match: false,
+            single_y_range: [-60.0, 0.0],
+            compare_y_range: [-60.0, 0.0],
+            ui_zoom: 1.0,
+            normalize_mode: NormalizeMode::default(),
+            compare_lower_plot: CompareLowerPlot::default(),
+            export_resample: false,
+            export_interval: 0.5,
+            delivery_spec: DeliverySpec::default(),
+            console_monitor_width: 360.0,
+            console_show_monitor: true,
+            console_show_log: true,
+            consistency_weights: ConsistencyWeights::default(),
+            time_format: TimeFormat::default(),
+            analysis: AnalysisSettings::default(),
+            power_average_noted: false,
+            error_region_count: 5,
+            log_capacity: DEFAULT_LOG_CAPACITY,
+            log_file: false,
+            log_file_dir: None,
+            track_colors: TrackColors::default(),
+            track_custom_colors: [[86, 180, 233], [230, 159, 0]],
+            track_styles: [TrackLineStyle::Solid, TrackLineStyle::Solid],
+            live_device: None,
+            live_retain_minutes: 5.0,
+            auto_reload_all: false,
+            min_correlation: 0.9,
+            jump_threshold_db: 6.0,
+            jump_window_sec: 0.5,
+            synthetic_reference: SyntheticReference::default(),
+            analysis_profiles: Vec::new(),
+            profile_rules: Vec::new(),
+        }
+    }
+}
 
-                // --- 新增：帮助按钮 --- 修正：使用 I18N 字段替代硬编码的 "❓ 帮助"
-                if ui.button(self.lang.nav_help_btn).clicked() {
-                    self.show_help_popup = true;
-                }
-            });
-        });
+impl AppSettings {
+    /// 当前的响度分区边界 (按显示的电平值，与图表中绘制的曲线一致)
+    fn zone_edges(&self, target_lufs: f32) -> [f64; 3] {
+        self.zone_edges.unwrap_or_else(|| zone_edges_for_target(target_lufs)).map(f64::from)
+    }
 
-        // --- 底部状态栏 (I18N) ---
-        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
-            if self.loading {
+    /// 响度分区开关及边界设置 (单文件和对比模式共用)
+    fn ui_zone_controls(&mut self, ui: &mut egui::Ui, lang: &Lang, target_lufs: f32) {
+        ui.checkbox(&mut self.show_loudness_zones, lang.zones_label) // I18N
+            .on_hover_text(lang.zones_hint);
+        if !self.show_loudness_zones {
+            return;
+        }
+        let mut edges = self.zone_edges.unwrap_or_else(|| zone_edges_for_target(target_lufs));
+        let mut changed = false;
+        for (i, label) in [lang.zones_green, lang.zones_yellow, lang.zones_red].into_iter().enumerate() {
+            ui.label(label); // I18N
+            // 每个边界限制在相邻边界之间，保持 绿 < 黄 < 红
+            let low = if i == 0 { -120.0 } else { edges[i - 1] };
+            let high = if i == 2 { 20.0 } else { edges[i + 1] };
+            changed |= ui.add(egui::DragValue::new(&mut edges[i]).speed(0.1).range(low..=high).suffix(" dB")).changed();
+        }
+        if changed {
+            self.zone_edges = Some(edges);
+        }
+        if ui.add_enabled(self.zone_edges.is_some(), egui::Button::new(lang.zones_follow_target)) // I18N
+            .on_hover_text(lang.zones_follow_target_hint)
+            .clicked()
+        {
+            self.zone_edges = None;
+        }
+    }
+
+    /// 对比原始曲线图的 Track A / B 配色和线型菜单
+    fn ui_track_style_controls(&mut self, ui: &mut egui::Ui, lang: &Lang) {
+        ui.menu_button(lang.track_style_menu, |ui| { // I18N
+            ui.label(lang.track_colors_label); // I18N
+            ui.radio_value(&mut self.track_colors, TrackColors::ColorblindSafe, lang.track_colors_colorblind);
+            ui.radio_value(&mut self.track_colors, TrackColors::Classic, lang.track_colors_classic);
+            ui.radio_value(&mut self.track_colors, TrackColors::Custom, lang.track_colors_custom);
+            ui.separator();
+            let custom = self.track_colors == TrackColors::Custom;
+            for (i, label) in [lang.compare_track_a_label, lang.compare_track_b_label].into_iter().enumerate() {
                 ui.horizontal(|ui| {
-                    ui.spinner();
-                    ui.label(self.lang.status_loading); // I18N
-                    ctx.request_repaint();
+                    ui.label(label); // I18N
+                    ui.add_enabled_ui(custom, |ui| ui.color_edit_button_srgb(&mut self.track_custom_colors[i]));
+                    let style = &mut self.track_styles[i];
+                    ui.selectable_value(style, TrackLineStyle::Solid, lang.track_style_solid);
+                    ui.selectable_value(style, TrackLineStyle::Dashed, lang.track_style_dashed);
+                    ui.selectable_value(style, TrackLineStyle::Dotted, lang.track_style_dotted);
                 });
-            } else if let Some(err) = &self.error_msg {
-                ui.colored_label(egui::Color32::RED, err);
-            } else {
-                ui.label(self.lang.status_ready); // I18N
-            }
-        });
-
-        // 中央内容区
-        egui::CentralPanel::default().show(ctx, |ui| {
-            match self.mode {
-                AppMode::Single => self.ui_single_mode(ui, ctx),
-                AppMode::Compare => self.ui_compare_mode(ui),
-                AppMode::Console => self.ui_console_mode(ui),
             }
         });
+    }
 
-        // --- 新增：帮助悬浮窗口 ---
-        self.ui_help_popup(ctx);
+    /// 单文件图表中该曲线 (主线) 显示时加上的电平偏移：原始电平为 0，否则为归一化偏移
+    fn display_offset(&self, curve: &AudioCurve, target: f64) -> f64 {
+        if curve.show_raw || self.level_display == LevelDisplay::Raw { 0.0 } else { curve.normalization_gain(self.normalize_mode, target) }
     }
-}
 
-impl WavLufsApp {
+    /// 分析配置编辑器：各配置的参数及 CSV 列布局，以及按文件名选用配置的规则
+    fn ui_profile_editor(&mut self, ui: &mut egui::Ui, lang: &Lang) {
+        egui::CollapsingHeader::new(lang.profile_editor_title) // I18N
+            .id_salt("analysis_profiles")
+            .show(ui, |ui| {
+                ui.weak(lang.profile_editor_hint); // I18N
+                let mut remove = None;
+                let mut renamed = None;
+                for (i, profile) in self.analysis_profiles.iter_mut().enumerate() {
+                    egui::CollapsingHeader::new(&profile.name).id_salt(("analysis_profile", i)).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(lang.profile_name_label); // I18N
+                            let old_name = profile.name.clone();
+                            if ui.text_edit_singleline(&mut profile.name).changed() {
+                                renamed = Some((old_name, profile.name.clone()));
+                            }
+                            if ui.button(lang.profile_remove_btn).clicked() { // I18N
+                                remove = Some(i);
+                            }
+                        });
+                        ui_analysis_params(ui, &mut profile.analysis, lang, &format!("profile_downmix_{}", i));
+                        let mut custom_csv = profile.csv_layout.is_some();
+                        if ui.checkbox(&mut custom_csv, lang.profile_csv_layout_label) // I18N
+                            .on_hover_text(lang.profile_csv_layout_hint)
+                            .changed()
+                        {
+                            profile.csv_layout = custom_csv.then(CsvLayout::default);
+                        }
+                        if let Some(layout) = &mut profile.csv_layout {
+                            ui_csv_layout(ui, layout, lang, i);
+                        }
+                    });
+                }
+                // 改名时同步引用旧名称的规则
+                if let Some((old_name, new_name)) = renamed {
+                    for rule in self.profile_rules.iter_mut().filter(|rule| rule.profile == old_name) {
+                        rule.profile = new_name.clone();
+                    }
+                }
+                if let Some(i) = remove {
+                    self.analysis_profiles.remove(i);
+                }
+                if ui.button(lang.profile_add_btn).clicked() { // I18N
+                    let name = format!("{} {}", lang.profile_default_name, self.analysis_profiles.len() + 1);
+                    self.analysis_profiles.push(AnalysisProfile { name, analysis: self.analysis.clone(), csv_layout: None });
+                }
 
-    // --- 新增：帮助悬浮窗口的实现 ---
-    fn ui_help_popup(&mut self, ctx: &egui::Context) {
-        if self.show_help_popup {
-            let lang = &self.lang; // 获取当前语言文本
-            // 这里使用一个唯一的 ID 源，以防与任何其他 Window 冲突
-            egui::Window::new(lang.help_title)
-                .id(egui::Id::new("help_window"))
-                .open(&mut self.show_help_popup)
+                ui.separator();
+                ui.label(lang.profile_rules_label); // I18N
+                let mut remove = None;
+                egui::Grid::new("profile_rules").num_columns(3).show(ui, |ui| {
+                    for (i, rule) in self.profile_rules.iter_mut().enumerate() {
+                        ui.add(egui::TextEdit::singleline(&mut rule.pattern).hint_text("*_dx.wav").desired_width(140.0));
+                        egui::ComboBox::from_id_salt(("profile_rule", i))
+                            .selected_text(&rule.profile)
+                            .show_ui(ui, |ui| {
+                                for profile in &self.analysis_profiles {
+                                    ui.selectable_value(&mut rule.profile, profile.name.clone(), &profile.name);
+                                }
+                            });
+                        if ui.button("🗑").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = remove {
+                    self.profile_rules.remove(i);
+                }
+                if ui.add_enabled(!self.analysis_profiles.is_empty(), egui::Button::new(lang.profile_rule_add_btn)).clicked() { // I18N
+                    let profile = self.analysis_profiles[0].name.clone();
+                    self.profile_rules.push(ProfileRule { pattern: String::new(), profile });
+                }
+            });
+    }
+
+    /// 按文件名规则为文件选中的分析配置；没有匹配时为 None (使用全局分析参数)
+    fn profile_for(&self, path: &Path) -> Option<&AnalysisProfile> {
+        select_profile(path, &self.profile_rules, &self.analysis_profiles)
+    }
+
+    /// 现在加载该文件时使用的分析参数
+    fn analysis_for(&self, path: Option<&Path>) -> &AnalysisSettings {
+        path.and_then(|path| self.profile_for(path)).map_or(&self.analysis, |profile| &profile.analysis)
+    }
+
+    /// 对比结果的时间重叠是否低于最低要求 (即仅为部分对比)
+    fn is_partial_overlap(&self, overlap_ratio: f64) -> bool {
+        overlap_ratio * 100.0 < self.min_overlap_percent as f64
+    }
+
+    /// 根据动态标准差和当前阈值给出一致性判定
+    fn verdict(&self, std_dev: f64) -> Verdict {
+        if std_dev < self.high_match_threshold as f64 {
+            Verdict::HighMatch
+        } else if std_dev < self.huge_diff_threshold as f64 {
+            Verdict::MidDiff
+        } else {
+            Verdict::HugeDiff
+        }
+    }
+}
+
+/// 动态一致性判定
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum Verdict {
+    HighMatch,
+    MidDiff,
+    HugeDiff,
+}
+
+/// 批量对比中单个候选文件的结果
+#[derive(Clone, Debug)]
+struct BatchRow {
+    name: String,
+    // 失败时为 Err(错误信息)
+    result: Result<BatchStats, String>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct BatchStats {
+    mean_diff: f64,
+    std_dev: f64,
+    correlation_coefficient: f64,
+    t_statistic: f64,
+    overlap_ratio: f64,
+    // 配对的点数 (相关系数检验的样本数)
+    points: usize,
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum AppMode {
+    Single,
+    Compare,
+    Batch,
+    Console,
+    Live,
+}
+
+// --- 日志视图与通知 (Logger 见 lib.rs) ---
+
+/// 一条控制台命令的执行结果；异步命令 (load 等) 只回显命令本身，进度和结果见日志
+#[derive(Default)]
+struct CommandOutput {
+    text: String,
+    success: bool,
+}
+
+impl CommandOutput {
+    fn ok(text: String) -> Self {
+        Self { text, success: true }
+    }
+
+    fn error(text: String) -> Self {
+        Self { text, success: false }
+    }
+}
+
+/// 控制台输出区中的一条记录：输入的命令及其结果
+struct ConsoleEcho {
+    command: String,
+    output: CommandOutput,
+}
+
+/// 控制台日志视图的过滤条件
+struct LogFilter {
+    show_info: bool,
+    show_error: bool,
+    show_debug: bool,
+    show_command: bool,
+    search: String,
+    // 停留在最新日志时跟随新日志；向上滚动后停止跳动
+    auto_scroll: bool,
+    // 不跟随最新日志时视图首行的日志序号及该行已滚出的高度
+    anchor: Option<(u64, f32)>,
+}
+
+impl Default for LogFilter {
+    fn default() -> Self {
+        Self {
+            show_info: true,
+            show_error: true,
+            show_debug: true,
+            show_command: true,
+            search: String::new(),
+            auto_scroll: true,
+            anchor: None,
+        }
+    }
+}
+
+impl LogFilter {
+    /// `needle` 为预先转成小写的搜索词，避免每条日志重复转换
+    fn matches(&self, entry: &LogEntry, needle: &str) -> bool {
+        let level_shown = match entry.level {
+            LogLevel::Info => self.show_info,
+            LogLevel::Error => self.show_error,
+            LogLevel::Debug => self.show_debug,
+            LogLevel::Command => self.show_command,
+        };
+        level_shown && (needle.is_empty() || entry.message.to_lowercase().contains(needle))
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// 窗口角落显示的临时通知
+struct Toast {
+    message: String,
+    level: ToastLevel,
+    created: Instant,
+}
+
+/// 同时显示的通知上限及各级别的显示时长
+const MAX_VISIBLE_TOASTS: usize = 5;
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+const ERROR_TOAST_DURATION: Duration = Duration::from_secs(10);
+const RECENT_ERRORS_CAP: usize = 100;
+
+/// 通知队列；错误另外保存在"最近错误"列表中，通知过期后仍可查看
+#[derive(Default)]
+struct Notifications {
+    toasts: Vec<Toast>,
+    recent_errors: Vec<(String, String)>, // (时间, 消息)
+    show_recent_errors: bool,
+}
+
+impl Notifications {
+    fn push(&mut self, level: ToastLevel, message: impl Into<String>) {
+        let message = message.into();
+        if level == ToastLevel::Error {
+            self.recent_errors.push((Local::now().format("%H:%M:%S").to_string(), message.clone()));
+            if self.recent_errors.len() > RECENT_ERRORS_CAP {
+                self.recent_errors.remove(0);
+            }
+        }
+        // 与最新一条相同时只刷新时间 (例如拖动参数时反复触发的对比结果)
+        if let Some(last) = self.toasts.last_mut()
+            && last.message == message
+            && last.level == level
+        {
+            last.created = Instant::now();
+            return;
+        }
+        self.toasts.push(Toast { message, level, created: Instant::now() });
+    }
+
+    /// 在右下角绘制通知 (点击关闭) 和最近错误窗口
+    fn show(&mut self, ctx: &egui::Context, lang: &Lang, palette: &Palette) {
+        self.toasts.retain(|toast| {
+            let lifetime = if toast.level == ToastLevel::Error { ERROR_TOAST_DURATION } else { TOAST_DURATION };
+            toast.created.elapsed() < lifetime
+        });
+
+        if !self.toasts.is_empty() {
+            let mut dismissed = None;
+            egui::Area::new(egui::Id::new("toast_area"))
+                .anchor(egui::Align2::RIGHT_BOTTOM, [-12.0, -40.0])
+                .order(egui::Order::Foreground)
+                .show(ctx, |ui| {
+                    let start = self.toasts.len().saturating_sub(MAX_VISIBLE_TOASTS);
+                    for (index, toast) in self.toasts.iter().enumerate().skip(start) {
+                        let color = match toast.level {
+                            ToastLevel::Info => palette.log_info,
+                            ToastLevel::Success => palette.ok,
+                            ToastLevel::Warning => palette.warn,
+                            ToastLevel::Error => palette.error,
+                        };
+                        let response = egui::Frame::popup(ui.style())
+                            .stroke(egui::Stroke::new(1.5, color))
+                            .show(ui, |ui| {
+                                ui.set_max_width(360.0);
+                                ui.colored_label(color, &toast.message);
+                            })
+                            .response
+                            .interact(egui::Sense::click());
+                        if response.clicked() {
+                            dismissed = Some(index);
+                        }
+                    }
+                });
+            if let Some(index) = dismissed {
+                self.toasts.remove(index);
+            }
+            ctx.request_repaint_after(Duration::from_millis(250));
+        }
+
+        if self.show_recent_errors {
+            let mut open = true;
+            let mut clear = false;
+            egui::Window::new(lang.recent_errors_title)
+                .id(egui::Id::new("recent_errors_window"))
+                .open(&mut open)
                 .resizable(true)
-                .default_size([400.0, 300.0])
+                .default_size([420.0, 240.0])
                 .show(ctx, |ui| {
-                    ui.label(lang.help_desc);
+                    if ui.button(lang.recent_errors_clear).clicked() {
+                        clear = true;
+                    }
                     ui.separator();
+                    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                        for (time, message) in self.recent_errors.iter().rev() {
+                            ui.colored_label(palette.error, format!("[{}] {}", time, message));
+                        }
+                    });
+                });
+            if clear {
+                self.recent_errors.clear();
+            }
+            self.show_recent_errors = open;
+        }
+    }
+}
 
-                    ui.heading(lang.help_monitor_title);
-                    // 修正：使用 I18N 字段替代硬编码的中文描述
-                    ui.label(lang.help_monitor_desc);
-                    ui.separator();
+// --- 任务管理结构 ---
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum TaskState {
+    Waiting,
+    Running(f32), // 0.0 - 1.0 进度
+    Completed,
+    Killed,
+    Error(TaskError),
+}
+
+/// 失败任务的错误：加载任务保留结构化的 AnalyzerError，显示时按当前界面语言渲染；其他任务为已格式化的文本
+#[derive(Serialize, Deserialize, Clone, Debug)]
+enum TaskError {
+    Load { file: String, error: Arc<AnalyzerError> },
+    Message(String),
+}
+
+impl TaskError {
+    fn load(file: &str, error: AnalyzerError) -> Self {
+        TaskError::Load { file: file.to_string(), error: Arc::new(error) }
+    }
+
+    /// 完整的错误说明
+    fn text(&self, lang: &Lang) -> String {
+        match self {
+            TaskError::Load { file, error } => lang.load_failed_fmt.replacen("{}", file, 1).replacen("{}", &analyzer_error_detail(error, lang), 1),
+            TaskError::Message(text) => text.clone(),
+        }
+    }
+
+    /// 针对错误类型的处理建议
+    fn hint(&self, lang: &Lang) -> Option<&'static str> {
+        match self {
+            TaskError::Load { error, .. } => analyzer_error_hint(error, lang),
+            TaskError::Message(_) => None,
+        }
+    }
+
+    /// 说明加处理建议 (通知和对比插槽的提示使用)
+    fn text_with_hint(&self, lang: &Lang) -> String {
+        match self.hint(lang) {
+            Some(hint) => format!("{}\n💡 {}", self.text(lang), hint),
+            None => self.text(lang),
+        }
+    }
+}
+
+impl TaskState {
+    /// 任务是否已结束 (结束状态不再被后续消息覆盖)
+    fn is_finished(&self) -> bool {
+        matches!(self, TaskState::Completed | TaskState::Killed | TaskState::Error(_))
+    }
+}
+
+/// 状态栏进度所统计的一批任务：从空闲后启动的第一个任务开始，到这些任务全部结束为止
+struct TaskBatch {
+    first_id: usize,
+    started: Instant,
+}
+
+/// 一批任务完成后在状态栏显示摘要的时长
+const STATUS_FLASH_DURATION: Duration = Duration::from_secs(4);
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct AudioTask {
+    id: usize,
+    name: String,
+    state: TaskState,
+}
+
+/// 对比插槽 (Track A / B) 关联的加载任务
+struct SlotLoad {
+    task_id: usize,
+    filename: String,
+}
+
+/// 加载任务读取的来源，进程监视器中重试失败的任务时使用
+#[derive(Clone, Debug)]
+enum TaskSource {
+    File(PathBuf),
+    #[cfg(not(target_arch = "wasm32"))]
+    Url(String),
+    // 对比模式插槽 ('A' / 'B')
+    Slot(PathBuf, char),
+    // 按手动选择的列布局导入的 CSV
+    Csv(PathBuf, CsvLayout),
+    // 用当前分析参数重新分析单文件列表中已有的曲线，结果原位替换
+    Reanalyze(PathBuf),
+    // 浏览器中选择的文件 (文件名, 内容, 对比插槽)
+    #[cfg(target_arch = "wasm32")]
+    Picked(String, Arc<[u8]>, Option<char>),
+}
+
+impl TaskSource {
+    /// 结果进入对比插槽 (而不是单文件列表) 的加载
+    fn is_slot(&self) -> bool {
+        match self {
+            TaskSource::Slot(..) => true,
+            #[cfg(target_arch = "wasm32")]
+            TaskSource::Picked(_, _, slot) => slot.is_some(),
+            _ => false,
+        }
+    }
+}
+
+/// 加载文件：有按文件名规则选中的配置时按配置分析，否则使用全局分析参数
+fn load_with_profile(path: PathBuf, analysis: &AnalysisSettings, profile: Option<&AnalysisProfile>, logger: &Logger, progress: &mut dyn Progress) -> Result<AudioCurve, AnalyzerError> {
+    match profile {
+        Some(profile) => load_file_with_profile(path, profile, logger, progress),
+        None => load_file_with_retry(path, analysis, logger, progress),
+    }
+}
+
+/// 导入 CSV 响度日志前的列选择：预览开头几行，可修改自动识别的时间列、电平列和时间单位
+struct CsvImportDialog {
+    path: PathBuf,
+    preview: CsvPreview,
+    layout: CsvLayout,
+}
+
+/// CSV 导入预览显示的数据行数
+const CSV_PREVIEW_ROWS: usize = 8;
+
+/// 'A' -> 0, 'B' -> 1
+fn slot_index(slot: char) -> usize {
+    if slot == 'B' { 1 } else { 0 }
+}
+
+// UI 线程发送给 WorkerPool 主线程的命令
+#[derive(Debug)]
+enum WorkerCommand {
+    Kill(usize), // 杀死指定 ID 的任务
+    #[cfg(not(target_arch = "wasm32"))]
+    Shutdown,    // 关闭所有 worker
+}
+
+// Worker/Task 线程发送给 UI 线程的消息
+enum WorkerMessage {
+    Log(LogEntry),
+    UpdateTaskState(usize, TaskState),
+    NewCurve(Box<AudioCurve>, Option<char>, usize), // 专门用于返回处理结果 ('A'/'B' 对比插槽, 'R' 批量参考) 及产生它的任务 ID
+    BatchResult(BatchRow),              // 批量对比中一个候选文件的结果
+    PlaybackReady(PlaybackBuffer),      // 回放用音频解码完成
+    TaskPerf(usize, TaskPerf),          // 加载任务的耗时统计
+    #[cfg(target_arch = "wasm32")]
+    FilePicked(String, Arc<[u8]>, Option<char>), // 浏览器文件对话框读取完成 (文件名, 内容, 对比插槽)
+}
+
+struct WorkerPool {
+    tasks: Arc<Mutex<Vec<AudioTask>>>, // 共享任务列表
+    next_id: usize,
+    command_tx: mpsc::Sender<WorkerCommand>, // UI -> Worker 命令发送端
+    cancel_flags: Arc<Mutex<HashMap<usize, Arc<AtomicBool>>>>, // 未结束任务的取消标志，由任务在报告进度时检查
+    #[cfg(not(target_arch = "wasm32"))]
+    manager: Option<thread::JoinHandle<()>>,   // Worker 管理线程句柄
+    #[cfg(not(target_arch = "wasm32"))]
+    workers: Vec<thread::JoinHandle<()>>,      // 工作线程句柄，退出时等待其结束
+    // 浏览器中没有线程：命令和排队的任务由界面线程在每帧调用 run_pending 时处理 (协作式调度)
+    #[cfg(target_arch = "wasm32")]
+    command_rx: mpsc::Receiver<WorkerCommand>,
+    #[cfg(target_arch = "wasm32")]
+    ui_tx: mpsc::Sender<WorkerMessage>,
+    #[cfg(target_arch = "wasm32")]
+    queue: VecDeque<Box<dyn FnOnce()>>,
+}
+
+impl WorkerPool {
+    fn new(ui_tx: mpsc::Sender<WorkerMessage>) -> Self {
+        let (command_tx, command_rx) = mpsc::channel();
+        let tasks = Arc::new(Mutex::new(Vec::<AudioTask>::new()));
+        let cancel_flags = Arc::new(Mutex::new(HashMap::<usize, Arc<AtomicBool>>::new()));
+
+        // 启动 WorkerPool 管理线程 (非阻塞)
+        #[cfg(not(target_arch = "wasm32"))]
+        let manager = {
+            let tasks_clone = tasks.clone();
+            let flags_clone = cancel_flags.clone();
+            thread::spawn(move || {
+                loop {
+                    // 1. 检查来自 UI 的命令
+                    match command_rx.try_recv() {
+                        Ok(command) => {
+                            if !handle_worker_command(command, &tasks_clone, &flags_clone, &ui_tx) {
+                                break;
+                            }
+                        }
+                        Err(mpsc::TryRecvError::Empty) => {
+                            // 无命令，继续
+                        }
+                        Err(mpsc::TryRecvError::Disconnected) => break, // 通道断开
+                    }
+
+                    thread::sleep(Duration::from_millis(100));
+                }
+            })
+        };
+
+        Self {
+            tasks,
+            next_id: 1,
+            command_tx,
+            cancel_flags,
+            #[cfg(not(target_arch = "wasm32"))]
+            manager: Some(manager),
+            #[cfg(not(target_arch = "wasm32"))]
+            workers: Vec::new(),
+            #[cfg(target_arch = "wasm32")]
+            command_rx,
+            #[cfg(target_arch = "wasm32")]
+            ui_tx,
+            #[cfg(target_arch = "wasm32")]
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// 仍在 Waiting / Running 的任务数
+    fn unfinished_count(&self) -> usize {
+        self.tasks.lock().map(|tasks| tasks.iter().filter(|t| !t.state.is_finished()).count()).unwrap_or(0)
+    }
+
+    /// 退出前取消全部未结束的任务，等待工作线程在下一次报告进度时停止，再关闭管理线程
+    fn cancel_all(&mut self, logger: &Logger) {
+        if let Ok(mut tasks) = self.tasks.lock() {
+            for task in tasks.iter_mut().filter(|t| !t.state.is_finished()) {
+                task.state = TaskState::Killed;
+                log_command(logger, &format!("退出前取消任务 {} ({})", task.id, task.name));
+            }
+        }
+        if let Ok(flags) = self.cancel_flags.lock() {
+            flags.values().for_each(|flag| flag.store(true, Ordering::Relaxed));
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            for worker in self.workers.drain(..) {
+                worker.join().unwrap_or_default();
+            }
+            self.command_tx.send(WorkerCommand::Shutdown).unwrap_or_default();
+            if let Some(manager) = self.manager.take() {
+                manager.join().unwrap_or_default();
+            }
+        }
+        // 还在排队的任务不再执行
+        #[cfg(target_arch = "wasm32")]
+        self.queue.clear();
+    }
+
+    /// 处理界面发来的命令并执行一个排队的任务；还有任务排队时返回 true，调用方应请求重绘以继续处理
+    #[cfg(target_arch = "wasm32")]
+    fn run_pending(&mut self) -> bool {
+        while let Ok(command) = self.command_rx.try_recv() {
+            handle_worker_command(command, &self.tasks, &self.cancel_flags, &self.ui_tx);
+        }
+        if let Some(job) = self.queue.pop_front() {
+            job();
+        }
+        !self.queue.is_empty()
+    }
+
+    /// 启动一个后台任务，返回其任务 ID
+    fn spawn_task<F>(&mut self, name: String, f: F, ui_tx: mpsc::Sender<WorkerMessage>, logger: &Logger) -> usize
+    where
+        F: FnOnce(usize, mpsc::Sender<WorkerMessage>, Arc<Mutex<LogBuffer>>, Arc<AtomicBool>) + Send + 'static,
+    {
+        let id = self.next_id;
+        self.next_id += 1;
+        let task_name = name.clone();
+        let cancel = Arc::new(AtomicBool::new(false));
+        if let Ok(mut flags) = self.cancel_flags.lock() {
+            flags.insert(id, cancel.clone());
+        }
+        let cancel_flags = self.cancel_flags.clone();
+
+        // 传递日志条目 Arc<Mutex<...>> 的克隆给工作线程
+        let logger_entries_clone = logger.entries.clone();
+        let ui_tx_clone = ui_tx.clone();
+
+        // 1. 记录初始状态
+        log_info(logger, &format!("⚙️ 任务 {} 启动: {}", id, task_name));
+
+        let initial_task = AudioTask {
+            id,
+            name: task_name.clone(),
+            state: TaskState::Waiting, // 工作线程启动后发送 Running(0.0)
+        };
+
+        // 2. 任务体：本地程序在新的工作线程中执行，浏览器中排队等待界面线程执行
+        let job = move || {
+            // 此线程写入的日志在日志文件中带上任务 ID
+            set_log_task_id(Some(id));
+            ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Running(0.0))).unwrap_or_default();
+
+            // 执行实际任务；任务 panic 时标记为错误，避免状态永远停留在 Running。排队期间已被取消的任务不再执行
+            let task_tx = ui_tx_clone.clone();
+            let task_entries = logger_entries_clone.clone();
+            if !cancel.load(Ordering::Relaxed)
+                && std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| f(id, task_tx, task_entries, cancel))).is_err()
+            {
+                ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Error(TaskError::Message("task panicked".to_string())))).unwrap_or_default();
+            }
+
+            // 任务完成，发送最终状态 (这里仅作为兜底，实际应在 f 中发送 Completed/Error/Killed)
+            ui_tx_clone.send(WorkerMessage::UpdateTaskState(id, TaskState::Completed)).unwrap_or_default();
+
+            let thread_logger = Logger { entries: logger_entries_clone };
+            log_info(&thread_logger, &format!("✔️ 任务 {} 完成: {}", id, task_name));
+            if let Ok(mut flags) = cancel_flags.lock() {
+                flags.remove(&id);
+            }
+            // 浏览器中任务在界面线程上执行，之后的日志不再属于该任务
+            set_log_task_id(None);
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            // 已结束的工作线程不再需要等待
+            self.workers.retain(|worker| !worker.is_finished());
+            self.workers.push(thread::spawn(job));
+        }
+        #[cfg(target_arch = "wasm32")]
+        self.queue.push_back(Box::new(job));
+
+        // 3. 存储任务信息
+        if let Ok(mut tasks_lock) = self.tasks.lock() {
+            tasks_lock.push(initial_task);
+        }
+        id
+    }
+}
+
+/// 处理一条 WorkerPool 命令 (本地程序在管理线程中，浏览器中在界面线程上)；收到 Shutdown 时返回 false
+fn handle_worker_command(command: WorkerCommand, tasks: &Mutex<Vec<AudioTask>>, cancel_flags: &Mutex<HashMap<usize, Arc<AtomicBool>>>, ui_tx: &mpsc::Sender<WorkerMessage>) -> bool {
+    match command {
+        WorkerCommand::Kill(id) => {
+            if let Ok(mut tasks_lock) = tasks.lock()
+                && let Some(task) = tasks_lock.iter_mut().find(|t| t.id == id && !matches!(t.state, TaskState::Completed | TaskState::Killed))
+            {
+                // 在任务列表中标记为 Killed，并通知工作线程在下一次报告进度时停止
+                task.state = TaskState::Killed;
+                if let Some(flag) = cancel_flags.lock().ok().and_then(|flags| flags.get(&id).cloned()) {
+                    flag.store(true, Ordering::Relaxed);
+                }
+                ui_tx.send(WorkerMessage::UpdateTaskState(id, TaskState::Killed)).unwrap_or_default();
+
+                // 记录到日志
+                ui_tx.send(WorkerMessage::Log(LogEntry::new(
+                    LogLevel::Command,
+                    format!("Command: Task {} ({}) cancelled.", id, task.name),
+                ))).unwrap_or_default();
+            }
+            true
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        WorkerCommand::Shutdown => {
+            ui_tx.send(WorkerMessage::Log(LogEntry::new(
+                LogLevel::Debug,
+                "WorkerPool received Shutdown command. Exiting.".to_string(),
+            ))).unwrap_or_default();
+            false
+        }
+    }
+}
+
+/// 日志文件目录：设置中指定的目录，否则为平台数据目录 (eframe 保存设置的目录) 下的 logs
+#[cfg(not(target_arch = "wasm32"))]
+fn log_file_dir(settings: &AppSettings) -> PathBuf {
+    settings.log_file_dir.clone()
+        .or_else(|| eframe::storage_dir(APP_NAME).map(|dir| dir.join("logs")))
+        .unwrap_or_else(|| std::env::temp_dir().join("wav_lufs_curve_logs"))
+}
+
+/// 浏览器中没有本地文件系统，只显示设置中的目录
+#[cfg(target_arch = "wasm32")]
+fn log_file_dir(settings: &AppSettings) -> PathBuf {
+    settings.log_file_dir.clone().unwrap_or_default()
+}
+
+/// 启动 (或按新目录重新启动) 日志文件镜像，并把文件路径记入日志方便查找
+#[cfg(not(target_arch = "wasm32"))]
+fn start_log_file(logger: &Logger, settings: &AppSettings) {
+    let dir = log_file_dir(settings);
+    match LogFileMirror::start(dir.clone(), LOG_FILE_MAX_BYTES) {
+        Ok(mirror) => {
+            let path = mirror.current_path();
+            if let Ok(mut entries) = logger.entries.lock() {
+                entries.set_mirror(Some(mirror));
+            }
+            log_info(logger, &format!("📝 日志文件: {}", path.display()));
+        }
+        Err(e) => log_error(logger, &format!("❌ 无法创建日志目录 {}: {}", dir.display(), e)),
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+fn start_log_file(logger: &Logger, _settings: &AppSettings) {
+    log_error(logger, "⚠️ 浏览器版不支持日志文件");
+}
+
+// --- 会话自动保存与崩溃恢复 ---
+
+/// 自动保存的间隔；每批任务完成后也会保存一次
+const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(120);
+
+/// 会话恢复文件：平台数据目录 (eframe 保存设置的目录) 下的 recovery.ron。
+/// 正常退出时删除，因此启动时存在即说明上次运行没有正常退出
+#[cfg(not(target_arch = "wasm32"))]
+fn recovery_file_path() -> PathBuf {
+    eframe::storage_dir(APP_NAME)
+        .map(|dir| dir.join("recovery.ron"))
+        .unwrap_or_else(|| std::env::temp_dir().join("wav_lufs_curve_recovery.ron"))
+}
+
+/// 自动保存的会话：单文件模式的曲线 (含来源路径) 及对比插槽和参数；对比结果在恢复后重新计算
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+struct SessionSnapshot {
+    // 保存时间 (本地时间)，在恢复提示中显示
+    saved_at: String,
+    single_files: Vec<AudioCurve>,
+    compare_a: Option<AudioCurve>,
+    compare_b: Option<AudioCurve>,
+    compare_offset: f32,
+    target_mean_diff: f32,
+}
+
+impl SessionSnapshot {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn is_empty(&self) -> bool {
+        self.single_files.is_empty() && self.compare_a.is_none() && self.compare_b.is_none()
+    }
+
+    fn curve_count(&self) -> usize {
+        self.single_files.len() + self.compare_a.iter().count() + self.compare_b.iter().count()
+    }
+}
+
+/// 判断会话自上次自动保存后是否变化：曲线列表、对比插槽及对比参数
+type SessionKey = (u64, u64, u32, u32);
+
+/// 读取上次运行留下的恢复文件；没有文件或内容为空时为 None，无法解析的文件记录日志后删除
+#[cfg(not(target_arch = "wasm32"))]
+fn load_recovery(path: &Path, logger: &Logger) -> Option<SessionSnapshot> {
+    let text = std::fs::read_to_string(path).ok()?;
+    match ron::from_str::<Versioned<SessionSnapshot>>(&text) {
+        Ok(session) if !session.data.is_empty() => {
+            log_info(logger, &format!("发现会话恢复文件: {} ({} 条曲线)", path.display(), session.data.curve_count()));
+            Some(session.data)
+        }
+        Ok(_) => None,
+        Err(e) => {
+            log_error(logger, &format!("❌ 无法解析会话恢复文件 {}: {}", path.display(), e));
+            std::fs::remove_file(path).ok();
+            None
+        }
+    }
+}
+
+// 浏览器中不写恢复文件，命令只会被丢弃
+#[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+enum AutosaveCommand {
+    Save(Box<SessionSnapshot>),
+    // 正常退出或放弃恢复：删除恢复文件
+    Discard,
+}
+
+/// 在后台线程中序列化并写入恢复文件 (先写临时文件再改名，写到一半崩溃也不会损坏上一次的内容)
+struct AutoSaver {
+    sender: Option<mpsc::Sender<AutosaveCommand>>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl AutoSaver {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn start(path: PathBuf, logger: Logger) -> Self {
+        let (sender, receiver) = mpsc::channel::<AutosaveCommand>();
+        let thread_logger = Logger { entries: logger.entries.clone() };
+        let handle = thread::Builder::new()
+            .name("autosave".to_string())
+            .spawn(move || {
+                let logger = thread_logger;
+                for command in receiver {
+                    let result = match command {
+                        // 清空后的会话不值得恢复
+                        AutosaveCommand::Save(session) if session.is_empty() => remove_recovery_file(&path),
+                        AutosaveCommand::Save(session) => write_recovery_file(&path, &session),
+                        AutosaveCommand::Discard => remove_recovery_file(&path),
+                    };
+                    if let Err(e) = result {
+                        log_error(&logger, &format!("❌ 会话自动保存失败 ({}): {}", path.display(), e));
+                    }
+                }
+            });
+        match handle {
+            Ok(handle) => Self { sender: Some(sender), handle: Some(handle) },
+            Err(e) => {
+                log_error(&logger, &format!("❌ 无法启动自动保存线程: {}", e));
+                Self::disabled()
+            }
+        }
+    }
+
+    /// 不保存：浏览器中没有恢复文件，自动保存线程无法启动时也使用
+    fn disabled() -> Self {
+        Self { sender: None, handle: None }
+    }
+
+    fn send(&self, command: AutosaveCommand) {
+        if let Some(sender) = &self.sender {
+            sender.send(command).ok();
+        }
+    }
+
+    /// 发送最后一条命令，关闭通道并等待写入线程处理完剩余的命令；之后的 send 不再生效
+    fn finish(&mut self, last: Option<AutosaveCommand>) {
+        if let Some(command) = last {
+            self.send(command);
+        }
+        self.sender = None;
+        if let Some(handle) = self.handle.take() {
+            handle.join().ok();
+        }
+    }
+}
+
+impl Drop for AutoSaver {
+    /// 没有经过 on_exit 就被丢弃时 (测试等)：正常情况下删除恢复文件；因 panic 退出时保留，下次启动提示恢复
+    fn drop(&mut self) {
+        let last = (!thread::panicking()).then_some(AutosaveCommand::Discard);
+        self.finish(last);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn write_recovery_file(path: &Path, session: &SessionSnapshot) -> Result<(), Box<dyn Error + Send + Sync>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let text = ron::to_string(&Versioned::new(session))?;
+    let temp = path.with_extension("ron.tmp");
+    std::fs::write(&temp, text)?;
+    std::fs::rename(&temp, path)?;
+    Ok(())
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn remove_recovery_file(path: &Path) -> Result<(), Box<dyn Error + Send + Sync>> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// --- 文件监视 (自动重新分析) ---
+
+#[cfg(not(target_arch = "wasm32"))]
+/// 系统文件通知不可用时 (如 inotify 数量达到上限、网络文件系统)，PollWatcher 扫描目录的间隔；
+/// 也是防抖线程检查写入是否完成的间隔
+const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(250);
+#[cfg(not(target_arch = "wasm32"))]
+/// 文件大小和修改时间保持不变这么久才认为写入完成，导出时的多次连续写入只触发一次重新分析
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(800);
+
+#[cfg(not(target_arch = "wasm32"))]
+/// 文件的 (大小, 修改时间)；文件不存在 (如保存时先删除再改名) 时为 None
+type FileSignature = Option<(u64, std::time::SystemTime)>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_signature(path: &Path) -> FileSignature {
+    let metadata = std::fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// 一个被监视文件的防抖状态
+struct WatchedFile {
+    signature: FileSignature,
+    // 最近一次发现变化的时间；稳定 WATCH_DEBOUNCE 后报告
+    changed_at: Option<Instant>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WatchedFile {
+    fn new(signature: FileSignature) -> Self {
+        Self { signature, changed_at: None }
+    }
+
+    /// 记录本次检查的结果；变化后稳定了 WATCH_DEBOUNCE 且文件存在时返回 true (每次变化只报告一次)
+    fn poll(&mut self, signature: FileSignature, now: Instant) -> bool {
+        if signature != self.signature {
+            self.signature = signature;
+            self.changed_at = Some(now);
+            return false;
+        }
+        match self.changed_at {
+            Some(changed) if now.duration_since(changed) >= WATCH_DEBOUNCE && signature.is_some() => {
+                self.changed_at = None;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// 发现变化后还在等待写入完成
+    fn pending(&self) -> bool {
+        self.changed_at.is_some()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// 发给防抖线程的消息
+enum WatchEvent {
+    // 界面设置的被监视文件
+    Watch(BTreeSet<PathBuf>),
+    // notify 报告的变化 (目录中的任意文件)
+    Changed(PathBuf),
+}
+
+/// 用 notify 监视被监视文件所在的目录 (保存时先写临时文件再改名也能发现)，系统通知不可用的目录改用
+/// notify 的 PollWatcher。防抖线程只在收到通知后检查文件，写入完成后发送其路径并唤醒界面
+#[cfg(not(target_arch = "wasm32"))]
+struct FileWatcher {
+    native: Option<notify::RecommendedWatcher>,
+    poller: Option<notify::PollWatcher>,
+    // 正在监视的目录 -> 是否由 PollWatcher 轮询
+    dirs: BTreeMap<PathBuf, bool>,
+    // 界面上次设置的路径，相同时不再更新
+    watched: BTreeSet<PathBuf>,
+    events: mpsc::Sender<WatchEvent>,
+    changes: mpsc::Receiver<PathBuf>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileWatcher {
+    fn start(ctx: egui::Context, logger: &Logger) -> Self {
+        let (events, event_rx) = mpsc::channel();
+        let (sender, changes) = mpsc::channel();
+        let event_tx = events.clone();
+        // 读取文件产生的访问事件不算变化，否则重新分析会再次触发自身
+        let handler = move |event: notify::Result<notify::Event>| {
+            if let Ok(event) = event && !event.kind.is_access() {
+                for path in event.paths {
+                    event_tx.send(WatchEvent::Changed(path)).unwrap_or_default();
+                }
+            }
+        };
+        let native = notify::recommended_watcher(handler.clone())
+            .inspect_err(|e| log_error(logger, &format!("⚠️ 系统文件通知不可用，改为轮询: {}", e)))
+            .ok();
+        let poller = notify::PollWatcher::new(handler, notify::Config::default().with_poll_interval(WATCH_POLL_INTERVAL))
+            .inspect_err(|e| log_error(logger, &format!("❌ 无法创建轮询文件监视: {}", e)))
+            .ok();
+
+        let spawned = thread::Builder::new()
+            .name("file-watcher".to_string())
+            .spawn(move || {
+                let mut files: HashMap<PathBuf, WatchedFile> = HashMap::new();
+                loop {
+                    match event_rx.recv_timeout(WATCH_POLL_INTERVAL) {
+                        Ok(WatchEvent::Watch(paths)) => {
+                            // 新加入的文件从当前状态开始监视
+                            files.retain(|path, _| paths.contains(path));
+                            for path in paths {
+                                let signature = file_signature(&path);
+                                files.entry(path).or_insert_with(|| WatchedFile::new(signature));
+                            }
+                        }
+                        Ok(WatchEvent::Changed(path)) => {
+                            if let Some(file) = files.get_mut(&path) {
+                                file.poll(file_signature(&path), Instant::now());
+                            }
+                        }
+                        Err(mpsc::RecvTimeoutError::Timeout) => {}
+                        // FileWatcher 已释放
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                    // 只检查收到通知后还没写完的文件
+                    let now = Instant::now();
+                    for (path, file) in files.iter_mut().filter(|(_, file)| file.pending()) {
+                        if file.poll(file_signature(path), now) {
+                            if sender.send(path.clone()).is_err() {
+                                return;
+                            }
+                            ctx.request_repaint();
+                        }
+                    }
+                }
+            });
+        if let Err(e) = spawned {
+            log_error(logger, &format!("❌ 无法启动文件监视线程: {}", e));
+        }
+        Self { native, poller, dirs: BTreeMap::new(), watched: BTreeSet::new(), events, changes }
+    }
+
+    /// 设置要监视的文件，并按需增减监视的目录
+    fn set_watched(&mut self, paths: BTreeSet<PathBuf>, logger: &Logger) {
+        if paths == self.watched {
+            return;
+        }
+        let dirs: BTreeSet<PathBuf> = paths.iter().filter_map(|path| path.parent()).map(Path::to_path_buf).collect();
+        let removed: Vec<(PathBuf, bool)> = self.dirs.iter().filter(|(dir, _)| !dirs.contains(*dir)).map(|(dir, polled)| (dir.clone(), *polled)).collect();
+        for (dir, polled) in removed {
+            let unwatched = if polled { self.poller.as_mut().map(|w| w.unwatch(&dir)) } else { self.native.as_mut().map(|w| w.unwatch(&dir)) };
+            if let Some(Err(e)) = unwatched {
+                log_debug(logger, &format!("停止监视目录 {} 失败: {}", dir.display(), e));
+            }
+            self.dirs.remove(&dir);
+        }
+        for dir in dirs {
+            if !self.dirs.contains_key(&dir) && let Some(polled) = self.watch_dir(&dir, logger) {
+                self.dirs.insert(dir, polled);
+            }
+        }
+        self.events.send(WatchEvent::Watch(paths.clone())).unwrap_or_default();
+        self.watched = paths;
+    }
+
+    /// 监视一个目录：优先使用系统通知，失败时改用轮询；返回是否为轮询，两者都失败时返回 None
+    fn watch_dir(&mut self, dir: &Path, logger: &Logger) -> Option<bool> {
+        if let Some(native) = &mut self.native {
+            match native.watch(dir, notify::RecursiveMode::NonRecursive) {
+                Ok(()) => return Some(false),
+                Err(e) => log_error(logger, &format!("⚠️ 无法通过系统通知监视 {}，改为轮询: {}", dir.display(), e)),
+            }
+        }
+        match self.poller.as_mut()?.watch(dir, notify::RecursiveMode::NonRecursive) {
+            Ok(()) => Some(true),
+            Err(e) => {
+                log_error(logger, &format!("❌ 无法监视目录 {}: {}", dir.display(), e));
+                None
+            }
+        }
+    }
+}
+
+/// 浏览器中打开的文件是内存中的副本，没有可监视的文件，changes 永远为空
+#[cfg(target_arch = "wasm32")]
+struct FileWatcher {
+    changes: mpsc::Receiver<PathBuf>,
+    _sender: mpsc::Sender<PathBuf>,
+}
+
+#[cfg(target_arch = "wasm32")]
+impl FileWatcher {
+    fn start(_ctx: egui::Context, _logger: &Logger) -> Self {
+        let (_sender, changes) = mpsc::channel();
+        Self { changes, _sender }
+    }
+
+    fn set_watched(&mut self, _paths: BTreeSet<PathBuf>, _logger: &Logger) {}
+}
+
+// --- 加载错误与导出 ---
+
+/// 解析进度回调：转发为任务监视器中的进度；任务被取消 (Kill / 退出) 后解析返回 Cancelled
+#[derive(Clone, Copy)]
+struct TaskProgress<'a> {
+    task_id: usize,
+    ui_tx: &'a mpsc::Sender<WorkerMessage>,
+    cancel: &'a AtomicBool,
+    // 本阶段在任务总进度中的起点和跨度 (分阶段的任务如下载 + 解析)
+    start: f32,
+    span: f32,
+}
+
+impl TaskProgress<'_> {
+    /// 把本阶段的 0.0 - 1.0 映射到任务总进度的 start .. start + span
+    fn stage(self, start: f32, span: f32) -> Self {
+        Self { start, span, ..self }
+    }
+}
+
+impl Progress for TaskProgress<'_> {
+    fn report(&mut self, fraction: f32) {
+        let fraction = self.start + fraction * self.span;
+        self.ui_tx.send(WorkerMessage::UpdateTaskState(self.task_id, TaskState::Running(fraction))).unwrap_or_default();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::Relaxed)
+    }
+}
+
+fn task_progress<'a>(task_id: usize, ui_tx: &'a mpsc::Sender<WorkerMessage>, cancel: &'a AtomicBool) -> TaskProgress<'a> {
+    TaskProgress { task_id, ui_tx, cancel, start: 0.0, span: 1.0 }
+}
+
+/// AnalyzerError 的本地化说明 (不含文件名)
+fn analyzer_error_detail(err: &AnalyzerError, lang: &Lang) -> String {
+    match err {
+        AnalyzerError::UnsupportedFormat { format, bits } => {
+            let format = match format {
+                hound::SampleFormat::Int => format!("{}-bit PCM", bits),
+                hound::SampleFormat::Float => format!("{}-bit float", bits),
+            };
+            lang.load_err_unsupported_fmt.replacen("{}", &format, 1)
+        }
+        AnalyzerError::EmptyFile => lang.load_err_no_audio.to_string(),
+        AnalyzerError::DecodeFailed(detail) => lang.load_err_decode_fmt.replacen("{}", detail, 1),
+        AnalyzerError::CsvSchema { line, detail } => lang.load_err_csv_schema_fmt.replacen("{}", &line.to_string(), 1).replacen("{}", detail, 1),
+        AnalyzerError::Io(io_err) if is_file_in_use(io_err) => lang.load_err_in_use.to_string(),
+        AnalyzerError::Io(io_err) if io_err.kind() == std::io::ErrorKind::NotFound => lang.load_err_not_found.to_string(),
+        AnalyzerError::Io(io_err) if io_err.kind() == std::io::ErrorKind::PermissionDenied => lang.load_err_permission.to_string(),
+        AnalyzerError::Io(io_err) => io_err.to_string(),
+        AnalyzerError::Download(detail) => lang.load_err_download_fmt.replacen("{}", detail, 1),
+        AnalyzerError::NotAudio(content_type) => lang.url_err_not_audio_fmt.replacen("{}", content_type, 1),
+        AnalyzerError::Cancelled => lang.load_err_cancelled.to_string(),
+    }
+}
+
+/// 针对错误类型的处理建议；没有合适建议时为 None
+fn analyzer_error_hint(err: &AnalyzerError, lang: &Lang) -> Option<&'static str> {
+    match err {
+        AnalyzerError::UnsupportedFormat { .. } => Some(lang.load_hint_convert_pcm),
+        AnalyzerError::DecodeFailed(_) => Some(lang.load_hint_reexport),
+        AnalyzerError::CsvSchema { .. } => Some(lang.load_hint_csv_import),
+        AnalyzerError::Io(io_err) if is_file_in_use(io_err) => Some(lang.load_hint_close_other),
+        _ => None,
+    }
+}
+
+/// 一个加载任务的吞吐量统计 (进程监视器和控制台 `perf` 使用)
+#[derive(Clone, Debug, PartialEq)]
+struct TaskPerf {
+    file: String,
+    bytes: u64,
+    timing: LoadTiming,
+    points: usize,
+}
+
+impl TaskPerf {
+    fn total_sec(&self) -> f64 {
+        self.timing.decode_sec + self.timing.windowing_sec
+    }
+
+    /// 进程监视器任务行后附加的耗时
+    fn summary(&self, lang: &Lang) -> String {
+        lang.perf_task_fmt
+            .replacen("{}", &format!("{:.1}", self.bytes as f64 / 1e6), 1)
+            .replacen("{}", &format!("{:.0}", self.timing.decode_sec * 1000.0), 1)
+            .replacen("{}", &format!("{:.0}", self.timing.windowing_sec * 1000.0), 1)
+            .replacen("{}", &self.points.to_string(), 1)
+    }
+}
+
+/// 加载成功后把耗时统计交给界面 (曲线本身由调用方另行发送或就地使用)
+fn send_task_perf(ui_tx: &mpsc::Sender<WorkerMessage>, task_id: usize, curve: &AudioCurve) {
+    let Some(details) = &curve.details else { return };
+    let perf = TaskPerf { file: curve.file_name(), bytes: details.file_size, timing: details.timing, points: curve.points.len() };
+    ui_tx.send(WorkerMessage::TaskPerf(task_id, perf)).unwrap_or_default();
+}
+
+/// 本次会话各加载任务的耗时汇总：总吞吐量、最慢的文件，以及解码和加窗哪一阶段占用时间更多
+fn perf_report(perf: &BTreeMap<usize, TaskPerf>, lang: &Lang) -> Vec<String> {
+    if perf.is_empty() {
+        return vec![lang.perf_none.to_string()];
+    }
+    let bytes: u64 = perf.values().map(|p| p.bytes).sum();
+    let points: usize = perf.values().map(|p| p.points).sum();
+    let decode: f64 = perf.values().map(|p| p.timing.decode_sec).sum();
+    let windowing: f64 = perf.values().map(|p| p.timing.windowing_sec).sum();
+    let total = (decode + windowing).max(f64::EPSILON);
+    let mut lines = vec![
+        lang.perf_totals_fmt
+            .replacen("{}", &perf.len().to_string(), 1)
+            .replacen("{}", &format!("{:.1}", bytes as f64 / 1e6), 1)
+            .replacen("{}", &format!("{:.2}", decode), 1)
+            .replacen("{}", &format!("{:.2}", windowing), 1),
+        lang.perf_throughput_fmt
+            .replacen("{}", &format!("{:.1}", bytes as f64 / 1e6 / total), 1)
+            .replacen("{}", &format!("{:.0}", points as f64 / total), 1),
+        if decode >= windowing { lang.perf_bottleneck_decode_fmt } else { lang.perf_bottleneck_windowing_fmt }
+            .replacen("{}", &format!("{:.0}", decode.max(windowing) / total * 100.0), 1),
+        lang.perf_slowest_header.to_string(),
+    ];
+    let mut slowest: Vec<&TaskPerf> = perf.values().collect();
+    slowest.sort_by(|a, b| b.total_sec().total_cmp(&a.total_sec()));
+    for p in slowest.into_iter().take(PERF_SLOWEST_COUNT) {
+        lines.push(format!("  {:<32}  {:>7.2} s  ({:.2} + {:.2})  {:>8.1} MB/s",
+            p.file, p.total_sec(), p.timing.decode_sec, p.timing.windowing_sec, p.bytes as f64 / 1e6 / p.total_sec().max(f64::EPSILON)));
+    }
+    lines
+}
+
+/// `perf` 报告列出的最慢文件数
+const PERF_SLOWEST_COUNT: usize = 5;
+
+/// 加载任务失败：按任务启动时的语言记录日志，并把结构化错误交给界面 (界面按当前语言显示)
+fn send_load_error(ui_tx: &mpsc::Sender<WorkerMessage>, logger: &Logger, task_id: usize, file: &str, error: AnalyzerError, language: Language) {
+    let cancelled = matches!(error, AnalyzerError::Cancelled);
+    let error = TaskError::load(file, error);
+    // 取消是用户的操作而不是错误，任务已被标记为 Killed
+    if cancelled {
+        log_info(logger, &error.text(&Lang::load(language)));
+        return;
+    }
+    log_error(logger, &error.text(&Lang::load(language)));
+    ui_tx.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(error))).unwrap_or_default();
+}
+
+// --- 从 URL 加载 ---
+
+/// 可以当作 WAV 下载的 Content-Type：audio/*，以及服务器常用于任意二进制文件的类型
+#[cfg(not(target_arch = "wasm32"))]
+fn is_audio_content_type(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_ascii_lowercase();
+    mime.starts_with("audio/") || matches!(mime.as_str(), "" | "application/octet-stream" | "binary/octet-stream" | "application/x-wav")
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// URL 路径的最后一段 (去掉查询参数和片段并解码 %XX) 作为本地文件名，没有时为 download.wav
+fn url_file_name(url: &str) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let encoded = path.rsplit('/').next().unwrap_or("").as_bytes();
+    let mut bytes = Vec::with_capacity(encoded.len());
+    let mut i = 0;
+    while i < encoded.len() {
+        let hex = encoded.get(i + 1..i + 3).and_then(|h| std::str::from_utf8(h).ok()).and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (encoded[i], hex) {
+            (b'%', Some(byte)) => {
+                bytes.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                i += 1;
+            }
+        }
+    }
+    let name: String = String::from_utf8_lossy(&bytes).chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '.' | '-' | '_' | ' ') { c } else { '_' })
+        .collect();
+    if name.trim_matches('.').is_empty() { "download.wav".to_string() } else { name }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// 把 URL 指向的文件流式下载到 dir，返回本地路径。重定向自动跟随，文件名取自最终地址；
+/// progress 报告已下载的比例 (服务器未给出 Content-Length 时不报告)；任务被取消时返回 AnalyzerError::Cancelled
+fn download_url(url: &str, dir: &Path, logger: &Logger, progress: &mut dyn Progress) -> Result<PathBuf, AnalyzerError> {
+    use std::io::{Read, Write};
+
+    let agent = ureq::AgentBuilder::new()
+        .redirects(10)
+        .timeout_connect(Duration::from_secs(15))
+        // 连接停滞时也要能响应取消 (退出时会等待工作线程结束)
+        .timeout_read(Duration::from_secs(30))
+        .build();
+    let response = agent.get(url).call().map_err(|e| match e {
+        ureq::Error::Status(code, response) => AnalyzerError::Download(format!("HTTP {} {}", code, response.status_text())),
+        ureq::Error::Transport(transport) => AnalyzerError::Download(transport.to_string()),
+    })?;
+    let final_url = response.get_url().to_string();
+    if final_url != url {
+        log_debug(logger, &format!("重定向到: {}", final_url));
+    }
+    if let Some(content_type) = response.header("Content-Type")
+        && !is_audio_content_type(content_type)
+    {
+        return Err(AnalyzerError::NotAudio(content_type.to_string()));
+    }
+    let total: Option<u64> = response.header("Content-Length").and_then(|v| v.parse().ok());
+
+    let path = dir.join(url_file_name(&final_url));
+    let mut file = std::io::BufWriter::new(File::create(&path)?);
+    let mut reader = response.into_reader();
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut downloaded = 0u64;
+    loop {
+        if progress.is_cancelled() {
+            return Err(AnalyzerError::Cancelled);
+        }
+        let n = reader.read(&mut buffer)?;
+        if n == 0 {
+            break;
+        }
+        file.write_all(&buffer[..n])?;
+        downloaded += n as u64;
+        if let Some(total) = total.filter(|&t| t > 0) {
+            progress.report((downloaded as f64 / total as f64).min(1.0) as f32);
+        }
+    }
+    file.flush()?;
+    log_info(logger, &format!("⬇️ 已下载 {} ({}) 到 {}", url, format_file_size(downloaded), path.display()));
+    Ok(path)
+}
+
+/// 能否读写本地路径；浏览器版中需要本地文件或文件夹的按钮禁用
+const LOCAL_FILES: bool = cfg!(not(target_arch = "wasm32"));
+
+/// 需要本地文件的按钮：浏览器版中禁用，悬停时说明原因
+fn local_file_button(ui: &mut egui::Ui, enabled: bool, button: egui::Button<'_>, unavailable_hint: &str) -> egui::Response {
+    let response = ui.add_enabled(LOCAL_FILES && enabled, button);
+    if LOCAL_FILES { response } else { response.on_disabled_hover_text(unavailable_hint) }
+}
+
+/// 导出 AudioCurve 数据到 CSV 文件
+#[cfg(not(target_arch = "wasm32"))]
+fn export_to_csv(curve: &AudioCurve, options: &CsvExportOptions, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let default_name = format!("{}.csv", export_file_stem(curve));
+
+    // 允许用户选择保存位置
+    let path = FileDialog::new()
+        .set_file_name(&default_name)
+        .add_filter("CSV File", &["csv"])
+        .save_file();
+
+    if let Some(path) = path {
+        write_curve_csv(curve, options, &path, logger)?;
+    }
+    Ok(())
+}
+
+/// 浏览器中没有保存对话框：生成 CSV 后作为下载文件交给浏览器
+#[cfg(target_arch = "wasm32")]
+fn export_to_csv(curve: &AudioCurve, options: &CsvExportOptions, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let name = format!("{}.csv", export_file_stem(curve));
+    let mut bytes = Vec::new();
+    write_curve_csv_to(curve, options, &mut bytes, logger)?;
+    download_bytes(&name, "text/csv", &bytes)?;
+    log_info(logger, &format!("✅ 已下载 CSV: {}", name));
+    Ok(())
+}
+
+/// 导出标注 (曲线名, 标注) 列表；Audacity 标签轨只有一条曲线时不加曲线名前缀
+fn write_annotations(rows: &[(&str, &Annotation)], format: AnnotationFormat, path: &Path, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
+    log_info(logger, &format!("▶️ 导出 {} 条标注到: {}", rows.len(), path.display()));
+    match format {
+        AnnotationFormat::Audacity => {
+            use std::io::Write;
+            let single_curve = rows.windows(2).all(|w| w[0].0 == w[1].0);
+            let mut file = std::io::BufWriter::new(File::create(path)?);
+            for (curve, annotation) in rows {
+                let label = if single_curve { annotation.text.clone() } else { format!("[{}] {}", curve, annotation.text) };
+                writeln!(file, "{:.6}\t{:.6}\t{}", annotation.time, annotation.time, label)?;
+            }
+            file.flush()?;
+        }
+        AnnotationFormat::Csv => {
+            let mut wtr = csv::Writer::from_path(path)?;
+            wtr.write_record(["Curve", "Time (s)", "Text"])?;
+            for (curve, annotation) in rows {
+                wtr.write_record([curve.to_string(), format!("{:.6}", annotation.time), annotation.text.clone()])?;
+            }
+            wtr.flush()?;
+        }
+    }
+    log_info(logger, "✅ 标注导出成功");
+    Ok(())
+}
+
+/// 导出响度跳变 (曲线名, 跳变) 列表；Audacity 标签为变化前后两点之间的区间
+fn write_jump_events(rows: &[(&str, LoudnessJump)], format: AnnotationFormat, path: &Path, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
+    log_info(logger, &format!("▶️ 导出 {} 处响度跳变到: {}", rows.len(), path.display()));
+    match format {
+        AnnotationFormat::Audacity => {
+            use std::io::Write;
+            let single_curve = rows.windows(2).all(|w| w[0].0 == w[1].0);
+            let mut file = std::io::BufWriter::new(File::create(path)?);
+            for (curve, jump) in rows {
+                let change = format!("{:+.1} dB", jump.change_db);
+                let label = if single_curve { change } else { format!("[{}] {}", curve, change) };
+                writeln!(file, "{:.6}\t{:.6}\t{}", jump.start, jump.end, label)?;
+            }
+            file.flush()?;
+        }
+        AnnotationFormat::Csv => {
+            let mut wtr = csv::Writer::from_path(path)?;
+            wtr.write_record(["Curve", "Start (s)", "End (s)", "Change (dB)", "Direction"])?;
+            for (curve, jump) in rows {
+                wtr.write_record([
+                    curve.to_string(),
+                    format!("{:.6}", jump.start),
+                    format!("{:.6}", jump.end),
+                    format!("{:+.2}", jump.change_db),
+                    if jump.change_db > 0.0 { "up" } else { "down" }.to_string(),
+                ])?;
+            }
+            wtr.flush()?;
+        }
+    }
+    log_info(logger, "✅ 响度跳变导出成功");
+    Ok(())
+}
+
+/// 导出批量对比报告到 CSV 文件 (判定基于当前阈值设置，相关系数检验使用 confidence 置信度)
+fn export_batch_report(rows: &[BatchRow], settings: &AppSettings, confidence: f64, logger: &Logger) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let path = FileDialog::new()
+        .set_file_name("batch_report.csv")
+        .add_filter("CSV File", &["csv"])
+        .save_file();
+
+    if let Some(path) = path {
+        log_info(logger, &format!("▶️ 导出批量报告到: {}", path.display()));
+        let file = File::create(&path)?;
+        let mut wtr = csv::WriterBuilder::new().flexible(true).from_writer(file);
+
+        // 表头之前记录计算各行曲线时的分析参数
+        for (name, value) in analysis_metadata(&settings.analysis) {
+            wtr.write_record([format!("# {}", name), value])?;
+        }
+        wtr.write_record(["# min_correlation".to_string(), settings.min_correlation.to_string()])?;
+        wtr.write_record(["# confidence".to_string(), confidence.to_string()])?;
+        wtr.write_record(["File", "Mean Diff (dB)", "Std Dev", "Correlation (r)", "r CI Low", "r CI High", "r Test", "T-Statistic", "Overlap (%)", "Partial", "Verdict", "Error"])?;
+        for row in rows {
+            match &row.result {
+                Ok(stats) => {
+                    let test = correlation_test(stats.correlation_coefficient, stats.points, settings.min_correlation as f64, confidence);
+                    wtr.write_record([
+                        row.name.clone(),
+                        format!("{:.2}", stats.mean_diff),
+                        format!("{:.4}", stats.std_dev),
+                        format!("{:.4}", stats.correlation_coefficient),
+                        test.map_or_else(String::new, |t| format!("{:.4}", t.interval.0)),
+                        test.map_or_else(String::new, |t| format!("{:.4}", t.interval.1)),
+                        test.map_or("", |t| if t.passes { "Pass" } else { "Fail" }).to_string(),
+                        format!("{:.2}", stats.t_statistic),
+                        format!("{:.1}", stats.overlap_ratio * 100.0),
+                        settings.is_partial_overlap(stats.overlap_ratio).to_string(),
+                        format!("{:?}", settings.verdict(stats.std_dev)),
+                        String::new(),
+                    ])?
+                }
+                Err(e) => wtr.write_record([
+                    row.name.as_str(), "", "", "", "", "", "", "", "", "", "", e.as_str(),
+                ])?,
+            }
+        }
+
+        wtr.flush()?;
+        log_info(logger, &format!("✅ 批量报告导出成功 ({} 行)", rows.len()));
+    }
+    Ok(())
+}
+
+
+// --- 系统字体探测 ---
+
+/// 各平台常见的 CJK 字体路径及其在字体集合 (.ttc) 中的索引
+#[cfg(target_os = "windows")]
+const CJK_FONT_CANDIDATES: &[(&str, u32)] = &[
+    (r"C:\Windows\Fonts\msyh.ttc", 0),   // Microsoft YaHei
+    (r"C:\Windows\Fonts\msyh.ttf", 0),
+    (r"C:\Windows\Fonts\simhei.ttf", 0),
+    (r"C:\Windows\Fonts\simsun.ttc", 0),
+];
+
+#[cfg(target_os = "macos")]
+const CJK_FONT_CANDIDATES: &[(&str, u32)] = &[
+    ("/System/Library/Fonts/PingFang.ttc", 0),
+    ("/System/Library/Fonts/STHeiti Medium.ttc", 0),
+    ("/System/Library/Fonts/Hiragino Sans GB.ttc", 0),
+    ("/Library/Fonts/Arial Unicode.ttf", 0),
+];
+
+// Noto Sans CJK 的 .ttc 中索引 2 为简体中文 (SC)
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const CJK_FONT_CANDIDATES: &[(&str, u32)] = &[
+    ("/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/opentype/noto/NotoSansCJKsc-Regular.otf", 0),
+    ("/usr/share/fonts/truetype/wqy/wqy-microhei.ttc", 0),
+    ("/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc", 0),
+    ("/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf", 0),
+];
+
+/// 按顺序探测系统 CJK 字体，返回第一个可读取的字体路径、数据和集合索引
+fn find_system_cjk_font() -> Option<(PathBuf, Vec<u8>, u32)> {
+    CJK_FONT_CANDIDATES.iter().find_map(|(path, index)| {
+        let path = PathBuf::from(path);
+        std::fs::read(&path).ok().map(|bytes| (path, bytes, *index))
+    })
+}
+
+/// CJK 字体加载状态
+#[derive(PartialEq, Clone, Debug)]
+enum CjkFontState {
+    NotLoaded,
+    Loaded(PathBuf),
+    Missing, // 已探测但系统中没有可用字体
+}
+
+impl CjkFontState {
+    /// 中日韩界面下确保 CJK 字体已安装到 egui；已加载或已确认缺失时不再重复配置
+    fn ensure_loaded(&mut self, ctx: &egui::Context, lang: Language, logger: &Logger) {
+        if !lang.needs_cjk_font() || *self != CjkFontState::NotLoaded {
+            return;
+        }
+
+        match find_system_cjk_font() {
+            Some((path, bytes, index)) => {
+                let mut fonts = egui::FontDefinitions::default();
+                let mut font_data = egui::FontData::from_owned(bytes);
+                font_data.index = index;
+                fonts.font_data.insert("cjk_font".to_owned(), font_data.into());
+
+                // 中文字体放在比例字体首位，等宽字体作为后备
+                fonts.families.get_mut(&egui::FontFamily::Proportional).unwrap().insert(0, "cjk_font".to_owned());
+                fonts.families.get_mut(&egui::FontFamily::Monospace).unwrap().push("cjk_font".to_owned());
+                ctx.set_fonts(fonts);
+
+                log_info(logger, &format!("已加载系统 CJK 字体: {}", path.display()));
+                *self = CjkFontState::Loaded(path);
+            }
+            None => {
+                log_error(logger, "⚠️ 未找到系统 CJK 字体，中文将显示为方块。请安装 Noto Sans CJK / Microsoft YaHei / PingFang。");
+                *self = CjkFontState::Missing;
+            }
+        }
+    }
+}
+
+
+// --- 控制台命令表 ---
+
+/// 命令参数类型，决定 Tab 补全的候选来源
+#[derive(Clone, Copy, PartialEq)]
+enum CommandArg {
+    None,
+    TaskId,
+    Path,
+    Command,
+}
+
+/// 控制台命令描述：帮助弹窗、`help` 命令和 Tab 补全共用此表
+struct ConsoleCommand {
+    // 第一个为主名称，其余为别名
+    names: &'static [&'static str],
+    usage: &'static str,
+    arg: CommandArg,
+    help: fn(&Lang) -> &'static str,
+}
+
+const CONSOLE_COMMANDS: &[ConsoleCommand] = &[
+    ConsoleCommand { names: &["load"], usage: "load <path> [more paths]", arg: CommandArg::Path, help: |l| l.help_cmd_load },
+    ConsoleCommand { names: &["loadurl"], usage: "loadurl <url>", arg: CommandArg::None, help: |l| l.help_cmd_loadurl },
+    ConsoleCommand { names: &["loadA", "loadB"], usage: "loadA <path> / loadB <path>", arg: CommandArg::Path, help: |l| l.help_cmd_load_ab },
+    ConsoleCommand { names: &["compare"], usage: "compare", arg: CommandArg::None, help: |l| l.help_cmd_compare },
+    ConsoleCommand { names: &["export"], usage: "export <index-or-name> <path.csv>", arg: CommandArg::Path, help: |l| l.help_cmd_export },
+    ConsoleCommand { names: &["tasks", "list"], usage: "tasks / list", arg: CommandArg::None, help: |l| l.help_cmd_list },
+    ConsoleCommand { names: &["kill"], usage: "kill <ID>", arg: CommandArg::TaskId, help: |l| l.help_cmd_kill },
+    ConsoleCommand { names: &["quickscan"], usage: "quickscan <dir>", arg: CommandArg::Path, help: |l| l.help_cmd_quickscan },
+    ConsoleCommand { names: &["perf"], usage: "perf", arg: CommandArg::None, help: |l| l.help_cmd_perf },
+    ConsoleCommand { names: &["history"], usage: "history [N] / !<n>", arg: CommandArg::None, help: |l| l.help_cmd_history },
+    ConsoleCommand { names: &["help"], usage: "help [command]", arg: CommandArg::Command, help: |l| l.help_cmd_help },
+    ConsoleCommand { names: &["clear"], usage: "clear", arg: CommandArg::None, help: |l| l.help_cmd_clear },
+    ConsoleCommand { names: &["logcap"], usage: "logcap [N]", arg: CommandArg::None, help: |l| l.help_cmd_logcap },
+    ConsoleCommand { names: &["logfile"], usage: "logfile on|off|path|<dir>", arg: CommandArg::Path, help: |l| l.help_cmd_logfile },
+    ConsoleCommand { names: &["quit", "exit"], usage: "quit / exit", arg: CommandArg::None, help: |l| l.help_cmd_quit },
+];
+
+fn find_console_command(name: &str) -> Option<&'static ConsoleCommand> {
+    CONSOLE_COMMANDS.iter().find(|c| c.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+}
+
+/// 将命令行拆分为参数：以空白分隔，单引号或双引号包裹的部分可以包含空格。
+/// 引号未闭合时返回该引号字符
+fn tokenize_command(line: &str) -> Result<Vec<String>, char> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut in_token = false;
+    let mut quote: Option<char> = None;
+
+    for c in line.chars() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => current.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                in_token = true;
+            }
+            None if c.is_whitespace() => {
+                if in_token {
+                    tokens.push(std::mem::take(&mut current));
+                    in_token = false;
+                }
+            }
+            None => {
+                current.push(c);
+                in_token = true;
+            }
+        }
+    }
+
+    if let Some(q) = quote {
+        return Err(q);
+    }
+    if in_token {
+        tokens.push(current);
+    }
+    Ok(tokens)
+}
+
+/// 最后一个参数在输入中的起始位置，以及它是否处于未闭合的引号中 (用于 Tab 补全)
+fn last_token_start(input: &str) -> (usize, Option<char>) {
+    let mut start: Option<usize> = None;
+    let mut quote: Option<char> = None;
+    for (i, c) in input.char_indices() {
+        match quote {
+            Some(q) if c == q => quote = None,
+            Some(_) => {}
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                start.get_or_insert(i);
+            }
+            None if c.is_whitespace() => start = None,
+            None => {
+                start.get_or_insert(i);
+            }
+        }
+    }
+    (start.unwrap_or(input.len()), quote)
+}
+
+/// 列出以 `prefix` 开头的文件系统路径 (目录以分隔符结尾)
+fn complete_path(prefix: &str) -> Vec<String> {
+    let split = prefix.rfind(['/', std::path::MAIN_SEPARATOR]).map(|i| i + 1).unwrap_or(0);
+    let (dir_part, file_part) = prefix.split_at(split);
+    let dir = if dir_part.is_empty() { Path::new(".") } else { Path::new(dir_part) };
+    let Ok(entries) = std::fs::read_dir(dir) else { return Vec::new(); };
+
+    let mut candidates: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            if !name.starts_with(file_part) { return None; }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            Some(format!("{}{}{}", dir_part, name, if is_dir { std::path::MAIN_SEPARATOR_STR } else { "" }))
+        })
+        .collect();
+    candidates.sort();
+    candidates
+}
+
+/// 候选项的最长公共前缀 (按字符)
+fn common_prefix(candidates: &[String]) -> String {
+    let Some(first) = candidates.first() else { return String::new(); };
+    let mut prefix: &str = first;
+    for candidate in &candidates[1..] {
+        let len = prefix.char_indices()
+            .zip(candidate.chars())
+            .find(|((_, a), b)| a != b)
+            .map(|((i, _), _)| i)
+            .unwrap_or_else(|| prefix.len().min(candidate.len()));
+        prefix = &prefix[..len];
+    }
+    prefix.to_string()
+}
+
+
+// --- 音频回放 ---
+
+/// 编译时是否启用了 `playback` 功能 (cpal 音频输出)；未启用时回放控件置灰
+const PLAYBACK_AVAILABLE: bool = cfg!(feature = "playback");
+
+/// 回放用的已解码音频 (交错排列，归一化到 [-1.0, 1.0])
+struct PlaybackBuffer {
+    source: PathBuf,
+    samples: Vec<f32>,
+    channels: usize,
+    sample_rate: u32,
+}
+
+/// 在后台线程中把整个 WAV 解码到内存；任务被取消时返回 Cancelled
+fn decode_for_playback(path: PathBuf, progress: &mut dyn Progress) -> Result<PlaybackBuffer, AnalyzerError> {
+    let mut reader = WavReader::open(&path)?;
+    let spec = reader.spec();
+    let mut samples = Vec::with_capacity(reader.len() as usize);
+    for_each_wav_sample_with_progress(&mut reader, progress, |s| samples.push(s as f32))?;
+    Ok(PlaybackBuffer { source: path, samples, channels: spec.channels.max(1) as usize, sample_rate: spec.sample_rate })
+}
+
+/// UI 线程与音频回调线程共享的回放状态
+struct PlaybackShared {
+    playing: AtomicBool,
+    // 当前位置 (源采样帧，f64 的位模式)
+    position: AtomicU64,
+    // 待音频回调处理的跳转目标 (源采样帧，f64 的位模式)，NO_SEEK 表示没有
+    seek_request: AtomicU64,
+}
+
+const NO_SEEK: u64 = u64::MAX;
+
+impl PlaybackBuffer {
+    fn frames(&self) -> usize {
+        self.samples.len() / self.channels
+    }
+
+    /// 以线性插值把源音频重采样到输出设备的采样率，填充交错的输出缓冲。
+    /// 输出声道多于源声道时循环复用源声道；暂停或播放到末尾时输出静音
+    #[cfg_attr(not(feature = "playback"), allow(dead_code))]
+    fn render(&self, shared: &PlaybackShared, out: &mut [f32], out_channels: usize, out_rate: u32) {
+        let seek = shared.seek_request.swap(NO_SEEK, Ordering::AcqRel);
+        let mut pos = f64::from_bits(if seek == NO_SEEK { shared.position.load(Ordering::Acquire) } else { seek });
+        let playing = shared.playing.load(Ordering::Acquire);
+        let last = self.frames().saturating_sub(1) as f64;
+        let step = self.sample_rate as f64 / out_rate as f64;
+
+        for out_frame in out.chunks_mut(out_channels.max(1)) {
+            if !playing || pos >= last {
+                out_frame.fill(0.0);
+                continue;
+            }
+            let i = pos as usize;
+            let frac = (pos - i as f64) as f32;
+            for (c, sample) in out_frame.iter_mut().enumerate() {
+                let a = self.samples[i * self.channels + c % self.channels];
+                let b = self.samples[(i + 1) * self.channels + c % self.channels];
+                *sample = a + (b - a) * frac;
+            }
+            pos += step;
+        }
+
+        if playing && pos >= last {
+            shared.playing.store(false, Ordering::Release);
+        }
+        shared.position.store(pos.min(last.max(0.0)).to_bits(), Ordering::Release);
+    }
+}
+
+#[cfg(feature = "playback")]
+type PlaybackStream = cpal::Stream;
+/// 输出回调的临时缓冲区最多预分配的帧数；设备报告的最大缓冲更大 (或未知) 时回调分段渲染
+#[cfg(feature = "playback")]
+const MAX_PLAYBACK_SCRATCH_FRAMES: usize = 16384;
+// 未启用 playback 功能时不存在输出流
+#[cfg(not(feature = "playback"))]
+enum PlaybackStream {}
+
+/// 打开默认输出设备，音频回调在 cpal 的音频线程上运行
+#[cfg(feature = "playback")]
+fn open_output_stream(buffer: Arc<PlaybackBuffer>, shared: Arc<PlaybackShared>, logger: Logger) -> Result<PlaybackStream, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    // 错误信息由界面套入本地化的 playback_open_failed_fmt
+    let device = cpal::default_host().default_output_device().ok_or("no default output device")?;
+    let supported = device.default_output_config().map_err(|e| e.to_string())?;
+    let sample_format = supported.sample_format();
+    let max_frames = match supported.buffer_size() {
+        cpal::SupportedBufferSize::Range { max, .. } => (*max as usize).clamp(1, MAX_PLAYBACK_SCRATCH_FRAMES),
+        cpal::SupportedBufferSize::Unknown => MAX_PLAYBACK_SCRATCH_FRAMES,
+    };
+    let config: cpal::StreamConfig = supported.into();
+    log_debug(&logger, &format!("输出设备: {}Hz, {} 声道, {:?}, 最大缓冲 {} 帧", config.sample_rate.0, config.channels, sample_format, max_frames));
+
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_output_stream::<f32>(&device, &config, max_frames, buffer, shared, logger),
+        cpal::SampleFormat::I16 => build_output_stream::<i16>(&device, &config, max_frames, buffer, shared, logger),
+        cpal::SampleFormat::U16 => build_output_stream::<u16>(&device, &config, max_frames, buffer, shared, logger),
+        cpal::SampleFormat::I32 => build_output_stream::<i32>(&device, &config, max_frames, buffer, shared, logger),
+        other => return Err(format!("unsupported output sample format {:?}", other)),
+    }
+    .map_err(|e| e.to_string())?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+#[cfg(feature = "playback")]
+fn build_output_stream<T: cpal::SizedSample + cpal::FromSample<f32>>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    max_frames: usize,
+    buffer: Arc<PlaybackBuffer>,
+    shared: Arc<PlaybackShared>,
+    logger: Logger,
+) -> Result<cpal::Stream, cpal::BuildStreamError> {
+    use cpal::traits::DeviceTrait;
+
+    let channels = config.channels as usize;
+    let rate = config.sample_rate.0;
+    // 在建立输出流时一次分配好，音频线程上不再分配内存
+    let mut scratch = vec![0.0f32; max_frames * channels];
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _| {
+            // 回调缓冲比预分配的大时按整帧分段渲染
+            for chunk in data.chunks_mut(scratch.len()) {
+                let scratch = &mut scratch[..chunk.len()];
+                buffer.render(&shared, scratch, channels, rate);
+                for (out, s) in chunk.iter_mut().zip(scratch.iter()) {
+                    *out = T::from_sample(*s);
+                }
+            }
+        },
+        move |e| log_error(&logger, &format!("音频输出错误: {}", e)),
+        None,
+    )
+}
+
+#[cfg(not(feature = "playback"))]
+fn open_output_stream(_buffer: Arc<PlaybackBuffer>, _shared: Arc<PlaybackShared>, _logger: Logger) -> Result<PlaybackStream, String> {
+    Err("built without the playback feature".to_string())
+}
+
+/// 单文件模式中正在回放的 WAV；丢弃时输出流随之关闭
+struct Player {
+    source: PathBuf,
+    sample_rate: u32,
+    duration: f64,
+    shared: Arc<PlaybackShared>,
+    _stream: PlaybackStream,
+}
+
+impl Player {
+    fn start(buffer: PlaybackBuffer, logger: Logger) -> Result<Self, String> {
+        let shared = Arc::new(PlaybackShared {
+            playing: AtomicBool::new(true),
+            position: AtomicU64::new(0.0f64.to_bits()),
+            seek_request: AtomicU64::new(NO_SEEK),
+        });
+        let source = buffer.source.clone();
+        let sample_rate = buffer.sample_rate;
+        let duration = buffer.frames() as f64 / sample_rate as f64;
+        let stream = open_output_stream(Arc::new(buffer), shared.clone(), logger)?;
+        Ok(Self { source, sample_rate, duration, shared, _stream: stream })
+    }
+
+    fn is_playing(&self) -> bool {
+        self.shared.playing.load(Ordering::Acquire)
+    }
+
+    /// 播放/暂停；已播放到末尾时从头开始
+    fn toggle(&self) {
+        let playing = !self.is_playing();
+        if playing && self.position_secs() >= self.duration - 1.0 / self.sample_rate as f64 {
+            self.seek(0.0);
+        }
+        self.shared.playing.store(playing, Ordering::Release);
+    }
+
+    /// 当前回放位置 (秒)，与曲线的时间轴一致
+    fn position_secs(&self) -> f64 {
+        f64::from_bits(self.shared.position.load(Ordering::Acquire)) / self.sample_rate as f64
+    }
+
+    fn seek(&self, secs: f64) {
+        let frame = secs.max(0.0) * self.sample_rate as f64;
+        self.shared.seek_request.store(frame.to_bits(), Ordering::Release);
+        // 立即更新位置，暂停时光标也能跟随
+        self.shared.position.store(frame.to_bits(), Ordering::Release);
+    }
+}
+
+
+// --- 实时输入监视 ---
+
+/// 编译时是否启用了 `live-input` 功能 (cpal 音频输入)；未启用时实时监视模式只显示提示
+const LIVE_INPUT_AVAILABLE: bool = cfg!(feature = "live-input");
+
+/// 音频回调送往界面的块能量缓冲能容纳的块数 (默认 100 ms 步进时约 100 秒)
+const LIVE_RING_CAPACITY: usize = 1024;
+
+/// 音频回调与界面线程之间的单生产者单消费者环形缓冲，每项为一个块的均方值 (f64 的位模式)。
+/// 回调中只做原子读写，不分配内存也不加锁；缓冲满时丢弃新块并计数
+struct BlockRing {
+    slots: Box<[AtomicU64]>,
+    // 累计写入 / 读取的块数：只有音频回调修改 written，只有界面线程修改 read
+    written: AtomicUsize,
+    read: AtomicUsize,
+    dropped: AtomicUsize,
+}
+
+#[cfg_attr(not(feature = "live-input"), allow(dead_code))]
+impl BlockRing {
+    fn new(capacity: usize) -> Self {
+        Self {
+            slots: (0..capacity.max(1)).map(|_| AtomicU64::new(0)).collect(),
+            written: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            dropped: AtomicUsize::new(0),
+        }
+    }
+
+    /// 由音频回调调用
+    fn push(&self, mean_square: f64) {
+        let written = self.written.load(Ordering::Relaxed);
+        if written - self.read.load(Ordering::Acquire) == self.slots.len() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            return;
+        }
+        self.slots[written % self.slots.len()].store(mean_square.to_bits(), Ordering::Relaxed);
+        self.written.store(written + 1, Ordering::Release);
+    }
+
+    /// 由界面线程调用
+    fn pop(&self) -> Option<f64> {
+        let read = self.read.load(Ordering::Relaxed);
+        if read == self.written.load(Ordering::Acquire) {
+            return None;
+        }
+        let value = f64::from_bits(self.slots[read % self.slots.len()].load(Ordering::Relaxed));
+        self.read.store(read + 1, Ordering::Release);
+        Some(value)
+    }
+}
+
+/// 在音频回调中把交错样本累加为固定长度的块 (一个分析步进)，每满一块得到该块的均方值
+#[cfg_attr(not(feature = "live-input"), allow(dead_code))]
+struct BlockAccumulator {
+    block_samples: usize,
+    filled: usize,
+    energy: f64,
+}
+
+#[cfg_attr(not(feature = "live-input"), allow(dead_code))]
+impl BlockAccumulator {
+    fn new(block_frames: usize, channels: usize) -> Self {
+        Self { block_samples: block_frames.max(1) * channels.max(1), filled: 0, energy: 0.0 }
+    }
+
+    fn push(&mut self, sample: f32) -> Option<f64> {
+        self.energy += sample as f64 * sample as f64;
+        self.filled += 1;
+        if self.filled < self.block_samples {
+            return None;
+        }
+        let mean_square = self.energy / self.block_samples as f64;
+        self.filled = 0;
+        self.energy = 0.0;
+        Some(mean_square)
+    }
+}
+
+/// 由块均方值组成与文件分析相同的滑动 RMS 窗口：每块为一个步进，连续 window_blocks 块为一个窗口
+struct LiveWindows {
+    step_sec: f64,
+    window_blocks: usize,
+    noise_floor_dbfs: f64,
+    recent: VecDeque<f64>,
+    blocks_seen: u64,
+}
+
+impl LiveWindows {
+    fn new(analysis: &AnalysisSettings) -> Self {
+        let step_sec = analysis.step_sec.max(0.001);
+        Self {
+            step_sec,
+            window_blocks: ((analysis.window_sec / step_sec).round() as usize).max(1),
+            noise_floor_dbfs: analysis.noise_floor_dbfs,
+            recent: VecDeque::new(),
+            blocks_seen: 0,
+        }
+    }
+
+    fn window_sec(&self) -> f64 {
+        self.window_blocks as f64 * self.step_sec
+    }
+
+    /// 加入一块；凑满一个窗口后返回 (窗口中心时间, dBFS)，时间从开始监视算起
+    fn push(&mut self, mean_square: f64) -> Option<[f64; 2]> {
+        self.blocks_seen += 1;
+        self.recent.push_back(mean_square);
+        if self.recent.len() > self.window_blocks {
+            self.recent.pop_front();
+        }
+        if self.recent.len() < self.window_blocks {
+            return None;
+        }
+        let rms = (self.recent.iter().sum::<f64>() / self.window_blocks as f64).sqrt();
+        let db = match if rms < 1e-9 { SILENCE_FLOOR_DBFS } else { 20.0 * rms.log10() } {
+            db if db < self.noise_floor_dbfs => SILENCE_FLOOR_DBFS,
+            db => db,
+        };
+        Some([self.blocks_seen as f64 * self.step_sec - self.window_sec() / 2.0, db])
+    }
+}
+
+#[cfg(feature = "live-input")]
+type InputStream = cpal::Stream;
+// 未启用 live-input 功能时不存在输入流
+#[cfg(not(feature = "live-input"))]
+enum InputStream {}
+
+/// 已打开的输入流及其格式
+struct LiveInput {
+    device: String,
+    sample_rate: u32,
+    channels: u16,
+    _stream: InputStream,
+}
+
+/// 当前主机的输入设备名称
+#[cfg(feature = "live-input")]
+fn list_input_devices() -> Result<Vec<String>, String> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let devices = cpal::default_host().input_devices().map_err(|e| e.to_string())?;
+    Ok(devices.filter_map(|device| device.name().ok()).collect())
+}
+
+#[cfg(not(feature = "live-input"))]
+fn list_input_devices() -> Result<Vec<String>, String> {
+    Err("built without the live-input feature".to_string())
+}
+
+/// 打开输入设备 (None 为默认设备)，音频回调每满 step_sec 秒的一块就写入 ring
+#[cfg(feature = "live-input")]
+fn open_input_stream(device_name: Option<&str>, step_sec: f64, ring: Arc<BlockRing>, logger: Logger) -> Result<LiveInput, String> {
+    use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+
+    // 错误信息由界面套入本地化的 live_open_failed_fmt
+    let host = cpal::default_host();
+    let device = match device_name {
+        Some(name) => host.input_devices().map_err(|e| e.to_string())?
+            .find(|device| device.name().is_ok_and(|n| n == name))
+            .ok_or_else(|| format!("input device {:?} not found", name))?,
+        None => host.default_input_device().ok_or("no default input device")?,
+    };
+    let supported = device.default_input_config().map_err(|e| e.to_string())?;
+    let sample_format = supported.sample_format();
+    let config: cpal::StreamConfig = supported.into();
+    let device_label = device.name().unwrap_or_default();
+    log_debug(&logger, &format!("输入设备: {} ({}Hz, {} 声道, {:?})", device_label, config.sample_rate.0, config.channels, sample_format));
+
+    let block_frames = ((step_sec * config.sample_rate.0 as f64).round() as usize).max(1);
+    let stream = match sample_format {
+        cpal::SampleFormat::F32 => build_input_stream::<f32>(&device, &config, block_frames, ring, logger),
+        cpal::SampleFormat::I16 => build_input_stream::<i16>(&device, &config, block_frames, ring, logger),
+        cpal::SampleFormat::U16 => build_input_stream::<u16>(&device, &config, block_frames, ring, logger),
+        cpal::SampleFormat::I32 => build_input_stream::<i32>(&device, &config, block_frames, ring, logger),
+        other => return Err(format!("unsupported input sample format {:?}", other)),
+    }
+    .map_err(|e| e.to_string())?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(LiveInput { device: device_label, sample_rate: config.sample_rate.0, channels: config.channels, _stream: stream })
+}
+
+#[cfg(feature = "live-input")]
+fn build_input_stream<T: cpal::SizedSample>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    block_frames: usize,
+    ring: Arc<BlockRing>,
+    logger: Logger,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    f32: cpal::FromSample<T>,
+{
+    use cpal::Sample;
+    use cpal::traits::DeviceTrait;
+
+    let mut block = BlockAccumulator::new(block_frames, config.channels as usize);
+    device.build_input_stream(
+        config,
+        move |data: &[T], _| {
+            for &sample in data {
+                if let Some(mean_square) = block.push(f32::from_sample(sample)) {
+                    ring.push(mean_square);
+                }
+            }
+        },
+        move |e| log_error(&logger, &format!("音频输入错误: {}", e)),
+        None,
+    )
+}
+
+#[cfg(not(feature = "live-input"))]
+fn open_input_stream(_device_name: Option<&str>, _step_sec: f64, _ring: Arc<BlockRing>, _logger: Logger) -> Result<LiveInput, String> {
+    Err("built without the live-input feature".to_string())
+}
+
+/// 实时监视模式的一次采集：滚动的电平曲线及 (运行中时的) 输入流；停止后曲线保留，可导出
+struct LiveMonitor {
+    device: String,
+    sample_rate: u32,
+    channels: u16,
+    started: chrono::DateTime<Local>,
+    ring: Arc<BlockRing>,
+    windows: LiveWindows,
+    // 计算曲线时使用的分析参数 (窗口、步进、噪声门限取自当时的设置)
+    analysis: AnalysisSettings,
+    points: VecDeque<[f64; 2]>,
+    frozen: bool,
+    input: Option<LiveInput>,
+}
+
+impl LiveMonitor {
+    fn start(device_name: Option<&str>, analysis: &AnalysisSettings, logger: Logger) -> Result<Self, String> {
+        let ring = Arc::new(BlockRing::new(LIVE_RING_CAPACITY));
+        let windows = LiveWindows::new(analysis);
+        let input = open_input_stream(device_name, windows.step_sec, ring.clone(), logger)?;
+        // 实时曲线不计权、不跳过首尾，各声道的样本一起求 RMS (与交错方式相同)
+        let analysis = AnalysisSettings {
+            downmix: DownmixMode::Interleaved,
+            weighting: CurveWeighting::Flat,
+            window_sec: windows.window_sec(),
+            step_sec: windows.step_sec,
+            skip_head_sec: 0.0,
+            skip_tail_sec: 0.0,
+            ..analysis.clone()
+        };
+        Ok(Self {
+            device: input.device.clone(),
+            sample_rate: input.sample_rate,
+            channels: input.channels,
+            started: Local::now(),
+            ring,
+            windows,
+            analysis,
+            points: VecDeque::new(),
+            frozen: false,
+            input: Some(input),
+        })
+    }
+
+    fn is_running(&self) -> bool {
+        self.input.is_some()
+    }
+
+    /// 关闭输入流，保留已采集的曲线
+    fn stop(&mut self) {
+        self.input = None;
+    }
+
+    /// 取出音频回调送来的全部块并更新曲线；冻结时继续取出 (避免缓冲溢出) 但不追加点。
+    /// 只保留最近 retain_sec 秒
+    fn poll(&mut self, retain_sec: f64) {
+        while let Some(mean_square) = self.ring.pop() {
+            if let Some(point) = self.windows.push(mean_square)
+                && !self.frozen
+            {
+                self.points.push_back(point);
+            }
+        }
+        let Some(&[last, _]) = self.points.back() else { return };
+        while self.points.front().is_some_and(|p| p[0] < last - retain_sec) {
+            self.points.pop_front();
+        }
+    }
+
+    fn dropped_blocks(&self) -> usize {
+        self.ring.dropped.load(Ordering::Relaxed)
+    }
+
+    /// 当前曲线作为 AudioCurve (可添加到单文件列表或导出 CSV)
+    fn to_curve(&self) -> AudioCurve {
+        let name = format!("Live {} {}", self.device, self.started.format("%Y%m%d-%H%M%S"));
+        let points: Vec<[f64; 2]> = self.points.iter().copied().collect();
+        let duration = points.last().map_or(0.0, |p| p[0] + self.windows.window_sec() / 2.0);
+        let mut curve = AudioCurve::new(name, points, duration, SILENCE_FLOOR_DBFS);
+        curve.measure_points(&self.analysis);
+        curve.analysis = Some(self.analysis.clone());
+        curve
+    }
+}
+
+
+// --- GUI 应用程序结构 ---
+
+/// 命令历史在 eframe::Storage 中的键及最大保存条数
+const CMD_HISTORY_KEY: &str = "cmd_history";
+const CMD_HISTORY_CAP: usize = 200;
+/// 控制台输出区保留的命令条数
+const CONSOLE_OUTPUT_CAP: usize = 200;
+/// 标注 (按曲线键分组) 在 eframe::Storage 中的键
+const ANNOTATIONS_KEY: &str = "annotations";
+/// 曲线重命名及标签 (按曲线键) 在 eframe::Storage 中的键
+const CURVE_LABELS_KEY: &str = "curve_labels";
+/// 单文件图表绘制顺序 (曲线键列表，最下层在前) 在 eframe::Storage 中的键
+const CURVE_ORDER_KEY: &str = "curve_order";
+
+/// AppState::on_worker_message 不在状态层处理、交回 UI 层的后续动作 (需要工作池或音频设备)
+enum StateEffect {
+    // 批量对比的参考曲线就绪，派发候选任务
+    BatchReference(Arc<AudioCurve>),
+    BatchResult(BatchRow),
+    Playback(PlaybackBuffer),
+    // 浏览器中选择的文件已读入内存，由 App 启动加载任务
+    #[cfg(target_arch = "wasm32")]
+    FilePicked(String, Arc<[u8]>, Option<char>),
+}
+
+/// 应用状态：曲线、对比插槽与结果、任务簿记及设置。
+/// 状态转换 (任务结束、曲线到达、对比、清空列表) 都在这里完成，egui 层只负责绘制并转发用户操作，
+/// 因此不开窗口也能测试
+struct AppState {
+    settings: AppSettings,
+    logger: Logger,
+    notifications: Notifications,
+    error_msg: Option<String>,
+
+    // 与 WorkerPool 共享的任务列表
+    tasks: Arc<Mutex<Vec<AudioTask>>>,
+    loading: bool,
+    task_batch: Option<TaskBatch>,
+    // 一批任务完成后的摘要及显示开始时间
+    status_flash: Option<(String, Instant)>,
+    // 加载任务的来源 (用于重试) 及单文件任务产生的曲线 (curve_key)，供进程监视器定位结果
+    task_sources: HashMap<usize, TaskSource>,
+    task_curves: HashMap<usize, String>,
+    // 加载任务的耗时统计 (任务 ID -> 统计)，整个会话累计
+    task_perf: BTreeMap<usize, TaskPerf>,
+    // 清空列表时仍在加载的单文件任务，它们迟到的曲线直接丢弃
+    discarded_tasks: HashSet<usize>,
+
+    // 单文件模式数据
+    single_files: Arc<Mutex<Vec<AudioCurve>>>,
+    // single_files 每次增删都会递增，用于判断统计表排序缓存是否过期
+    curves_revision: u64,
+    draw_order: DrawOrder,
+    // 上次保存的绘制顺序，重新加载同一批文件时恢复
+    saved_order: Vec<String>,
+    // 曲线键 -> 该曲线的标注 (按时间排序)，随会话持久化，重新加载同一文件时恢复
+    annotations: BTreeMap<String, Vec<Annotation>>,
+    curve_labels: BTreeMap<String, CurveLabel>,
+
+    // 对比模式数据
+    compare_a: Option<AudioCurve>,
+    compare_b: Option<AudioCurve>,
+    compare_loads: [Option<SlotLoad>; 2], // Track A / B 当前 (或最近失败) 的加载任务
+    // 用 Arc 共享，绘制时每帧只复制指针
+    compare_result: Option<Arc<ComparisonResult>>,
+    // compare_result 对应的输入；插槽曲线替换后清空
+    compare_result_key: Option<ComparisonKey>,
+    // 插槽曲线或对比结果每次变化都会递增，用于判断对比图缓存是否过期
+    compare_revision: u64,
+    // ⭐ 新增: 目标平均差值 (Target Mean Difference)
+    target_mean_diff: f32,
+    // 对比前 Track B 的时间平移 (秒)，用于手动对齐起点不同的两段录音
+    compare_offset: f32,
+    // Track A 使用合成参考时的定义；compare_a 随 Track B 按其步进重新生成
+    synthetic_a: Option<SyntheticReference>,
+}
+
+impl AppState {
+    fn new(settings: AppSettings, logger: Logger, tasks: Arc<Mutex<Vec<AudioTask>>>) -> Self {
+        Self {
+            settings,
+            logger,
+            notifications: Notifications::default(),
+            error_msg: None,
+            tasks,
+            loading: false,
+            task_batch: None,
+            status_flash: None,
+            task_sources: HashMap::new(),
+            task_curves: HashMap::new(),
+            task_perf: BTreeMap::new(),
+            discarded_tasks: HashSet::new(),
+            single_files: Arc::new(Mutex::new(Vec::new())),
+            curves_revision: 0,
+            draw_order: DrawOrder::default(),
+            saved_order: Vec::new(),
+            annotations: BTreeMap::new(),
+            curve_labels: BTreeMap::new(),
+            compare_a: None,
+            compare_b: None,
+            compare_loads: [None, None],
+            compare_result: None,
+            compare_result_key: None,
+            compare_revision: 0,
+            // ⭐ 初始化目标差值为 0.0 (默认为检查绝对匹配)
+            target_mean_diff: 0.0,
+            compare_offset: 0.0,
+            synthetic_a: None,
+        }
+    }
+
+    /// 处理工作线程发来的一条消息
+    fn on_worker_message(&mut self, msg: WorkerMessage, lang: &Lang) -> Option<StateEffect> {
+        match msg {
+            WorkerMessage::Log(entry) => {
+                if let Ok(mut entries) = self.logger.entries.lock() {
+                    entries.push(entry);
+                }
+            }
+            WorkerMessage::UpdateTaskState(id, state) => {
+                // 工作线程总在发送结果之后才发送兜底的 Completed，此后不会再有该任务的曲线
+                if matches!(state, TaskState::Completed) {
+                    self.discarded_tasks.remove(&id);
+                }
+                let slot_load_ended = matches!(state, TaskState::Error(_) | TaskState::Killed)
+                    && self.compare_loads.iter().flatten().any(|l| l.task_id == id);
+                if let Ok(mut tasks) = self.tasks.lock()
+                    && let Some(task) = tasks.iter_mut().find(|t| t.id == id)
+                    && !task.state.is_finished() // 兜底发送的 Completed 不覆盖 Error/Killed
+                {
+                    task.state = state.clone();
+
+                    if let TaskState::Error(e) = state {
+                        let err_msg = lang.task_error_fmt.replacen("{}", &id.to_string(), 1).replacen("{}", &e.text_with_hint(lang), 1);
+                        self.notifications.push(ToastLevel::Error, err_msg.clone());
+                        self.error_msg = Some(err_msg);
+                    }
+                }
+                self.refresh_loading();
+                // 插槽加载失败或被取消后，仍可用两边现有的曲线对比
+                if slot_load_ended {
+                    self.request_compare(lang);
+                }
+            }
+            WorkerMessage::NewCurve(curve, slot_opt, task_id) => {
+                let mut curve = *curve;
+                // 恢复该文件之前的重命名和标签
+                if let Some(label) = self.curve_labels.get(&curve_key(&curve)) {
+                    if let Some(name) = &label.name {
+                        curve.name = name.clone();
+                    }
+                    curve.tags = label.tags.clone();
+                }
+                if slot_opt == Some('R') {
+                    return Some(StateEffect::BatchReference(Arc::new(curve)));
+                } else if let Some(slot) = slot_opt {
+                    // 对比模式结果：只接受插槽当前任务的结果 (已取消或被替换的任务直接丢弃)
+                    let load = &mut self.compare_loads[slot_index(slot)];
+                    if load.as_ref().is_none_or(|l| l.task_id != task_id) {
+                        log_debug(&self.logger, &format!("丢弃任务 {} 的过期结果 (Track {})", task_id, slot));
+                        return None;
+                    }
+                    *load = None;
+                    if slot == 'A' {
+                        // 加载文件即不再使用合成参考
+                        self.synthetic_a = None;
+                        self.compare_a = Some(curve);
+                    } else if slot == 'B' {
+                        self.compare_b = Some(curve);
+                        self.regenerate_synthetic(lang);
+                    }
+                    self.compare_result_key = None;
+                    self.compare_revision += 1;
+
+                    if self.compare_a.is_some() && self.compare_b.is_some() {
+                        self.request_compare(lang);
+                    }
+                } else if self.discarded_tasks.contains(&task_id) {
+                    log_debug(&self.logger, &format!("丢弃任务 {} 的结果 (文件列表已清空)", task_id));
+                } else if let Some(TaskSource::Reanalyze(path)) = self.task_sources.get(&task_id) {
+                    self.replace_reanalyzed(task_id, path.clone(), curve);
+                } else {
+                    // 单文件模式结果 (也可能来自控制台 `load`，此时不在单文件模式)
+                    // 记录任务产生的曲线，供进程监视器中单击任务时定位
+                    self.task_curves.insert(task_id, curve_key(&curve));
+                    self.warn_if_duplicate(&curve, lang);
+                    self.add_single_curve(curve);
+                }
+            }
+            WorkerMessage::BatchResult(row) => return Some(StateEffect::BatchResult(row)),
+            WorkerMessage::PlaybackReady(buffer) => return Some(StateEffect::Playback(buffer)),
+            #[cfg(target_arch = "wasm32")]
+            WorkerMessage::FilePicked(name, bytes, slot) => return Some(StateEffect::FilePicked(name, bytes, slot)),
+            WorkerMessage::TaskPerf(id, perf) => {
+                log_debug(&self.logger, &format!("任务 {} 耗时: {} 解码 {:.1} ms, 加窗 {:.1} ms", id, perf.file, perf.timing.decode_sec * 1000.0, perf.timing.windowing_sec * 1000.0));
+                self.task_perf.insert(id, perf);
+            }
+        }
+        None
+    }
+
+    /// 重新分析的结果原位替换列表中同一文件的曲线，保留名称、标签和显示设置；曲线已被移除时丢弃
+    fn replace_reanalyzed(&mut self, task_id: usize, path: PathBuf, mut curve: AudioCurve) {
+        let mut files = self.single_files.lock().unwrap();
+        let Some(old) = files.iter_mut().find(|c| c.source.as_ref() == Some(&path)) else {
+            log_debug(&self.logger, &format!("丢弃任务 {} 的重新分析结果 (曲线已移除): {}", task_id, path.display()));
+            return;
+        };
+        curve.name = std::mem::take(&mut old.name);
+        curve.tags = std::mem::take(&mut old.tags);
+        curve.show_raw = old.show_raw;
+        *old = curve;
+        drop(files);
+        self.task_curves.insert(task_id, path.display().to_string());
+        self.curves_revision += 1;
+        log_info(&self.logger, &format!("已按当前参数重新分析: {}", path.display()));
+    }
+
+    /// 新加载的曲线与列表中已有的曲线内容相同 (同一内容以不同文件名加载了两次) 时记录警告
+    fn warn_if_duplicate(&mut self, curve: &AudioCurve, lang: &Lang) {
+        let Ok(files) = self.single_files.lock() else { return };
+        let hash = curve.content_hash();
+        if let Some(original) = files.iter().find(|c| c.content_hash() == hash) {
+            log_error(&self.logger, &format!("⚠️ {} 与 {} 内容相同 (重复加载)", curve.name, original.name));
+            self.notifications.push(ToastLevel::Warning, lang.duplicate_loaded_fmt.replacen("{}", &curve.name, 1).replacen("{}", &original.name, 1));
+        }
+    }
+
+    /// 把曲线加入单文件列表 (按保存的绘制顺序放置)
+    fn add_single_curve(&mut self, curve: AudioCurve) {
+        let Ok(mut files) = self.single_files.lock() else { return };
+        files.push(curve);
+        let keys: Vec<String> = files.iter().map(curve_key).collect();
+        self.draw_order.sync(files.len() - 1);
+        self.draw_order.insert(files.len() - 1, &keys, &self.saved_order);
+        self.curves_revision += 1;
+    }
+
+    /// 当前会话的副本，交给自动保存线程写入恢复文件
+    fn snapshot(&self) -> SessionSnapshot {
+        SessionSnapshot {
+            saved_at: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            single_files: self.single_files.lock().map(|files| files.clone()).unwrap_or_default(),
+            compare_a: self.compare_a.clone(),
+            compare_b: self.compare_b.clone(),
+            compare_offset: self.compare_offset,
+            target_mean_diff: self.target_mean_diff,
+        }
+    }
+
+    fn session_key(&self) -> SessionKey {
+        (self.curves_revision, self.compare_revision, self.compare_offset.to_bits(), self.target_mean_diff.to_bits())
+    }
+
+    /// 恢复自动保存的会话：曲线追加到单文件列表，对比插槽被替换后重新对比
+    fn restore(&mut self, session: SessionSnapshot, lang: &Lang) {
+        for curve in session.single_files {
+            self.add_single_curve(curve);
+        }
+        if session.compare_a.is_some() || session.compare_b.is_some() {
+            // 会话中的 Track A 已是普通曲线，不再随 Track B 重新生成
+            self.synthetic_a = None;
+            self.compare_a = session.compare_a;
+            self.compare_b = session.compare_b;
+            self.compare_offset = session.compare_offset;
+            self.target_mean_diff = session.target_mean_diff;
+            self.compare_result_key = None;
+            self.compare_revision += 1;
+            if self.compare_a.is_some() && self.compare_b.is_some() {
+                self.request_compare(lang);
+            }
+        }
+    }
+
+    /// 用合成参考作为 Track A (None 时停止使用并清空 Track A)，按 Track B 的步进生成后重新对比
+    fn set_synthetic_reference(&mut self, reference: Option<SyntheticReference>, lang: &Lang) {
+        match &reference {
+            Some(reference) => log_info(&self.logger, &format!("Track A 使用合成参考: {}", synthetic_description(reference, lang))),
+            None => log_info(&self.logger, "Track A 不再使用合成参考"),
+        }
+        let stopped = reference.is_none() && self.synthetic_a.is_some();
+        self.synthetic_a = reference;
+        if stopped {
+            self.compare_a = None;
+            self.compare_result = None;
+            self.compare_result_key = None;
+            self.compare_revision += 1;
+        }
+        self.regenerate_synthetic(lang);
+        self.request_compare(lang);
+    }
+
+    /// 使用合成参考时按 Track B 当前的曲线重新生成 Track A；还没有 Track B 时 Track A 为空
+    fn regenerate_synthetic(&mut self, lang: &Lang) {
+        let Some(reference) = &self.synthetic_a else { return };
+        let name = lang.synthetic_name_fmt.replacen("{}", &synthetic_description(reference, lang), 1);
+        self.compare_a = self.compare_b.as_ref().and_then(|b| reference.curve_like(b, name));
+        self.compare_result_key = None;
+        self.compare_revision += 1;
+    }
+
+    /// 按任务列表重新计算 loading，避免任务异常结束后 loading 一直为 true
+    fn refresh_loading(&mut self) {
+        if let Ok(tasks) = self.tasks.lock() {
+            self.loading = tasks.iter().any(|t| !t.state.is_finished());
+        }
+    }
+
+    /// 每帧根据任务列表刷新 loading 标志和状态栏的任务批次；批次全部结束后显示摘要并返回 true
+    fn update_task_batch(&mut self, lang: &Lang) -> bool {
+        self.refresh_loading();
+        let Ok(tasks) = self.tasks.lock() else { return false; };
+        let mut finished = false;
+
+        if self.task_batch.is_none()
+            && let Some(first) = tasks.iter().filter(|t| !t.state.is_finished()).map(|t| t.id).min()
+        {
+            self.task_batch = Some(TaskBatch { first_id: first, started: Instant::now() });
+        }
+
+        if let Some(batch) = &self.task_batch
+            && tasks.iter().filter(|t| t.id >= batch.first_id).all(|t| t.state.is_finished())
+        {
+            let total = tasks.iter().filter(|t| t.id >= batch.first_id).count();
+            let seconds = format!("{:.0}", batch.started.elapsed().as_secs_f64());
+            let summary = lang.status_done_fmt
+                .replacen("{}", &total.to_string(), 1)
+                .replacen("{}", &seconds, 1);
+            self.notifications.push(ToastLevel::Info, summary.clone());
+            self.status_flash = Some((summary, Instant::now()));
+            self.task_batch = None;
+            finished = true;
+        }
+        drop(tasks);
+
+        if self.status_flash.as_ref().is_some_and(|(_, shown)| shown.elapsed() > STATUS_FLASH_DURATION) {
+            self.status_flash = None;
+        }
+        finished
+    }
+
+    /// 当前批次的 (已结束数, 总数, 最近启动的进行中任务名)
+    fn batch_progress(&self) -> Option<(usize, usize, String)> {
+        let batch = self.task_batch.as_ref()?;
+        let tasks = self.tasks.lock().ok()?;
+        let in_batch = || tasks.iter().filter(|t| t.id >= batch.first_id);
+        let total = in_batch().count();
+        let done = in_batch().filter(|t| t.state.is_finished()).count();
+        let current = in_batch()
+            .filter(|t| !t.state.is_finished())
+            .max_by_key(|t| t.id)
+            .map(|t| t.name.clone())
+            .unwrap_or_default();
+        Some((done, total, current))
+    }
+
+    /// 对比插槽仍有未结束的加载任务
+    fn compare_slot_pending(&self) -> bool {
+        let Ok(tasks) = self.tasks.lock() else { return false };
+        self.compare_loads.iter().flatten().any(|load| tasks.iter().any(|t| t.id == load.task_id && !t.state.is_finished()))
+    }
+
+    /// 用 Track A / B 当前的曲线运行对比
+    fn request_compare(&mut self, lang: &Lang) {
+        // 重新选择文件期间插槽里仍是旧曲线，等新文件加载完成再对比
+        if self.compare_slot_pending() {
+            log_debug(&self.logger, "对比插槽仍在加载，暂不运行对比");
+            return;
+        }
+        if let (Some(a), Some(b)) = (&self.compare_a, &self.compare_b) {
+            // 1. 检查时间长度
+            if !durations_comparable(a, b) {
+                let final_err_msg = lang.format_duration_error(a.duration, b.duration);
+
+                log_error(&self.logger, &format!("⚠️ 对比失败: {}", final_err_msg));
+                self.notifications.push(ToastLevel::Error, final_err_msg.clone());
+                self.error_msg = Some(final_err_msg);
+                self.compare_result = None;
+                self.compare_result_key = None;
+                self.compare_revision += 1;
+                return;
+            }
+
+            // 2. 输入未变时复用上一次的逐点对比 (如取消插槽加载后、控制台 `compare`)
+            let key = ComparisonKey {
+                a: curve_key(a),
+                b: curve_key(b),
+                offset: self.compare_offset,
+                level_match: self.settings.compare_level_match,
+            };
+            if self.compare_result.is_some() && self.compare_result_key.as_ref() == Some(&key) {
+                log_debug(&self.logger, "对比输入未变，复用缓存的对比结果");
+                self.error_msg = None;
+                return;
+            }
+
+            // 3. 逐点计算差值与统计量
+            log_debug(&self.logger, &format!("对比点数: {}, Track B 偏移: {:+.3}s", a.points.len().min(b.points.len()), self.compare_offset));
+            // 电平匹配时各自减去平均电平后再求差，差值只反映动态差异
+            let compared = compare(a, b, &CompareOptions {
+                offset: self.compare_offset as f64,
+                target_diff: self.target_mean_diff as f64,
+                level_match: self.settings.compare_level_match,
+                // 导出对比数据表时直接使用配对后的电平
+                keep_aligned: true,
+            });
+            // 偏移超过曲线时长时没有可对比的点，不保存 (NaN 的) 结果
+            let Some(result) = compared else {
+                let final_err_msg = lang.format_no_overlap_error(self.compare_offset as f64);
+                log_error(&self.logger, &format!("⚠️ 对比失败: {}", final_err_msg));
+                self.notifications.push(ToastLevel::Error, final_err_msg.clone());
+                self.error_msg = Some(final_err_msg);
+                self.compare_result = None;
+                self.compare_result_key = None;
+                self.compare_revision += 1;
+                return;
+            };
+
+            log_info(&self.logger, &format!("✅ 对比完成。 Mean Diff: {:.2} dB, Std Dev: {:.4}", result.mean_diff, result.std_dev));
+            if self.settings.is_partial_overlap(result.overlap_ratio) {
+                log_error(&self.logger, &format!(
+                    "⚠️ 对比仅覆盖 {:.1}% 的时长 (最低要求 {:.0}%)，结果为部分对比。",
+                    result.overlap_ratio * 100.0, self.settings.min_overlap_percent
+                ));
+            }
+            log_debug(&self.logger, &format!("Correlation (r): {:.4}, T-Stat: {:.2}", result.correlation_coefficient, result.t_statistic));
+            if let (Some(used_a), Some(used_b)) = (&a.analysis, &b.analysis)
+                && !used_a.same_results(used_b)
+            {
+                log_error(&self.logger, &format!("⚠️ Track A 与 B 使用不同的分析参数计算: A {:?} / B {:?}", used_a, used_b));
+            }
+            if a.profile != b.profile {
+                log_error(&self.logger, &format!("⚠️ Track A 与 B 使用不同的分析配置: A {:?} / B {:?}", a.profile, b.profile));
+                self.notifications.push(ToastLevel::Warning, lang.profile_mixed_compare_fmt
+                    .replacen("{}", a.profile.as_deref().unwrap_or(lang.profile_none), 1)
+                    .replacen("{}", b.profile.as_deref().unwrap_or(lang.profile_none), 1));
+            }
+            if result.resampled {
+                log_info(&self.logger, &format!(
+                    "ℹ️ 两条曲线的时间基不同 (点间隔 A {:.3}s, B {:.3}s)，已重采样到共同的时间点后对比。",
+                    median_spacing(&a.points).unwrap_or(f64::NAN), median_spacing(&b.points).unwrap_or(f64::NAN)
+                ));
+            }
+
+            let verdict = self.settings.verdict(result.std_dev);
+            let level = if verdict == Verdict::HighMatch { ToastLevel::Success } else { ToastLevel::Warning };
+            self.notifications.push(level, format!("{} ↔ {}: {}", a.name, b.name, lang.verdict_label(verdict)));
+
+            self.compare_result = Some(Arc::new(result));
+            self.compare_result_key = Some(key);
+            self.compare_revision += 1;
+            self.error_msg = None;
+        } else {
+            log_error(&self.logger, "⚠️ 对比失败: 缺少 Track A 或 Track B。");
+        }
+    }
+
+    /// 当前列表中曲线的标注总数
+    fn loaded_annotation_count(&self) -> usize {
+        self.single_files.lock().unwrap().iter()
+            .filter_map(|curve| self.annotations.get(&curve_key(curve)))
+            .map(Vec::len)
+            .sum()
+    }
+
+    /// 移除内容与更早加载的曲线相同的曲线，各组只保留最早的一条；返回移除的条数。
+    /// 被移除曲线的标注在没有保留的曲线使用同一曲线键时一并删除
+    fn remove_duplicate_curves(&mut self) -> usize {
+        let mut files = self.single_files.lock().unwrap();
+        let duplicates = find_duplicates(&files);
+        let mut removed = 0;
+        for index in (0..files.len()).rev() {
+            let Some(original) = duplicates[index] else { continue };
+            let curve = files.remove(index);
+            log_info(&self.logger, &format!("移除重复曲线: {} (与 {} 相同)", curve.name, files[original].name));
+            let key = curve_key(&curve);
+            if !files.iter().any(|c| curve_key(c) == key) {
+                self.annotations.remove(&key);
+            }
+            self.draw_order.remove(index);
+            removed += 1;
+        }
+        if removed > 0 {
+            self.curves_revision += 1;
+        }
+        removed
+    }
+
+    /// 清空单文件列表，同时删除这些曲线的标注。
+    /// 返回仍在为列表加载曲线的任务，由 UI 层取消；这些任务之后送达的曲线会被丢弃
+    fn clear_single_files(&mut self) -> Vec<usize> {
+        let mut files = self.single_files.lock().unwrap();
+        for curve in files.iter() {
+            self.annotations.remove(&curve_key(curve));
+        }
+        files.clear();
+        drop(files);
+        self.draw_order.clear();
+        self.curves_revision += 1;
+        log_info(&self.logger, "文件列表已清空。");
+
+        let Ok(tasks) = self.tasks.lock() else { return Vec::new() };
+        let pending: Vec<usize> = tasks.iter()
+            .filter(|t| !t.state.is_finished())
+            .filter(|t| self.task_sources.get(&t.id).is_some_and(|source| !source.is_slot()))
+            .map(|t| t.id)
+            .collect();
+        self.discarded_tasks.extend(&pending);
+        pending
+    }
+}
+
+struct WavLufsApp {
+    mode: AppMode,
+    lang: Lang,
+    current_lang: Language,
+    cjk_font: CjkFontState,
+
+    // 全局日志系统
+    logger: Logger,
+
+    // 曲线、对比、任务簿记及设置等可测试的状态
+    state: AppState,
+
+    // 异步工作池
+    worker_pool: WorkerPool,
+    // 显示完整错误信息的失败任务
+    task_error_popup: Option<usize>,
+    ui_tx: mpsc::Sender<WorkerMessage>,
+    ui_rx: mpsc::Receiver<WorkerMessage>, // Worker -> UI 消息接收端
+
+    // 命令行相关
+    cmd_input: String,
+    // 单文件模式 "从 URL 加载" 输入框
+    url_input: String,
+    log_filter: LogFilter,
+    cmd_history: Vec<String>,     // 持久化的命令历史 (最旧在前)
+    console_output: Vec<ConsoleEcho>, // 控制台输出区 (最新在后)
+    history_cursor: Option<usize>, // 上/下键浏览历史时的位置
+
+    // 单机模式数据
+    target_lufs: f32,
+    // 峰值归一化时最响窗口的目标电平
+    peak_target: f32,
+    // 单文件模式中键盘聚焦的曲线，其余曲线变暗
+    focused_index: Option<usize>,
+    stats_table: StatsTable,
+    plot_cache: PlotCache,
+    jump_scan: JumpScan,
+    duplicate_scan: DuplicateScan,
+    frame_stats: FrameStats,
+    single_cursors: MeasureCursors,
+    single_y_axis: YAxisControl,
+    // 曲线列表的行内重命名 / 添加标签
+    curve_edit: Option<CurveEdit>,
+    // 统计表只显示带有该标签的曲线
+    tag_filter: Option<String>,
+    // 交付检查表按积分响度排序 (Some(升序))，None 为加载顺序
+    delivery_sort: Option<bool>,
+    // 统计表和交付检查表只显示未通过交付规格的文件 (单击顶部摘要切换)
+    delivery_failures_only: bool,
+    pending_annotation: Option<PendingAnnotation>,
+    // 清空列表会删除已有标注时，先请求确认
+    confirm_clear: bool,
+    // 文件详情窗口显示的曲线 (curve_key)，曲线被移除后自动关闭
+    details_curve: Option<String>,
+    csv_import: Option<CsvImportDialog>,
+    // 关闭窗口时仍有任务未结束，先请求确认；确认后不再拦截关闭请求
+    confirm_exit: bool,
+    exit_confirmed: bool,
+    // 控制台 `quit` 请求关闭窗口，在下一帧发送关闭命令以复用同一确认流程
+    quit_requested: bool,
+    // 统计表双击行后待应用的图表范围
+    pending_plot_zoom: Option<PlotBounds>,
+    // 点击偏差区间后对比图 (原始曲线图和差值图) 要显示的时间范围
+    pending_compare_zoom: Option<[f64; 2]>,
+    // 正在回放的 WAV (只能回放单文件模式中的曲线)
+    player: Option<Player>,
+    // 会话自动保存：上次保存的时间及会话状态；启动时发现的恢复文件 (等待用户选择恢复或丢弃，期间不自动保存)
+    autosaver: AutoSaver,
+    last_autosave: Instant,
+    autosave_key: Option<SessionKey>,
+    pending_recovery: Option<SessionSnapshot>,
+    // 文件变化后自动重新分析：监视线程及单独勾选监视的文件
+    file_watcher: FileWatcher,
+    watched_files: BTreeSet<PathBuf>,
+    // 实时监视模式的采集及输入设备列表 (首次进入该模式或点击刷新时枚举)
+    live: Option<LiveMonitor>,
+    live_devices: Option<Vec<String>>,
+    show_help_popup: bool, // 新增：控制帮助悬浮窗
+
+    // 对比模式数据
+    compare_plot_cache: ComparePlotCache,
+    confidence_level: f32,
+    // 对比的两个图表共用同一对测量光标 (时间轴相同)
+    compare_cursors: MeasureCursors,
+    compare_y_axis: YAxisControl,
+
+    // 批量对比模式数据
+    batch_ref_path: Option<PathBuf>,
+    batch_folder: Option<PathBuf>,
+    batch_reference: Option<Arc<AudioCurve>>, // 参考曲线加载完成后才派发候选任务
+    batch_total: usize,
+    batch_results: Vec<BatchRow>,
+
+    // 当前已应用的主题及其配色 (用于检测系统主题变化)
+    applied_theme: Option<egui::Theme>,
+    palette: Palette,
+}
+
+impl WavLufsApp {
+    fn new(cc: &eframe::CreationContext<'_>) -> Self {
+        let current_lang = Language::CHINESE;
+        let lang = Lang::load(current_lang);
+
+        // 显式关闭调试功能，避免显示 ID 冲突的调试信息
+
+        // 在 egui 0.27 中，该功能已移至 Context 上的 set_debug_on_hover 方法。
+
+
+        let logger = Logger::new();
+        log_info(&logger, "✅ 应用启动成功。");
+
+        // 语言文件：报告找到的语言和解析失败的文件
+        let registry = locale_registry();
+        let codes: Vec<&str> = registry.locales.iter().map(|locale| locale.code.as_str()).collect();
+        log_info(&logger, &format!("已加载 {} 种界面语言: {}", codes.len(), codes.join(", ")));
+        for error in &registry.errors {
+            log_error(&logger, &format!("⚠️ 语言文件无效: {}", error));
+        }
+
+        // 中日韩界面需要系统 CJK 字体，在运行时探测加载
+        let mut cjk_font = CjkFontState::NotLoaded;
+        cjk_font.ensure_loaded(&cc.egui_ctx, current_lang, &logger);
+
+        // 从 eframe::Storage 恢复上次保存的设置
+        let stored: Option<AppSettings> = cc.storage.and_then(|storage| eframe::get_value(storage, eframe::APP_KEY));
+        if stored.as_ref().is_some_and(|s| !s.power_average_noted) {
+            log_info(&logger, "ℹ️ 行为变更：平均电平 (图例、归一化、CSV 归一化列、对比电平匹配) 现在按各窗口的功率平均计算，\
+                动态大的文件会比旧版本高几 dB。需要旧数值时可在分析参数中勾选 \"旧版 dB 平均\"。");
+        }
+        let mut settings = stored.unwrap_or_default();
+        settings.power_average_noted = true;
+        if settings.schema_version < SCHEMA_VERSION {
+            log_debug(&logger, &format!("设置格式从版本 {} 升级到 {}", settings.schema_version, SCHEMA_VERSION));
+            settings.schema_version = SCHEMA_VERSION;
+        }
+        settings.log_capacity = settings.log_capacity.clamp(*LOG_CAPACITY_RANGE.start(), *LOG_CAPACITY_RANGE.end());
+        if let Ok(mut entries) = logger.entries.lock() {
+            entries.set_capacity(settings.log_capacity);
+        }
+        if settings.log_file {
+            start_log_file(&logger, &settings);
+        }
+        let cmd_history: Vec<String> = cc.storage
+            .and_then(|storage| eframe::get_value(storage, CMD_HISTORY_KEY))
+            .unwrap_or_default();
+        let annotations: BTreeMap<String, Vec<Annotation>> = cc.storage
+            .and_then(|storage| eframe::get_value(storage, ANNOTATIONS_KEY))
+            .unwrap_or_default();
+        let curve_labels: BTreeMap<String, CurveLabel> = cc.storage
+            .and_then(|storage| eframe::get_value(storage, CURVE_LABELS_KEY))
+            .unwrap_or_default();
+        let saved_order: Vec<String> = cc.storage
+            .and_then(|storage| eframe::get_value(storage, CURVE_ORDER_KEY))
+            .unwrap_or_default();
+        cc.egui_ctx.set_zoom_factor(settings.ui_zoom.clamp(*UI_ZOOM_RANGE.start(), *UI_ZOOM_RANGE.end()));
+        // 上次运行没有正常退出时留下的会话
+        #[cfg(not(target_arch = "wasm32"))]
+        let (pending_recovery, autosaver) = {
+            let recovery_path = recovery_file_path();
+            (load_recovery(&recovery_path, &logger), AutoSaver::start(recovery_path, Logger { entries: logger.entries.clone() }))
+        };
+        // 浏览器中没有恢复文件
+        #[cfg(target_arch = "wasm32")]
+        let (pending_recovery, autosaver) = (None, AutoSaver::disabled());
+        let file_watcher = FileWatcher::start(cc.egui_ctx.clone(), &logger);
+
+        // --- 初始化 MPSC 通道和 WorkerPool ---
+        let (ui_tx, ui_rx) = mpsc::channel();
+        let worker_pool = WorkerPool::new(ui_tx.clone());
+
+        let mut state = AppState::new(settings, Logger { entries: logger.entries.clone() }, worker_pool.tasks.clone());
+        state.annotations = annotations;
+        state.curve_labels = curve_labels;
+        state.saved_order = saved_order;
+
+        Self {
+            mode: AppMode::Single,
+            lang,
+            current_lang,
+            cjk_font,
+            state,
+            logger,
+            worker_pool,
+            task_error_popup: None,
+            ui_tx,
+            ui_rx,
+            cmd_input: String::new(),
+            url_input: String::new(),
+            log_filter: LogFilter::default(),
+            cmd_history,
+            console_output: Vec::new(),
+            history_cursor: None,
+            target_lufs: -23.0,
+            peak_target: -1.0,
+            focused_index: None,
+            stats_table: StatsTable::default(),
+            plot_cache: PlotCache::default(),
+            jump_scan: JumpScan::default(),
+            duplicate_scan: DuplicateScan::default(),
+            frame_stats: FrameStats::default(),
+            single_cursors: MeasureCursors::default(),
+            single_y_axis: YAxisControl::default(),
+            curve_edit: None,
+            tag_filter: None,
+            delivery_sort: None,
+            delivery_failures_only: false,
+            pending_annotation: None,
+            confirm_clear: false,
+            details_curve: None,
+            csv_import: None,
+            confirm_exit: false,
+            exit_confirmed: false,
+            quit_requested: false,
+            pending_plot_zoom: None,
+            pending_compare_zoom: None,
+            player: None,
+            autosaver,
+            last_autosave: Instant::now(),
+            autosave_key: None,
+            pending_recovery,
+            file_watcher,
+            watched_files: BTreeSet::new(),
+            live: None,
+            live_devices: None,
+            show_help_popup: false, // 默认关闭
+            compare_plot_cache: ComparePlotCache::default(),
+            confidence_level: 0.95,
+            compare_cursors: MeasureCursors::default(),
+            compare_y_axis: YAxisControl::default(),
+            batch_ref_path: None,
+            batch_folder: None,
+            batch_reference: None,
+            batch_total: 0,
+            batch_results: Vec::new(),
+            applied_theme: None,
+            palette: Palette::for_theme(egui::Theme::Dark),
+        }
+    }
+
+    /// 根据设置应用主题；仅在实际主题变化时调用 set_visuals
+    fn apply_theme(&mut self, ctx: &egui::Context) {
+        let theme = self.state.settings.theme.resolve(ctx);
+        if self.applied_theme != Some(theme) {
+            log_debug(&self.logger, &format!("应用主题: {:?}", theme));
+            ctx.set_theme(theme);
+            ctx.set_visuals(theme.default_visuals());
+            self.palette = Palette::for_theme(theme);
+            self.applied_theme = Some(theme);
+        }
+        // 配色方案可随时修改，每帧覆盖主题默认的 Track 颜色
+        self.palette.set_track_colors(theme, self.state.settings.track_colors, self.state.settings.track_custom_colors);
+    }
+
+
+    /// 启动单文件模式的后台加载任务 (文件对话框和控制台 `load` 共用)
+    fn spawn_load_tasks(&mut self, paths: Vec<PathBuf>) {
+        log_info(&self.logger, &format!("选中文件数: {}", paths.len()));
+        self.spawn_file_tasks(paths, TaskSource::File);
+    }
+
+    /// 用当前分析参数重新分析单文件列表中的曲线，结果到达后原位替换
+    fn spawn_reanalyze_tasks(&mut self, paths: Vec<PathBuf>) {
+        self.spawn_file_tasks(paths, TaskSource::Reanalyze);
+    }
+
+    /// 为每个文件启动一个 load_file 任务，结果按单文件模式的曲线返回；`source` 决定结果是追加还是替换
+    fn spawn_file_tasks(&mut self, paths: Vec<PathBuf>, source: fn(PathBuf) -> TaskSource) {
+        self.state.loading = true;
+        self.state.error_msg = None;
+
+        let logger_ref = &self.logger;
+        let ui_result_tx_base = self.ui_tx.clone();
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+
+        for path in paths {
+            let filename = file_display_name(&path);
+            let task_ui_tx = ui_result_tx_base.clone();
+            let analysis = analysis.clone();
+            let profile = self.state.settings.profile_for(&path).cloned();
+            let source = source(path.clone());
+            let task_name = match source {
+                TaskSource::Reanalyze(_) => format!("Reanalyze: {}", filename),
+                _ => filename.clone(),
+            };
+
+            let task_id = self.worker_pool.spawn_task(
+                task_name,
+                move |task_id, ui_tx_clone, logger_entries, cancel| { // 注意: ui_tx_clone 是正确的变量名
+                    let thread_logger = Logger { entries: logger_entries };
+
+                    // 实际的文件加载逻辑 (文件被占用时自动重试)
+                    match load_with_profile(path, &analysis, profile.as_ref(), &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                        Ok(curve) => {
+                            // 任务成功，将结果发送回主 UI 线程
+                            send_task_perf(&ui_tx_clone, task_id, &curve);
+                            ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), None, task_id)).unwrap_or_default();
+                        }
+                        Err(e) => {
+                            // 任务失败，发送错误状态
+                            send_load_error(&ui_tx_clone, &thread_logger, task_id, &filename, e, current_lang);
+                        }
+                    }
+                },
+                task_ui_tx,
+                logger_ref
+            );
+            self.state.task_sources.insert(task_id, source);
+        }
+    }
+
+    /// 用当前分析参数重新分析所有已加载的曲线 (单文件列表和对比插槽)；没有原始音频的 CSV 曲线跳过并提示
+    fn reanalyze_all(&mut self) {
+        let mut skipped = Vec::new();
+        let paths: Vec<PathBuf> = self.state.single_files.lock().unwrap().iter()
+            .filter_map(|curve| {
+                if curve.source.is_none() {
+                    skipped.push(curve.name.clone());
+                }
+                curve.source.clone()
+            })
+            .collect();
+        let synthetic = self.state.synthetic_a.is_some();
+        let slots: Vec<(PathBuf, char)> = [('A', &self.state.compare_a), ('B', &self.state.compare_b)].into_iter()
+            .filter_map(|(slot, curve)| {
+                // 合成参考随 Track B 重新生成，不需要重新分析
+                let curve = curve.as_ref().filter(|_| !(slot == 'A' && synthetic))?;
+                if curve.source.is_none() {
+                    skipped.push(curve.name.clone());
+                }
+                Some((curve.source.clone()?, slot))
+            })
+            .collect();
+        log_command(&self.logger, &format!("按当前参数重新分析 {} 条曲线", paths.len() + slots.len()));
+        if !skipped.is_empty() {
+            let note = self.lang.analysis_reanalyze_skipped_fmt // I18N
+                .replacen("{}", &skipped.len().to_string(), 1)
+                .replacen("{}", &skipped.join(", "), 1);
+            log_info(&self.logger, &note);
+            self.state.notifications.push(ToastLevel::Warning, note);
+        }
+        if !paths.is_empty() {
+            self.spawn_reanalyze_tasks(paths);
+        }
+        for (path, slot) in slots {
+            self.spawn_compare_load(path, slot);
+        }
+    }
+
+    /// 需要监视的来源文件：开启全局自动重新分析时为全部已加载的文件，否则为单独勾选的文件
+    fn watched_paths(&self) -> BTreeSet<PathBuf> {
+        let files = self.state.single_files.lock().unwrap();
+        files.iter().chain(self.state.compare_a.as_ref()).chain(self.state.compare_b.as_ref())
+            .filter_map(|curve| curve.source.clone())
+            .filter(|path| self.state.settings.auto_reload_all || self.watched_files.contains(path))
+            .collect()
+    }
+
+    /// 被监视的文件在磁盘上变化：重新分析使用它的单文件曲线及对比插槽 (插槽曲线到达后自动重新对比)
+    fn reload_changed_file(&mut self, path: PathBuf) {
+        let name = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        log_info(&self.logger, &format!("🔄 文件已更改，重新分析: {}", path.display()));
+        self.state.notifications.push(ToastLevel::Info, self.lang.watch_reloaded_fmt.replacen("{}", &name, 1));
+        let in_single = self.state.single_files.lock().unwrap().iter().any(|c| c.source.as_ref() == Some(&path));
+        let slots: Vec<char> = [('A', &self.state.compare_a), ('B', &self.state.compare_b)].into_iter()
+            .filter(|(_, curve)| curve.as_ref().and_then(|c| c.source.as_ref()) == Some(&path))
+            .map(|(slot, _)| slot)
+            .collect();
+        if in_single {
+            self.spawn_reanalyze_tasks(vec![path.clone()]);
+        }
+        for slot in slots {
+            self.spawn_compare_load(path.clone(), slot);
+        }
+    }
+
+    /// 按导入对话框中选择的列布局在后台解析 CSV 响度日志
+    fn spawn_csv_import(&mut self, path: PathBuf, layout: CsvLayout) {
+        self.state.loading = true;
+        self.state.error_msg = None;
+        let filename = path.file_name().unwrap_or_default().to_string_lossy().to_string();
+        let current_lang = self.current_lang;
+        let source = TaskSource::Csv(path.clone(), layout);
+        let analysis = self.state.settings.analysis.clone();
+
+        let task_id = self.worker_pool.spawn_task(
+            filename.clone(),
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                match parse_csv_with_layout(path, &layout, &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                    Ok(mut curve) => {
+                        curve.measure_points(&analysis);
+                        send_task_perf(&ui_tx_clone, task_id, &curve);
+                        ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), None, task_id)).unwrap_or_default();
+                    }
+                    Err(e) => {
+                        send_load_error(&ui_tx_clone, &thread_logger, task_id, &filename, e, current_lang);
+                    }
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+        self.state.task_sources.insert(task_id, source);
+    }
+
+    /// 选择 CSV 响度日志并预览，打开列选择对话框
+    fn open_csv_import(&mut self) {
+        let Some(path) = FileDialog::new().add_filter("CSV / TXT", &["csv", "txt"]).pick_file() else { return };
+        match preview_csv(&path, CSV_PREVIEW_ROWS) {
+            Ok(preview) => {
+                log_debug(&self.logger, &format!("CSV 预览 {}: {:?}", path.display(), preview.layout));
+                self.csv_import = Some(CsvImportDialog { path, layout: preview.layout, preview });
+            }
+            Err(e) => {
+                let err_msg = TaskError::load(&path.file_name().unwrap_or_default().to_string_lossy(), e).text_with_hint(&self.lang);
+                log_error(&self.logger, &err_msg);
+                self.state.notifications.push(ToastLevel::Error, err_msg);
+            }
+        }
+    }
+
+    /// CSV 导入对话框：下拉选择时间列 / 电平列，预览表中高亮所选列
+    fn show_csv_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.csv_import else { return };
+        let lang = &self.lang;
+        let column_name = |i: usize| match dialog.preview.headers.get(i) {
+            Some(name) if !name.is_empty() => format!("#{} {}", i + 1, name),
+            _ => format!("#{}", i + 1),
+        };
+        let columns = dialog.preview.headers.len();
+        let delimiter = match dialog.layout.delimiter {
+            b'\t' => "Tab".to_string(),
+            d => format!("'{}'", d as char),
+        };
+        let (mut import, mut cancel) = (false, false);
+        egui::Window::new(format!("{} — {}", lang.csv_import_title, dialog.path.file_name().unwrap_or_default().to_string_lossy())) // I18N
+            .id(egui::Id::new("csv_import_window"))
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(lang.csv_import_detected_fmt // I18N
+                    .replacen("{}", &delimiter, 1)
+                    .replacen("{}", &dialog.layout.skip_lines.to_string(), 1));
+                let layout = &mut dialog.layout;
+                egui::Grid::new("csv_import_columns").num_columns(2).show(ui, |ui| {
+                    ui.label(lang.csv_import_time_column); // I18N
+                    egui::ComboBox::from_id_salt("csv_time_column")
+                        .selected_text(column_name(layout.time_column))
+                        .show_ui(ui, |ui| {
+                            for i in 0..columns {
+                                ui.selectable_value(&mut layout.time_column, i, column_name(i));
+                            }
+                        });
+                    ui.end_row();
+                    ui.label(lang.csv_import_value_column); // I18N
+                    egui::ComboBox::from_id_salt("csv_value_column")
+                        .selected_text(column_name(layout.value_column))
+                        .show_ui(ui, |ui| {
+                            for i in 0..columns {
+                                ui.selectable_value(&mut layout.value_column, i, column_name(i));
+                            }
+                        });
+                    ui.end_row();
+                    ui.label(lang.csv_import_time_unit); // I18N
+                    ui.horizontal(|ui| {
+                        ui.selectable_value(&mut layout.time_unit, CsvTimeUnit::Seconds, lang.csv_import_unit_seconds)
+                            .on_hover_text(lang.csv_import_timecode_hint);
+                        ui.selectable_value(&mut layout.time_unit, CsvTimeUnit::Milliseconds, lang.csv_import_unit_ms);
+                    });
+                    ui.end_row();
+                });
+
+                ui.separator();
+                egui::ScrollArea::both().max_height(200.0).show(ui, |ui| {
+                    egui::Grid::new("csv_import_preview").striped(true).show(ui, |ui| {
+                        let selected = |i: usize| i == layout.time_column || i == layout.value_column;
+                        for i in 0..columns {
+                            let text = egui::RichText::new(column_name(i)).strong();
+                            ui.label(if selected(i) { text.color(self.palette.ok) } else { text });
+                        }
+                        ui.end_row();
+                        for row in &dialog.preview.rows {
+                            for i in 0..columns {
+                                let text = egui::RichText::new(row.get(i).map_or("", String::as_str)).monospace();
+                                ui.label(if selected(i) { text.color(self.palette.ok) } else { text.weak() });
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                ui.separator();
+                ui.horizontal(|ui| {
+                    let valid = layout.time_column != layout.value_column;
+                    import = ui.add_enabled(valid, egui::Button::new(lang.csv_import_btn)) // I18N
+                        .on_disabled_hover_text(lang.csv_import_same_column)
+                        .clicked();
+                    cancel = ui.button(lang.csv_import_cancel).clicked(); // I18N
+                });
+            });
+        cancel |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+        if import && let Some(dialog) = self.csv_import.take() {
+            log_info(&self.logger, &format!("导入 CSV 响度日志: {} (时间列 {}, 电平列 {})", dialog.path.display(), dialog.layout.time_column + 1, dialog.layout.value_column + 1));
+            self.spawn_csv_import(dialog.path, dialog.layout);
+        } else if cancel {
+            self.csv_import = None;
+        }
+    }
+
+    /// 下载 URL 指向的 WAV 到临时目录后按普通文件加载 (控制台 `loadurl` 和单文件模式的 URL 输入共用)。
+    /// 下载占任务进度的前一半；加载完成后删除临时文件，曲线不再关联本地文件 (无法回放)
+#[cfg(not(target_arch = "wasm32"))]
+    fn spawn_url_load(&mut self, url: String) {
+        log_info(&self.logger, &format!("从 URL 加载: {}", url));
+        self.state.loading = true;
+        self.state.error_msg = None;
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+        let source = TaskSource::Url(url.clone());
+
+        let task_id = self.worker_pool.spawn_task(
+            format!("URL Load: {}", url),
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                let dir = std::env::temp_dir().join(format!("wav_lufs_curve_url_{}_{}", std::process::id(), task_id));
+                let report = task_progress(task_id, &ui_tx_clone, &cancel);
+                let result = std::fs::create_dir_all(&dir)
+                    .map_err(AnalyzerError::from)
+                    .and_then(|_| download_url(&url, &dir, &thread_logger, &mut report.stage(0.0, 0.5)))
+                    .and_then(|path| load_file_with_retry(path, &analysis, &thread_logger, &mut report.stage(0.5, 0.5)));
+                if let Err(e) = std::fs::remove_dir_all(&dir) {
+                    log_debug(&thread_logger, &format!("删除临时目录 {} 失败: {}", dir.display(), e));
+                }
+
+                match result {
+                    Ok(mut curve) => {
+                        send_task_perf(&ui_tx_clone, task_id, &curve);
+                        // 临时文件已删除：详情中显示来源 URL
+                        curve.source = None;
+                        if let Some(details) = &mut curve.details {
+                            details.path = PathBuf::from(&url);
+                        }
+                        ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), None, task_id)).unwrap_or_default();
+                    }
+                    Err(e) => send_load_error(&ui_tx_clone, &thread_logger, task_id, &url, e, current_lang),
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+        self.state.task_sources.insert(task_id, source);
+    }
+
+    /// 浏览器中无法绕过跨域限制下载任意 URL
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_url_load(&mut self, url: String) {
+        log_error(&self.logger, &format!("⚠️ 浏览器版不支持从 URL 加载: {}", url));
+        self.state.error_msg = Some(self.lang.web_unavailable_hint.to_string());
+    }
+
+    /// 分析浏览器中选择的文件 (已读入内存)；`slot` 为 None 时加入单文件列表，否则载入对比插槽
+    #[cfg(target_arch = "wasm32")]
+    fn spawn_picked_load(&mut self, name: String, bytes: Arc<[u8]>, slot: Option<char>) {
+        log_info(&self.logger, &format!("选中文件: {} ({})", name, format_file_size(bytes.len() as u64)));
+        self.state.loading = true;
+        self.state.error_msg = None;
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+        let source = TaskSource::Picked(name.clone(), bytes.clone(), slot);
+        let task_name = match slot {
+            Some(slot) => format!("Track {} Load: {}", slot, name),
+            None => name.clone(),
+        };
+        let slot_filename = name.clone();
+
+        let task_id = self.worker_pool.spawn_task(
+            task_name,
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                match load_bytes(&name, &bytes, &analysis, &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                    Ok(curve) => {
+                        send_task_perf(&ui_tx_clone, task_id, &curve);
+                        ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), slot, task_id)).unwrap_or_default();
+                        if slot.is_some() {
+                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Completed)).unwrap_or_default();
+                        }
+                    }
+                    Err(e) => send_load_error(&ui_tx_clone, &thread_logger, task_id, &name, e, current_lang),
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+        if let Some(slot) = slot {
+            self.state.compare_loads[slot_index(slot)] = Some(SlotLoad { task_id, filename: slot_filename });
+        }
+        self.state.task_sources.insert(task_id, source);
+    }
+
+
+    /// 清空单文件列表，并取消仍在为列表加载曲线的任务
+    fn clear_single_files(&mut self) {
+        for task_id in self.state.clear_single_files() {
+            log_command(&self.logger, &format!("清空列表，取消加载任务 {}", task_id));
+            self.worker_pool.command_tx.send(WorkerCommand::Kill(task_id)).unwrap_or_default();
+        }
+    }
+
+    /// 标注输入框及清空列表的确认框
+    fn show_annotation_dialogs(&mut self, ctx: &egui::Context) {
+        if let Some(pending) = &mut self.pending_annotation {
+            let (mut add, mut cancel) = (false, false);
+            egui::Window::new(self.lang.annotation_add_title)
+                .id(egui::Id::new("annotation_window"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(format!("{} @ {}", pending.curve_name, self.state.settings.time_format.format(pending.time, 2)));
+                    let response = ui.text_edit_singleline(&mut pending.text);
+                    response.request_focus();
+                    add = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    ui.horizontal(|ui| {
+                        add |= ui.button(self.lang.annotation_add).clicked(); // I18N
+                        cancel = ui.button(self.lang.dialog_cancel).clicked(); // I18N
+                    });
+                });
+            cancel |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+            let text = pending.text.trim().to_string();
+            if add && !text.is_empty() {
+                let pending = self.pending_annotation.take().unwrap();
+                log_info(&self.logger, &format!("添加标注: {} @ {:.2}s: {}", pending.curve_name, pending.time, text));
+                let list = self.state.annotations.entry(pending.key).or_default();
+                list.push(Annotation { time: pending.time, text });
+                list.sort_by(|a, b| a.time.total_cmp(&b.time));
+            } else if add || cancel {
+                self.pending_annotation = None;
+            }
+        }
+
+        if self.confirm_clear {
+            let count = self.state.loaded_annotation_count();
+            let (mut confirmed, mut cancelled) = (false, false);
+            egui::Window::new(self.lang.clear_confirm_title)
+                .id(egui::Id::new("clear_confirm_window"))
+                .collapsible(false)
+                .resizable(false)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .show(ctx, |ui| {
+                    ui.label(self.lang.clear_confirm_fmt.replacen("{}", &count.to_string(), 1)); // I18N
+                    ui.horizontal(|ui| {
+                        confirmed = ui.button(self.lang.clear_confirm_ok).clicked(); // I18N
+                        cancelled = ui.button(self.lang.dialog_cancel).clicked(); // I18N
+                    });
+                });
+            if confirmed {
+                self.clear_single_files();
+            }
+            if confirmed || cancelled {
+                self.confirm_clear = false;
+            }
+        }
+    }
+
+    /// 文件详情窗口：来源文件、WAV 格式、分析参数及统计；CSV 曲线的音频字段显示 n/a
+    fn show_details_window(&mut self, ctx: &egui::Context) {
+        let Some(key) = &self.details_curve else { return };
+        let curves = self.state.single_files.lock().unwrap();
+        let Some(curve) = curves.iter().find(|c| curve_key(c) == *key) else {
+            drop(curves);
+            self.details_curve = None;
+            return;
+        };
+        let lang = &self.lang;
+        let na = || lang.details_na.to_string();
+        let wav = curve.details.as_ref().and_then(|d| d.wav);
+        let time = self.state.settings.time_format;
+        let level = |p: Option<[f64; 2]>| p.map_or_else(na, |p| format!("{:.2} @ {}", p[1], time.format(p[0], 1)));
+        let rows = [
+            (lang.details_path, curve.details.as_ref().map_or_else(na, |d| d.path.display().to_string())),
+            (lang.details_size, curve.details.as_ref().map_or_else(na, |d| format_file_size(d.file_size))),
+            (lang.details_sample_rate, wav.map_or_else(na, |w| format!("{} Hz", w.spec.sample_rate))),
+            (lang.details_channels, wav.map_or_else(na, |w| w.spec.channels.to_string())),
+            (lang.details_format, wav.map_or_else(na, |w| match w.spec.sample_format {
+                hound::SampleFormat::Int => format!("{}-bit PCM", w.spec.bits_per_sample),
+                hound::SampleFormat::Float => format!("{}-bit float", w.spec.bits_per_sample),
+            })),
+            (lang.details_decoded, wav.map_or_else(na, |w| w.decoded_samples.to_string())),
+            (lang.details_profile, curve.profile.clone().unwrap_or_else(|| lang.profile_none.to_string())),
+            (lang.details_window, wav.map_or_else(na, |w| format!("{:.0} ms / {:.0} ms", w.window_sec * 1000.0, w.step_sec * 1000.0))),
+            (lang.details_tail, wav.map_or_else(na, |w| format!("{:.0} ms", w.unanalyzed_tail_sec * 1000.0))),
+            (lang.stats_col_duration, time.format(curve.duration, 2)),
+            (lang.stats_col_points, curve.points.len().to_string()),
+            (lang.stats_col_avg, format!("{:.2}", curve.average_dbfs)),
+            (lang.stats_col_loudest, level(curve.loudest)),
+            (lang.stats_col_quietest, level(curve.quietest)),
+            (lang.details_silent, curve.silence_gating.to_string()),
+        ];
+
+        let mut open = true;
+        egui::Window::new(format!("{} — {}", lang.details_title, curve.name)) // I18N
+            .id(egui::Id::new("file_details_window"))
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("file_details_grid").num_columns(2).striped(true).show(ui, |ui| {
+                    for (label, value) in rows {
+                        ui.label(label);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+            });
+        drop(curves);
+        if !open {
+            self.details_curve = None;
+        }
+    }
+
+    /// 会话有变化时交给自动保存线程写入恢复文件 (序列化和写盘都不在界面线程)
+    fn autosave(&mut self) {
+        self.last_autosave = Instant::now();
+        let key = self.state.session_key();
+        if self.pending_recovery.is_some() || self.autosave_key == Some(key) {
+            return;
+        }
+        self.autosave_key = Some(key);
+        log_debug(&self.logger, "自动保存会话");
+        self.autosaver.send(AutosaveCommand::Save(Box::new(self.state.snapshot())));
+    }
+
+    /// 上次运行没有正常退出时询问是否恢复自动保存的会话
+    fn show_recovery_dialog(&mut self, ctx: &egui::Context) {
+        let Some(session) = &self.pending_recovery else { return };
+        let (mut restore, mut discard) = (false, false);
+        egui::Window::new(self.lang.recovery_title)
+            .id(egui::Id::new("recovery_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                let text = self.lang.recovery_body_fmt
+                    .replacen("{}", &session.saved_at, 1)
+                    .replacen("{}", &session.curve_count().to_string(), 1);
+                ui.label(text); // I18N
+                ui.horizontal(|ui| {
+                    restore = ui.button(self.lang.recovery_restore).clicked(); // I18N
+                    discard = ui.button(self.lang.recovery_discard).clicked(); // I18N
+                });
+            });
+
+        if restore && let Some(session) = self.pending_recovery.take() {
+            let count = session.curve_count();
+            log_info(&self.logger, &format!("恢复上次的会话 ({} 条曲线)", count));
+            self.state.restore(session, &self.lang);
+            self.state.notifications.push(ToastLevel::Success, self.lang.recovery_restored_fmt.replacen("{}", &count.to_string(), 1));
+            // 恢复的会话立即成为新的恢复文件
+            self.autosave();
+        } else if discard {
+            log_info(&self.logger, "放弃恢复上次的会话");
+            self.pending_recovery = None;
+            self.autosaver.send(AutosaveCommand::Discard);
+        }
+    }
+
+    /// 关闭窗口时仍有任务运行的确认框：取消任务并退出，或继续工作
+    fn show_exit_dialog(&mut self, ctx: &egui::Context) {
+        if !self.confirm_exit {
+            return;
+        }
+        let count = self.worker_pool.unfinished_count();
+        let (mut exit, mut keep) = (false, false);
+        egui::Window::new(self.lang.exit_confirm_title)
+            .id(egui::Id::new("exit_confirm_window"))
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(self.lang.exit_confirm_fmt.replacen("{}", &count.to_string(), 1)); // I18N
+                ui.horizontal(|ui| {
+                    exit = ui.button(self.lang.exit_confirm_ok).clicked(); // I18N
+                    keep = ui.button(self.lang.exit_confirm_keep).clicked(); // I18N
+                });
+            });
+        keep |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+        // 确认框显示期间任务可能已全部结束，此时直接退出
+        if exit || count == 0 {
+            self.worker_pool.cancel_all(&self.logger);
+            self.exit_confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        if exit || keep || count == 0 {
+            self.confirm_exit = false;
+        }
+    }
+
+    /// 进程监视器中单击任务：已完成的加载任务跳转到其曲线，失败的任务打开错误详情
+    fn reveal_task_result(&mut self, task_id: usize, state: &TaskState) {
+        match state {
+            TaskState::Error(_) => self.task_error_popup = Some(task_id),
+            TaskState::Completed => {
+                if self.state.task_sources.get(&task_id).is_some_and(TaskSource::is_slot) {
+                    self.mode = AppMode::Compare;
+                    return;
+                }
+                let Some(key) = self.state.task_curves.get(&task_id) else { return };
+                let files = self.state.single_files.lock().unwrap();
+                let Some(index) = files.iter().position(|c| curve_key(c) == *key) else {
+                    drop(files);
+                    self.state.notifications.push(ToastLevel::Warning, self.lang.task_curve_gone); // I18N
+                    return;
+                };
+                // 当前标签过滤会隐藏该曲线时取消过滤
+                if self.tag_filter.as_ref().is_some_and(|tag| !files[index].tags.contains(tag)) {
+                    self.tag_filter = None;
+                }
+                drop(files);
+                log_debug(&self.logger, &format!("定位任务 {} 的曲线: {}", task_id, key));
+                self.mode = AppMode::Single;
+                self.focused_index = Some(index);
+                self.state.settings.show_stats_table = true;
+                self.stats_table.scroll_to = Some(index);
+            }
+            _ => {}
+        }
+    }
+
+    /// 失败任务的完整错误信息，可重试加载任务
+    fn show_task_error_dialog(&mut self, ctx: &egui::Context) {
+        let Some(task_id) = self.task_error_popup else { return };
+        let task = self.worker_pool.tasks.lock().unwrap().iter().find(|t| t.id == task_id).cloned();
+        let Some(AudioTask { name, state: TaskState::Error(error), .. }) = task else {
+            self.task_error_popup = None;
+            return;
+        };
+        let source = self.state.task_sources.get(&task_id).cloned();
+        let text = error.text(&self.lang);
+        let hint = error.hint(&self.lang);
+        let (mut retry, mut close) = (false, false);
+        egui::Window::new(self.lang.task_error_title) // I18N
+            .id(egui::Id::new("task_error_window"))
+            .collapsible(false)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .show(ctx, |ui| {
+                ui.label(egui::RichText::new(format!("[{}] {}", task_id, name)).strong());
+                egui::ScrollArea::vertical().max_height(240.0).show(ui, |ui| {
+                    ui.add(egui::Label::new(egui::RichText::new(&text).color(self.palette.error)).selectable(true));
+                });
+                if let Some(hint) = hint {
+                    ui.label(format!("💡 {}", hint));
+                }
+                ui.horizontal(|ui| {
+                    retry = ui.add_enabled(source.is_some(), egui::Button::new(self.lang.task_retry)) // I18N
+                        .on_disabled_hover_text(self.lang.task_retry_unavailable)
+                        .clicked();
+                    if ui.button(self.lang.task_copy_error).clicked() { // I18N
+                        ui.ctx().copy_text(text.clone());
+                    }
+                    close = ui.button(self.lang.task_error_close).clicked(); // I18N
+                });
+            });
+        close |= ctx.input(|i| i.key_pressed(egui::Key::Escape));
+
+        if retry && let Some(source) = source {
+            log_command(&self.logger, &format!("重试任务 {} ({})", task_id, name));
+            match source {
+                TaskSource::File(path) => self.spawn_load_tasks(vec![path]),
+                #[cfg(not(target_arch = "wasm32"))]
+                TaskSource::Url(url) => self.spawn_url_load(url),
+                TaskSource::Slot(path, slot) => self.spawn_compare_load(path, slot),
+                TaskSource::Csv(path, layout) => self.spawn_csv_import(path, layout),
+                TaskSource::Reanalyze(path) => self.spawn_reanalyze_tasks(vec![path]),
+                #[cfg(target_arch = "wasm32")]
+                TaskSource::Picked(name, bytes, slot) => self.spawn_picked_load(name, bytes, slot),
+            }
+        }
+        if retry || close {
+            self.task_error_popup = None;
+        }
+    }
+
+    /// 当前归一化方式对应的目标电平 (dBFS)
+    fn normalize_target(&self) -> f64 {
+        match self.state.settings.normalize_mode {
+            NormalizeMode::Average => self.target_lufs as f64,
+            NormalizeMode::Peak => self.peak_target as f64,
+        }
+    }
+
+    /// 当前设置下的 CSV 导出选项 (归一化列及可选的等间隔重采样)
+    fn csv_export_options(&self) -> CsvExportOptions {
+        CsvExportOptions {
+            normalize_mode: self.state.settings.normalize_mode,
+            target: self.normalize_target(),
+            resample_interval: self.state.settings.export_resample.then_some(self.state.settings.export_interval as f64),
+        }
+    }
+
+    /// 为每条 WAV 曲线启动一个后台任务，按当前归一化目标的增益写出新的 WAV 文件
+    fn spawn_normalized_export(&mut self, folder: PathBuf) {
+        let (mode, target) = (self.state.settings.normalize_mode, self.normalize_target());
+        let jobs: Vec<(PathBuf, f64)> = self.state.single_files.lock().unwrap().iter()
+            .filter_map(|curve| match &curve.source {
+                Some(source) => Some((source.clone(), curve.normalization_gain(mode, target))),
+                None => {
+                    log_info(&self.logger, &format!("跳过 CSV 曲线 (没有音频): {}", curve.name));
+                    None
+                }
+            })
+            .collect();
+        log_info(&self.logger, &format!("导出归一化 WAV: {} 个文件 → {} (目标 {:.1} dBFS)", jobs.len(), folder.display(), target));
+        let failed_fmt = self.lang.normalize_export_failed_fmt;
+
+        let sources: Vec<PathBuf> = jobs.iter().map(|(source, _)| source.clone()).collect();
+        for ((source, gain_db), name) in jobs.into_iter().zip(normalized_file_names(&sources)) {
+            let filename = file_display_name(&source);
+            let target_path = folder.join(name);
+
+            self.worker_pool.spawn_task(
+                format!("Normalize: {}", filename),
+                move |task_id, ui_tx_clone, logger_entries, cancel| {
+                    let thread_logger = Logger { entries: logger_entries };
+                    log_info(&thread_logger, &format!("▶️ 写出归一化 WAV: {} ({:+.2} dB)", target_path.display(), gain_db));
+                    match write_gain_adjusted_wav(&source, &target_path, gain_db, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                        Ok(0) => log_info(&thread_logger, &format!("✅ 归一化 WAV 已写出: {}", target_path.display())),
+                        Ok(over) => log_error(&thread_logger, &format!("⚠️ {}: {} 个样本超出满刻度 (PCM 已削波，浮点格式保留原值)", target_path.display(), over)),
+                        Err(e) if matches!(e.downcast_ref::<AnalyzerError>(), Some(AnalyzerError::Cancelled)) => {
+                            log_info(&thread_logger, &format!("⏹️ 已取消写出: {}", target_path.display()));
+                        }
+                        Err(e) => {
+                            let err_msg = failed_fmt.replacen("{}", &filename, 1).replacen("{}", &e.to_string(), 1);
+                            log_error(&thread_logger, &err_msg);
+                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(TaskError::Message(err_msg)))).unwrap_or_default();
+                        }
+                    }
+                },
+                self.ui_tx.clone(),
+                &self.logger,
+            );
+        }
+    }
+
+    /// 启动对比模式 Track A / B 的后台加载任务 (文件对话框和控制台 `loadA` / `loadB` 共用)
+    fn spawn_compare_load(&mut self, path: PathBuf, file_slot: char) {
+        let filename = file_display_name(&path);
+        let task_name = format!("Track {} Load: {}", file_slot, filename);
+        let retry_path = path.clone();
+        let logger_ref = &self.logger;
+        let ui_result_tx_base = self.ui_tx.clone();
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+        let profile = self.state.settings.profile_for(&path).cloned();
+
+        self.state.loading = true; // 增加 loading 状态
+        self.state.error_msg = None;
+
+        // 启动后台加载任务；重新选择文件会替换插槽当前的任务，旧任务的结果随后被丢弃
+        let slot_filename = filename.clone();
+        let task_id = self.worker_pool.spawn_task(
+            task_name,
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                match load_with_profile(path, &analysis, profile.as_ref(), &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                    Ok(curve) => {
+                        // 发送结果和插槽信息
+                        send_task_perf(&ui_tx_clone, task_id, &curve);
+                        ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), Some(file_slot), task_id)).unwrap_or_default();
+                        ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Completed)).unwrap_or_default();
+                    }
+                    Err(e) => send_load_error(&ui_tx_clone, &thread_logger, task_id, &filename, e, current_lang),
+                }
+            },
+            ui_result_tx_base,
+            logger_ref
+        );
+        self.state.compare_loads[slot_index(file_slot)] = Some(SlotLoad { task_id, filename: slot_filename });
+        self.state.task_sources.insert(task_id, TaskSource::Slot(retry_path, file_slot));
+    }
+
+    /// 在后台解码 WAV 用于回放，完成后由 UI 线程打开输出设备
+    fn spawn_playback_load(&mut self, path: PathBuf) {
+        let filename = file_display_name(&path);
+        let failed_fmt = self.lang.playback_decode_failed_fmt;
+        self.player = None;
+
+        self.worker_pool.spawn_task(
+            format!("Playback Load: {}", filename),
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                match decode_for_playback(path, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                    Ok(buffer) => {
+                        ui_tx_clone.send(WorkerMessage::PlaybackReady(buffer)).unwrap_or_default();
+                    }
+                    Err(AnalyzerError::Cancelled) => log_info(&thread_logger, &format!("⏹️ 已取消回放解码: {}", filename)),
+                    Err(e) => {
+                        let err_msg = failed_fmt.replacen("{}", &filename, 1).replacen("{}", &e.to_string(), 1);
+                        log_error(&thread_logger, &err_msg);
+                        ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(TaskError::Message(err_msg)))).unwrap_or_default();
+                    }
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+    }
+
+    /// 在后台快速扫描目录中所有 WAV 的积分响度和真峰值，结果写入 <dir>/quickscan.csv
+    fn start_quick_scan(&mut self, dir: PathBuf) {
+        let mut files: Vec<PathBuf> = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && p.extension().map(|ext| ext.eq_ignore_ascii_case("wav")).unwrap_or(false))
+                .collect(),
+            Err(e) => {
+                let err_msg = self.lang.folder_read_failed_fmt.replacen("{}", &dir.display().to_string(), 1).replacen("{}", &e.to_string(), 1);
+                log_error(&self.logger, &err_msg);
+                self.state.error_msg = Some(err_msg);
+                return;
+            }
+        };
+        files.sort();
+        log_info(&self.logger, &format!("快速扫描: {} 个 WAV 文件 ({})", files.len(), dir.display()));
+
+        let out_path = dir.join("quickscan.csv");
+        let failed_fmt = self.lang.quickscan_failed_fmt;
+        self.worker_pool.spawn_task(
+            format!("QuickScan: {}", dir.display()),
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                let report = task_progress(task_id, &ui_tx_clone, &cancel);
+                let write_report = || -> Result<(), Box<dyn Error + Send + Sync>> {
+                    let mut wtr = csv::Writer::from_path(&out_path)?;
+                    wtr.write_record(["File", "Integrated (LUFS)", "True Peak (dBTP)", "Gated Blocks", "Error"])?;
+                    let span = 1.0 / files.len() as f32;
+                    for (i, path) in files.iter().enumerate() {
+                        let filename = file_display_name(path);
+                        // 每个文件占总进度的 1/n，取消后在文件内的下一次进度报告时停止
+                        match quick_scan_wav(path, &mut report.stage(i as f32 * span, span)) {
+                            Err(AnalyzerError::Cancelled) => {
+                                log_info(&thread_logger, &format!("⏹️ 快速扫描已取消，结果只包含前 {} 个文件", i));
+                                break;
+                            }
+                            Ok((lufs, true_peak, gating)) => {
+                                log_debug(&thread_logger, &format!("{}: {:.2} LUFS, {:.2} dBTP, 门限排除 {} 个块", filename, lufs, true_peak, gating));
+                                wtr.write_record([filename, format!("{:.2}", lufs), format!("{:.2}", true_peak), gating.to_string(), String::new()])?;
+                            }
+                            Err(e) => {
+                                log_error(&thread_logger, &format!("快速扫描失败 ({}): {}", filename, e));
+                                wtr.write_record([filename, String::new(), String::new(), String::new(), e.to_string()])?;
+                            }
+                        }
+                    }
+                    wtr.flush()?;
+                    Ok(())
+                };
+
+                match write_report() {
+                    Ok(()) => log_info(&thread_logger, &format!("✅ 快速扫描结果已写入: {}", out_path.display())),
+                    Err(e) => {
+                        let err_msg = failed_fmt.replacen("{}", &e.to_string(), 1);
+                        log_error(&thread_logger, &err_msg);
+                        ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(TaskError::Message(err_msg)))).unwrap_or_default();
+                    }
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+    }
+
+    /// 开始批量对比：先在后台加载参考文件，完成后再派发各候选文件的对比任务
+    fn start_batch(&mut self) {
+        let Some(ref_path) = self.batch_ref_path.clone() else { return };
+        let filename = file_display_name(&ref_path);
+
+        self.batch_reference = None;
+        self.batch_results.clear();
+        self.batch_total = 0;
+        self.state.loading = true;
+        self.state.error_msg = None;
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+        let profile = self.state.settings.profile_for(&ref_path).cloned();
+
+        self.worker_pool.spawn_task(
+            format!("Batch Ref Load: {}", filename),
+            move |task_id, ui_tx_clone, logger_entries, cancel| {
+                let thread_logger = Logger { entries: logger_entries };
+                match load_with_profile(ref_path, &analysis, profile.as_ref(), &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel)) {
+                    Ok(curve) => {
+                        send_task_perf(&ui_tx_clone, task_id, &curve);
+                        ui_tx_clone.send(WorkerMessage::NewCurve(Box::new(curve), Some('R'), task_id)).unwrap_or_default();
+                    }
+                    Err(e) => {
+                        send_load_error(&ui_tx_clone, &thread_logger, task_id, &filename, e, current_lang);
+                    }
+                }
+            },
+            self.ui_tx.clone(),
+            &self.logger,
+        );
+    }
+
+    /// 参考曲线就绪后，为文件夹中每个 WAV/CSV 候选文件启动一个对比任务
+    fn spawn_batch_candidates(&mut self) {
+        let (Some(reference), Some(folder)) = (self.batch_reference.clone(), self.batch_folder.clone()) else { return };
+
+        let mut candidates: Vec<PathBuf> = match std::fs::read_dir(&folder) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|p| p.is_file() && Some(p) != self.batch_ref_path.as_ref())
+                .filter(|p| {
+                    p.extension()
+                        .map(|ext| ext.eq_ignore_ascii_case("wav") || ext.eq_ignore_ascii_case("csv"))
+                        .unwrap_or(false)
+                })
+                .collect(),
+            Err(e) => {
+                let err_msg = self.lang.folder_read_failed_fmt.replacen("{}", &folder.display().to_string(), 1).replacen("{}", &e.to_string(), 1);
+                log_error(&self.logger, &err_msg);
+                self.state.error_msg = Some(err_msg);
+                return;
+            }
+        };
+        candidates.sort();
+
+        log_info(&self.logger, &format!("批量对比: {} 个候选文件", candidates.len()));
+        self.batch_total = candidates.len();
+        let target_c = self.state.target_mean_diff as f64;
+        let current_lang = self.current_lang;
+        let analysis = self.state.settings.analysis.clone();
+
+        for path in candidates {
+            let filename = file_display_name(&path);
+            let reference = reference.clone();
+            let analysis = analysis.clone();
+            let profile = self.state.settings.profile_for(&path).cloned();
+
+            self.worker_pool.spawn_task(
+                format!("Batch Compare: {}", filename),
+                move |task_id, ui_tx_clone, logger_entries, cancel| {
+                    let thread_logger = Logger { entries: logger_entries };
+                    let loaded = load_with_profile(path, &analysis, profile.as_ref(), &thread_logger, &mut task_progress(task_id, &ui_tx_clone, &cancel));
+                    if let Ok(curve) = &loaded {
+                        send_task_perf(&ui_tx_clone, task_id, curve);
+                    }
+                    let result = match loaded {
+                        Ok(curve) if durations_comparable(&reference, &curve) => {
+                            // 参考曲线与候选的时间基可能不同 (如 CSV 参考)，由 compare 重采样
+                            match compare(&reference, &curve, &CompareOptions { target_diff: target_c, ..CompareOptions::default() }) {
+                                Some(res) => Ok(BatchStats {
+                                    mean_diff: res.mean_diff,
+                                    std_dev: res.std_dev,
+                                    correlation_coefficient: res.correlation_coefficient,
+                                    t_statistic: res.t_statistic,
+                                    overlap_ratio: res.overlap_ratio,
+                                    points: res.diff_points.len(),
+                                }),
+                                None => Err(Lang::load(current_lang).format_no_overlap_error(0.0)),
+                            }
+                        }
+                        Ok(curve) => Err(Lang::load(current_lang).format_duration_error(reference.duration, curve.duration)),
+                        Err(e) => Err(TaskError::load(&filename, e).text(&Lang::load(current_lang))),
+                    };
+
+                    if let Err(err_msg) = &result {
+                        log_error(&thread_logger, err_msg);
+                        ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(TaskError::Message(err_msg.clone())))).unwrap_or_default();
+                    }
+                    ui_tx_clone.send(WorkerMessage::BatchResult(BatchRow { name: filename, result })).unwrap_or_default();
+                },
+                self.ui_tx.clone(),
+                &self.logger,
+            );
+        }
+    }
+
+
+}
+
+impl eframe::App for WavLufsApp {
+    /// 退出时等待工作线程结束，同步完成最后一次自动保存并关闭日志文件，确保进程结束前都已写入磁盘
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        // 退出确认框已取消过任务时这里只是等待管理线程结束
+        self.worker_pool.cancel_all(&self.logger);
+        // 正常退出删除恢复文件；恢复对话框还没有回答时保留上次的会话，下次启动再询问
+        if self.pending_recovery.is_some() {
+            log_info(&self.logger, "恢复对话框未回答，保留上次的会话");
+            self.autosaver.finish(None);
+        } else {
+            self.autosaver.finish(Some(AutosaveCommand::Discard));
+        }
+        log_info(&self.logger, "👋 退出");
+        if let Ok(mut entries) = self.logger.entries.lock() {
+            entries.close_mirror();
+        }
+    }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.state.settings);
+        eframe::set_value(storage, CMD_HISTORY_KEY, &self.cmd_history);
+        eframe::set_value(storage, ANNOTATIONS_KEY, &self.state.annotations);
+        eframe::set_value(storage, CURVE_LABELS_KEY, &self.state.curve_labels);
+        // 当前加载的曲线按绘制顺序在前，其余保留上次的顺序
+        let curves = self.state.single_files.lock().unwrap();
+        let mut order: Vec<String> = self.state.draw_order.order.iter().filter_map(|&i| curves.get(i)).map(curve_key).collect();
+        order.extend(self.state.saved_order.iter().filter(|key| !order.contains(key)).cloned().collect::<Vec<_>>());
+        drop(curves);
+        self.state.saved_order = order;
+        eframe::set_value(storage, CURVE_ORDER_KEY, &self.state.saved_order);
+    }
+
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        let frame_start = Instant::now();
+        // 界面缩放：egui 的 Ctrl+= / Ctrl+- / Ctrl+0 快捷键会修改 zoom_factor，同步回设置并限制在允许范围内
+        let zoom = ctx.zoom_factor();
+        let clamped = zoom.clamp(*UI_ZOOM_RANGE.start(), *UI_ZOOM_RANGE.end());
+        if clamped != zoom {
+            ctx.set_zoom_factor(clamped);
+        }
+        self.state.settings.ui_zoom = clamped;
+        self.apply_theme(ctx);
+
+        // --- 关闭请求：仍有任务未结束时先确认 ---
+        if std::mem::take(&mut self.quit_requested) {
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+        if ctx.input(|i| i.viewport().close_requested()) && !self.exit_confirmed && self.worker_pool.unfinished_count() > 0 {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.confirm_exit = true;
+        }
+
+        // --- 浏览器中每帧执行一个排队的任务 ---
+        #[cfg(target_arch = "wasm32")]
+        if self.worker_pool.run_pending() {
+            ctx.request_repaint();
+        }
+
+        // --- 异步消息处理 (非阻塞循环) ---
+        while let Ok(msg) = self.ui_rx.try_recv() {
+            match self.state.on_worker_message(msg, &self.lang) {
+                Some(StateEffect::BatchReference(curve)) => {
+                    // 批量对比参考曲线就绪，派发候选任务
+                    self.batch_reference = Some(curve);
+                    self.spawn_batch_candidates();
+                }
+                Some(StateEffect::BatchResult(row)) => self.batch_results.push(row),
+                Some(StateEffect::Playback(buffer)) => {
+                    // 解码期间曲线可能已被移除
+                    let still_loaded = self.state.single_files.lock().unwrap().iter().any(|c| c.source.as_ref() == Some(&buffer.source));
+                    if still_loaded {
+                        match Player::start(buffer, Logger { entries: self.logger.entries.clone() }) {
+                            Ok(player) => self.player = Some(player),
+                            Err(e) => {
+                                let err_msg = self.lang.playback_open_failed_fmt.replacen("{}", &e, 1);
+                                log_error(&self.logger, &err_msg);
+                                self.state.notifications.push(ToastLevel::Error, err_msg);
+                            }
+                        }
+                    }
+                }
+                #[cfg(target_arch = "wasm32")]
+                Some(StateEffect::FilePicked(name, bytes, slot)) => self.spawn_picked_load(name, bytes, slot),
+                None => {}
+            }
+            ctx.request_repaint();
+        }
+
+        // F1 打开/关闭帮助窗口
+        if ctx.input_mut(|i| i.consume_key(egui::Modifiers::NONE, egui::Key::F1)) {
+            self.show_help_popup = !self.show_help_popup;
+        }
+
+        // 被监视的文件写入完成后重新分析
+        let watched = self.watched_paths();
+        self.file_watcher.set_watched(watched, &self.logger);
+        while let Ok(path) = self.file_watcher.changes.try_recv() {
+            self.reload_changed_file(path);
+        }
+
+        let batch_finished = self.state.update_task_batch(&self.lang);
+        if batch_finished || self.last_autosave.elapsed() >= AUTOSAVE_INTERVAL {
+            self.autosave();
+        }
+        ctx.request_repaint_after(AUTOSAVE_INTERVAL);
+
+        // 回放中的曲线被移除 (如清空列表) 时停止回放
+        if let Some(player) = &self.player {
+            let source = player.source.clone();
+            if !self.state.single_files.lock().unwrap().iter().any(|c| c.source.as_ref() == Some(&source)) {
+                self.player = None;
+                log_info(&self.logger, &format!("回放已停止: {}", source.display()));
+            } else if player.is_playing() {
+                ctx.request_repaint(); // 驱动回放光标
+            }
+        }
+
+        // 离开实时监视模式时关闭输入流；采集中每个步进刷新一次曲线
+        if let Some(live) = &mut self.live
+            && live.is_running()
+        {
+            if self.mode != AppMode::Live {
+                live.stop();
+                log_info(&self.logger, &format!("实时监视已停止: {}", live.device));
+            } else {
+                live.poll(self.state.settings.live_retain_minutes as f64 * 60.0);
+                ctx.request_repaint_after(Duration::from_secs_f64(live.windows.step_sec));
+            }
+        }
+
+        // --- 顶部导航栏 (I18N & 语言选择) ---
+        egui::TopBottomPanel::top("top_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                // 模式选择
+                ui.selectable_value(&mut self.mode, AppMode::Single, self.lang.mode_single);
+                ui.selectable_value(&mut self.mode, AppMode::Compare, self.lang.mode_compare);
+                ui.selectable_value(&mut self.mode, AppMode::Batch, self.lang.mode_batch);
+                ui.selectable_value(&mut self.mode, AppMode::Console, self.lang.mode_console);
+                ui.selectable_value(&mut self.mode, AppMode::Live, self.lang.mode_live);
+
+                ui.separator();
+
+                // 语言选择
+                // 修正：使用 I18N 字段替代硬编码的 "语言:"
+                ui.label(self.lang.nav_lang_label);
+                let old_lang = self.current_lang; // 记录旧语言
+
+                // 列出内置及 locales/ 目录中找到的所有语言，每种语言以自身名称显示
+                egui::ComboBox::from_id_salt("language_select")
+                    .selected_text(self.current_lang.display_name())
+                    .show_ui(ui, |ui| {
+                        for lang in Language::available() {
+                            ui.selectable_value(&mut self.current_lang, lang, lang.display_name());
+                        }
+                    });
+
+                // 修正语言切换逻辑：在 selectable_value 之外检查并重新加载
+                if self.current_lang != old_lang {
+                    log_info(&self.logger, &format!("切换语言到: {:?}", self.current_lang));
+
+                    // 核心切换逻辑：重新加载语言数据；CJK 字体只在首次需要时加载一次
+                    self.lang = Lang::load(self.current_lang);
+                    self.cjk_font.ensure_loaded(ctx, self.current_lang, &self.logger);
+
+                    // 由于 selectable_value 已经点击了，我们不需要 if clicked() 包装
+                    ui.ctx().request_repaint();
+                }
+
+                ui.separator();
+
+                // 主题选择 (持久化)
+                ui.label(self.lang.nav_theme_label);
+                ui.selectable_value(&mut self.state.settings.theme, ThemeChoice::System, self.lang.theme_system);
+                ui.selectable_value(&mut self.state.settings.theme, ThemeChoice::Dark, self.lang.theme_dark);
+                ui.selectable_value(&mut self.state.settings.theme, ThemeChoice::Light, self.lang.theme_light);
+
+                ui.separator();
+
+                // 时间显示格式 (图表横轴和时间字段)
+                ui.label(self.lang.nav_time_label); // I18N
+                ui.selectable_value(&mut self.state.settings.time_format, TimeFormat::Seconds, self.lang.time_format_seconds); // I18N
+                ui.selectable_value(&mut self.state.settings.time_format, TimeFormat::MinSec, "mm:ss")
+                    .on_hover_text(self.lang.time_format_minsec_hint); // I18N
+
+                ui.separator();
+
+                // 界面缩放 (以系统显示缩放为基准)
+                ui.label(self.lang.ui_zoom_label);
+                let mut percent = self.state.settings.ui_zoom * 100.0;
+                let range = UI_ZOOM_RANGE.start() * 100.0..=UI_ZOOM_RANGE.end() * 100.0;
+                if ui.add(egui::Slider::new(&mut percent, range).step_by(5.0).suffix("%"))
+                    .on_hover_text(self.lang.ui_zoom_hint)
+                    .changed()
+                {
+                    self.state.settings.ui_zoom = percent / 100.0;
+                    ctx.set_zoom_factor(self.state.settings.ui_zoom);
+                }
+
+                ui.separator();
+
+                // --- 新增：帮助按钮 --- 修正：使用 I18N 字段替代硬编码的 "❓ 帮助"
+                if ui.button(self.lang.nav_help_btn).on_hover_text(self.lang.nav_help_hint).clicked() {
+                    self.show_help_popup = !self.show_help_popup;
+                }
+            });
+        });
+
+        // --- 缺少 CJK 字体时的提示横幅 ---
+        if self.current_lang.needs_cjk_font() && self.cjk_font == CjkFontState::Missing {
+            egui::TopBottomPanel::top("font_banner").show(ctx, |ui| {
+                ui.colored_label(self.palette.warn, self.lang.font_missing_banner);
+            });
+        }
+
+        // --- 底部状态栏 (I18N) ---
+        egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                if let Some((done, total, current)) = self.state.batch_progress() {
+                    ui.horizontal(|ui| {
+                        let fraction = done as f32 / total.max(1) as f32;
+                        ui.add(egui::ProgressBar::new(fraction).desired_width(200.0).show_percentage());
+                        let text = self.lang.status_progress_fmt
+                            .replacen("{}", &done.to_string(), 1)
+                            .replacen("{}", &total.to_string(), 1)
+                            .replacen("{}", &current, 1);
+                        ui.label(text); // I18N
+                        ctx.request_repaint();
+                    });
+                } else if self.state.loading {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(self.lang.status_loading); // I18N
+                        ctx.request_repaint();
+                    });
+                } else if let Some((summary, _)) = &self.state.status_flash {
+                    ui.colored_label(self.palette.ok, summary);
+                    ctx.request_repaint_after(Duration::from_millis(250));
+                } else if let Some(err) = &self.state.error_msg {
+                    ui.colored_label(self.palette.error, err);
+                } else {
+                    ui.label(self.lang.status_ready); // I18N
+                }
+
+                // 右侧 (占用剩余宽度，需放在最后)：最近错误入口 (通知过期后仍可查看)
+                if !self.state.notifications.recent_errors.is_empty() {
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let label = self.lang.recent_errors_btn_fmt.replacen("{}", &self.state.notifications.recent_errors.len().to_string(), 1);
+                        if ui.small_button(label).clicked() {
+                            self.state.notifications.show_recent_errors = !self.state.notifications.show_recent_errors;
+                        }
+                    });
+                }
+            });
+        });
+
+        // 中央内容区
+        egui::CentralPanel::default().show(ctx, |ui| {
+            self.ui_analysis_settings(ui);
+            match self.mode {
+                AppMode::Single => self.ui_single_mode(ui, ctx),
+                AppMode::Compare => self.ui_compare_mode(ui),
+                AppMode::Batch => self.ui_batch_mode(ui),
+                AppMode::Console => self.ui_console_mode(ui),
+                AppMode::Live => self.ui_live_mode(ui),
+            }
+        });
+
+        // --- 新增：帮助悬浮窗口 ---
+        self.ui_help_popup(ctx);
+        // 进程监视器中点开的失败任务 (控制台模式下触发，需在任何模式下都能显示)
+        self.show_task_error_dialog(ctx);
+        // 启动时发现的会话恢复文件 (任何模式下都要显示，用户选择之前不会自动保存)
+        self.show_recovery_dialog(ctx);
+
+        // 通知与最近错误窗口
+        self.state.notifications.show(ctx, &self.lang, &self.palette);
+
+        self.frame_stats.record(frame_start.elapsed(), &self.logger);
+    }
+}
+
+impl WavLufsApp {
+
+    // --- 新增：帮助悬浮窗口的实现 ---
+    fn ui_help_popup(&mut self, ctx: &egui::Context) {
+        if self.show_help_popup {
+            let lang = &self.lang; // 获取当前语言文本
+            // 这里使用一个唯一的 ID 源，以防与任何其他 Window 冲突
+            egui::Window::new(lang.help_title)
+                .id(egui::Id::new("help_window"))
+                .open(&mut self.show_help_popup)
+                .resizable(true)
+                .default_size([400.0, 300.0])
+                .show(ctx, |ui| {
+                    // 窗口较小时命令列表会超出默认尺寸，内容可滚动
+                    egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                        ui.label(lang.help_desc);
+                        ui.separator();
+
+                        ui.heading(lang.help_monitor_title);
+                        // 修正：使用 I18N 字段替代硬编码的中文描述
+                        ui.label(lang.help_monitor_desc);
+                        ui.separator();
+
+                        ui.heading(lang.help_console_title);
+                        ui.vertical(|ui| {
+                            // 与 `help` 命令共用 CONSOLE_COMMANDS 表
+                            for command in CONSOLE_COMMANDS {
+                                ui.label(format!("**`{}`**: {}", command.usage, (command.help)(lang)));
+                            }
+                        });
+                    });
+                });
+        }
+    }
+    // ---------------------------------
+
+    /// 各模式共用的分析参数面板 (可折叠)；参数改变后，用旧参数计算的已加载曲线标记为过期
+    fn ui_analysis_settings(&mut self, ui: &mut egui::Ui) {
+        let stale = {
+            let files = self.state.single_files.lock().unwrap();
+            files.iter().chain(self.state.compare_a.as_ref()).chain(self.state.compare_b.as_ref())
+                .filter(|c| curve_is_stale(c, &self.state.settings))
+                .count()
+        };
+        egui::CollapsingHeader::new(self.lang.analysis_settings_title) // I18N
+            .id_salt("analysis_settings")
+            .show(ui, |ui| {
+                ui_analysis_params(ui, &mut self.state.settings.analysis, &self.lang, "downmix_mode");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut self.state.settings.analysis.use_mmap, self.lang.analysis_mmap_label) // I18N
+                        .on_hover_text(self.lang.analysis_mmap_hint);
+                    ui.separator();
+                    ui.checkbox(&mut self.state.settings.auto_reload_all, self.lang.watch_all_label) // I18N
+                        .on_hover_text(self.lang.watch_all_hint);
+                });
+                ui.weak(self.lang.downmix_hint); // I18N
+                self.state.settings.ui_profile_editor(ui, &self.lang);
+            });
+        if stale > 0 {
+            ui.horizontal(|ui| {
+                ui.colored_label(self.palette.warn, self.lang.analysis_stale_fmt.replacen("{}", &stale.to_string(), 1)); // I18N
+                if ui.button(self.lang.analysis_reanalyze_btn).on_hover_text(self.lang.analysis_reanalyze_hint).clicked() { // I18N
+                    self.reanalyze_all();
+                }
+            });
+        }
+    }
+
+    fn ui_single_mode(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
+        self.show_annotation_dialogs(ctx);
+        self.show_exit_dialog(ctx);
+        self.show_csv_import_dialog(ctx);
+        self.show_details_window(ctx);
+        ui.heading(self.lang.single_heading); // I18N
+        // 曲线用不同的分析参数计算时 (如不同窗口长度)，电平不能直接相互比较
+        let mixed: Vec<String> = distinct_analysis_settings(self.state.single_files.lock().unwrap().iter())
+            .into_iter()
+            .map(|analysis| analysis_summary(analysis, &self.lang))
+            .collect();
+        if mixed.len() > 1 {
+            ui.colored_label(self.palette.warn, self.lang.analysis_mixed_single_fmt.replacen("{}", &mixed.len().to_string(), 1)) // I18N
+                .on_hover_text(mixed.join("\n"));
+        }
+        self.ui_delivery_headline(ui);
+        ui.horizontal(|ui| {
+            if ui.button(self.lang.single_open_btn).clicked() { // I18N
+                log_info(&self.logger, "用户点击: 打开文件对话框");
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(paths) = FileDialog::new().add_filter("Audio/Data", &["wav", "csv"]).pick_files() {
+                    self.spawn_load_tasks(paths);
+                }
+                #[cfg(target_arch = "wasm32")]
+                pick_files_async(ui.ctx().clone(), self.ui_tx.clone(), None);
+            }
+            if local_file_button(ui, true, egui::Button::new(self.lang.csv_import_open_btn), self.lang.web_unavailable_hint) // I18N
+                .on_hover_text(self.lang.csv_import_open_hint)
+                .clicked()
+            {
+                self.open_csv_import();
+            }
+            ui.add_enabled_ui(LOCAL_FILES, |ui| ui.menu_button(self.lang.url_open_btn, |ui| { // I18N
+                let response = ui.add(egui::TextEdit::singleline(&mut self.url_input)
+                    .hint_text("https://example.com/master.wav")
+                    .desired_width(320.0));
+                let submitted = response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                let url = self.url_input.trim().to_string();
+                let valid = url.starts_with("http://") || url.starts_with("https://");
+                if ui.add_enabled(valid, egui::Button::new(self.lang.url_load_btn)).clicked() || (submitted && valid) { // I18N
+                    self.spawn_url_load(url);
+                    self.url_input.clear();
+                    ui.close();
+                }
+            })).inner.response.on_disabled_hover_text(self.lang.web_unavailable_hint);
+
+            if ui.button(self.lang.single_clear_btn).clicked() { // I18N
+                if self.state.loaded_annotation_count() > 0 {
+                    self.confirm_clear = true;
+                } else {
+                    self.clear_single_files();
+                }
+            }
+
+            let curves = self.state.single_files.lock().unwrap();
+            self.duplicate_scan.refresh(&curves, self.state.curves_revision);
+            let remove_duplicates = ui.add_enabled(self.duplicate_scan.any(), egui::Button::new(self.lang.duplicate_remove_btn)) // I18N
+                .on_hover_text(self.lang.duplicate_remove_hint)
+                .clicked();
+            // 导出选中 (聚焦) 的曲线
+            let selected = self.focused_index.and_then(|i| curves.get(i));
+            if ui.add_enabled(selected.is_some(), egui::Button::new(self.lang.export_csv_btn)).clicked() // I18N
+                && let Some(curve) = selected
+            {
+                match export_to_csv(curve, &self.csv_export_options(), &self.logger) {
+                    Ok(_) => self.state.notifications.push(ToastLevel::Success, self.lang.export_success_fmt.replacen("{}", &curve.name, 1)),
+                    Err(e) => {
+                        let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                        log_error(&self.logger, &err_msg);
+                        self.state.notifications.push(ToastLevel::Error, err_msg.clone());
+                        self.state.error_msg = Some(err_msg);
+                    }
+                }
+            }
+            // 全部导出到所选文件夹，每条曲线一个 CSV
+            if local_file_button(ui, !curves.is_empty(), egui::Button::new(self.lang.export_all_btn), self.lang.web_unavailable_hint).clicked() // I18N
+                && let Some(folder) = FileDialog::new().pick_folder()
+            {
+                let mut failed = 0;
+                for curve in curves.iter() {
+                    let path = folder.join(format!("{}.csv", export_file_stem(curve)));
+                    if let Err(e) = write_curve_csv(curve, &self.csv_export_options(), &path, &self.logger) {
+                        let err_msg = self.lang.export_failed_fmt.replacen("{}", &format!("{}: {}", curve.name, e), 1);
+                        log_error(&self.logger, &err_msg);
+                        self.state.notifications.push(ToastLevel::Error, err_msg);
+                        failed += 1;
+                    }
+                }
+                let level = if failed == 0 { ToastLevel::Success } else { ToastLevel::Warning };
+                let summary = self.lang.export_all_done_fmt
+                    .replacen("{}", &(curves.len() - failed).to_string(), 1)
+                    .replacen("{}", &curves.len().to_string(), 1)
+                    .replacen("{}", &folder.display().to_string(), 1);
+                self.state.notifications.push(level, summary);
+            }
+            drop(curves); // 释放锁
+            if remove_duplicates {
+                let removed = self.state.remove_duplicate_curves();
+                self.focused_index = None;
+                self.state.notifications.push(ToastLevel::Info, self.lang.duplicate_removed_fmt.replacen("{}", &removed.to_string(), 1));
+            }
+            ui.checkbox(&mut self.state.settings.export_resample, self.lang.export_resample_label) // I18N
+                .on_hover_text(self.lang.export_resample_hint);
+            ui.add_enabled(self.state.settings.export_resample, egui::DragValue::new(&mut self.state.settings.export_interval)
+                .speed(0.01)
+                .range(0.01..=60.0)
+                .suffix(" s")
+            );
+        });
+
+        // --- 归一化设置 ---
+        ui.horizontal(|ui| {
+            ui.label(self.lang.normalize_mode_label); // I18N
+            ui.selectable_value(&mut self.state.settings.normalize_mode, NormalizeMode::Average, self.lang.normalize_mode_average);
+            ui.selectable_value(&mut self.state.settings.normalize_mode, NormalizeMode::Peak, self.lang.normalize_mode_peak)
+                .on_hover_text(self.lang.normalize_peak_hint);
+            let (label, target) = match self.state.settings.normalize_mode {
+                NormalizeMode::Average => (self.lang.normalize_label, &mut self.target_lufs),
+                NormalizeMode::Peak => (self.lang.normalize_peak_label, &mut self.peak_target),
+            };
+            ui.label(label); // I18N
+            ui.add(egui::DragValue::new(target)
+                .speed(0.1)
+                .range(-60.0..=0.0)
+                .suffix(" dBFS")
+            );
+            // 归一化在图表中实时显示；此按钮把增益实际写入音频文件
+            let has_audio = self.state.single_files.lock().unwrap().iter().any(|c| c.source.is_some());
+            if local_file_button(ui, has_audio, egui::Button::new(self.lang.normalize_apply), self.lang.web_unavailable_hint) // I18N
+                .on_hover_text(self.lang.normalize_apply_hint)
+                .clicked()
+                && let Some(folder) = FileDialog::new().pick_folder()
+            {
+                self.spawn_normalized_export(folder);
+            }
+            ui.separator();
+            ui.checkbox(&mut self.state.settings.show_extreme_markers, self.lang.single_show_markers); // I18N
+            ui.checkbox(&mut self.state.settings.hide_silence_floor, self.lang.single_hide_silence) // I18N
+                .on_hover_text(self.lang.single_hide_silence_hint);
+            ui.separator();
+            ui.label(self.lang.single_view_label); // I18N
+            ui.selectable_value(&mut self.state.settings.single_view, SingleView::Overall, self.lang.single_view_overall);
+            ui.selectable_value(&mut self.state.settings.single_view, SingleView::Bands, self.lang.single_view_bands);
+            ui.selectable_value(&mut self.state.settings.single_view, SingleView::Bars, self.lang.single_view_bars)
+                .on_hover_text(self.lang.bars_hint);
+            ui.add_enabled_ui(self.state.settings.single_view == SingleView::Overall, |ui| {
+                ui.checkbox(&mut self.state.settings.show_momentary, self.lang.meter_momentary) // I18N
+                    .on_hover_text(self.lang.meter_momentary_hint);
+                ui.checkbox(&mut self.state.settings.show_short_term, self.lang.meter_short_term) // I18N
+                    .on_hover_text(self.lang.meter_short_term_hint);
+                ui.checkbox(&mut self.state.settings.show_integrated, self.lang.meter_integrated) // I18N
+                    .on_hover_text(self.lang.meter_integrated_hint);
+            });
+            ui.separator();
+            ui.label(self.lang.level_display_label); // I18N
+            ui.selectable_value(&mut self.state.settings.level_display, LevelDisplay::Normalized, self.lang.level_display_normalized);
+            ui.selectable_value(&mut self.state.settings.level_display, LevelDisplay::Raw, self.lang.level_display_raw);
+            ui.selectable_value(&mut self.state.settings.level_display, LevelDisplay::Both, self.lang.level_display_both);
+            ui.separator();
+            ui.checkbox(&mut self.state.settings.show_stats_table, self.lang.single_table_toggle); // I18N
+        });
+        ui.horizontal(|ui| {
+            self.single_y_axis.ui(ui, &self.lang, &mut self.state.settings.single_y_range);
+            ui.separator();
+            self.state.settings.ui_zone_controls(ui, &self.lang, self.target_lufs);
+        });
+
+        // --- 归一化预览：逐文件显示实际应用的增益 (与图表使用同一偏移) ---
+        egui::CollapsingHeader::new(self.lang.normalize_preview_title) // I18N
+            .id_salt("normalize_preview")
+            .show(ui, |ui| {
+                ui.weak(self.lang.normalize_preview_note); // I18N
+                let curves = self.state.single_files.lock().unwrap();
+                let (mode, target) = (self.state.settings.normalize_mode, self.normalize_target());
+                egui::ScrollArea::vertical().max_height(160.0).show(ui, |ui| {
+                    egui::Grid::new("normalize_preview_grid")
+                        .striped(true)
+                        .num_columns(5)
+                        .show(ui, |ui| {
+                            ui.strong(self.lang.stats_col_name);
+                            ui.strong(self.lang.normalize_col_current);
+                            ui.strong(self.lang.normalize_col_gain);
+                            ui.strong(self.lang.normalize_col_result);
+                            ui.strong(self.lang.normalize_col_loudest);
+                            ui.end_row();
+
+                            for (index, curve) in curves.iter().enumerate() {
+                                let offset = curve.normalization_gain(mode, target);
+                                ui.colored_label(Palette::curve_color(index), &curve.name);
+                                ui.label(format!("{:.2}", curve.average_dbfs));
+                                ui.label(format!("{:+.2}", offset));
+                                ui.label(format!("{:.2}", curve.average_dbfs + offset));
+                                match curve.loudest {
+                                    Some(p) if p[1] + offset > 0.0 => {
+                                        ui.colored_label(self.palette.error, format!("{:.2} ⚠", p[1] + offset));
+                                    }
+                                    Some(p) => {
+                                        ui.label(format!("{:.2}", p[1] + offset));
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                }
+                                ui.end_row();
+                            }
+                        });
+                });
+            });
+
+        // --- 交付规格检查 ---
+        if !self.state.single_files.lock().unwrap().is_empty() {
+            self.ui_delivery_dashboard(ui);
+        }
+        ui.separator();
+
+
+        // 临时按钮：用于在异步加载结束后手动关闭 loading 状态 (仅用于调试)
+        if self.state.loading && ui.button(self.lang.debug_end_loading).clicked() { // I18N
+            self.state.loading = false;
+            ctx.request_repaint(); // 手动重绘
+        }
+
+        // 绘图区域
+        let mut curves = self.state.single_files.lock().unwrap();
+        self.focused_index = self.focused_index.filter(|&i| i < curves.len());
+        self.state.draw_order.sync(curves.len());
+        if curves.is_empty() {
+            ui.label(self.lang.single_empty_label); // I18N
+        } else {
+            // 没有控件持有键盘焦点时，Tab/↓ 聚焦下一条曲线，Shift+Tab/↑ 上一条，Esc 取消
+            if ui.memory(|m| m.focused().is_none()) {
+                let (prev, next, clear) = ui.input_mut(|i| {
+                    // Shift+Tab 需先于 Tab 检查 (consume_key 在未指定 Shift 时会忽略 Shift)
+                    let prev = i.consume_key(egui::Modifiers::SHIFT, egui::Key::Tab)
+                        | i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp);
+                    let next = i.consume_key(egui::Modifiers::NONE, egui::Key::Tab)
+                        | i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown);
+                    (prev, next, i.consume_key(egui::Modifiers::NONE, egui::Key::Escape))
+                });
+                let count = curves.len();
+                if next {
+                    self.focused_index = Some(self.focused_index.map_or(0, |i| (i + 1) % count));
+                } else if prev {
+                    self.focused_index = Some(self.focused_index.map_or(count - 1, |i| (i + count - 1) % count));
+                } else if clear {
+                    self.focused_index = None;
+                }
+                if next || prev {
+                    // 阻止 Tab 同时把焦点移到按钮上
+                    ui.memory_mut(|m| m.move_focus(egui::FocusDirection::None));
+                }
+            }
+
+            let mut playback_request = None;
+            let mut stop_playback = false;
+            let mut raw_toggled = None;
+            let mut watch_toggled = None;
+            let mut details_request = None;
+            ui.horizontal(|ui| {
+                match self.focused_index {
+                    Some(index) => {
+                        let curve = &curves[index];
+                        // 回放控件作用于聚焦曲线；CSV 曲线或未启用 playback 功能时置灰
+                        let active = self.player.as_ref().filter(|p| curve.source.as_ref() == Some(&p.source));
+                        let label = if active.is_some_and(|p| p.is_playing()) { self.lang.playback_pause } else { self.lang.playback_play };
+                        let response = ui.add_enabled(PLAYBACK_AVAILABLE && curve.source.is_some(), egui::Button::new(label))
+                            .on_hover_text(self.lang.playback_seek_hint)
+                            .on_disabled_hover_text(if PLAYBACK_AVAILABLE { self.lang.playback_no_audio } else { self.lang.playback_unavailable });
+                        if response.clicked() {
+                            match active {
+                                Some(player) => player.toggle(),
+                                None => playback_request = curve.source.clone(),
+                            }
+                        }
+                        if let Some(player) = active {
+                            if ui.button(self.lang.playback_stop).clicked() {
+                                stop_playback = true;
+                            }
+                            // 拖动进度条跳转 (与 Ctrl+单击图表相同)
+                            let mut position = player.position_secs();
+                            let time = self.state.settings.time_format;
+                            let slider = ui.add(egui::Slider::new(&mut position, 0.0..=player.duration)
+                                .show_value(false)
+                                .custom_formatter(move |secs, _| time.format(secs, 1)))
+                                .on_hover_text(self.lang.playback_slider_hint); // I18N
+                            if slider.changed() {
+                                player.seek(position);
+                            }
+                            ui.label(format!("{} / {}", time.format(player.position_secs(), 1), time.format(player.duration, 1)));
+                        }
+                        ui.separator();
+                        ui.colored_label(Palette::curve_color(index), format!("{} {}", self.lang.single_focus_label, curve.display_label()));
+                        if ui.small_button("ℹ").on_hover_text(self.lang.details_hint).clicked() {
+                            details_request = Some(curve_key(curve));
+                        }
+                        ui.separator();
+                        ui.label(format!("{} {:.2} dBFS", self.lang.single_focus_avg, curve.average_dbfs));
+                        if let Some(p) = curve.loudest {
+                            ui.label(format!("{} {:.2} dBFS @ {}", self.lang.single_focus_loudest, p[1], self.state.settings.time_format.format(p[0], 1)));
+                        }
+                        if let Some(p) = curve.quietest {
+                            ui.label(format!("{} {:.2} dBFS @ {}", self.lang.single_focus_quietest, p[1], self.state.settings.time_format.format(p[0], 1)));
+                        }
+                        ui.label(format!("{} {}", self.lang.single_focus_duration, self.state.settings.time_format.format(curve.duration, 1)));
+                        ui.label(format!("{} {}", self.lang.single_focus_points, curve.points.len()));
+                        if curve.silence_gating.excluded > 0 {
+                            ui.label(self.lang.single_focus_silent_fmt.replacen("{}", &curve.silence_gating.to_string(), 1)); // I18N
+                        }
+                        ui.separator();
+                        let mut show_raw = curve.show_raw;
+                        if ui.checkbox(&mut show_raw, self.lang.level_raw_override).on_hover_text(self.lang.level_raw_override_hint).changed() {
+                            raw_toggled = Some(index);
+                        }
+                        // 单独监视该文件 (全局开启时所有文件都被监视)
+                        if let Some(source) = &curve.source {
+                            let all = self.state.settings.auto_reload_all;
+                            let mut watched = all || self.watched_files.contains(source);
+                            if ui.add_enabled(!all, egui::Checkbox::new(&mut watched, self.lang.watch_file_label))
+                                .on_hover_text(self.lang.watch_file_hint) // I18N
+                                .changed()
+                            {
+                                watch_toggled = Some(source.clone());
+                            }
+                        }
+                    }
+                    None => {
+                        ui.weak(self.lang.single_focus_hint); // I18N
+                    }
+                }
+            });
+
+            // 绘制顺序：拖动 ☰ 或 ▲▼ 调整，📌 置顶，最上面的一行画在最上层；单击名称聚焦并置于最上层
+            egui::CollapsingHeader::new(self.lang.draw_order_title) // I18N
+                .id_salt("draw_order_list")
+                .show(ui, |ui| {
+                    ui.weak(self.lang.draw_order_hint); // I18N
+                    let mut moved = None;
+                    let mut front = None;
+                    let mut back = None;
+                    let mut stepped = None;
+                    let mut pin = None;
+                    let count = self.state.draw_order.order.len();
+                    for (rank, &index) in self.state.draw_order.order.iter().rev().enumerate() {
+                        let curve = &curves[index];
+                        let pinned = self.state.draw_order.is_pinned(index);
+                        let row = ui.horizontal(|ui| {
+                            ui.dnd_drag_source(egui::Id::new(("draw_order_handle", index)), index, |ui| {
+                                ui.label("☰");
+                            });
+                            if ui.add_enabled(rank > 0, egui::Button::new("▲").small()).on_hover_text(self.lang.draw_order_up_hint).clicked() { // I18N
+                                stepped = Some((index, true));
+                            }
+                            if ui.add_enabled(rank + 1 < count, egui::Button::new("▼").small()).on_hover_text(self.lang.draw_order_down_hint).clicked() { // I18N
+                                stepped = Some((index, false));
+                            }
+                            if ui.selectable_label(pinned, "📌").on_hover_text(self.lang.draw_order_pin_hint).clicked() { // I18N
+                                pin = Some(index);
+                            }
+                            let response = ui.add(egui::Label::new(egui::RichText::new(curve.display_label()).color(Palette::curve_color(index)))
+                                .sense(egui::Sense::click()))
+                                .on_hover_text(self.lang.draw_order_front_hint);
+                            if response.clicked() {
+                                front = Some(index);
+                            }
+                            if curve_is_stale(curve, &self.state.settings) {
+                                ui.colored_label(self.palette.warn, "⚠").on_hover_text(self.lang.analysis_stale_curve_hint); // I18N
+                            }
+                            duplicate_badge(ui, &self.duplicate_scan, &curves, index, &self.lang, self.palette.warn);
+                            response.context_menu(|ui| {
+                                if ui.button(self.lang.draw_order_move_top).clicked() {
+                                    front = Some(index);
+                                    ui.close();
+                                }
+                                if ui.button(self.lang.draw_order_move_bottom).clicked() {
+                                    back = Some(index);
+                                    ui.close();
+                                }
+                                if ui.button(if pinned { self.lang.draw_order_unpin } else { self.lang.draw_order_pin }).clicked() { // I18N
+                                    pin = Some(index);
+                                    ui.close();
+                                }
+                            });
+                        });
+                        // 拖到某行上：放到该曲线的上一层，并画出插入位置
+                        if row.response.dnd_hover_payload::<usize>().is_some() {
+                            let rect = row.response.rect;
+                            ui.painter().hline(rect.x_range(), rect.top(), ui.visuals().selection.stroke);
+                        }
+                        if let Some(dragged) = row.response.dnd_release_payload::<usize>() {
+                            moved = Some((*dragged, index));
+                        }
+                    }
+                    if let Some((dragged, target)) = moved {
+                        self.state.draw_order.move_above(dragged, target);
+                    }
+                    if let Some(index) = front {
+                        self.state.draw_order.bring_to_front(index);
+                        self.focused_index = Some(index);
+                    }
+                    if let Some(index) = back {
+                        self.state.draw_order.send_to_back(index);
+                    }
+                    if let Some((index, up)) = stepped {
+                        self.state.draw_order.step(index, up);
+                    }
+                    if let Some(index) = pin {
+                        self.state.draw_order.toggle_pin(index);
+                    }
+                });
+
+            // 统计表：单击行聚焦曲线，双击行将图表缩放到该曲线
+            if self.state.settings.show_stats_table {
+                self.stats_table.refresh(&curves, self.state.curves_revision);
+                if let Some(tag) = self.tag_filter.clone() {
+                    ui.horizontal(|ui| {
+                        ui.label(self.lang.tag_filter_label); // I18N
+                        tag_chip(ui, &tag);
+                        if ui.small_button("✖").on_hover_text(self.lang.tag_filter_clear).clicked() {
+                            self.tag_filter = None;
+                        }
+                    });
+                }
+                // 标签过滤后实际显示的行
+                let spec = self.state.settings.delivery_spec;
+                let visible: Vec<usize> = self.stats_table.order.iter().copied()
+                    .filter(|&i| self.tag_filter.as_ref().is_none_or(|tag| curves[i].tags.contains(tag)))
+                    .filter(|&i| !self.delivery_failures_only || spec.fails(&curves[i]))
+                    .collect();
+                // 没有专用列的指标各占一列 (平均电平和最响窗口已有 Average / Loudest 列)
+                let metric_columns: Vec<String> = curves.iter()
+                    .flat_map(|curve| curve.metrics.keys())
+                    .filter(|name| ![AVERAGE_METRIC, PEAK_WINDOW_METRIC].contains(&name.as_str()))
+                    .cloned()
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .collect();
+                let mut sort_clicked = None;
+                let mut row_clicked = None;
+                let mut row_double_clicked = None;
+                let mut edit_request = None;
+                let mut edit_finished = None;
+                let mut tag_removed = None;
+                let mut filter_request = None;
+                // 从进程监视器跳转过来时滚动到该曲线所在行
+                let scroll_row = self.stats_table.scroll_to.take().and_then(|index| visible.iter().position(|&i| i == index));
+                ui.push_id("single_stats_table", |ui| {
+                    let table = TableBuilder::new(ui)
+                        .striped(true)
+                        .sense(egui::Sense::click())
+                        .max_scroll_height(180.0)
+                        .column(Column::auto())
+                        .column(Column::remainder().at_least(160.0).clip(true))
+                        .columns(Column::auto().at_least(80.0), StatsColumn::ALL.len() - 1)
+                        .columns(Column::auto().at_least(80.0), metric_columns.len());
+                    let table = match scroll_row {
+                        Some(row) => table.scroll_to_row(row, Some(egui::Align::Center)),
+                        None => table,
+                    };
+                    table
+                        .header(20.0, |mut header| {
+                            header.col(|ui| {
+                                ui.label(self.lang.level_raw_override).on_hover_text(self.lang.level_raw_override_hint);
+                            });
+                            for column in StatsColumn::ALL {
+                                header.col(|ui| {
+                                    let arrow = match (self.stats_table.sort == column, self.stats_table.ascending) {
+                                        (true, true) => " ▲",
+                                        (true, false) => " ▼",
+                                        _ => "",
+                                    };
+                                    if ui.button(format!("{}{}", self.lang.stats_column_label(column), arrow)).clicked() {
+                                        sort_clicked = Some(column);
+                                    }
+                                });
+                            }
+                            for name in &metric_columns {
+                                header.col(|ui| { ui.strong(name); });
+                            }
+                        })
+                        .body(|body| {
+                            // 只绘制可见行
+                            body.rows(18.0, visible.len(), |mut row| {
+                                let index = visible[row.index()];
+                                let curve = &curves[index];
+                                let level = |p: Option<[f64; 2]>| p.map_or_else(|| "-".to_string(), |p| format!("{:.2}", p[1]));
+                                row.set_selected(self.focused_index == Some(index));
+                                row.col(|ui| {
+                                    let mut show_raw = curve.show_raw;
+                                    if ui.checkbox(&mut show_raw, "").changed() {
+                                        raw_toggled = Some(index);
+                                    }
+                                });
+                                row.col(|ui| {
+                                    let key = curve_key(curve);
+                                    if ui.small_button("ℹ").on_hover_text(self.lang.details_hint).clicked() {
+                                        details_request = Some(key.clone());
+                                    }
+                                    // 行内编辑框：回车或点击别处提交
+                                    let mut edit_box = |ui: &mut egui::Ui, edit: &mut CurveEdit| {
+                                        let response = ui.add(egui::TextEdit::singleline(&mut edit.text).desired_width(120.0));
+                                        if std::mem::take(&mut edit.focus_pending) {
+                                            response.request_focus();
+                                        }
+                                        if response.lost_focus() {
+                                            edit_finished = Some((index, edit.kind, edit.text.trim().to_string()));
+                                        }
+                                    };
+                                    match &mut self.curve_edit {
+                                        Some(edit) if edit.key == key && edit.kind == CurveEditKind::Rename => edit_box(ui, edit),
+                                        _ => {
+                                            let response = ui.add(egui::Label::new(egui::RichText::new(&curve.name).color(Palette::curve_color(index)))
+                                                .sense(egui::Sense::click()))
+                                                .on_hover_text(self.lang.curve_rename_hint);
+                                            if response.double_clicked() {
+                                                edit_request = Some(CurveEdit { key: key.clone(), kind: CurveEditKind::Rename, text: curve.name.clone(), focus_pending: true });
+                                            }
+                                        }
+                                    }
+                                    duplicate_badge(ui, &self.duplicate_scan, &curves, index, &self.lang, self.palette.warn);
+                                    for (tag_index, tag) in curve.tags.iter().enumerate() {
+                                        let response = tag_chip(ui, tag).on_hover_text(self.lang.tag_chip_hint);
+                                        if response.clicked() {
+                                            filter_request = Some(tag.clone());
+                                        } else if response.secondary_clicked() {
+                                            tag_removed = Some((index, tag_index));
+                                        }
+                                    }
+                                    match &mut self.curve_edit {
+                                        Some(edit) if edit.key == key && edit.kind == CurveEditKind::AddTag => edit_box(ui, edit),
+                                        _ => {
+                                            if ui.small_button("+").on_hover_text(self.lang.tag_add_hint).clicked() {
+                                                edit_request = Some(CurveEdit { key, kind: CurveEditKind::AddTag, text: String::new(), focus_pending: true });
+                                            }
+                                        }
+                                    }
+                                });
+                                row.col(|ui| { ui.label(self.state.settings.time_format.format(curve.duration, 1)); });
+                                row.col(|ui| { ui.label(format!("{:.2}", curve.average_dbfs)); });
+                                row.col(|ui| { ui.label(level(curve.loudest)); });
+                                row.col(|ui| { ui.label(level(curve.quietest)); });
+                                row.col(|ui| { ui.label(curve.points.len().to_string()); });
+                                for name in &metric_columns {
+                                    row.col(|ui| { ui.label(curve.metric(name).map_or_else(|| "-".to_string(), |v| v.to_string())); });
+                                }
+
+                                let response = row.response();
+                                if response.double_clicked() {
+                                    row_double_clicked = Some(index);
+                                } else if response.clicked() {
+                                    row_clicked = Some(index);
+                                }
+                            });
+                        });
+                });
+
+                if let Some(column) = sort_clicked {
+                    self.stats_table.toggle_sort(column);
+                }
+                if filter_request.is_some() {
+                    self.tag_filter = filter_request;
+                }
+                // 重命名和标签修改后写回曲线并记录，使图例、导出及下次加载保持一致
+                let mut label_changed = None;
+                if let Some((index, kind, text)) = edit_finished {
+                    self.curve_edit = None;
+                    let curve = &mut curves[index];
+                    match kind {
+                        CurveEditKind::Rename if !text.is_empty() && text != curve.name => {
+                            log_info(&self.logger, &format!("重命名曲线: {} -> {}", curve.name, text));
+                            curve.name = text;
+                            label_changed = Some(index);
+                        }
+                        CurveEditKind::AddTag if !text.is_empty() && !curve.tags.contains(&text) => {
+                            curve.tags.push(text);
+                            label_changed = Some(index);
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some((index, tag_index)) = tag_removed {
+                    curves[index].tags.remove(tag_index);
+                    label_changed = Some(index);
+                }
+                if let Some(index) = label_changed {
+                    CurveLabel::store(&mut self.state.curve_labels, &curves[index]);
+                    self.state.curves_revision += 1;
+                }
+                if edit_request.is_some() {
+                    self.curve_edit = edit_request;
+                }
+                if let Some(index) = row_clicked.or(row_double_clicked) {
+                    self.focused_index = Some(index);
+                }
+                if let Some(index) = row_double_clicked {
+                    // 缩放到该曲线 (按其显示的原始/归一化电平) 的时间和响度范围
+                    let curve = &curves[index];
+                    let offset = self.state.settings.display_offset(curve, self.normalize_target());
+                    let (min_db, max_db) = curve.points.iter()
+                        .filter(|p| p[1] > SILENCE_FLOOR_DBFS)
+                        .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), p| (lo.min(p[1]), hi.max(p[1])));
+                    if min_db.is_finite() {
+                        self.pending_plot_zoom = Some(PlotBounds::from_min_max(
+                            [0.0, min_db + offset - 3.0],
+                            [curve.duration.max(0.1), max_db + offset + 3.0],
+                        ));
+                    }
+                }
+            }
+
+            // 标注列表：跳转到标注时间或删除，可导出为 Audacity 标签轨或 CSV
+            let rows: Vec<(usize, usize, &Annotation)> = curves.iter().enumerate()
+                .flat_map(|(curve_index, curve)| {
+                    self.state.annotations.get(&curve_key(curve)).into_iter().flatten().enumerate()
+                        .map(move |(index, annotation)| (curve_index, index, annotation))
+                })
+                .collect();
+            let (mut jump, mut delete, mut export) = (None, None, None);
+            egui::CollapsingHeader::new(format!("{} ({})", self.lang.annotation_title, rows.len())) // I18N
+                .id_salt("annotations_panel")
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        if local_file_button(ui, !rows.is_empty(), egui::Button::new(self.lang.annotation_export_audacity), self.lang.web_unavailable_hint).clicked() { // I18N
+                            export = Some(AnnotationFormat::Audacity);
+                        }
+                        if local_file_button(ui, !rows.is_empty(), egui::Button::new(self.lang.annotation_export_csv), self.lang.web_unavailable_hint).clicked() { // I18N
+                            export = Some(AnnotationFormat::Csv);
+                        }
+                        ui.weak(self.lang.annotation_hint); // I18N
+                    });
+                    egui::ScrollArea::vertical().max_height(120.0).show(ui, |ui| {
+                        for &(curve_index, index, annotation) in &rows {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(Palette::curve_color(curve_index), &curves[curve_index].name);
+                                ui.monospace(self.state.settings.time_format.format(annotation.time, 2));
+                                ui.label(&annotation.text);
+                                if ui.small_button(self.lang.annotation_jump).clicked() { // I18N
+                                    jump = Some((curve_index, annotation.time));
+                                }
+                                if ui.small_button(self.lang.annotation_delete).clicked() { // I18N
+                                    delete = Some((curve_index, index));
+                                }
+                            });
+                        }
+                    });
+                });
+
+            if let Some(format) = export {
+                let (name, filter, extension) = match format {
+                    AnnotationFormat::Audacity => ("labels.txt", "Audacity Labels", "txt"),
+                    AnnotationFormat::Csv => ("annotations.csv", "CSV File", "csv"),
+                };
+                if let Some(path) = FileDialog::new().set_file_name(name).add_filter(filter, &[extension]).save_file() {
+                    let export_rows: Vec<(&str, &Annotation)> = rows.iter().map(|&(c, _, a)| (curves[c].name.as_str(), a)).collect();
+                    match write_annotations(&export_rows, format, &path, &self.logger) {
+                        Ok(()) => self.state.notifications.push(ToastLevel::Success, self.lang.export_success_fmt.replacen("{}", &path.display().to_string(), 1)),
+                        Err(e) => {
+                            let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                            log_error(&self.logger, &err_msg);
+                            self.state.notifications.push(ToastLevel::Error, err_msg);
+                        }
+                    }
+                }
+            }
+            drop(rows);
+            if let Some((curve_index, time)) = jump {
+                // 聚焦该曲线并把图表缩放到标注附近
+                let curve = &curves[curve_index];
+                self.focused_index = Some(curve_index);
+                if let Some(level) = interpolate_at(&curve.points, time) {
+                    let level = level + self.state.settings.display_offset(curve, self.normalize_target());
+                    self.pending_plot_zoom = Some(PlotBounds::from_min_max([time - 5.0, level - 15.0], [time + 5.0, level + 15.0]));
+                }
+            }
+            if let Some((curve_index, index)) = delete {
+                let key = curve_key(&curves[curve_index]);
+                if let Some(list) = self.state.annotations.get_mut(&key) {
+                    list.remove(index);
+                    if list.is_empty() {
+                        self.state.annotations.remove(&key);
+                    }
+                }
+            }
+
+            // 响度跳变：查找剪辑点前后增益不一致之处，单击跳到该位置，可导出为 Audacity 标签轨或 CSV
+            let time_format = self.state.settings.time_format;
+            let settings = &mut self.state.settings;
+            self.jump_scan.refresh(&curves, self.state.curves_revision, settings.jump_threshold_db, settings.jump_window_sec);
+            let (mut jump, mut export) = (None, None);
+            egui::CollapsingHeader::new(format!("{} ({})", self.lang.jumps_title, self.jump_scan.events.len())) // I18N
+                .id_salt("loudness_jumps_panel")
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        ui.label(self.lang.jumps_threshold_label); // I18N
+                        ui.add(egui::DragValue::new(&mut settings.jump_threshold_db).speed(0.1).range(0.5..=60.0).suffix(" dB"));
+                        ui.label(self.lang.jumps_window_label); // I18N
+                        ui.add(egui::DragValue::new(&mut settings.jump_window_sec).speed(0.01).range(0.01..=10.0).suffix(" s"));
+                        ui.separator();
+                        let any = !self.jump_scan.events.is_empty();
+                        if local_file_button(ui, any, egui::Button::new(self.lang.annotation_export_audacity), self.lang.web_unavailable_hint).clicked() { // I18N
+                            export = Some(AnnotationFormat::Audacity);
+                        }
+                        if local_file_button(ui, any, egui::Button::new(self.lang.annotation_export_csv), self.lang.web_unavailable_hint).clicked() { // I18N
+                            export = Some(AnnotationFormat::Csv);
+                        }
+                    });
+                    ui.weak(self.lang.jumps_hint); // I18N
+                    egui::ScrollArea::vertical().id_salt("loudness_jumps_list").max_height(160.0).show(ui, |ui| {
+                        for &(curve_index, event) in &self.jump_scan.events {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(Palette::curve_color(curve_index), &curves[curve_index].name);
+                                let time = time_format.format(event.start, 2);
+                                let (arrow, color) = if event.change_db > 0.0 { ("▲", self.palette.warn) } else { ("▼", self.palette.track_b) };
+                                let response = ui.add(egui::Label::new(egui::RichText::new(format!("{}  {} {:+.1} dB", time, arrow, event.change_db)).monospace().color(color))
+                                    .sense(egui::Sense::click()))
+                                    .on_hover_text(self.lang.jumps_row_hint); // I18N
+                                if response.clicked() {
+                                    jump = Some((curve_index, event));
+                                }
+                            });
+                        }
+                    });
+                });
+            if let Some(format) = export {
+                let (name, filter, extension) = match format {
+                    AnnotationFormat::Audacity => ("jumps.txt", "Audacity Labels", "txt"),
+                    AnnotationFormat::Csv => ("jumps.csv", "CSV File", "csv"),
+                };
+                if let Some(path) = FileDialog::new().set_file_name(name).add_filter(filter, &[extension]).save_file() {
+                    let rows: Vec<(&str, LoudnessJump)> = self.jump_scan.events.iter().map(|&(c, event)| (curves[c].name.as_str(), event)).collect();
+                    match write_jump_events(&rows, format, &path, &self.logger) {
+                        Ok(()) => self.state.notifications.push(ToastLevel::Success, self.lang.export_success_fmt.replacen("{}", &path.display().to_string(), 1)),
+                        Err(e) => {
+                            let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                            log_error(&self.logger, &err_msg);
+                            self.state.notifications.push(ToastLevel::Error, err_msg);
+                        }
+                    }
+                }
+            }
+            if let Some((curve_index, event)) = jump {
+                // 聚焦该曲线，图表缩放到跳变前后各几秒
+                let curve = &curves[curve_index];
+                self.focused_index = Some(curve_index);
+                let offset = self.state.settings.display_offset(curve, self.normalize_target());
+                let levels = [event.start, event.end].map(|t| interpolate_at(&curve.points, t).unwrap_or(SILENCE_FLOOR_DBFS) + offset);
+                let (low, high) = (levels[0].min(levels[1]), levels[0].max(levels[1]));
+                self.pending_plot_zoom = Some(PlotBounds::from_min_max([event.start - 3.0, low - 10.0], [event.end + 3.0, high + 10.0]));
+            }
+
+            if stop_playback {
+                self.player = None;
+            }
+            if let Some(path) = watch_toggled
+                && !self.watched_files.remove(&path)
+            {
+                log_info(&self.logger, &format!("监视文件变化: {}", path.display()));
+                self.watched_files.insert(path);
+            }
+            if let Some(index) = raw_toggled {
+                curves[index].show_raw = !curves[index].show_raw;
+            }
+            if details_request.is_some() {
+                self.details_curve = details_request;
+            }
+            self.plot_cache.refresh(&curves, self.state.curves_revision, self.state.settings.normalize_mode, self.normalize_target());
+
+            // 测量光标读数按各曲线当前显示的电平 (原始或归一化) 计算
+            let target = self.normalize_target();
+            let series: Vec<CursorSeries> = curves.iter()
+                .map(|curve| CursorSeries {
+                    name: &curve.name,
+                    points: &curve.points,
+                    time_shift: 0.0,
+                    level_shift: self.state.settings.display_offset(curve, target),
+                })
+                .collect();
+            self.single_cursors.show_readout(ui, &self.lang, &series, self.state.settings.time_format);
+
+            let zoom = self.pending_plot_zoom.take();
+            let cursor_color = ui.visuals().strong_text_color();
+            let mut annotation_request = None;
+            let mut copied = None;
+            let y_range = self.state.settings.single_y_range;
+            let set_y = self.single_y_axis.take_apply(y_range);
+            let fit = self.single_y_axis.fit.take();
+            // 分区按绘制的电平值画：归一化显示时曲线已移到目标附近，原始显示时即原始电平
+            let zones = self.state.settings.show_loudness_zones.then(|| {
+                (self.state.settings.zone_edges(self.target_lufs), [0.0, curves.iter().map(|c| c.duration).fold(0.0, f64::max)])
+            });
+            // 有曲线按等响曲线计权时，纵轴是感知响度而不是 dBFS
+            let perceived = curves.iter().any(|c| matches!(c.analysis.as_ref().map(|a| a.weighting), Some(CurveWeighting::EqualLoudness { .. })));
+            // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源，防止与布局中其他控件冲突
+            let fitted = if self.state.settings.single_view == SingleView::Bars {
+                if let Some(index) = self.ui_loudness_bars(ui, &curves) {
+                    self.focused_index = Some(index);
+                }
+                None
+            } else {
+                ui.push_id("single_plot_area", |ui| {
+                    self.state.settings.time_format.apply(Plot::new("single_plot"))
+                        .legend(Legend::default())
+                        .default_y_bounds(y_range[0], y_range[1])
+                        .allow_drag(!self.single_cursors.hovered)
+                        .y_axis_label(if perceived { self.lang.single_y_label_perceived } else { self.lang.single_y_label }) // I18N
+                        .x_axis_label(if self.state.settings.time_format == TimeFormat::MinSec { self.lang.single_x_label_minsec } else { self.lang.single_x_label }) // I18N
+                        .show(ui, |plot_ui| {
+                            if let Some(bounds) = zoom {
+                                plot_ui.set_plot_bounds(bounds);
+                            }
+                            if let Some(range) = set_y {
+                                plot_ui.set_plot_bounds_y(range[0]..=range[1]);
+                            }
+                            if let Some((edges, x)) = zones {
+                                draw_loudness_zones(plot_ui, edges, x, &self.palette);
+                            }
+                            // 分析时跳过的开头 / 结尾
+                            for &index in &self.state.draw_order.order {
+                                draw_excluded_ranges(plot_ui, &curves[index], 0.0, Palette::curve_color(index), index);
+                            }
+                            let x_range = plot_ui.plot_bounds().range_x();
+                            // 本帧绘制的线，用于按可见点适配 Y 轴
+                            let mut visible: Vec<&[PlotPoint]> = Vec::new();
+                            for &index in &self.state.draw_order.order {
+                                let (curve, cached) = (&curves[index], &self.plot_cache.curves[index]);
+                                // 隐藏底噪时按非静音区间拆成多段画 (同名的线在图例中合并为一项)，统计仍使用完整数据
+                                let hide_silence = self.state.settings.hide_silence_floor;
+                                let segments = |points| cached.segments(points, hide_silence);
+                                // (是否归一化, 图例后缀)；仅显示归一化电平时图例保持原样
+                                let display = if curve.show_raw { LevelDisplay::Raw } else { self.state.settings.level_display };
+                                let layers: &[(bool, Option<&str>)] = match display {
+                                    LevelDisplay::Normalized => &[(true, None)],
+                                    LevelDisplay::Raw => &[(false, Some(self.lang.level_display_raw))],
+                                    LevelDisplay::Both => &[
+                                        (false, Some(self.lang.level_display_raw)),
+                                        (true, Some(self.lang.level_display_normalized)),
+                                    ],
+                                };
+
+                                // 有聚焦曲线时：聚焦曲线加粗，其余变暗
+                                let (color, width) = match self.focused_index {
+                                    Some(focused) if focused == index => (Palette::curve_color(index), 3.0),
+                                    Some(_) => (Palette::curve_color(index).gamma_multiply(0.3), 1.0),
+                                    None => (Palette::curve_color(index), 1.5),
+                                };
+
+                                for &(normalized, tag) in layers {
+                                    let (offset, points, bands) = if normalized {
+                                        (cached.offset, &cached.normalized, &cached.normalized_bands)
+                                    } else {
+                                        (0.0, &cached.raw, &cached.raw_bands)
+                                    };
+                                    let suffix = tag.map(|tag| format!(" · {}", tag)).unwrap_or_default();
+                                    // "两者" 模式下原始曲线画虚线 (多频段视图已用线型区分频段，改为变暗)
+                                    let secondary = display == LevelDisplay::Both && !normalized;
+
+                                    // 多频段视图：同一文件的各频段同色，用线型区分；偏移沿用整体响度的归一化偏移
+                                    if self.state.settings.single_view == SingleView::Bands && !bands.is_empty() {
+                                        let styles = [
+                                            egui_plot::LineStyle::Solid,
+                                            egui_plot::LineStyle::Dashed { length: 8.0 },
+                                            egui_plot::LineStyle::Dotted { spacing: 4.0 },
+                                        ];
+                                        let color = if secondary { color.gamma_multiply(0.5) } else { color };
+                                        for ((band, style), band_name) in bands.iter().zip(styles).zip(self.lang.band_names) {
+                                            let name = format!("{} · {}{}", curve.display_label(), band_name, suffix);
+                                            for segment in segments(band) {
+                                                visible.push(segment);
+                                                plot_ui.line(Line::new(name.as_str(), PlotPoints::Borrowed(segment))
+                                                    .color(color)
+                                                    .width(width)
+                                                    .style(style));
+                                            }
+                                        }
+                                        continue;
+                                    }
+
+                                    let average_label = match curve.analysis.as_ref().map_or(self.state.settings.analysis.average, |a| a.average) {
+                                        AverageMode::Power => self.lang.legend_avg_power,
+                                        AverageMode::DbMean => self.lang.legend_avg_db_mean,
+                                    };
+                                    let name = format!("{} ({}: {:.2} dBFS){}", curve.display_label(), average_label, curve.average_dbfs, suffix); // I18N
+
+                                    // 极值标记与曲线同名，图例中隐藏曲线时标记一并隐藏；"两者" 模式下只标在归一化曲线上
+                                    if self.state.settings.show_extreme_markers && !secondary {
+                                        if let Some(p) = curve.loudest {
+                                            plot_ui.points(Points::new(name.clone(), vec![[p[0], p[1] + offset]])
+                                                .shape(MarkerShape::Up)
+                                                .radius(5.0)
+                                                .color(color));
+                                        }
+                                        if let Some(p) = curve.quietest {
+                                            plot_ui.points(Points::new(name.clone(), vec![[p[0], p[1] + offset]])
+                                                .shape(MarkerShape::Down)
+                                                .radius(5.0)
+                                                .color(color));
+                                        }
+                                    }
+
+                                    let style = if secondary { egui_plot::LineStyle::Dashed { length: 8.0 } } else { egui_plot::LineStyle::Solid };
+                                    for segment in segments(points) {
+                                        visible.push(segment);
+                                        plot_ui.line(Line::new(name.as_str(), PlotPoints::Borrowed(segment)).color(color).width(width).style(style));
+                                    }
+
+                                    // EBU 瞬时 (点线) / 短期 (长虚线) / 累计积分 (短虚线) 响度，与主曲线同色；单位为 LUFS
+                                    let meters = [
+                                        (self.state.settings.show_momentary, self.lang.meter_momentary, egui_plot::LineStyle::Dotted { spacing: 3.0 }),
+                                        (self.state.settings.show_short_term, self.lang.meter_short_term, egui_plot::LineStyle::Dashed { length: 14.0 }),
+                                        (self.state.settings.show_integrated, self.lang.meter_integrated, egui_plot::LineStyle::Dashed { length: 4.0 }),
+                                    ];
+                                    let meter_color = if secondary { color.gamma_multiply(0.5) } else { color };
+                                    for ((show, label, style), series) in meters.into_iter().zip(&cached.meters) {
+                                        if !show {
+                                            continue;
+                                        }
+                                        let name = format!("{} · {}{}", curve.display_label(), label, suffix);
+                                        for segment in series.segments(normalized, hide_silence) {
+                                            visible.push(segment);
+                                            plot_ui.line(Line::new(name.as_str(), PlotPoints::Borrowed(segment)).color(meter_color).width(width).style(style));
+                                        }
+                                    }
+                                }
+                            }
+
+                            self.single_cursors.interact(plot_ui);
+                            self.single_cursors.draw(plot_ui, cursor_color);
+                            let time = self.state.settings.time_format;
+                            copied = copy_value_on_click(plot_ui, &series, |x| time.format(x, 3));
+
+                            // 标注：菱形标记加文本，图例中统一归入 "标注"
+                            for (index, curve) in curves.iter().enumerate() {
+                                let Some(list) = self.state.annotations.get(&curve_key(curve)) else { continue };
+                                let offset = self.state.settings.display_offset(curve, target);
+                                for annotation in list {
+                                    let Some(level) = interpolate_at(&curve.points, annotation.time) else { continue };
+                                    let position = PlotPoint::new(annotation.time, level + offset);
+                                    plot_ui.points(Points::new(self.lang.annotation_title, vec![[position.x, position.y]])
+                                        .shape(MarkerShape::Diamond)
+                                        .radius(6.0)
+                                        .color(Palette::curve_color(index)));
+                                    plot_ui.text(egui_plot::Text::new(self.lang.annotation_title, position, annotation.text.as_str())
+                                        .anchor(egui::Align2::LEFT_BOTTOM)
+                                        .color(Palette::curve_color(index)));
+                                }
+                            }
+
+                            // 右键单击：为聚焦曲线 (没有聚焦时为该时刻最接近指针的曲线) 添加标注
+                            if plot_ui.response().secondary_clicked()
+                                && let Some(pointer) = plot_ui.pointer_coordinate()
+                            {
+                                let index = self.focused_index.or_else(|| {
+                                    curves.iter().enumerate()
+                                        .filter_map(|(i, c)| {
+                                            interpolate_at(&c.points, pointer.x)
+                                                .map(|v| (i, (v + self.state.settings.display_offset(c, target) - pointer.y).abs()))
+                                        })
+                                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                                        .map(|(i, _)| i)
+                                });
+                                annotation_request = index.map(|i| (i, pointer.x.max(0.0)));
+                            }
+
+                            // 回放光标；Ctrl+单击图表跳转到对应时间
+                            if let Some(player) = &self.player {
+                                if plot_ui.response().clicked()
+                                    && plot_ui.ctx().input(|i| i.modifiers.command)
+                                    && let Some(pointer) = plot_ui.pointer_coordinate()
+                                {
+                                    player.seek(pointer.x.min(player.duration));
+                                }
+                                plot_ui.vline(egui_plot::VLine::new(self.lang.playback_cursor, player.position_secs())
+                                    .color(cursor_color)
+                                    .width(1.5));
+                            }
+
+                            // 目标响度线 (曲线已按目标归一化显示)
+                            let target_fmt = format!("{:.1}", target);
+                            plot_ui.hline(egui_plot::HLine::new(self.lang.single_target_line_fmt.replacen("{}", &target_fmt, 1), target)
+                                .color(self.palette.mean_line)
+                                .style(egui_plot::LineStyle::Dashed { length: 8.0 })
+                            );
+
+                            let fitted = fit.and_then(|fit| fit_y_range(visible_levels(&visible, x_range), fit));
+                            if let Some(range) = fitted {
+                                plot_ui.set_plot_bounds_y(range[0]..=range[1]);
+                            }
+                            fitted
+                        })
+                        .inner
+                }).inner
+            };
+            if let Some(range) = fitted {
+                self.state.settings.single_y_range = range;
+            }
+            if let Some(text) = copied {
+                self.state.notifications.push(ToastLevel::Info, self.lang.value_copied_fmt.replacen("{}", &text, 1)); // I18N
+            }
+
+            if let Some((index, time)) = annotation_request {
+                self.pending_annotation = Some(PendingAnnotation {
+                    key: curve_key(&curves[index]),
+                    curve_name: curves[index].name.clone(),
+                    time,
+                    text: String::new(),
+                });
+            }
+
+            drop(curves);
+            if let Some(path) = playback_request {
+                self.spawn_playback_load(path);
+            }
+        }
+    }
+
+    /// 积分响度条形图：按响度从低到高排列，按交付规格着色并画出目标线；返回本帧单击的曲线
+    fn ui_loudness_bars(&self, ui: &mut egui::Ui, curves: &[AudioCurve]) -> Option<usize> {
+        let spec = self.state.settings.delivery_spec;
+        let mut measured: Vec<(usize, f64)> = curves.iter().enumerate()
+            .filter_map(|(index, curve)| Some((index, curve.loudness.as_ref()?.integrated_lufs)))
+            .filter(|&(_, lufs)| lufs.is_finite())
+            .collect();
+        measured.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let unmeasured = curves.len() - measured.len();
+        if unmeasured > 0 {
+            ui.weak(self.lang.bars_unmeasured_fmt.replacen("{}", &unmeasured.to_string(), 1)); // I18N
+        }
+
+        // 条形从低于最安静文件和目标的整 10 dB 处画起，差异才不会被从 0 LUFS 起画的长条淹没
+        let target = spec.integrated_target as f64;
+        let lowest = measured.first().map_or(target, |&(_, lufs)| lufs.min(target));
+        let base = ((lowest - 6.0) / 10.0).floor() * 10.0;
+        let bars: Vec<egui_plot::Bar> = measured.iter().enumerate()
+            .map(|(position, &(index, lufs))| {
+                let color = if (lufs - target).abs() <= spec.integrated_tolerance as f64 { self.palette.ok } else { self.palette.error };
+                egui_plot::Bar::new(position as f64, lufs - base)
+                    .base_offset(base)
+                    .name(curves[index].display_label())
+                    .fill(color.gamma_multiply(if self.focused_index == Some(index) { 1.0 } else { 0.6 }))
+            })
+            .collect();
+        let names: Vec<String> = measured.iter().map(|&(index, _)| curves[index].name.clone()).collect();
+
+        let response = ui.push_id("single_bars_area", |ui| {
+            Plot::new("single_bars")
+                .y_axis_label(self.lang.bars_y_label) // I18N
+                .x_axis_formatter(move |mark, _| {
+                    // 只在整数位置标注文件名
+                    if mark.value.fract() != 0.0 || mark.value < 0.0 { return String::new() }
+                    names.get(mark.value as usize).cloned().unwrap_or_default()
+                })
+                .allow_drag(false)
+                .show(ui, |plot_ui| {
+                    plot_ui.bar_chart(egui_plot::BarChart::new(self.lang.single_view_bars, bars)
+                        .element_formatter(Box::new(|bar, _| format!("{}\n{:.1} LUFS", bar.name, bar.value + bar.base_offset.unwrap_or(0.0)))));
+                    let target_fmt = format!("{:.1}", target);
+                    plot_ui.hline(egui_plot::HLine::new(self.lang.bars_target_fmt.replacen("{}", &target_fmt, 1), target)
+                        .color(self.palette.mean_line)
+                        .style(egui_plot::LineStyle::Dashed { length: 8.0 }));
+                    let clicked = plot_ui.response().clicked();
+                    clicked.then(|| plot_ui.pointer_coordinate()).flatten()
+                })
+                .inner
+        }).inner;
+
+        let position = response?.x.round();
+        (position >= 0.0).then(|| measured.get(position as usize).map(|&(index, _)| index)).flatten()
+    }
+
+    /// 单文件模式顶部的交付规格摘要 ("3 / 5 个文件符合规格 (...)")，单击切换只显示未通过的文件
+    fn ui_delivery_headline(&mut self, ui: &mut egui::Ui) {
+        let spec = self.state.settings.delivery_spec;
+        let (passed, measured) = {
+            let curves = self.state.single_files.lock().unwrap();
+            let measured = curves.iter().filter(|c| c.loudness.is_some()).count();
+            (measured - curves.iter().filter(|c| spec.fails(c)).count(), measured)
+        };
+        if measured == 0 {
+            self.delivery_failures_only = false;
+            return;
+        }
+        // 规格值为整数时不显示小数 (-23 LUFS ±1)
+        let number = |v: f32| format!("{:.1}", v).trim_end_matches(".0").to_string();
+        let text = self.lang.delivery_headline_fmt // I18N
+            .replacen("{}", &passed.to_string(), 1)
+            .replacen("{}", &measured.to_string(), 1)
+            .replacen("{}", &number(spec.integrated_target), 1)
+            .replacen("{}", &number(spec.integrated_tolerance), 1)
+            .replacen("{}", &number(spec.max_true_peak), 1)
+            .replacen("{}", &number(spec.max_loudness_range), 1);
+        let color = if passed == measured { self.palette.ok } else { self.palette.error };
+        ui.horizontal(|ui| {
+            let label = egui::Label::new(egui::RichText::new(text).strong().color(color)).sense(egui::Sense::click());
+            if ui.add(label).on_hover_text(self.lang.delivery_headline_hint).clicked() { // I18N
+                self.delivery_failures_only = !self.delivery_failures_only;
+                log_debug(&self.logger, &format!("只显示未通过交付规格的文件: {}", self.delivery_failures_only));
+            }
+            if self.delivery_failures_only {
+                ui.separator();
+                ui.label(self.lang.delivery_failures_only); // I18N
+                if ui.small_button("✖").on_hover_text(self.lang.delivery_failures_clear).clicked() { // I18N
+                    self.delivery_failures_only = false;
+                }
+            }
+        });
+    }
+
+    /// 交付检查面板：逐文件显示积分响度 / 真峰值 / 响度范围是否符合规格，单击行聚焦该曲线
+    fn ui_delivery_dashboard(&mut self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(self.lang.delivery_title) // I18N
+            .id_salt("delivery_dashboard")
+            .default_open(true)
+            .show(ui, |ui| {
+                let spec = &mut self.state.settings.delivery_spec;
+                ui.horizontal(|ui| {
+                    ui.label(self.lang.delivery_integrated_label); // I18N
+                    ui.add(egui::DragValue::new(&mut spec.integrated_target).speed(0.1).range(-60.0..=0.0).suffix(" LUFS"));
+                    ui.label("±");
+                    ui.add(egui::DragValue::new(&mut spec.integrated_tolerance).speed(0.1).range(0.0..=10.0).suffix(" LU"));
+                    ui.separator();
+                    ui.label(self.lang.delivery_true_peak_label); // I18N
+                    ui.add(egui::DragValue::new(&mut spec.max_true_peak).speed(0.1).range(-20.0..=3.0).suffix(" dBTP"));
+                    ui.separator();
+                    ui.label(self.lang.delivery_lra_label); // I18N
+                    ui.add(egui::DragValue::new(&mut spec.max_loudness_range).speed(0.5).range(1.0..=40.0).suffix(" LU"));
+                });
+
+                let spec = self.state.settings.delivery_spec;
+                let curves = self.state.single_files.lock().unwrap();
+                let mut order: Vec<usize> = (0..curves.len())
+                    .filter(|&i| !self.delivery_failures_only || spec.fails(&curves[i]))
+                    .collect();
+                if let Some(ascending) = self.delivery_sort {
+                    let level = |i: usize| curves[i].loudness.map_or(f64::NEG_INFINITY, |m| m.integrated_lufs);
+                    order.sort_by(|&a, &b| level(a).total_cmp(&level(b)));
+                    if !ascending {
+                        order.reverse();
+                    }
+                }
+                let measured = curves.iter().filter(|c| c.loudness.is_some()).count();
+                let passed = curves.iter().filter_map(|c| c.loudness).filter(|m| spec.check(m).passed()).count();
+                let summary = self.lang.delivery_summary_fmt.replacen("{}", &passed.to_string(), 1).replacen("{}", &measured.to_string(), 1);
+                ui.label(summary); // I18N
+
+                let level = |v: f64, unit: &str| if v.is_finite() { format!("{:.1} {}", v, unit) } else { format!("-∞ {}", unit) };
+                let palette = &self.palette;
+                let mut sort_clicked = false;
+                let mut row_clicked = None;
+                ui.push_id("delivery_table", |ui| {
+                    TableBuilder::new(ui)
+                        .striped(true)
+                        .sense(egui::Sense::click())
+                        .max_scroll_height(180.0)
+                        .column(Column::remainder().at_least(160.0).clip(true))
+                        .columns(Column::auto().at_least(90.0), 4)
+                        .header(20.0, |mut header| {
+                            header.col(|ui| { ui.strong(self.lang.stats_col_name); });
+                            header.col(|ui| {
+                                let arrow = match self.delivery_sort {
+                                    Some(true) => " ▲",
+                                    Some(false) => " ▼",
+                                    None => "",
+                                };
+                                if ui.button(format!("{}{}", self.lang.delivery_col_integrated, arrow)).clicked() {
+                                    sort_clicked = true;
+                                }
+                            });
+                            header.col(|ui| { ui.strong(self.lang.delivery_col_true_peak); });
+                            header.col(|ui| { ui.strong(self.lang.delivery_col_lra); });
+                            header.col(|ui| { ui.strong(self.lang.delivery_col_result); });
+                        })
+                        .body(|body| {
+                            body.rows(18.0, order.len(), |mut row| {
+                                let index = order[row.index()];
+                                let curve = &curves[index];
+                                row.set_selected(self.focused_index == Some(index));
+                                let Some(m) = curve.loudness else {
+                                    // CSV 曲线没有音频，无法检查
+                                    row.col(|ui| { ui.colored_label(Palette::curve_color(index), curve.display_label()); });
+                                    for _ in 0..3 {
+                                        row.col(|ui| { ui.weak(self.lang.details_na); });
+                                    }
+                                    row.col(|ui| { ui.weak(self.lang.delivery_not_checked); });
+                                    if row.response().clicked() {
+                                        row_clicked = Some(index);
+                                    }
+                                    return;
+                                };
+                                let compliance = spec.check(&m);
+                                // 整行按结果着色，未通过的单项用错误色
+                                let tint = if compliance.passed() { palette.ok } else { palette.error }.gamma_multiply(0.15);
+                                let value_color = |ok: bool| (!ok).then_some(palette.error);
+                                let cell = |ui: &mut egui::Ui, text: String, color: Option<egui::Color32>| {
+                                    ui.painter().rect_filled(ui.max_rect(), 0.0, tint);
+                                    match color {
+                                        Some(color) => ui.colored_label(color, text),
+                                        None => ui.label(text),
+                                    };
+                                };
+                                row.col(|ui| cell(ui, curve.display_label(), Some(Palette::curve_color(index))));
+                                row.col(|ui| cell(ui, level(m.integrated_lufs, "LUFS"), value_color(compliance.integrated)));
+                                row.col(|ui| cell(ui, level(m.true_peak_dbtp, "dBTP"), value_color(compliance.true_peak)));
+                                let lra = m.loudness_range_lu.map_or_else(|| self.lang.details_na.to_string(), |lra| format!("{:.1} LU", lra));
+                                row.col(|ui| cell(ui, lra, value_color(compliance.loudness_range)));
+                                let (result, color) = if compliance.passed() {
+                                    (self.lang.delivery_pass, palette.ok)
+                                } else {
+                                    (self.lang.delivery_fail, palette.error)
+                                };
+                                row.col(|ui| cell(ui, result.to_string(), Some(color)));
+                                if row.response().clicked() {
+                                    row_clicked = Some(index);
+                                }
+                            });
+                        });
+                });
+                if sort_clicked {
+                    self.delivery_sort = Some(!self.delivery_sort.unwrap_or(true));
+                }
+                if row_clicked.is_some() {
+                    self.focused_index = row_clicked;
+                }
+            });
+    }
+
+    /// 对比插槽的文件按钮：加载中显示进度并可右键取消，失败时标红
+    fn ui_compare_slot(&mut self, ui: &mut egui::Ui, slot: char) {
+        let index = slot_index(slot);
+        let (track_label, track_color, select_label, current) = if slot == 'A' {
+            (self.lang.compare_track_a_label, self.palette.track_a, self.lang.compare_select_a, &self.state.compare_a)
+        } else {
+            (self.lang.compare_track_b_label, self.palette.track_b, self.lang.compare_select_b, &self.state.compare_b)
+        };
+        let current_name = current.as_ref().map(|c| c.name.clone());
+        let stale = current.as_ref().is_some_and(|c| curve_is_stale(c, &self.state.settings));
+        let load_state = self.state.compare_loads[index].as_ref().and_then(|load| {
+            let tasks = self.worker_pool.tasks.lock().ok()?;
+            let task = tasks.iter().find(|t| t.id == load.task_id)?;
+            Some((load.task_id, load.filename.clone(), task.state.clone()))
+        });
+
+        let mut cancel = None;
+        ui.vertical(|ui| {
+            ui.horizontal(|ui| {
+                // 标签与图中曲线同色
+                ui.colored_label(track_color, track_label); // I18N
+                if stale {
+                    ui.colored_label(self.palette.warn, "⚠").on_hover_text(self.lang.analysis_stale_curve_hint); // I18N
+                }
+            });
+            let response = match &load_state {
+                Some((task_id, filename, state)) if !state.is_finished() => {
+                    let progress = if let TaskState::Running(fraction) = state { *fraction } else { 0.0 };
+                    let text = self.lang.compare_slot_loading_fmt
+                        .replacen("{}", filename, 1)
+                        .replacen("{}", &format!("{:.0}", progress * 100.0), 1); // I18N
+                    let response = ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.button(text)
+                    }).inner;
+                    response.context_menu(|ui| {
+                        if ui.button(self.lang.compare_slot_cancel).clicked() { // I18N
+                            cancel = Some(*task_id);
+                            ui.close();
+                        }
+                    });
+                    response.on_hover_text(self.lang.compare_slot_cancel_hint) // I18N
+                }
+                Some((_, filename, TaskState::Error(e))) => {
+                    let text = egui::RichText::new(format!("⚠ {}", filename)).color(ui.visuals().error_fg_color);
+                    ui.button(text).on_hover_text(self.lang.compare_slot_error_fmt.replacen("{}", &e.text_with_hint(&self.lang), 1)) // I18N
+                }
+                _ => ui.button(current_name.as_deref().unwrap_or(select_label)), // I18N
+            };
+            if response.clicked() {
+                log_info(&self.logger, &format!("选择 Track {}", slot));
+                #[cfg(not(target_arch = "wasm32"))]
+                if let Some(path) = FileDialog::new().add_filter("Audio", &["wav", "csv"]).pick_file() {
+                    self.spawn_compare_load(path, slot);
+                }
+                #[cfg(target_arch = "wasm32")]
+                pick_files_async(ui.ctx().clone(), self.ui_tx.clone(), Some(slot));
+            }
+            if slot == 'A' {
+                self.ui_synthetic_reference(ui);
+            }
+        });
 
-                    ui.heading(lang.help_console_title);
-                    ui.vertical(|ui| {
-                        // 使用 help_cmd_tasks 来描述 tasks/list 命令
-                        ui.label(format!("**`tasks`** 或 **`list`**: {}", lang.help_cmd_list));
-                        ui.label(format!("**`kill <ID>`**: {}", lang.help_cmd_kill));
-                        ui.label(format!("**`clear`**: {}", lang.help_cmd_clear));
-                        ui.label(format!("**`quit`** 或 **`exit`**: {}", lang.help_cmd_quit));
-                    });
-                });
+        if let Some(task_id) = cancel {
+            log_command(&self.logger, &format!("取消 Track {} 的加载任务 {}", slot, task_id));
+            self.worker_pool.command_tx.send(WorkerCommand::Kill(task_id)).unwrap_or_default();
+            self.state.compare_loads[index] = None;
+            // 插槽保留原来的曲线，按现有两条曲线重新对比
+            self.state.request_compare(&self.lang);
         }
     }
-    // ---------------------------------
-
-    fn ui_single_mode(&mut self, ui: &mut egui::Ui, ctx: &egui::Context) {
-        ui.heading(self.lang.single_heading); // I18N
-        ui.horizontal(|ui| {
-            if ui.button(self.lang.single_open_btn).clicked() { // I18N
-                log_info(&self.logger, "用户点击: 打开文件对话框");
-                let files = FileDialog::new()
-                    .add_filter("Audio/Data", &["wav", "csv"])
-                    .pick_files();
-
-                if let Some(paths) = files {
-                    log_info(&self.logger, &format!("选中文件数: {}", paths.len()));
-                    self.loading = true;
-                    self.error_msg = None;
-
-                    let logger_ref = &self.logger;
-                    let ui_result_tx_base = self.ui_tx.clone();
-
-                    for path in paths {
-                        let filename = path.file_name().unwrap().to_string_lossy().to_string();
-                        let task_ui_tx = ui_result_tx_base.clone();
 
-                        self.worker_pool.spawn_task(
-                            filename.clone(),
-                            move |task_id, ui_tx_clone, logger_entries| { // 注意: ui_tx_clone 是正确的变量名
-                                let thread_logger = Logger { entries: logger_entries };
-
-                                // 实际的文件加载逻辑
-                                match load_file(path, &thread_logger) {
+    /// Track A 的合成参考：恒定电平或断点包络 (可从 CSV 导入断点)，使用中修改立即重新生成并对比
+    fn ui_synthetic_reference(&mut self, ui: &mut egui::Ui) {
+        let active = self.state.synthetic_a.is_some();
+        let reference = &mut self.state.settings.synthetic_reference;
+        let mut changed = false;
+        let (mut apply, mut stop) = (false, false);
+        egui::CollapsingHeader::new(self.lang.synthetic_title) // I18N
+            .id_salt("synthetic_reference")
+            .default_open(active)
+            .show(ui, |ui| {
+                ui.weak(self.lang.synthetic_hint); // I18N
+                ui.horizontal(|ui| {
+                    let is_constant = matches!(reference, SyntheticReference::Constant(_));
+                    if ui.selectable_label(is_constant, self.lang.synthetic_constant).clicked() && !is_constant { // I18N
+                        let level = reference.level_at(0.0).unwrap_or(-20.0);
+                        *reference = SyntheticReference::Constant(level);
+                        changed = true;
+                    }
+                    if ui.selectable_label(!is_constant, self.lang.synthetic_breakpoints).clicked() && is_constant { // I18N
+                        let level = reference.level_at(0.0).unwrap_or(-20.0);
+                        let end = self.state.compare_b.as_ref().map_or(60.0, |b| b.duration.ceil());
+                        *reference = SyntheticReference::Breakpoints(vec![[0.0, level], [end, level]]);
+                        changed = true;
+                    }
+                });
+                match reference {
+                    SyntheticReference::Constant(level) => {
+                        changed |= ui.add(egui::DragValue::new(level).speed(0.1).range(-120.0..=0.0).suffix(" dBFS")).changed();
+                    }
+                    SyntheticReference::Breakpoints(points) => {
+                        let mut removed = None;
+                        egui::ScrollArea::vertical().id_salt("synthetic_breakpoints").max_height(140.0).show(ui, |ui| {
+                            for (i, point) in points.iter_mut().enumerate() {
+                                ui.horizontal(|ui| {
+                                    changed |= ui.add(egui::DragValue::new(&mut point[0]).speed(0.05).range(0.0..=f64::MAX).suffix(" s")).changed();
+                                    changed |= ui.add(egui::DragValue::new(&mut point[1]).speed(0.1).range(-120.0..=0.0).suffix(" dBFS")).changed();
+                                    if ui.small_button("🗑").clicked() {
+                                        removed = Some(i);
+                                    }
+                                });
+                            }
+                        });
+                        if let Some(i) = removed {
+                            points.remove(i);
+                            changed = true;
+                        }
+                        ui.horizontal(|ui| {
+                            if ui.small_button(self.lang.synthetic_add_point).clicked() { // I18N
+                                let next = points.last().map_or([0.0, -20.0], |p| [p[0] + 1.0, p[1]]);
+                                points.push(next);
+                                changed = true;
+                            }
+                            if local_file_button(ui, true, egui::Button::new(self.lang.synthetic_import_csv).small(), self.lang.web_unavailable_hint).on_hover_text(self.lang.synthetic_import_csv_hint).clicked() // I18N
+                                && let Some(path) = FileDialog::new().add_filter("CSV File", &["csv"]).pick_file()
+                            {
+                                // 断点文件通常只有几行，直接在界面线程读取
+                                match parse_csv(path.clone(), &self.logger, &mut |_| {}) {
                                     Ok(curve) => {
-                                        // 任务成功，将结果发送回主 UI 线程
-                                        ui_tx_clone.send(WorkerMessage::NewCurve(curve, None)).unwrap_or_default();
+                                        log_info(&self.logger, &format!("从 {} 导入 {} 个断点", path.display(), curve.points.len()));
+                                        *points = curve.points;
+                                        changed = true;
                                     }
                                     Err(e) => {
-                                        // 任务失败，发送错误状态
-                                        let err_msg = format!("文件加载失败 ({}): {}", filename, e);
-                                        log_error(&thread_logger, &err_msg);
-                                        ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(err_msg))).unwrap_or_default();
+                                        let err_msg = self.lang.synthetic_import_failed_fmt.replacen("{}", &e.to_string(), 1);
+                                        log_error(&self.logger, &err_msg);
+                                        self.state.notifications.push(ToastLevel::Error, err_msg);
                                     }
                                 }
-                            },
-                            task_ui_tx,
-                            logger_ref
-                        );
+                            }
+                        });
                     }
                 }
-            }
-
-            if ui.button(self.lang.single_clear_btn).clicked() { // I18N
-                self.single_files.lock().unwrap().clear();
-                log_info(&self.logger, "文件列表已清空。");
-            }
-
-            let curves = self.single_files.lock().unwrap();
-            // 导出 CSV 按钮 - 仅当有数据时启用
-            if !curves.is_empty() {
-                if ui.button(self.lang.export_csv_btn).clicked() { // I18N
-                    // 仅导出列表中的第一个文件作为示例
-                    if let Some(curve) = curves.first() {
-                        match export_to_csv(curve, self.target_lufs as f64, &self.logger) {
-                            Ok(_) => self.error_msg = Some(format!("✅ {} exported successfully!", curve.name)),
-                            Err(e) => {
-                                let err_msg = format!("❌ Export failed: {}", e);
-                                log_error(&self.logger, &err_msg);
-                                self.error_msg = Some(err_msg);
-                            }
+                ui.horizontal(|ui| {
+                    if active {
+                        stop = ui.button(self.lang.synthetic_stop).clicked(); // I18N
+                        if self.state.compare_b.is_none() {
+                            ui.weak(self.lang.synthetic_waiting_b); // I18N
                         }
+                    } else {
+                        apply = ui.button(self.lang.synthetic_use).clicked(); // I18N
                     }
-                }
-            }
-            drop(curves); // 释放锁
-        });
+                });
+            });
 
-        // --- 归一化设置 ---
-        ui.horizontal(|ui| {
-            ui.label(self.lang.normalize_label); // I18N
-            ui.add(egui::DragValue::new(&mut self.target_lufs)
-                .speed(0.1)
-                .range(-60.0..=0.0)
-                .suffix(" dBFS")
-            );
-            if ui.button(self.lang.normalize_apply).clicked() {
-                log_info(&self.logger, &format!("归一化目标设定为: {:.1} dBFS", self.target_lufs));
-                self.error_msg = Some(format!("已应用归一化目标: {:.1} dBFS", self.target_lufs));
+        if stop {
+            self.state.set_synthetic_reference(None, &self.lang);
+        } else if apply || (active && changed) {
+            // 界面上按输入顺序编辑，生成曲线前再按时间排序
+            let mut reference = self.state.settings.synthetic_reference.clone();
+            reference.normalize();
+            if apply && let Some(load) = self.state.compare_loads[0].take() {
+                log_command(&self.logger, &format!("改用合成参考，取消 Track A 的加载任务 {}", load.task_id));
+                self.worker_pool.command_tx.send(WorkerCommand::Kill(load.task_id)).unwrap_or_default();
             }
-        });
-        ui.separator();
-
-
-        // 临时按钮：用于在异步加载结束后手动关闭 loading 状态 (仅用于调试)
-        if self.loading && ui.button(self.lang.debug_end_loading).clicked() { // I18N
-            self.loading = false;
-            ctx.request_repaint(); // 手动重绘
+            self.state.set_synthetic_reference(Some(reference), &self.lang);
         }
+    }
 
-        // 绘图区域
-        let curves = self.single_files.lock().unwrap();
-        if curves.is_empty() {
-            ui.label(self.lang.single_empty_label); // I18N
-        } else {
-            // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源，防止与布局中其他控件冲突
-            ui.push_id("single_plot_area", |ui| {
-                Plot::new("single_plot")
-                    .legend(Legend::default())
-                    .y_axis_label(self.lang.single_y_label) // I18N
-                    .x_axis_label(self.lang.single_x_label) // I18N
-                    .show(ui, |plot_ui| {
-                        let target = self.target_lufs as f64;
-                        for curve in curves.iter() {
-                            // 计算归一化偏移量：目标 - 平均 dBFS
-                            let offset = target - curve.average_dbfs;
-
-                            // 应用偏移量到曲线数据
-                            let shifted_points: PlotPoints = curve.points.iter()
-                                .map(|p| [p[0], p[1] + offset])
-                                .collect();
-
-                            let name = format!("{} (Avg: {:.2} dBFS)", curve.name, curve.average_dbfs);
-
-                            plot_ui.line(Line::new(name, shifted_points));
-                        }
-                    });
+    /// 对比报告下方的偏差区间列表：|差值 - 目标| 超过一致性容差、偏离最大的几段，点击后把对比图缩放到该段
+    fn ui_error_regions(&mut self, ui: &mut egui::Ui, res: &ComparisonResult) {
+        let target = self.state.target_mean_diff as f64;
+        let tolerance = self.state.settings.consistency_weights.tolerance_db;
+        let regions = res.error_regions(target, tolerance, self.state.settings.error_region_count);
+        let title = self.lang.error_regions_title_fmt.replacen("{}", &format!("{:.1}", tolerance), 1); // I18N
+        egui::CollapsingHeader::new(title)
+            .id_salt("compare_error_regions")
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(self.lang.error_regions_count_label); // I18N
+                    ui.add(egui::DragValue::new(&mut self.state.settings.error_region_count).range(1..=20));
+                });
+                if regions.is_empty() {
+                    ui.weak(self.lang.error_regions_none); // I18N
+                    return;
+                }
+                let time = self.state.settings.time_format;
+                for (i, region) in regions.iter().enumerate() {
+                    let text = self.lang.error_regions_row_fmt
+                        .replacen("{}", &(i + 1).to_string(), 1)
+                        .replacen("{}", &time.format(region.start, 1), 1)
+                        .replacen("{}", &time.format(region.end, 1), 1)
+                        .replacen("{}", &format!("{:+.2}", region.peak_diff), 1)
+                        .replacen("{}", &time.format(region.peak_time, 2), 1); // I18N
+                    if ui.add(egui::Button::new(text).frame(false)).on_hover_text(self.lang.error_regions_jump_hint).clicked() {
+                        // 区间两侧各留出区间长度一半 (至少 2 秒) 的上下文
+                        let margin = ((region.end - region.start) / 2.0).max(2.0);
+                        self.pending_compare_zoom = Some([region.start - margin, region.end + margin]);
+                    }
+                }
             });
-        }
     }
 
     fn ui_compare_mode(&mut self, ui: &mut egui::Ui) {
@@ -1178,82 +7820,8 @@ impl WavLufsApp {
         // 修复 ID 冲突：使用 ui.push_id 隔离文件选择区的列布局
         ui.push_id("compare_selection", |ui| {
             ui.columns(2, |columns| {
-                // Slot A
-                columns[0].vertical(|ui| {
-                    ui.label(self.lang.compare_track_a_label); // I18N
-                    if ui.button(self.compare_a.as_ref().map(|c| c.name.as_str()).unwrap_or(self.lang.compare_select_a)).clicked() { // I18N
-                        log_info(&self.logger, "选择 Track A");
-                        if let Some(path) = FileDialog::new().add_filter("Audio", &["wav", "csv"]).pick_file() {
-                            let file_slot = 'A'; // 定义插槽
-                            let filename = path.file_name().unwrap().to_string_lossy().to_string();
-                            let task_name = format!("Track {} Load: {}", file_slot, filename);
-                            let logger_ref = &self.logger;
-                            let ui_result_tx_base = self.ui_tx.clone();
-
-                            self.loading = true; // 增加 loading 状态
-                            self.error_msg = None;
-
-                            // 启动后台加载任务
-                            self.worker_pool.spawn_task(
-                                task_name,
-                                move |task_id, ui_tx_clone, logger_entries| {
-                                    let thread_logger = Logger { entries: logger_entries };
-                                    match load_file(path, &thread_logger) {
-                                        Ok(curve) => {
-                                            // 发送结果和插槽信息
-                                            ui_tx_clone.send(WorkerMessage::NewCurve(curve, Some(file_slot))).unwrap_or_default();
-                                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Completed)).unwrap_or_default();
-                                        }
-                                        Err(e) => {
-                                            let err_msg = format!("文件加载失败 ({}): {}", filename, e);
-                                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(err_msg))).unwrap_or_default();
-                                        }
-                                    }
-                                },
-                                ui_result_tx_base,
-                                logger_ref
-                            );
-                        }
-                    }
-                });
-                // Slot B
-                columns[1].vertical(|ui| {
-                    ui.label(self.lang.compare_track_b_label); // I18N
-                    if ui.button(self.compare_b.as_ref().map(|c| c.name.as_str()).unwrap_or(self.lang.compare_select_b)).clicked() { // I18N
-                        log_info(&self.logger, "选择 Track B");
-                        if let Some(path) = FileDialog::new().add_filter("Audio", &["wav", "csv"]).pick_file() {
-                            let file_slot = 'B'; // 定义插槽
-                            let filename = path.file_name().unwrap().to_string_lossy().to_string();
-                            let task_name = format!("Track {} Load: {}", file_slot, filename);
-                            let logger_ref = &self.logger;
-                            let ui_result_tx_base = self.ui_tx.clone();
-
-                            self.loading = true; // 增加 loading 状态
-                            self.error_msg = None;
-
-                            // 启动后台加载任务
-                            self.worker_pool.spawn_task(
-                                task_name,
-                                move |task_id, ui_tx_clone, logger_entries| {
-                                    let thread_logger = Logger { entries: logger_entries };
-                                    match load_file(path, &thread_logger) {
-                                        Ok(curve) => {
-                                            // 发送结果和插槽信息
-                                            ui_tx_clone.send(WorkerMessage::NewCurve(curve, Some(file_slot))).unwrap_or_default();
-                                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Completed)).unwrap_or_default();
-                                        }
-                                        Err(e) => {
-                                            let err_msg = format!("文件加载失败 ({}): {}", filename, e);
-                                            ui_tx_clone.send(WorkerMessage::UpdateTaskState(task_id, TaskState::Error(err_msg))).unwrap_or_default();
-                                        }
-                                    }
-                                },
-                                ui_result_tx_base,
-                                logger_ref
-                            );
-                        }
-                    }
-                });
+                self.ui_compare_slot(&mut columns[0], 'A');
+                self.ui_compare_slot(&mut columns[1], 'B');
             });
         });
 
@@ -1261,22 +7829,90 @@ impl WavLufsApp {
 
         // ⭐ 新增: 目标差值设置区
         ui.horizontal(|ui| {
-            ui.label("目标平均差值 (A - B) T 检验中心点:");
-            let response = ui.add(egui::DragValue::new(&mut self.target_mean_diff)
+            ui.label(self.lang.compare_target_diff_label); // I18N
+            // 目标值只影响 t 检验，显示时按当前目标重算 t 统计量，无需重新对比
+            ui.add(egui::DragValue::new(&mut self.state.target_mean_diff)
                 .speed(0.1)
                 .range(-20.0..=20.0)
                 .suffix(" dB")
             );
-            // 如果目标值改变或回车，重新运行对比
-            if response.changed() || (response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter))) {
-                self.run_comparison();
+        });
+
+        // Track B 时间偏移：手动对齐后重新对比
+        ui.horizontal(|ui| {
+            ui.label(self.lang.compare_offset_label); // I18N
+            let response = ui.add(egui::DragValue::new(&mut self.state.compare_offset)
+                .speed(0.01)
+                .range(-10.0..=10.0)
+                .fixed_decimals(3)
+                .suffix(" s")
+            );
+            if ui.add_enabled(self.state.compare_offset != 0.0, egui::Button::new("⟲")).clicked() {
+                self.state.compare_offset = 0.0;
+                self.state.request_compare(&self.lang);
+            } else if response.changed() {
+                self.state.request_compare(&self.lang);
             }
+            ui.separator();
+            if ui.checkbox(&mut self.state.settings.compare_level_match, self.lang.compare_level_match) // I18N
+                .on_hover_text(self.lang.compare_level_match_hint)
+                .changed()
+            {
+                self.state.request_compare(&self.lang);
+            }
+        });
+
+        // 判定阈值设置区 (持久化)
+        ui.horizontal(|ui| {
+            ui.label(self.lang.compare_threshold_label); // I18N
+            ui.label(self.lang.compare_high_match_threshold); // I18N
+            ui.add(egui::DragValue::new(&mut self.state.settings.high_match_threshold)
+                .speed(0.05)
+                .range(0.0..=self.state.settings.huge_diff_threshold)
+            );
+            ui.label(self.lang.compare_huge_diff_threshold); // I18N
+            ui.add(egui::DragValue::new(&mut self.state.settings.huge_diff_threshold)
+                .speed(0.05)
+                .range(self.state.settings.high_match_threshold..=60.0)
+            );
+            ui.separator();
+            ui.label(self.lang.compare_min_overlap_label); // I18N
+            ui.add(egui::DragValue::new(&mut self.state.settings.min_overlap_percent)
+                .speed(1.0)
+                .range(0.0..=100.0)
+                .suffix("%")
+            );
         });
 
-        // ⭐ FIX E0500: 先克隆结果，让后续的 'res' 引用不再阻塞对 'self' 的可变访问。
-        let comparison_result_clone = self.compare_result.clone();
+        // 一致性评分权重 (持久化)
+        egui::CollapsingHeader::new(self.lang.score_weights_title) // I18N
+            .id_salt("score_weights")
+            .show(ui, |ui| {
+                let weights = &mut self.state.settings.consistency_weights;
+                ui.horizontal(|ui| {
+                    for (label, value) in [
+                        (self.lang.score_weight_correlation, &mut weights.correlation),
+                        (self.lang.score_weight_spread, &mut weights.spread),
+                        (self.lang.score_weight_within, &mut weights.within_tolerance),
+                    ] {
+                        ui.label(label); // I18N
+                        ui.add(egui::DragValue::new(value).speed(0.01).range(0.0..=1.0));
+                    }
+                    ui.separator();
+                    ui.label(self.lang.score_tolerance_label); // I18N
+                    ui.add(egui::DragValue::new(&mut weights.tolerance_db).speed(0.05).range(0.1..=20.0).suffix(" dB"));
+                    if ui.button("⟲").on_hover_text(self.lang.score_weights_reset).clicked() { // I18N
+                        *weights = ConsistencyWeights::default();
+                    }
+                });
+                ui.weak(self.lang.score_formula); // I18N
+            });
+
+        // ⭐ FIX E0500: 先克隆结果 (Arc)，让后续的 'res' 引用不再阻塞对 'self' 的可变访问。
+        let comparison_result_clone = self.state.compare_result.clone();
 
         if let Some(res) = &comparison_result_clone {
+            self.compare_plot_cache.refresh(self.state.compare_revision, self.state.compare_a.as_ref(), self.state.compare_b.as_ref(), res);
 
             // --- 置信度选择 (UI 交互与可变操作) ---
             ui.horizontal(|ui| {
@@ -1287,11 +7923,38 @@ impl WavLufsApp {
                 if ui.selectable_value(&mut self.confidence_level, 0.90, "90%").clicked() { clicked = true; }
                 if ui.selectable_value(&mut self.confidence_level, 0.95, "95%").clicked() { clicked = true; }
                 if ui.selectable_value(&mut self.confidence_level, 0.99, "99%").clicked() { clicked = true; }
+                // 自定义置信度 (如关键交付用 99.9%)，临界值按 t 分布和实际自由度计算
+                let mut percent = self.confidence_level * 100.0;
+                let range = (*CONFIDENCE_RANGE.start() * 100.0) as f32..=(*CONFIDENCE_RANGE.end() * 100.0) as f32;
+                if ui.add(egui::DragValue::new(&mut percent).range(range).speed(0.05).max_decimals(2).suffix("%"))
+                    .on_hover_text(self.lang.compare_conf_custom_hint) // I18N
+                    .changed()
+                {
+                    self.confidence_level = percent / 100.0;
+                    clicked = true;
+                }
+                ui.separator();
+                ui.label(self.lang.compare_min_r_label).on_hover_text(self.lang.compare_min_r_hint); // I18N
+                ui.add(egui::DragValue::new(&mut self.state.settings.min_correlation).speed(0.005).range(-1.0..=0.999).fixed_decimals(3));
 
-                // 只有在点击后才调用 &mut self 的方法
+                // 置信度只影响临界值，下面的检验结果每帧按当前置信度判断，不重新对比
                 if clicked {
-                    log_debug(&self.logger, &format!("置信度设置为 {:.0}%", self.confidence_level * 100.0));
-                    self.run_comparison();
+                    log_debug(&self.logger, &format!("置信度设置为 {}%", confidence_percent(self.confidence_level)));
+                }
+                ui.separator();
+                // 逐点数据表 (时间, A, B, 差值)，在表格软件中对配对数据自行统计
+                if local_file_button(ui, true, egui::Button::new(self.lang.compare_export_table_btn), self.lang.web_unavailable_hint).on_hover_text(self.lang.compare_export_table_hint).clicked() // I18N
+                    && let Some(path) = FileDialog::new().set_file_name("comparison.csv").add_filter("CSV File", &["csv"]).save_file()
+                {
+                    let name = |curve: &Option<AudioCurve>| curve.as_ref().map_or_else(String::new, |c| c.name.clone());
+                    match write_comparison_table(res, &name(&self.state.compare_a), &name(&self.state.compare_b), &path, &self.logger) {
+                        Ok(()) => self.state.notifications.push(ToastLevel::Success, self.lang.export_success_fmt.replacen("{}", &path.display().to_string(), 1)),
+                        Err(e) => {
+                            let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                            log_error(&self.logger, &err_msg);
+                            self.state.notifications.push(ToastLevel::Error, err_msg);
+                        }
+                    }
                 }
             });
             ui.separator();
@@ -1304,6 +7967,36 @@ impl WavLufsApp {
                     ui.group(|ui| {
                         ui.vertical(|ui| {
                             ui.label(egui::RichText::new(self.lang.compare_report_title).strong()); // I18N
+                            if let (Some(a), Some(b)) = (&self.state.compare_a, &self.state.compare_b) {
+                                ui.label(self.lang.compare_tracks_fmt.replacen("{}", &a.display_label(), 1).replacen("{}", &b.display_label(), 1)); // I18N
+                            }
+                            if res.level_offset.is_some() {
+                                ui.weak(self.lang.compare_residual_note); // I18N
+                            }
+                            if let Some(note) = self.resampled_note(res) {
+                                ui.colored_label(self.palette.warn, note);
+                            }
+                            if let Some(details) = self.mixed_analysis_details() {
+                                ui.colored_label(self.palette.warn, self.lang.analysis_mixed_compare).on_hover_text(details); // I18N
+                            }
+                            if let Some(note) = self.mixed_profile_note() {
+                                ui.colored_label(self.palette.warn, note);
+                            }
+
+                            // 一致性评分：给非技术评审的单一数字，悬停显示各分量
+                            let weights = &self.state.settings.consistency_weights;
+                            let target = self.state.target_mean_diff as f64;
+                            let score = res.consistency_score(weights, target);
+                            let [correlation, spread, within] = weights.components(res, target);
+                            let color = if score >= 80.0 { self.palette.ok } else if score >= 50.0 { self.palette.warn } else { self.palette.error };
+                            let score_text = self.lang.compare_score_fmt.replacen("{}", &format!("{:.0}", score), 1); // I18N
+                            let parts = self.lang.compare_score_parts_fmt
+                                .replacen("{}", &format!("{:.2}", correlation), 1)
+                                .replacen("{}", &format!("{:.2}", spread), 1)
+                                .replacen("{}", &format!("{:.2}", weights.tolerance_db), 1)
+                                .replacen("{}", &format!("{:.0}", within * 100.0), 1); // I18N
+                            ui.label(egui::RichText::new(score_text).size(22.0).strong().color(color))
+                                .on_hover_text(format!("{}\n{}", parts, self.lang.score_formula));
 
                             // 平均差异
                             let avg_diff_fmt = format!("{:.2}", res.mean_diff);
@@ -1316,15 +8009,13 @@ impl WavLufsApp {
                             // 动态相关系数 (r) - 衡量曲线形状相似度
                             let corr_fmt = format!("{:.4}", res.correlation_coefficient);
                             ui.label(self.lang.compare_correlation_fmt.replacen("{}", &corr_fmt, 1)); // I18N
-
-                            // 状态结果 (基于标准差)
-                            if res.std_dev < 1.0 {
-                                ui.colored_label(egui::Color32::GREEN, self.lang.compare_high_match); // I18N
-                            } else if res.std_dev < 3.0 {
-                                ui.colored_label(egui::Color32::YELLOW, self.lang.compare_mid_diff); // I18N
-                            } else {
-                                ui.colored_label(egui::Color32::RED, self.lang.compare_huge_diff); // I18N
+                            for line in self.compare_metric_lines() {
+                                ui.label(line);
                             }
+
+                            // 状态结果 (基于标准差，阈值可在设置中调整)
+                            let verdict = self.state.settings.verdict(res.std_dev);
+                            ui.colored_label(self.palette.verdict_color(verdict), self.lang.verdict_label(verdict)); // I18N
                         });
                     });
                 });
@@ -1338,257 +8029,1210 @@ impl WavLufsApp {
                     let min_diff_fmt = format!("{:.2}", res.min_diff);
                     ui.label(self.lang.compare_min_diff_fmt.replacen("{}", &min_diff_fmt, 1)); // I18N
 
+                    // 时间重叠比例，低于要求时标记为部分对比
+                    let overlap_fmt = format!("{:.1}", res.overlap_ratio * 100.0);
+                    ui.label(self.lang.compare_overlap_fmt.replacen("{}", &overlap_fmt, 1)); // I18N
+                    if self.state.settings.is_partial_overlap(res.overlap_ratio) {
+                        let required_fmt = format!("{:.0}", self.state.settings.min_overlap_percent);
+                        ui.colored_label(self.palette.warn, self.lang.compare_partial_warning_fmt.replacen("{}", &required_fmt, 1)); // I18N
+                    }
+
+                    let offset_fmt = format!("{:+.3}", res.b_offset);
+                    ui.label(self.lang.compare_offset_fmt.replacen("{}", &offset_fmt, 1)); // I18N
+                    if let Some(level_offset) = res.level_offset {
+                        let level_fmt = format!("{:+.2}", level_offset);
+                        ui.label(self.lang.compare_level_offset_fmt.replacen("{}", &level_fmt, 1)); // I18N
+                    }
+
                     // ⭐ 新增: 报告 T 检验目标
-                    ui.label(format!("T 检验目标: {:.2} dB", self.target_mean_diff));
+                    let target_fmt = format!("{:.2}", self.state.target_mean_diff);
+                    ui.label(self.lang.compare_t_target_fmt.replacen("{}", &target_fmt, 1)); // I18N
 
                     // 均值差值 T-统计量
-                    let t_stat_fmt = format!("{:.2}", res.t_statistic);
+                    let t_statistic = res.t_statistic_for(self.state.target_mean_diff as f64);
+                    let t_stat_fmt = format!("{:.2}", t_statistic);
                     ui.label(self.lang.compare_t_stat_fmt.replacen("{}", &t_stat_fmt, 1)); // I18N
+                    ui.weak(self.t_critical_line(res));
 
-                    // --- 假设检验结果 (根据置信度动态判断) ---
-                    let critical_value = match self.confidence_level {
-                        0.90 => 1.645,
-                        0.95 => 1.960,
-                        0.99 => 2.576,
-                        _ => 1.960,
-                    };
-
+                    // --- 假设检验结果 (每帧按当前目标和置信度从缓存的统计量判断) ---
                     // 检验原假设 H0: Mean(Diff) = target_mean_diff
-                    if res.t_statistic.abs() > critical_value {
+                    if res.differs_from_target(self.state.target_mean_diff as f64, self.confidence_level as f64) {
                         // T 检验失败：实际平均差值与目标差值存在显著差异
-                        ui.colored_label(egui::Color32::RED, self.lang.compare_t_test_significant); // I18N
+                        ui.colored_label(self.palette.error, self.lang.compare_t_test_significant); // I18N
                     } else {
                         // T 检验通过：实际平均差值与目标差值不存在显著差异
-                        ui.colored_label(egui::Color32::GREEN, self.lang.compare_t_test_not_significant); // I18N
+                        ui.colored_label(self.palette.ok, self.lang.compare_t_test_not_significant); // I18N
+                    }
+
+                    // 相关系数检验：均差检验看电平，这里看曲线形状
+                    let (lines, passes) = self.correlation_test_lines(res);
+                    for line in lines {
+                        match passes {
+                            Some(true) => ui.colored_label(self.palette.ok, line),
+                            Some(false) => ui.colored_label(self.palette.error, line),
+                            None => ui.weak(line),
+                        };
                     }
                     // ------------------------------------
                 });
             });
 
-            ui.separator();
+            self.ui_error_regions(ui, res);
+
+            ui.separator();
+
+            // 测量光标读数 (两个图表共用)
+            let mut series = Vec::new();
+            if let Some(a) = &self.state.compare_a {
+                series.push(CursorSeries { name: "Track A", points: &a.points, time_shift: 0.0, level_shift: 0.0 });
+            }
+            if let Some(b) = &self.state.compare_b {
+                series.push(CursorSeries { name: "Track B", points: &b.points, time_shift: res.b_offset, level_shift: 0.0 });
+            }
+            series.push(CursorSeries { name: "Diff", points: &res.diff_points, time_shift: 0.0, level_shift: 0.0 });
+            self.compare_cursors.show_readout(ui, &self.lang, &series, self.state.settings.time_format);
+            let cursor_color = ui.visuals().strong_text_color();
+
+            // 双图表显示
+            // 上图：原始曲线对比
+            ui.horizontal(|ui| {
+                ui.label(self.lang.compare_plot_raw_label); // I18N
+                ui.separator();
+                self.compare_y_axis.ui(ui, &self.lang, &mut self.state.settings.compare_y_range);
+                ui.separator();
+                self.state.settings.ui_zone_controls(ui, &self.lang, self.target_lufs);
+                ui.separator();
+                self.state.settings.ui_track_style_controls(ui, &self.lang);
+            });
+            let height = ui.available_height() / 2.0 - 20.0;
+            let y_range = self.state.settings.compare_y_range;
+            let set_y = self.compare_y_axis.take_apply(y_range);
+            let fit = self.compare_y_axis.fit.take();
+            // 上图画的是原始电平 (Track B 只做时间偏移)
+            let zones = self.state.settings.show_loudness_zones.then(|| {
+                let end_a = self.state.compare_a.as_ref().map_or(0.0, |a| a.duration);
+                let end_b = self.state.compare_b.as_ref().map_or(0.0, |b| b.duration + res.b_offset);
+                (self.state.settings.zone_edges(self.target_lufs), [res.b_offset.min(0.0), end_a.max(end_b)])
+            });
+            // Alt+单击复制数值：上图吸附 Track A/B，差值图吸附 Diff (series 最后一项)
+            let (track_series, diff_series) = series.split_at(series.len() - 1);
+            let time = self.state.settings.time_format;
+            let mut copied = None;
+            // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源
+            let zoom = self.pending_compare_zoom.take();
+            let fitted = ui.push_id("compare_raw_plot", |ui| {
+                self.state.settings.time_format.apply(Plot::new("compare_raw"))
+                    .height(height)
+                    .legend(Legend::default())
+                    .default_y_bounds(y_range[0], y_range[1])
+                    .allow_drag(!self.compare_cursors.hovered)
+                    .show(ui, |plot_ui| {
+                        if let Some([start, end]) = zoom {
+                            plot_ui.set_plot_bounds_x(start..=end);
+                        }
+                        if let Some(range) = set_y {
+                            plot_ui.set_plot_bounds_y(range[0]..=range[1]);
+                        }
+                        if let Some((edges, x)) = zones {
+                            draw_loudness_zones(plot_ui, edges, x, &self.palette);
+                        }
+                        if let Some(a) = &self.state.compare_a {
+                            draw_excluded_ranges(plot_ui, a, 0.0, self.palette.track_a, 0);
+                        }
+                        if let Some(b) = &self.state.compare_b {
+                            draw_excluded_ranges(plot_ui, b, res.b_offset, self.palette.track_b, 1);
+                        }
+                        self.compare_cursors.interact(plot_ui);
+                        self.compare_cursors.draw(plot_ui, cursor_color);
+                        copied = copy_value_on_click(plot_ui, track_series, |x| time.format(x, 3));
+                        let cache = &self.compare_plot_cache;
+                        let mut visible: Vec<&[PlotPoint]> = Vec::new();
+                        if self.state.compare_a.is_some() {
+                            visible.push(&cache.track_a);
+                            plot_ui.line(Line::new("Track A", PlotPoints::Borrowed(&cache.track_a))
+                                .color(self.palette.track_a)
+                                .style(self.state.settings.track_styles[0].plot_style()));
+                        }
+                        if self.state.compare_b.is_some() {
+                            // 按对比时使用的偏移显示 Track B (缓存中已加上偏移)
+                            let offset = res.b_offset;
+                            let name = if offset == 0.0 { "Track B".to_string() } else { format!("Track B ({:+.3} s)", offset) };
+                            visible.push(&cache.track_b);
+                            plot_ui.line(Line::new(name, PlotPoints::Borrowed(&cache.track_b))
+                                .color(self.palette.track_b)
+                                .style(self.state.settings.track_styles[1].plot_style()));
+                        }
+                        // 按当前可见时间范围自适应 Y 轴
+                        let fit = fit?;
+                        let range = fit_y_range(visible_levels(&visible, plot_ui.plot_bounds().range_x()), fit)?;
+                        plot_ui.set_plot_bounds_y(range[0]..=range[1]);
+                        Some(range)
+                    })
+                    .inner
+            }).inner;
+            if let Some(range) = fitted {
+                self.state.settings.compare_y_range = range;
+            }
+
+            // 下图：差值曲线或 Bland-Altman 图
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut self.state.settings.compare_lower_plot, CompareLowerPlot::Diff, self.lang.compare_plot_diff_label); // I18N
+                ui.selectable_value(&mut self.state.settings.compare_lower_plot, CompareLowerPlot::BlandAltman, self.lang.compare_plot_bland_altman_label) // I18N
+                    .on_hover_text(self.lang.compare_plot_bland_altman_hint);
+            });
+            match self.state.settings.compare_lower_plot {
+                CompareLowerPlot::Diff => {
+                    // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源
+                    ui.push_id("compare_diff_plot", |ui| {
+                        self.state.settings.time_format.apply(Plot::new("compare_diff"))
+                            .height(height)
+                            .allow_drag(!self.compare_cursors.hovered)
+                            .show(ui, |plot_ui| {
+                                if let Some([start, end]) = zoom {
+                                    plot_ui.set_plot_bounds_x(start..=end);
+                                }
+                                self.compare_cursors.interact(plot_ui);
+                                self.compare_cursors.draw(plot_ui, cursor_color);
+                                copied = copy_value_on_click(plot_ui, diff_series, |x| time.format(x, 3)).or(copied.take());
+                                // 差值曲线颜色随主题变化 (深色下为青色)，提高可读性
+                                plot_ui.line(Line::new("Diff", PlotPoints::Borrowed(&self.compare_plot_cache.diff))
+                                    .color(self.palette.diff_line)
+                                );
+
+                                // 绘制平均线
+                                plot_ui.hline(egui_plot::HLine::new("Mean Diff", res.mean_diff)
+                                    .color(self.palette.mean_line)
+                                    .style(egui_plot::LineStyle::Dashed { length: 5.0 })
+                                );
+
+                                // 新增: 绘制零点线，提高可读性
+                                plot_ui.hline(egui_plot::HLine::new("Zero", 0.0)
+                                    .color(self.palette.zero_line) // 零点线与背景形成高对比
+                                    .style(egui_plot::LineStyle::Solid)
+                                );
+                            });
+                    });
+                }
+                CompareLowerPlot::BlandAltman => {
+                    // 每个配对窗口一个点；水平线为平均差及 ±1.96 倍标准差的一致性界限
+                    let (lower, upper) = res.limits_of_agreement();
+                    ui.push_id("compare_bland_altman_plot", |ui| {
+                        Plot::new("compare_bland_altman")
+                            .height(height)
+                            .legend(Legend::default())
+                            .x_axis_label(self.lang.bland_altman_x_label) // I18N
+                            .y_axis_label(self.lang.bland_altman_y_label) // I18N
+                            .show(ui, |plot_ui| {
+                                // 横轴为两轨平均电平，不是时间
+                                copied = copy_value_on_click(plot_ui, &[], |x| format!("{:.2} dB", x)).or(copied.take());
+                                plot_ui.points(Points::new("A - B", PlotPoints::Borrowed(&self.compare_plot_cache.agreement))
+                                    .color(self.palette.diff_line)
+                                    .radius(1.5)
+                                );
+                                plot_ui.hline(egui_plot::HLine::new("Mean Diff", res.mean_diff)
+                                    .color(self.palette.mean_line)
+                                    .style(egui_plot::LineStyle::Dashed { length: 5.0 })
+                                );
+                                let limits_name = format!("±{:.2}σ", LIMITS_OF_AGREEMENT_Z);
+                                for limit in [lower, upper] {
+                                    plot_ui.hline(egui_plot::HLine::new(limits_name.as_str(), limit)
+                                        .color(self.palette.warn)
+                                        .style(egui_plot::LineStyle::Dashed { length: 8.0 })
+                                    );
+                                }
+                                plot_ui.hline(egui_plot::HLine::new("Zero", 0.0)
+                                    .color(self.palette.zero_line)
+                                    .style(egui_plot::LineStyle::Solid)
+                                );
+                            });
+                    });
+                    let limits_fmt = format!("{:+.2} … {:+.2}", lower, upper);
+                    ui.weak(self.lang.bland_altman_limits_fmt.replacen("{}", &limits_fmt, 1)); // I18N
+                }
+            }
+            if let Some(text) = copied {
+                self.state.notifications.push(ToastLevel::Info, self.lang.value_copied_fmt.replacen("{}", &text, 1)); // I18N
+            }
+
+        } else {
+            ui.centered_and_justified(|ui| {
+                ui.label(self.lang.compare_empty_label); // I18N
+            });
+        }
+    }
+
+
+    fn ui_batch_mode(&mut self, ui: &mut egui::Ui) {
+        ui.heading(self.lang.batch_heading); // I18N
+
+        ui.horizontal(|ui| {
+            let ref_text = self.batch_ref_path.as_ref()
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.lang.batch_select_ref.to_string());
+            if local_file_button(ui, true, egui::Button::new(ref_text), self.lang.web_unavailable_hint).clicked() {
+                log_info(&self.logger, "选择批量参考文件");
+                if let Some(path) = FileDialog::new().add_filter("Audio", &["wav", "csv"]).pick_file() {
+                    self.batch_ref_path = Some(path);
+                }
+            }
+
+            let folder_text = self.batch_folder.as_ref()
+                .map(|p| p.display().to_string())
+                .unwrap_or_else(|| self.lang.batch_select_folder.to_string());
+            if local_file_button(ui, true, egui::Button::new(folder_text), self.lang.web_unavailable_hint).clicked() {
+                log_info(&self.logger, "选择批量候选文件夹");
+                if let Some(folder) = FileDialog::new().pick_folder() {
+                    self.batch_folder = Some(folder);
+                }
+            }
+
+            let ready = self.batch_ref_path.is_some() && self.batch_folder.is_some();
+            if ui.add_enabled(ready, egui::Button::new(self.lang.batch_run_btn)).clicked() {
+                self.start_batch();
+            }
+
+            if !self.batch_results.is_empty() && local_file_button(ui, true, egui::Button::new(self.lang.batch_export_btn), self.lang.web_unavailable_hint).clicked()
+                && let Err(e) = export_batch_report(&self.batch_results, &self.state.settings, self.confidence_level as f64, &self.logger)
+            {
+                let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                log_error(&self.logger, &err_msg);
+                self.state.notifications.push(ToastLevel::Error, err_msg.clone());
+                self.state.error_msg = Some(err_msg);
+            }
+        });
+
+        // 判定阈值与对比模式共用
+        ui.horizontal(|ui| {
+            ui.label(self.lang.compare_threshold_label); // I18N
+            ui.label(format!(
+                "{} {:.2} / {} {:.2}",
+                self.lang.compare_high_match_threshold, self.state.settings.high_match_threshold,
+                self.lang.compare_huge_diff_threshold, self.state.settings.huge_diff_threshold,
+            ));
+        });
+        ui.separator();
+
+        if self.batch_total == 0 && self.batch_results.is_empty() {
+            ui.label(self.lang.batch_empty_label); // I18N
+            return;
+        }
+
+        // 进度
+        let done = self.batch_results.len();
+        ui.horizontal(|ui| {
+            let progress_text = self.lang.batch_progress_fmt
+                .replacen("{}", &done.to_string(), 1)
+                .replacen("{}", &self.batch_total.to_string(), 1);
+            ui.label(progress_text);
+            let fraction = if self.batch_total == 0 { 0.0 } else { done as f32 / self.batch_total as f32 };
+            ui.add(egui::ProgressBar::new(fraction).show_percentage());
+        });
+
+        // 结果表
+        ui.push_id("batch_results_area", |ui| {
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                egui::Grid::new("batch_results_grid")
+                    .striped(true)
+                    .num_columns(5)
+                    .show(ui, |ui| {
+                        ui.strong(self.lang.batch_col_file);
+                        ui.strong(self.lang.batch_col_mean);
+                        ui.strong(self.lang.batch_col_std);
+                        ui.strong(self.lang.batch_col_corr);
+                        ui.strong(self.lang.batch_col_verdict);
+                        ui.end_row();
+
+                        for row in &self.batch_results {
+                            ui.label(&row.name);
+                            match &row.result {
+                                Ok(stats) => {
+                                    ui.label(format!("{:.2}", stats.mean_diff));
+                                    ui.label(format!("{:.4}", stats.std_dev));
+                                    ui.label(format!("{:.4}", stats.correlation_coefficient));
+                                    let verdict = self.state.settings.verdict(stats.std_dev);
+                                    ui.horizontal(|ui| {
+                                        ui.colored_label(self.palette.verdict_color(verdict), self.lang.verdict_label(verdict));
+                                        if self.state.settings.is_partial_overlap(stats.overlap_ratio) {
+                                            let overlap_fmt = format!("{:.1}", stats.overlap_ratio * 100.0);
+                                            ui.colored_label(self.palette.warn, "⚠")
+                                                .on_hover_text(self.lang.compare_overlap_fmt.replacen("{}", &overlap_fmt, 1));
+                                        }
+                                    });
+                                }
+                                Err(e) => {
+                                    ui.label("-");
+                                    ui.label("-");
+                                    ui.label("-");
+                                    ui.colored_label(self.palette.error, e);
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+        });
+    }
+
+    /// 多行对比报告 (控制台 `compare` 输出，文本与对比面板一致)
+    /// Track A / B 使用不同的分析参数计算时，两者参数的摘要 (每行一条)
+    fn mixed_analysis_details(&self) -> Option<String> {
+        let (a, b) = (self.state.compare_a.as_ref()?.analysis.as_ref()?, self.state.compare_b.as_ref()?.analysis.as_ref()?);
+        (!a.same_results(b)).then(|| format!("A: {}\nB: {}", analysis_summary(a, &self.lang), analysis_summary(b, &self.lang)))
+    }
+
+    /// 当前置信度下 t 检验的临界值及自由度
+    fn t_critical_line(&self, res: &ComparisonResult) -> String {
+        self.lang.compare_t_critical_fmt
+            .replacen("{}", &confidence_percent(self.confidence_level), 1)
+            .replacen("{}", &res.diff_points.len().saturating_sub(1).to_string(), 1)
+            .replacen("{}", &format!("{:.3}", res.t_critical_value(self.confidence_level as f64)), 1)
+    }
+
+    /// 相关系数检验的文字 (置信区间、是否显著高于最低可接受 r) 及是否通过；点数不足时只有一行说明
+    fn correlation_test_lines(&self, res: &ComparisonResult) -> (Vec<String>, Option<bool>) {
+        let min_r = format!("{:.3}", self.state.settings.min_correlation);
+        let Some(test) = res.correlation_test(self.state.settings.min_correlation as f64, self.confidence_level as f64) else {
+            return (vec![self.lang.compare_r_test_na.to_string()], None);
+        };
+        let interval = self.lang.compare_r_interval_fmt // I18N
+            .replacen("{}", &confidence_percent(self.confidence_level), 1)
+            .replacen("{}", &format!("{:.4}", test.interval.0), 1)
+            .replacen("{}", &format!("{:.4}", test.interval.1), 1);
+        let verdict = if test.passes { self.lang.compare_r_test_pass_fmt } else { self.lang.compare_r_test_fail_fmt }; // I18N
+        (vec![interval, verdict.replacen("{}", &min_r, 1)], Some(test.passes))
+    }
+
+    /// Track A / B 按不同的分析配置加载时的提示
+    fn mixed_profile_note(&self) -> Option<String> {
+        let (a, b) = (self.state.compare_a.as_ref()?, self.state.compare_b.as_ref()?);
+        (a.profile != b.profile).then(|| self.lang.profile_mixed_compare_fmt // I18N
+            .replacen("{}", a.profile.as_deref().unwrap_or(self.lang.profile_none), 1)
+            .replacen("{}", b.profile.as_deref().unwrap_or(self.lang.profile_none), 1))
+    }
+
+    /// 时间基不同而重采样时的说明，列出两条曲线的点间隔
+    fn resampled_note(&self, res: &ComparisonResult) -> Option<String> {
+        if !res.resampled {
+            return None;
+        }
+        let spacing = |curve: Option<&AudioCurve>| curve.and_then(|c| median_spacing(&c.points)).map_or_else(|| "?".to_string(), |s| format!("{:.3}", s));
+        Some(self.lang.compare_resampled_note_fmt
+            .replacen("{}", &spacing(self.state.compare_a.as_ref()), 1)
+            .replacen("{}", &spacing(self.state.compare_b.as_ref()), 1)) // I18N
+    }
+
+    /// Track A / B 上已有的各项指标，每项一行
+    fn compare_metric_lines(&self) -> Vec<String> {
+        let (Some(a), Some(b)) = (&self.state.compare_a, &self.state.compare_b) else { return Vec::new() };
+        let names: BTreeSet<&String> = a.metrics.keys().chain(b.metrics.keys()).collect();
+        let value = |curve: &AudioCurve, name: &str| curve.metric(name).map_or_else(|| "-".to_string(), |v| v.to_string());
+        names.into_iter()
+            .map(|name| self.lang.compare_metric_fmt // I18N
+                .replacen("{}", name, 1)
+                .replacen("{}", &value(a, name), 1)
+                .replacen("{}", &value(b, name), 1))
+            .collect()
+    }
+
+    fn comparison_report(&self, res: &ComparisonResult) -> String {
+        let verdict = self.state.settings.verdict(res.std_dev);
+        let mut lines = vec![
+            self.lang.compare_report_title.to_string(),
+        ];
+        if let (Some(a), Some(b)) = (&self.state.compare_a, &self.state.compare_b) {
+            lines.push(self.lang.compare_tracks_fmt.replacen("{}", &a.display_label(), 1).replacen("{}", &b.display_label(), 1));
+        }
+        if self.state.synthetic_a.is_some() {
+            lines.push(self.lang.synthetic_report_note.to_string());
+        }
+        if let Some(level_offset) = res.level_offset {
+            lines.push(self.lang.compare_level_offset_fmt.replacen("{}", &format!("{:+.2}", level_offset), 1));
+            lines.push(self.lang.compare_residual_note.to_string());
+        }
+        lines.extend(self.resampled_note(res));
+        if let Some(details) = self.mixed_analysis_details() {
+            lines.push(format!("{}\n{}", self.lang.analysis_mixed_compare, details));
+        }
+        lines.extend(self.mixed_profile_note());
+        let score = res.consistency_score(&self.state.settings.consistency_weights, self.state.target_mean_diff as f64);
+        lines.push(self.lang.compare_score_fmt.replacen("{}", &format!("{:.0}", score), 1));
+        lines.extend([
+            self.lang.compare_avg_diff_fmt.replacen("{}", &format!("{:.2}", res.mean_diff), 1),
+            self.lang.compare_std_dev_fmt.replacen("{}", &format!("{:.4}", res.std_dev), 1),
+            self.lang.compare_correlation_fmt.replacen("{}", &format!("{:.4}", res.correlation_coefficient), 1),
+            self.lang.compare_max_diff_fmt.replacen("{}", &format!("{:.2}", res.max_diff), 1),
+            self.lang.compare_min_diff_fmt.replacen("{}", &format!("{:.2}", res.min_diff), 1),
+            self.lang.compare_overlap_fmt.replacen("{}", &format!("{:.1}", res.overlap_ratio * 100.0), 1),
+            self.lang.compare_offset_fmt.replacen("{}", &format!("{:+.3}", res.b_offset), 1),
+            self.lang.compare_t_stat_fmt.replacen("{}", &format!("{:.2}", res.t_statistic_for(self.state.target_mean_diff as f64)), 1),
+            self.t_critical_line(res),
+            if res.differs_from_target(self.state.target_mean_diff as f64, self.confidence_level as f64) {
+                self.lang.compare_t_test_significant
+            } else {
+                self.lang.compare_t_test_not_significant
+            }.to_string(),
+        ]);
+        lines.extend(self.correlation_test_lines(res).0);
+        lines.push(self.lang.verdict_label(verdict).to_string());
+        lines.extend(self.compare_metric_lines());
+        if self.state.settings.is_partial_overlap(res.overlap_ratio) {
+            lines.push(self.lang.compare_partial_warning_fmt.replacen("{}", &format!("{:.0}", self.state.settings.min_overlap_percent), 1));
+        }
+        lines.join("\n")
+    }
+
+    /// 提交一行命令：展开 `!<n>`，记入历史 (忽略连续重复)，执行后把命令及结果追加到控制台输出区
+    fn submit_command(&mut self, input: String) {
+        self.history_cursor = None;
+        if input.is_empty() { return; }
+
+        let cmd = if let Some(n) = input.strip_prefix('!') {
+            match n.parse::<usize>().ok().and_then(|n| n.checked_sub(1)).and_then(|i| self.cmd_history.get(i)) {
+                Some(entry) => entry.clone(),
+                None => {
+                    let output = CommandOutput::error(self.lang.cmd_history_missing_fmt.replacen("{}", &input, 1));
+                    self.record_command_output(input, output);
+                    return;
+                }
+            }
+        } else {
+            input
+        };
+
+        if self.cmd_history.last() != Some(&cmd) {
+            self.cmd_history.push(cmd.clone());
+            if self.cmd_history.len() > CMD_HISTORY_CAP {
+                let excess = self.cmd_history.len() - CMD_HISTORY_CAP;
+                self.cmd_history.drain(0..excess);
+            }
+        }
+        let output = self.handle_command(cmd.clone());
+        self.record_command_output(cmd, output);
+    }
+
+    /// 追加一条命令回显；失败时错误同时显示在状态栏
+    fn record_command_output(&mut self, command: String, output: CommandOutput) {
+        self.state.error_msg = (!output.success).then(|| output.text.clone());
+        self.console_output.push(ConsoleEcho { command, output });
+        if self.console_output.len() > CONSOLE_OUTPUT_CAP {
+            let excess = self.console_output.len() - CONSOLE_OUTPUT_CAP;
+            self.console_output.drain(0..excess);
+        }
+    }
+
+    /// Tab 补全：第一个词补全命令名，之后按命令的参数类型补全任务 ID、路径或命令名。
+    /// 有多个候选时补到公共前缀并在日志中列出候选。返回输入是否被修改
+    fn complete_input(&mut self) -> bool {
+        let input = self.cmd_input.trim_start().to_string();
+        let (token_start, quote) = last_token_start(&input);
+        let head = &input[..token_start];
+        let prefix = &input[token_start + quote.map_or(0, |q| q.len_utf8())..];
+
+        let candidates: Vec<String> = if head.is_empty() {
+            CONSOLE_COMMANDS.iter()
+                .flat_map(|c| c.names.iter())
+                .filter(|n| n.to_lowercase().starts_with(&prefix.to_lowercase()))
+                .map(|n| n.to_string())
+                .collect()
+        } else {
+            let name = head.split_whitespace().next().unwrap_or_default();
+            match find_console_command(name).map(|c| c.arg) {
+                Some(CommandArg::TaskId) => self.worker_pool.tasks.lock()
+                    .map(|tasks| tasks.iter().map(|t| t.id.to_string()).filter(|id| id.starts_with(prefix)).collect())
+                    .unwrap_or_default(),
+                Some(CommandArg::Path) => complete_path(prefix),
+                Some(CommandArg::Command) => CONSOLE_COMMANDS.iter()
+                    .map(|c| c.names[0].to_string())
+                    .filter(|n| n.to_lowercase().starts_with(&prefix.to_lowercase()))
+                    .collect(),
+                _ => Vec::new(),
+            }
+        };
+
+        // 含空格的候选需要加引号；已经在引号中时沿用原引号
+        let open_quote = |text: &str| match quote {
+            Some(q) => q.to_string(),
+            None if text.contains(char::is_whitespace) => "\"".to_string(),
+            None => String::new(),
+        };
+        let completed = match candidates.len() {
+            0 => return false,
+            1 => {
+                let only = &candidates[0];
+                let q = open_quote(only);
+                // 补全完整后闭合引号并追加空格，目录保持开放以便继续补全
+                if only.ends_with(['/', std::path::MAIN_SEPARATOR]) {
+                    format!("{}{}{}", head, q, only)
+                } else {
+                    format!("{}{}{}{} ", head, q, only, q)
+                }
+            }
+            _ => {
+                log_info(&self.logger, &format!("补全候选: {}", candidates.join("  ")));
+                let common = common_prefix(&candidates);
+                format!("{}{}{}", head, open_quote(&common), common)
+            }
+        };
+
+        if completed == self.cmd_input {
+            return false;
+        }
+        self.cmd_input = completed;
+        self.history_cursor = None;
+        true
+    }
+
+    /// 输入框获得焦点时处理 上/下 (浏览历史)、Tab (补全) 和 Esc (清空输入)，需在绘制输入框之前调用以拦截按键
+    fn handle_history_keys(&mut self, ui: &egui::Ui, input_id: egui::Id) {
+        if !ui.memory(|m| m.has_focus(input_id)) { return; }
+
+        let (up, down, escape, tab) = ui.input_mut(|i| (
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Escape),
+            i.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+        ));
+
+        if tab {
+            if !self.complete_input() { return; }
+        } else if escape {
+            self.cmd_input.clear();
+            self.history_cursor = None;
+        } else if up && !self.cmd_history.is_empty() {
+            let index = match self.history_cursor {
+                None => self.cmd_history.len() - 1,
+                Some(i) => i.saturating_sub(1),
+            };
+            self.history_cursor = Some(index);
+            self.cmd_input = self.cmd_history[index].clone();
+        } else if down && let Some(i) = self.history_cursor {
+            if i + 1 < self.cmd_history.len() {
+                self.history_cursor = Some(i + 1);
+                self.cmd_input = self.cmd_history[i + 1].clone();
+            } else {
+                self.history_cursor = None;
+                self.cmd_input.clear();
+            }
+        } else {
+            return;
+        }
 
-            // 双图表显示
-            // 上图：原始曲线对比
-            ui.label(self.lang.compare_plot_raw_label); // I18N
-            let height = ui.available_height() / 2.0 - 20.0;
-            // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源
-            ui.push_id("compare_raw_plot", |ui| {
-                Plot::new("compare_raw")
-                    .height(height)
-                    .legend(Legend::default())
-                    .show(ui, |plot_ui| {
-                        if let Some(a) = &self.compare_a {
-                            plot_ui.line(Line::new("Track A", PlotPoints::new(a.points.clone())).color(egui::Color32::GREEN));
-                        }
-                        if let Some(b) = &self.compare_b {
-                            plot_ui.line(Line::new("Track B", PlotPoints::new(b.points.clone())).color(egui::Color32::RED));
-                        }
-                    });
-            });
+        // 光标移到行尾
+        if let Some(mut state) = egui::TextEdit::load_state(ui.ctx(), input_id) {
+            let end = egui::text::CCursor::new(self.cmd_input.chars().count());
+            state.cursor.set_char_range(Some(egui::text::CCursorRange::one(end)));
+            state.store(ui.ctx(), input_id);
+        }
+    }
 
-            // 下图：差值曲线
-            ui.label(self.lang.compare_plot_diff_label); // I18N
-            // ⭐ 修复 ID 冲突：为 Plot 控件提供唯一的 ID 源
-            ui.push_id("compare_diff_plot", |ui| {
-                Plot::new("compare_diff")
-                    .height(height)
-                    .show(ui, |plot_ui| {
-                        // 差值曲线颜色更改为 CYAN (青色)，提高可读性
-                        plot_ui.line(Line::new("Diff", PlotPoints::new(res.diff_points.clone()))
-                            .color(egui::Color32::from_rgb(0, 255, 255))
-                        );
-
-                        // 绘制平均线
-                        plot_ui.hline(egui_plot::HLine::new("Mean Diff", res.mean_diff)
-                            .color(egui::Color32::GRAY)
-                            .style(egui_plot::LineStyle::Dashed { length: 5.0 })
-                        );
-
-                        // 新增: 绘制零点线，提高可读性
-                        plot_ui.hline(egui_plot::HLine::new("Zero", 0.0)
-                            .color(egui::Color32::WHITE) // 零点线使用白色突出显示
-                            .style(egui_plot::LineStyle::Solid)
-                        );
-                    });
-            });
+    /// 打开或关闭日志文件镜像并保存到设置
+    fn set_log_file(&mut self, enabled: bool) {
+        self.state.settings.log_file = enabled;
+        if enabled {
+            start_log_file(&self.logger, &self.state.settings);
+        } else if let Ok(mut entries) = self.logger.entries.lock()
+            && entries.mirror().is_some()
+        {
+            entries.set_mirror(None);
+            drop(entries);
+            log_info(&self.logger, "📝 已停止写入日志文件");
+        }
+    }
 
-        } else {
-            ui.centered_and_justified(|ui| {
-                ui.label(self.lang.compare_empty_label); // I18N
-            });
+    /// 修改日志缓冲区容量并保存到设置；缩小时立即淘汰最旧的日志
+    fn set_log_capacity(&mut self, capacity: usize) {
+        self.state.settings.log_capacity = capacity;
+        if let Ok(mut entries) = self.logger.entries.lock() {
+            entries.set_capacity(capacity);
         }
     }
 
+    /// 命令参数错误时显示该命令在命令表中的用法
+    fn usage_error(&self, name: &str) -> String {
+        let usage = find_console_command(name).map_or(name, |command| command.usage);
+        self.lang.cmd_usage_fmt.replacen("{}", usage, 1)
+    }
 
-    /// 处理命令行输入
-    fn handle_command(&mut self, cmd: String) {
+    /// 处理命令行输入，返回回显在控制台输出区的结果；后台任务的进度和结果仍写入日志
+    fn handle_command(&mut self, cmd: String) -> CommandOutput {
         log_command(&self.logger, &format!("Executed: {}", cmd));
-        self.error_msg = None;
 
-        let parts: Vec<&str> = cmd.split_whitespace().collect();
-        if parts.is_empty() { return; }
+        let parts = match tokenize_command(&cmd) {
+            Ok(parts) => parts,
+            Err(quote) => return CommandOutput::error(self.lang.cmd_unclosed_quote_fmt.replacen("{}", &quote.to_string(), 1)),
+        };
+        if parts.is_empty() { return CommandOutput::default(); }
 
         match parts[0].to_lowercase().as_str() {
             "kill" => {
-                if parts.len() == 2 {
-                    if let Ok(id) = parts[1].parse::<usize>() {
+                if parts.len() != 2 {
+                    return CommandOutput::error(self.usage_error("kill"));
+                }
+                match parts[1].parse::<usize>() {
+                    Ok(id) => {
                         self.worker_pool.command_tx.send(WorkerCommand::Kill(id)).unwrap_or_default();
-                    } else {
-                        self.error_msg = Some("❌ 命令错误: 'kill <id>' 需要一个数字 ID.".to_string());
+                        CommandOutput::default()
                     }
-                } else {
-                    self.error_msg = Some("❌ 命令错误: 用法: kill <task_id>".to_string());
+                    Err(_) => CommandOutput::error(self.lang.cmd_kill_needs_id.to_string()),
                 }
             }
             "tasks" | "list" => {
-                if let Ok(tasks) = self.worker_pool.tasks.lock() {
-                    let mut msg = String::from("当前任务列表:\n");
-                    for task in tasks.iter() {
-                        let state_str = match &task.state {
-                            TaskState::Running(p) => format!("Running ({:.0}%)", p * 100.0),
-                            s => format!("{:?}", s),
-                        };
-                        msg.push_str(&format!("ID: {}, Name: {}, State: {}\n", task.id, task.name, state_str));
+                let Ok(tasks) = self.worker_pool.tasks.lock() else { return CommandOutput::default() };
+                let mut lines = vec![self.lang.cmd_tasks_header.to_string()];
+                for task in tasks.iter() {
+                    let state_str = match &task.state {
+                        TaskState::Running(p) => format!("Running ({:.0}%)", p * 100.0),
+                        s => format!("{:?}", s),
+                    };
+                    lines.push(format!("{:>4}  {:<32}  {}", task.id, task.name, state_str));
+                }
+                CommandOutput::ok(lines.join("\n"))
+            }
+            "perf" => CommandOutput::ok(perf_report(&self.state.task_perf, &self.lang).join("\n")),
+            "quickscan" => {
+                // 未加引号时把其余参数拼回目录，兼容旧的不带引号写法
+                let dir = parts[1..].join(" ");
+                if dir.is_empty() {
+                    return CommandOutput::error(self.usage_error("quickscan"));
+                }
+                self.start_quick_scan(PathBuf::from(dir));
+                CommandOutput::default()
+            }
+            "history" => {
+                let count = parts.get(1).and_then(|n| n.parse::<usize>().ok()).unwrap_or(20);
+                let start = self.cmd_history.len().saturating_sub(count);
+                let mut lines = vec![self.lang.cmd_history_header.to_string()];
+                for (i, entry) in self.cmd_history.iter().enumerate().skip(start) {
+                    lines.push(format!("{:>4}  {}", i + 1, entry));
+                }
+                CommandOutput::ok(lines.join("\n"))
+            }
+            "load" => {
+                if parts.len() < 2 {
+                    return CommandOutput::error(self.usage_error("load"));
+                }
+                self.spawn_load_tasks(parts[1..].iter().map(PathBuf::from).collect());
+                CommandOutput::default()
+            }
+            "loadurl" => {
+                if parts.len() != 2 {
+                    return CommandOutput::error(self.usage_error("loadurl"));
+                }
+                self.spawn_url_load(parts[1].clone());
+                CommandOutput::default()
+            }
+            "loada" | "loadb" => {
+                if parts.len() != 2 {
+                    return CommandOutput::error(self.usage_error(&parts[0]));
+                }
+                let file_slot = if parts[0].eq_ignore_ascii_case("loadA") { 'A' } else { 'B' };
+                self.spawn_compare_load(PathBuf::from(&parts[1]), file_slot);
+                CommandOutput::default()
+            }
+            "compare" => {
+                // 前置条件不满足时 run_comparison 不会更新结果，不能回显上一次的报告
+                if self.state.compare_slot_pending() {
+                    return CommandOutput::error(self.lang.cmd_compare_pending.to_string());
+                }
+                if self.state.compare_a.is_none() || self.state.compare_b.is_none() {
+                    return CommandOutput::error(self.lang.cmd_compare_missing.to_string());
+                }
+                self.state.request_compare(&self.lang);
+                match self.state.compare_result.clone() {
+                    Some(res) => CommandOutput::ok(self.comparison_report(&res)),
+                    None => CommandOutput::error(self.state.error_msg.clone().unwrap_or_default()),
+                }
+            }
+            "export" => {
+                if parts.len() != 3 {
+                    return CommandOutput::error(self.usage_error("export"));
+                }
+                let curves = self.state.single_files.lock().unwrap();
+                let curve = match parts[1].parse::<usize>() {
+                    Ok(n) => n.checked_sub(1).and_then(|i| curves.get(i)),
+                    Err(_) => curves.iter().find(|c| c.name == parts[1]),
+                };
+                let result = match curve {
+                    Some(curve) => write_curve_csv(curve, &self.csv_export_options(), Path::new(&parts[2]), &self.logger)
+                        .map(|_| self.lang.export_success_fmt.replacen("{}", &curve.name, 1))
+                        .map_err(|e| self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1)),
+                    None => Err(self.lang.cmd_curve_not_found_fmt.replacen("{}", &parts[1], 1)),
+                };
+                drop(curves);
+                match result {
+                    Ok(msg) => {
+                        self.state.notifications.push(ToastLevel::Success, msg.clone());
+                        CommandOutput::ok(msg)
+                    }
+                    Err(err_msg) => {
+                        log_error(&self.logger, &err_msg);
+                        self.state.notifications.push(ToastLevel::Error, err_msg.clone());
+                        CommandOutput::error(err_msg)
+                    }
+                }
+            }
+            "help" => {
+                match parts.get(1) {
+                    None => {
+                        let mut lines = vec![self.lang.cmd_help_header.to_string()];
+                        lines.extend(CONSOLE_COMMANDS.iter().map(|command| format!("  {}", command.usage)));
+                        CommandOutput::ok(lines.join("\n"))
                     }
-                    log_info(&self.logger, &msg);
+                    Some(name) => match find_console_command(name) {
+                        Some(command) => CommandOutput::ok(format!("{}\n  {}", command.usage, (command.help)(&self.lang))),
+                        None => CommandOutput::error(self.lang.cmd_unknown_fmt.replacen("{}", name, 1)),
+                    },
                 }
             }
             "clear" => {
+                // 同时清空日志和命令输出，本条命令的结果仍会回显
                 if let Ok(mut entries) = self.logger.entries.lock() {
                     entries.clear();
-                    log_info(&self.logger, "控制台日志已清空。");
+                }
+                self.console_output.clear();
+                CommandOutput::ok(self.lang.cmd_log_cleared.to_string())
+            }
+            "logcap" => {
+                // 不带参数时显示当前容量
+                let Some(arg) = parts.get(1) else {
+                    return CommandOutput::ok(self.lang.cmd_logcap_current_fmt.replacen("{}", &self.state.settings.log_capacity.to_string(), 1));
+                };
+                match arg.parse::<usize>() {
+                    Ok(capacity) if LOG_CAPACITY_RANGE.contains(&capacity) => {
+                        self.set_log_capacity(capacity);
+                        CommandOutput::ok(self.lang.cmd_logcap_set_fmt.replacen("{}", &capacity.to_string(), 1))
+                    }
+                    _ => CommandOutput::error(self.lang.cmd_logcap_invalid_fmt
+                        .replacen("{}", &LOG_CAPACITY_RANGE.start().to_string(), 1)
+                        .replacen("{}", &LOG_CAPACITY_RANGE.end().to_string(), 1)),
+                }
+            }
+            "logfile" => {
+                if parts.len() != 2 {
+                    return CommandOutput::error(self.usage_error("logfile"));
+                }
+                match parts[1].to_lowercase().as_str() {
+                    "on" => self.set_log_file(true),
+                    "off" => self.set_log_file(false),
+                    "path" => {}
+                    _ => {
+                        // 其他参数视为日志目录，切换目录并开启
+                        self.state.settings.log_file_dir = Some(PathBuf::from(&parts[1]));
+                        self.set_log_file(true);
+                    }
+                }
+                let current = self.logger.entries.lock().ok().and_then(|entries| entries.mirror().map(LogFileMirror::current_path));
+                match current {
+                    Some(path) => CommandOutput::ok(self.lang.cmd_logfile_on_fmt.replacen("{}", &path.display().to_string(), 1)),
+                    None => CommandOutput::ok(self.lang.cmd_logfile_off.to_string()),
                 }
             }
             "quit" | "exit" => {
-                self.worker_pool.command_tx.send(WorkerCommand::Shutdown).unwrap_or_default();
-                self.error_msg = Some("⚠️ 已发送关闭信号给工作池。请手动关闭窗口。".to_string());
+                self.quit_requested = true;
+                CommandOutput::default()
             }
-            _ => {
-                self.error_msg = Some(format!("❌ 未找到命令: {}", parts[0]));
+            _ => CommandOutput::error(self.lang.cmd_unknown_fmt.replacen("{}", &parts[0], 1)),
+        }
+    }
+
+    /// 实时监视模式 UI：选择输入设备、开始/停止采集，滚动显示最近几分钟的电平曲线
+    fn ui_live_mode(&mut self, ui: &mut egui::Ui) {
+        ui.heading(self.lang.mode_live); // I18N
+        if !LIVE_INPUT_AVAILABLE {
+            ui.colored_label(self.palette.warn, self.lang.live_unavailable); // I18N
+            return;
+        }
+        if self.live_devices.is_none() {
+            self.refresh_input_devices();
+        }
+
+        let running = self.live.as_ref().is_some_and(LiveMonitor::is_running);
+        ui.horizontal(|ui| {
+            ui.label(self.lang.live_device_label); // I18N
+            let selected = self.state.settings.live_device.clone().unwrap_or_else(|| self.lang.live_default_device.to_string());
+            ui.add_enabled_ui(!running, |ui| {
+                egui::ComboBox::from_id_salt("live_device")
+                    .selected_text(selected)
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.state.settings.live_device, None, self.lang.live_default_device);
+                        for name in self.live_devices.iter().flatten() {
+                            ui.selectable_value(&mut self.state.settings.live_device, Some(name.clone()), name);
+                        }
+                    });
+                if ui.button(self.lang.live_refresh_devices).clicked() {
+                    self.refresh_input_devices();
+                }
+            });
+
+            if running {
+                if ui.button(self.lang.live_stop_btn).clicked()
+                    && let Some(live) = &mut self.live
+                {
+                    live.stop();
+                    log_info(&self.logger, &format!("实时监视已停止: {}", live.device));
+                }
+            } else if ui.button(self.lang.live_start_btn).clicked() {
+                self.start_live_monitor();
+            }
+
+            ui.separator();
+            ui.label(self.lang.live_retain_label); // I18N
+            ui.add(egui::Slider::new(&mut self.state.settings.live_retain_minutes, 1.0..=60.0).suffix(" min"))
+                .on_hover_text(self.lang.live_retain_hint);
+        });
+
+        let Some(live) = &mut self.live else {
+            ui.label(self.lang.live_empty); // I18N
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            let status = self.lang.live_status_fmt
+                .replacen("{}", &live.device, 1)
+                .replacen("{}", &live.sample_rate.to_string(), 1)
+                .replacen("{}", &live.channels.to_string(), 1);
+            ui.label(status);
+            if let Some(&[_, db]) = live.points.back() {
+                ui.strong(self.lang.live_level_fmt.replacen("{}", &format!("{:.1}", db), 1)); // I18N
+            }
+            ui.checkbox(&mut live.frozen, self.lang.live_freeze).on_hover_text(self.lang.live_freeze_hint); // I18N
+            let dropped = live.dropped_blocks();
+            if dropped > 0 {
+                ui.colored_label(self.palette.warn, self.lang.live_dropped_fmt.replacen("{}", &dropped.to_string(), 1));
+            }
+        });
+
+        let has_points = !live.points.is_empty();
+        let mut add_curve = false;
+        let mut export_curve = false;
+        ui.horizontal(|ui| {
+            add_curve = ui.add_enabled(has_points, egui::Button::new(self.lang.live_add_btn)).clicked(); // I18N
+            export_curve = ui.add_enabled(has_points, egui::Button::new(self.lang.export_csv_btn)).clicked(); // I18N
+        });
+
+        // 采集中且未冻结时横轴跟随最新的点滚动；冻结或停止后可以自由缩放拖动
+        let follow = live.is_running() && !live.frozen;
+        let retain_sec = self.state.settings.live_retain_minutes as f64 * 60.0;
+        let y_range = self.state.settings.single_y_range;
+        let points: Vec<PlotPoint> = live.points.iter().map(|p| PlotPoint::new(p[0], p[1])).collect();
+        self.state.settings.time_format.apply(Plot::new("live_plot"))
+            .default_y_bounds(y_range[0], y_range[1])
+            .show(ui, |plot_ui| {
+                if follow && let Some(last) = points.last() {
+                    plot_ui.set_plot_bounds_x((last.x - retain_sec).max(0.0)..=last.x.max(1.0));
+                    plot_ui.set_plot_bounds_y(y_range[0]..=y_range[1]);
+                }
+                plot_ui.line(Line::new(live.device.as_str(), PlotPoints::Borrowed(&points)).color(self.palette.track_a));
+            });
+
+        if add_curve {
+            let curve = live.to_curve();
+            log_info(&self.logger, &format!("实时曲线已添加到单文件列表: {} ({} 点)", curve.name, curve.points.len()));
+            self.state.notifications.push(ToastLevel::Success, self.lang.live_added_fmt.replacen("{}", &curve.name, 1));
+            self.state.add_single_curve(curve);
+        }
+        if export_curve {
+            let curve = live.to_curve();
+            match export_to_csv(&curve, &self.csv_export_options(), &self.logger) {
+                Ok(_) => self.state.notifications.push(ToastLevel::Success, self.lang.export_success_fmt.replacen("{}", &curve.name, 1)),
+                Err(e) => {
+                    let err_msg = self.lang.export_failed_fmt.replacen("{}", &e.to_string(), 1);
+                    log_error(&self.logger, &err_msg);
+                    self.state.notifications.push(ToastLevel::Error, err_msg.clone());
+                    self.state.error_msg = Some(err_msg);
+                }
+            }
+        }
+    }
+
+    /// 重新枚举输入设备；失败时列表为空 (仍可使用默认设备)
+    fn refresh_input_devices(&mut self) {
+        match list_input_devices() {
+            Ok(devices) => {
+                log_debug(&self.logger, &format!("找到 {} 个输入设备", devices.len()));
+                self.live_devices = Some(devices);
+            }
+            Err(e) => {
+                let err_msg = self.lang.live_devices_failed_fmt.replacen("{}", &e, 1);
+                log_error(&self.logger, &err_msg);
+                self.state.notifications.push(ToastLevel::Error, err_msg);
+                self.live_devices = Some(Vec::new());
+            }
+        }
+    }
+
+    /// 打开选定的输入设备开始新的采集 (替换之前的曲线)
+    fn start_live_monitor(&mut self) {
+        let device = self.state.settings.live_device.clone();
+        log_info(&self.logger, &format!("开始实时监视: {}", device.as_deref().unwrap_or("默认输入设备")));
+        match LiveMonitor::start(device.as_deref(), &self.state.settings.analysis, Logger { entries: self.logger.entries.clone() }) {
+            Ok(live) => self.live = Some(live),
+            Err(e) => {
+                let err_msg = self.lang.live_open_failed_fmt.replacen("{}", &e, 1);
+                log_error(&self.logger, &err_msg);
+                self.state.notifications.push(ToastLevel::Error, err_msg.clone());
+                self.state.error_msg = Some(err_msg);
             }
         }
     }
 
     /// 控制台模式 UI (包含命令行和进程监视器)
     fn ui_console_mode(&mut self, ui: &mut egui::Ui) {
-        ui.heading(self.lang.mode_console);
+        ui.horizontal(|ui| {
+            ui.heading(self.lang.mode_console);
+            // 折叠按钮：可隐藏进程监视器或日志 (至少保留一列)
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                let (show_monitor, show_log) = (self.state.settings.console_show_monitor, self.state.settings.console_show_log);
+                if ui.add_enabled(show_monitor, egui::Button::selectable(show_log, self.lang.console_log_heading)) // I18N
+                    .on_hover_text(self.lang.console_toggle_hint)
+                    .clicked()
+                {
+                    self.state.settings.console_show_log = !show_log;
+                }
+                if ui.add_enabled(show_log, egui::Button::selectable(show_monitor, self.lang.console_monitor_heading)) // I18N
+                    .on_hover_text(self.lang.console_toggle_hint)
+                    .clicked()
+                {
+                    self.state.settings.console_show_monitor = !show_monitor;
+                }
+            });
+        });
         ui.separator();
 
-        // 估算顶部标题、分隔符和底部命令行输入区域的固定高度 (约 70.0)
-        let reserved_height = 70.0;
-        let available_height_for_group = ui.available_height() - reserved_height; // 计算 ScrollArea 可用的高度
-
-        // 1. 进程监视器和调试日志 (使用 Group 包含，并使用 ScrollArea)
-        // ⭐ 修复 ID 冲突：为 Group 控件提供唯一的 ID 源
-        ui.push_id("console_monitor_group", |ui| {
-            ui.group(|ui| {
-                // 关键：限定 ScrollArea 所在 group 的高度
-                ui.set_height(available_height_for_group.max(100.0));
-
-                // 使用 Columns 分离，方便左右布局
-                ui.columns(2, |columns| {
-                    // --- 进程监视器 (左侧列) ---
-                    columns[0].vertical(|ui| {
-                        ui.heading("📊 实时进程监视器");
-                        // ⭐ 修复 E0501/E0500: 在 vertical 闭包传入的 'ui' 上调用 push_id
-                        ui.push_id("process_monitor_scroll", |ui| {
-                            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
-                                if let Ok(tasks) = self.worker_pool.tasks.lock() {
-                                    if tasks.is_empty() {
-                                        ui.label("当前无活动任务。");
-                                    } else {
-                                        // 遍历所有任务
-                                        for task in tasks.iter() {
-                                            // ⭐ 修复 ID 冲突：为每个任务行提供唯一的 ID
-                                            ui.push_id(format!("task_{}", task.id), |ui| {
-                                                ui.horizontal(|ui| {
-                                                    let id_text = format!("[{}]", task.id);
-                                                    let state_text = match &task.state {
-                                                        TaskState::Waiting => egui::RichText::new("WAITING").color(egui::Color32::GRAY),
-                                                        TaskState::Running(progress) => egui::RichText::new(format!("RUNNING ({:.0}%)", progress * 100.0)).color(egui::Color32::GREEN),
-                                                        TaskState::Completed => egui::RichText::new("COMPLETED").color(egui::Color32::BLUE),
-                                                        TaskState::Killed => egui::RichText::new("KILLED").color(egui::Color32::RED),
-                                                        TaskState::Error(e) => egui::RichText::new(format!("ERROR: {}", e)).color(egui::Color32::DARK_RED),
-                                                    };
-
-                                                    ui.label(egui::RichText::new(id_text).strong());
-                                                    ui.add_space(5.0);
-                                                    ui.label(task.name.clone());
-                                                    ui.add_space(5.0);
-                                                    ui.label(state_text);
-
-                                                    // 仅对 Running 或 Waiting 的任务显示 Kill 按钮
-                                                    if matches!(task.state, TaskState::Running(_)) || task.state == TaskState::Waiting {
-                                                        if ui.button("❌ Kill").clicked() {
-                                                            self.worker_pool.command_tx.send(WorkerCommand::Kill(task.id)).unwrap_or_default();
-                                                        }
-                                                    }
-                                                });
-                                            });
+        // 命令行输入固定在底部，先于上方的面板布局，窗口再小也不会被挤出可见区域
+        egui::TopBottomPanel::bottom("console_input_panel").show_inside(ui, |ui| {
+            ui.horizontal(|ui| {
+                // 修正：使用 I18N 字段替代硬编码的 "CMD >"
+                ui.label(egui::RichText::new(self.lang.console_cmd_label).strong());
+                let input_id = egui::Id::new("cmd_input_field"); // 确保输入框 ID 唯一
+                self.handle_history_keys(ui, input_id);
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.cmd_input)
+                        .desired_width(ui.available_width() - 80.0)
+                        .id(input_id)
+                        .lock_focus(true) // Tab 用于补全，不切换焦点
+                );
+
+                // 监听回车键和失焦事件
+                if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
+                    self.submit_command(self.cmd_input.trim().to_string());
+                    self.cmd_input.clear();
+                    response.request_focus();
+                }
+
+                if ui.button(self.lang.console_run_btn).clicked() && !self.cmd_input.is_empty() {
+                    self.submit_command(self.cmd_input.trim().to_string());
+                    self.cmd_input.clear();
+                    response.request_focus();
+                }
+            });
+            // 修正：使用 I18N 字段替代 if/else 逻辑
+            ui.label(self.lang.console_cmd_hint_cn);
+        });
+
+        // 命令输出区 (紧贴输入框上方，像终端一样最新在底部)
+        egui::TopBottomPanel::bottom("console_output_panel")
+            .resizable(true)
+            .default_height(160.0)
+            .height_range(60.0..=(ui.available_height() - 120.0).max(60.0))
+            .show_inside(ui, |ui| self.ui_console_output(ui));
+
+        // 左侧进程监视器 (宽度可拖动，持久化)；日志隐藏时监视器占满剩余区域
+        if self.state.settings.console_show_monitor && self.state.settings.console_show_log {
+            let panel = egui::SidePanel::left("console_monitor_panel")
+                .resizable(true)
+                .default_width(self.state.settings.console_monitor_width)
+                .width_range(150.0..=(ui.available_width() - 150.0).max(150.0))
+                .show_inside(ui, |ui| self.ui_console_monitor(ui));
+            self.state.settings.console_monitor_width = panel.response.rect.width();
+            egui::CentralPanel::default().show_inside(ui, |ui| self.ui_console_log(ui));
+        } else if self.state.settings.console_show_monitor {
+            egui::CentralPanel::default().show_inside(ui, |ui| self.ui_console_monitor(ui));
+        } else {
+            egui::CentralPanel::default().show_inside(ui, |ui| self.ui_console_log(ui));
+        }
+    }
+
+    /// 控制台的进程监视器列
+    fn ui_console_monitor(&mut self, ui: &mut egui::Ui) {
+        ui.heading(self.lang.console_monitor_heading); // I18N
+        // 本次会话的吞吐量汇总 (与 `perf` 命令相同)
+        egui::CollapsingHeader::new(self.lang.perf_title) // I18N
+            .id_salt("console_perf_panel")
+            .show(ui, |ui| {
+                for line in perf_report(&self.state.task_perf, &self.lang) {
+                    ui.label(egui::RichText::new(line).monospace());
+                }
+            });
+        let mut clicked_task = None;
+        ui.push_id("process_monitor_scroll", |ui| {
+            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
+                if let Ok(tasks) = self.worker_pool.tasks.lock() {
+                    let palette = self.palette;
+                    if tasks.is_empty() {
+                        ui.label(self.lang.monitor_no_tasks); // I18N
+                    } else {
+                        // 遍历所有任务
+                        for task in tasks.iter() {
+                            // ⭐ 修复 ID 冲突：为每个任务行提供唯一的 ID
+                            ui.push_id(format!("task_{}", task.id), |ui| {
+                                ui.horizontal(|ui| {
+                                    let id_text = format!("[{}]", task.id);
+                                    let state_text = match &task.state {
+                                        TaskState::Waiting => egui::RichText::new("WAITING").color(palette.task_waiting),
+                                        TaskState::Running(progress) => egui::RichText::new(format!("RUNNING ({:.0}%)", progress * 100.0)).color(palette.ok),
+                                        TaskState::Completed => egui::RichText::new("COMPLETED").color(palette.task_completed),
+                                        TaskState::Killed => egui::RichText::new("KILLED").color(palette.error),
+                                        TaskState::Error(e) => egui::RichText::new(format!("ERROR: {}", e.text(&self.lang))).color(palette.task_error),
+                                    };
+
+                                    ui.label(egui::RichText::new(id_text).strong());
+                                    ui.add_space(5.0);
+                                    // 已完成或失败的任务可单击查看结果 / 错误详情
+                                    if matches!(task.state, TaskState::Completed | TaskState::Error(_)) {
+                                        let response = ui.add(egui::Label::new(egui::RichText::new(&task.name).underline()).sense(egui::Sense::click()))
+                                            .on_hover_cursor(egui::CursorIcon::PointingHand)
+                                            .on_hover_text(self.lang.task_reveal_hint); // I18N
+                                        if response.clicked() {
+                                            clicked_task = Some((task.id, task.state.clone()));
                                         }
+                                    } else {
+                                        ui.label(task.name.clone());
+                                    }
+                                    ui.add_space(5.0);
+                                    ui.label(state_text);
+                                    if let Some(perf) = self.state.task_perf.get(&task.id) {
+                                        ui.weak(perf.summary(&self.lang));
                                     }
-                                }
-                            });
-                        });
-                    });
 
-                    // --- 控制台/日志 (右侧列) ---
-                    columns[1].vertical(|ui| {
-                        ui.heading("🗒️ 调试日志");
-                        // ⭐ 修复 E0501/E0500: 在 vertical 闭包传入的 'ui' 上调用 push_id
-                        ui.push_id("debug_log_scroll", |ui| {
-                            egui::ScrollArea::vertical().auto_shrink([false, false]).show(ui, |ui| {
-                                if let Ok(entries) = self.logger.entries.lock() {
-                                    for entry in entries.iter().rev() { // 倒序显示，最新日志在最上面
-                                        let color = match entry.level {
-                                            LogLevel::Info => egui::Color32::LIGHT_GREEN,
-                                            LogLevel::Error => egui::Color32::RED,
-                                            LogLevel::Debug => egui::Color32::YELLOW,
-                                            LogLevel::Command => egui::Color32::LIGHT_BLUE,
-                                        };
-
-                                        let level_text = format!("{:?}", entry.level).to_uppercase();
-                                        let log_text = format!("[{}] <{}> {}", entry.time, level_text, entry.message);
-                                        ui.colored_label(color, log_text);
+                                    // 仅对 Running 或 Waiting 的任务显示 Kill 按钮
+                                    if matches!(task.state, TaskState::Running(_) | TaskState::Waiting)
+                                        && ui.button("❌ Kill").clicked()
+                                    {
+                                        self.worker_pool.command_tx.send(WorkerCommand::Kill(task.id)).unwrap_or_default();
                                     }
-                                }
+                                });
                             });
-                        });
-                    });
-                });
+                        }
+                    }
+                }
             });
         });
+        if let Some((task_id, state)) = clicked_task {
+            self.reveal_task_result(task_id, &state);
+        }
+    }
 
-        ui.separator();
+    /// 命令回显：CMD 前缀加命令，随后是等宽的结果块，失败的结果用错误色
+    fn ui_console_output(&self, ui: &mut egui::Ui) {
+        ui.push_id("console_output_scroll", |ui| {
+            egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(true)
+                .show(ui, |ui| {
+                    if self.console_output.is_empty() {
+                        ui.weak(self.lang.console_output_empty); // I18N
+                    }
+                    for echo in &self.console_output {
+                        let command = format!("{} {}", self.lang.console_cmd_label, echo.command);
+                        ui.add(egui::Label::new(egui::RichText::new(command).monospace().color(self.palette.log_command)).selectable(true));
+                        if !echo.output.text.is_empty() {
+                            let color = if echo.output.success { ui.visuals().text_color() } else { self.palette.error };
+                            ui.add(egui::Label::new(egui::RichText::new(&echo.output.text).monospace().color(color)).selectable(true));
+                        }
+                    }
+                });
+        });
+    }
 
-        // 2. 命令行输入 (底部)
+    /// 控制台的调试日志列 (级别过滤、搜索、自动滚动及缓冲区容量)
+    fn ui_console_log(&mut self, ui: &mut egui::Ui) {
+        ui.heading(self.lang.console_log_heading); // I18N
+        let filter = &mut self.log_filter;
         ui.horizontal(|ui| {
-            // 修正：使用 I18N 字段替代硬编码的 "CMD >"
-            ui.label(egui::RichText::new(self.lang.console_cmd_label).strong());
-            let response = ui.add(
-                egui::TextEdit::singleline(&mut self.cmd_input)
-                    .desired_width(ui.available_width() - 80.0)
-                    .id(egui::Id::new("cmd_input_field")) // 确保输入框 ID 唯一
-            );
+            ui.checkbox(&mut filter.show_info, "Info");
+            ui.checkbox(&mut filter.show_error, "Error");
+            ui.checkbox(&mut filter.show_debug, "Debug");
+            ui.checkbox(&mut filter.show_command, "Command");
+            ui.checkbox(&mut filter.auto_scroll, self.lang.log_autoscroll); // I18N
+        });
+        ui.add(egui::TextEdit::singleline(&mut filter.search).hint_text(self.lang.log_search_hint)); // I18N
 
-            // 监听回车键和失焦事件
-            if response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter)) {
-                self.handle_command(self.cmd_input.trim().to_string());
-                self.cmd_input.clear();
-                response.request_focus();
+        // 缓冲区容量；写满后最旧的日志被淘汰，提示累计淘汰了多少条
+        let dropped = self.logger.entries.lock().map(|entries| entries.dropped()).unwrap_or(0);
+        let mut capacity = self.state.settings.log_capacity;
+        let mut log_file = self.state.settings.log_file;
+        let log_dir = log_file_dir(&self.state.settings);
+        ui.horizontal(|ui| {
+            ui.label(self.lang.log_capacity_label); // I18N
+            ui.add(egui::DragValue::new(&mut capacity).range(LOG_CAPACITY_RANGE).speed(50.0))
+                .on_hover_text(self.lang.log_capacity_hint);
+            ui.add_enabled(LOCAL_FILES, egui::Checkbox::new(&mut log_file, self.lang.log_file_label)) // I18N
+                .on_hover_text(self.lang.log_file_hint_fmt.replacen("{}", &log_dir.display().to_string(), 1))
+                .on_disabled_hover_text(self.lang.web_unavailable_hint);
+            if dropped > 0 {
+                ui.colored_label(self.palette.warn, self.lang.log_dropped_fmt.replacen("{}", &dropped.to_string(), 1)); // I18N
             }
+        });
+        if capacity != self.state.settings.log_capacity {
+            self.set_log_capacity(capacity);
+        }
+        if log_file != self.state.settings.log_file {
+            self.set_log_file(log_file);
+        }
 
-            if ui.button("执行").clicked() && !self.cmd_input.is_empty() {
-                self.handle_command(self.cmd_input.trim().to_string());
-                self.cmd_input.clear();
-                response.request_focus();
+        // ⭐ 修复 E0501/E0500: 在 vertical 闭包传入的 'ui' 上调用 push_id
+        ui.push_id("debug_log_scroll", |ui| {
+            let needle = self.log_filter.search.to_lowercase();
+            // 直接在锁内过滤，只收集引用，不复制日志缓冲区
+            let Ok(entries) = self.logger.entries.lock() else { return };
+            let shown: Vec<&LogEntry> = entries.iter().filter(|entry| self.log_filter.matches(entry, &needle)).collect();
+
+            // 按时间顺序显示 (最新在底部)，每条一行；开启自动滚动且停在底部时跟随新日志。
+            // 其余情况按首行日志的序号定位，旧日志被淘汰时视图仍停在同一条日志上
+            let row_height = ui.text_style_height(&egui::TextStyle::Body);
+            let row_pitch = row_height + ui.spacing().item_spacing.y;
+            let mut scroll = egui::ScrollArea::vertical()
+                .auto_shrink([false, false])
+                .stick_to_bottom(self.log_filter.auto_scroll);
+            if let Some((seq, within_row)) = self.log_filter.anchor {
+                let row = shown.partition_point(|entry| entry.seq < seq);
+                scroll = scroll.vertical_scroll_offset(row as f32 * row_pitch + within_row);
             }
+            let output = scroll.show_rows(ui, row_height, shown.len(), |ui, rows| {
+                for entry in &shown[rows] {
+                    let color = match entry.level {
+                        LogLevel::Info => self.palette.log_info,
+                        LogLevel::Error => self.palette.error,
+                        LogLevel::Debug => self.palette.log_debug,
+                        LogLevel::Command => self.palette.log_command,
+                    };
+
+                    let level_text = format!("{:?}", entry.level).to_uppercase();
+                    let log_text = format!("[{}] <{}> {}", entry.timestamp.format("%H:%M:%S"), level_text, entry.message);
+                    // 多行日志截断为一行，悬停显示全文
+                    ui.add(egui::Label::new(egui::RichText::new(log_text).color(color)).truncate());
+                }
+            });
+
+            let top = output.state.offset.y;
+            let at_bottom = top + output.inner_rect.height() >= output.content_size.y - 1.0;
+            self.log_filter.anchor = if self.log_filter.auto_scroll && at_bottom {
+                None
+            } else {
+                let row = (top / row_pitch).floor().max(0.0);
+                shown.get(row as usize).map(|entry| (entry.seq, top - row * row_pitch))
+            };
         });
-        // 修正：使用 I18N 字段替代 if/else 逻辑
-        ui.label(self.lang.console_cmd_hint_cn);
     }
 }
 
+/// eframe 的应用名，决定设置和日志文件所在的平台数据目录
+#[cfg(not(target_arch = "wasm32"))]
+const APP_NAME: &str = "WAV Analyzer";
+
+#[cfg(not(target_arch = "wasm32"))]
 fn main() -> Result<(), eframe::Error> {
 
     let options = eframe::NativeOptions {
@@ -1598,8 +9242,439 @@ fn main() -> Result<(), eframe::Error> {
         ..Default::default()
     };
     eframe::run_native(
-        "WAV Analyzer",
+        APP_NAME,
         options,
         Box::new(|cc| Ok(Box::new(WavLufsApp::new(cc)))),
     )
-}
\ No newline at end of file
+}
+
+// --- 浏览器版 (wasm32) ---
+
+/// 浏览器中的文件对话框只能异步使用 (见 pick_files_async)，保存改为下载 (见 download_bytes)；
+/// 需要本地路径的同步对话框在浏览器中总是返回 None，对应的按钮已禁用
+#[cfg(target_arch = "wasm32")]
+struct FileDialog;
+
+#[cfg(target_arch = "wasm32")]
+impl FileDialog {
+    fn new() -> Self {
+        Self
+    }
+
+    fn add_filter(self, _name: &str, _extensions: &[&str]) -> Self {
+        self
+    }
+
+    fn set_file_name(self, _name: &str) -> Self {
+        self
+    }
+
+    fn pick_file(self) -> Option<PathBuf> {
+        None
+    }
+
+    fn pick_folder(self) -> Option<PathBuf> {
+        None
+    }
+
+    fn save_file(self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// 打开浏览器的文件选择框，读入选中的文件后通过 FilePicked 交给界面线程分析；
+/// `slot` 为 None 时可多选并加入单文件列表，否则载入该对比插槽
+#[cfg(target_arch = "wasm32")]
+fn pick_files_async(ctx: egui::Context, ui_tx: mpsc::Sender<WorkerMessage>, slot: Option<char>) {
+    wasm_bindgen_futures::spawn_local(async move {
+        let dialog = rfd::AsyncFileDialog::new().add_filter("Audio/Data", &["wav", "csv"]);
+        let handles = match slot {
+            None => dialog.pick_files().await.unwrap_or_default(),
+            Some(_) => dialog.pick_file().await.into_iter().collect(),
+        };
+        for handle in handles {
+            let bytes = handle.read().await;
+            ui_tx.send(WorkerMessage::FilePicked(handle.file_name(), bytes.into(), slot)).unwrap_or_default();
+        }
+        ctx.request_repaint();
+    });
+}
+
+/// 让浏览器把内存中的数据作为文件下载
+#[cfg(target_arch = "wasm32")]
+fn download_bytes(name: &str, mime: &str, bytes: &[u8]) -> Result<(), Box<dyn Error + Send + Sync>> {
+    use eframe::wasm_bindgen::JsCast;
+
+    let js_error = |e: eframe::wasm_bindgen::JsValue| -> Box<dyn Error + Send + Sync> { format!("{:?}", e).into() };
+    let parts = js_sys::Array::of1(&js_sys::Uint8Array::from(bytes));
+    let options = web_sys::BlobPropertyBag::new();
+    options.set_type(mime);
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(&parts, &options).map_err(js_error)?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(js_error)?;
+    let document = web_sys::window().and_then(|window| window.document()).ok_or("no document")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a").map_err(js_error)?
+        .dyn_into()
+        .map_err(|_| "not an anchor element")?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).map_err(js_error)?;
+    Ok(())
+}
+
+/// 浏览器入口：在 index.html 的 the_canvas_id 画布上运行
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use eframe::wasm_bindgen::JsCast;
+
+    wasm_bindgen_futures::spawn_local(async {
+        let canvas = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id("the_canvas_id"))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+            .expect("index.html 中缺少 id 为 the_canvas_id 的 canvas");
+        let result = eframe::WebRunner::new()
+            .start(canvas, eframe::WebOptions::default(), Box::new(|cc| Ok(Box::new(WavLufsApp::new(cc)))))
+            .await;
+        if let Err(e) = result {
+            web_sys::console::error_1(&e);
+        }
+    });
+}
+
+// 与分析库的测试共用合成 WAV 和临时目录；界面的测试只用到其中一部分
+#[cfg(test)]
+#[path = "testutil.rs"]
+#[allow(dead_code)]
+mod testutil;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutil::TempDir;
+
+    /// 英文语言文件必须包含全部键且不含中日韩文字，避免切换到英文后仍显示中文
+    #[test]
+    fn english_locale_is_complete_and_has_no_cjk() {
+        let registry = locale_registry();
+        assert!(registry.errors.is_empty(), "{:?}", registry.errors);
+        let english = registry.find(Language::ENGLISH).expect("bundled English locale");
+        for key in Lang::KEYS {
+            assert!(english.strings.contains_key(*key), "en.json is missing {}", key);
+        }
+        for (key, text) in &english.strings {
+            assert!(!text.chars().any(is_cjk_char), "{} contains CJK text: {}", key, text);
+        }
+    }
+
+    /// mm:ss 取整进位到下一分钟，秒模式保持原样
+    #[test]
+    fn time_format_rounds_into_minutes() {
+        assert_eq!(TimeFormat::MinSec.format(83.46, 1), "1:23.5");
+        assert_eq!(TimeFormat::MinSec.format(59.96, 1), "1:00.0");
+        assert_eq!(TimeFormat::MinSec.format(-5.0, 0), "-0:05");
+        assert_eq!(TimeFormat::MinSec.format(3725.0, 0), "62:05");
+        assert_eq!(TimeFormat::Seconds.format(83.46, 2), "83.46s");
+    }
+
+    /// 任务 (含结构化加载错误) 序列化后再读回，内容不变；没有版本号的旧设置按版本 1 读取
+    #[test]
+    fn tasks_and_settings_round_trip_through_serde() {
+        let tasks = vec![
+            AudioTask { id: 1, name: "load a.wav".to_string(), state: TaskState::Running(0.5) },
+            AudioTask { id: 2, name: "load b.wav".to_string(), state: TaskState::Error(TaskError::load("b.wav", AnalyzerError::CsvSchema { line: 3, detail: "bad".to_string() })) },
+            AudioTask { id: 3, name: "export".to_string(), state: TaskState::Error(TaskError::Message("disk full".to_string())) },
+        ];
+        // AnalyzerError 没有 PartialEq，比较再次序列化的文本
+        let text = ron::to_string(&Versioned::new(tasks)).unwrap();
+        let restored: Versioned<Vec<AudioTask>> = ron::from_str(&text).unwrap();
+        assert_eq!(ron::to_string(&restored).unwrap(), text);
+        assert!(matches!(&restored.data[1].state, TaskState::Error(TaskError::Load { error, .. }) if matches!(**error, AnalyzerError::CsvSchema { line: 3, .. })));
+
+        let settings = AppSettings { log_capacity: 1234, track_colors: TrackColors::Custom, ..AppSettings::default() };
+        let restored: AppSettings = ron::from_str(&ron::to_string(&settings).unwrap()).unwrap();
+        assert_eq!(ron::to_string(&restored).unwrap(), ron::to_string(&settings).unwrap());
+        let old: AppSettings = ron::from_str("(high_match_threshold: 0.5)").unwrap();
+        assert_eq!((old.schema_version, old.high_match_threshold, old.log_capacity), (1, 0.5, DEFAULT_LOG_CAPACITY));
+    }
+
+    /// 置顶的曲线留在最上层：新加载的曲线和向上移动的曲线都排在它下面
+    #[test]
+    fn pinned_curves_stay_above_new_and_moved_curves() {
+        let mut order = DrawOrder::default();
+        order.sync(3);
+        order.toggle_pin(0);
+        assert_eq!(order.order, vec![1, 2, 0]);
+
+        order.sync(4);
+        assert_eq!(order.order, vec![1, 2, 3, 0]);
+        order.step(3, true);
+        order.bring_to_front(1);
+        assert_eq!(order.order, vec![2, 3, 1, 0]);
+        order.step(2, true);
+        assert_eq!(order.order, vec![3, 2, 1, 0]);
+
+        order.toggle_pin(0);
+        order.step(0, false);
+        assert_eq!(order.order, vec![3, 2, 0, 1]);
+    }
+
+    /// 不同文件夹中的同名文件导出到同一文件夹时得到不同的文件名
+    #[test]
+    fn normalized_file_names_are_unique() {
+        let sources = ["a/mix.wav", "b/mix.wav", "c/MIX.wav", "a/other.wav"].map(PathBuf::from);
+        assert_eq!(normalized_file_names(&sources), ["mix_normalized.wav", "mix_normalized_2.wav", "MIX_normalized_3.wav", "other_normalized.wav"]);
+    }
+
+    /// 任务列表为空、使用默认设置的应用状态
+    fn test_state() -> AppState {
+        AppState::new(AppSettings::default(), Logger::new(), Arc::new(Mutex::new(Vec::new())))
+    }
+
+    fn add_task(state: &mut AppState, id: usize, task_state: TaskState, source: TaskSource) {
+        state.tasks.lock().unwrap().push(AudioTask { id, name: format!("task {}", id), state: task_state });
+        state.task_sources.insert(id, source);
+    }
+
+    /// 10 秒、每 0.5 秒一点的曲线，电平在 level 上下小幅起伏
+    fn test_curve(name: &str, level: f64) -> AudioCurve {
+        let points = (0..20).map(|i| [i as f64 * 0.5, level + (i % 3) as f64 * 0.1]).collect();
+        AudioCurve::new(name.to_string(), points, 10.0, level)
+    }
+
+    /// Track A 的曲线先到时不对比；Track B 随后到达即得到对比结果
+    #[test]
+    fn compare_runs_when_both_slot_curves_have_arrived() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        for (id, slot) in [(1, 'A'), (2, 'B')] {
+            add_task(&mut state, id, TaskState::Running(0.5), TaskSource::Slot(PathBuf::from(format!("{}.wav", slot)), slot));
+            state.compare_loads[slot_index(slot)] = Some(SlotLoad { task_id: id, filename: format!("{}.wav", slot) });
+        }
+
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve("a.wav", -20.0)), Some('A'), 1), &lang);
+        state.on_worker_message(WorkerMessage::UpdateTaskState(1, TaskState::Completed), &lang);
+        assert!(state.compare_a.is_some() && state.compare_result.is_none());
+
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve("b.wav", -23.0)), Some('B'), 2), &lang);
+        let result = state.compare_result.clone().expect("comparison result");
+        assert!((result.mean_diff.abs() - 3.0).abs() < 1e-9, "{}", result.mean_diff);
+        assert!(state.compare_loads.iter().all(Option::is_none));
+        assert!(state.error_msg.is_none());
+    }
+
+    /// Track B 偏移超过曲线时长时没有重叠：报错而不是保存 NaN 的对比结果
+    #[test]
+    fn offset_beyond_the_curves_is_refused_without_a_result() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        state.compare_a = Some(test_curve("a.wav", -20.0));
+        state.compare_b = Some(test_curve("b.wav", -23.0));
+        state.request_compare(&lang);
+        assert!(state.compare_result.is_some());
+
+        state.compare_offset = 10.5;
+        state.request_compare(&lang);
+        assert!(state.compare_result.is_none() && state.compare_result_key.is_none());
+        assert_eq!(state.error_msg.as_deref(), Some(lang.format_no_overlap_error(10.5).as_str()));
+    }
+
+    /// 合成参考在 Track B 到达后按 B 的时间点生成并对比；加载 A 文件后不再使用合成参考
+    #[test]
+    fn synthetic_track_a_follows_track_b() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        state.set_synthetic_reference(Some(SyntheticReference::Constant(-20.0)), &lang);
+        assert!(state.compare_a.is_none());
+
+        add_task(&mut state, 1, TaskState::Running(0.5), TaskSource::Slot(PathBuf::from("B.wav"), 'B'));
+        state.compare_loads[1] = Some(SlotLoad { task_id: 1, filename: "B.wav".to_string() });
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve("b.wav", -23.0)), Some('B'), 1), &lang);
+        let a = state.compare_a.as_ref().expect("synthetic track a");
+        assert_eq!(a.points.len(), 20);
+        assert!(a.points.iter().all(|p| p[1] == -20.0));
+        let result = state.compare_result.clone().expect("comparison result");
+        assert!((result.mean_diff.abs() - 3.0).abs() < 0.2, "{}", result.mean_diff);
+
+        state.set_synthetic_reference(None, &lang);
+        assert!(state.compare_a.is_none() && state.compare_result.is_none());
+    }
+
+    /// 最后一个未结束的任务失败后 loading 清除，并显示错误
+    #[test]
+    fn task_error_clears_loading_when_it_was_the_last_task() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        add_task(&mut state, 1, TaskState::Completed, TaskSource::File(PathBuf::from("a.wav")));
+        add_task(&mut state, 2, TaskState::Running(0.3), TaskSource::File(PathBuf::from("b.wav")));
+        state.refresh_loading();
+        assert!(state.loading);
+
+        state.on_worker_message(WorkerMessage::UpdateTaskState(2, TaskState::Error(TaskError::Message("broken".to_string()))), &lang);
+        assert!(!state.loading);
+        assert!(state.error_msg.as_deref().is_some_and(|msg| msg.contains("broken")));
+        assert_eq!(state.notifications.recent_errors.len(), 1);
+    }
+
+    /// 清空列表时仍在加载的任务被取消，它们迟到的曲线不再加入列表；之后启动的任务不受影响
+    #[test]
+    fn clearing_the_list_ignores_late_curves_from_cancelled_tasks() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        add_task(&mut state, 1, TaskState::Running(0.5), TaskSource::File(PathBuf::from("late.wav")));
+        add_task(&mut state, 2, TaskState::Running(0.5), TaskSource::Slot(PathBuf::from("a.wav"), 'A'));
+        state.single_files.lock().unwrap().push(test_curve("loaded.wav", -20.0));
+
+        assert_eq!(state.clear_single_files(), vec![1]);
+        assert!(state.single_files.lock().unwrap().is_empty());
+
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve("late.wav", -20.0)), None, 1), &lang);
+        state.on_worker_message(WorkerMessage::UpdateTaskState(1, TaskState::Completed), &lang);
+        assert!(state.single_files.lock().unwrap().is_empty());
+        assert!(state.discarded_tasks.is_empty());
+
+        add_task(&mut state, 3, TaskState::Running(0.5), TaskSource::File(PathBuf::from("new.wav")));
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve("new.wav", -20.0)), None, 3), &lang);
+        let names: Vec<String> = state.single_files.lock().unwrap().iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names, ["new.wav"]);
+    }
+
+    /// 内容相同的曲线加载时给出警告；移除重复时保留最早的一条，绘制顺序和置顶随下标调整
+    #[test]
+    fn removing_duplicates_keeps_the_first_copy_and_remaps_draw_order() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        for (id, (name, level)) in [("mix.wav", -20.0), ("mix_copy.wav", -20.0), ("other.wav", -18.0)].into_iter().enumerate() {
+            add_task(&mut state, id, TaskState::Running(0.5), TaskSource::File(PathBuf::from(name)));
+            state.on_worker_message(WorkerMessage::NewCurve(Box::new(test_curve(name, level)), None, id), &lang);
+        }
+        assert_eq!(state.notifications.toasts.iter().filter(|t| t.level == ToastLevel::Warning).count(), 1);
+        state.draw_order.toggle_pin(2);
+
+        assert_eq!(state.remove_duplicate_curves(), 1);
+        let names: Vec<String> = state.single_files.lock().unwrap().iter().map(|c| c.name.clone()).collect();
+        assert_eq!(names, ["mix.wav", "other.wav"]);
+        assert_eq!(state.draw_order.order, [0, 1]);
+        assert!(state.draw_order.is_pinned(1));
+        assert_eq!(state.remove_duplicate_curves(), 0);
+    }
+
+    /// 重新分析的结果原位替换同一文件的曲线，保留重命名和标签
+    #[test]
+    fn reanalyzed_curves_replace_the_loaded_curve_in_place() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        for name in ["a.wav", "b.wav"] {
+            let mut curve = test_curve(name, -20.0);
+            curve.source = Some(PathBuf::from(name));
+            state.single_files.lock().unwrap().push(curve);
+        }
+        state.single_files.lock().unwrap()[0].name = "Intro".to_string();
+        add_task(&mut state, 1, TaskState::Running(0.5), TaskSource::Reanalyze(PathBuf::from("a.wav")));
+
+        let mut reanalyzed = test_curve("a.wav", -14.0);
+        reanalyzed.source = Some(PathBuf::from("a.wav"));
+        state.on_worker_message(WorkerMessage::NewCurve(Box::new(reanalyzed), None, 1), &lang);
+
+        let files = state.single_files.lock().unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!((files[0].name.as_str(), files[0].average_dbfs), ("Intro", -14.0));
+        assert_eq!(files[1].name, "b.wav");
+    }
+
+    /// 自动保存的会话恢复后曲线与对比插槽不变，并重新计算对比结果
+    #[test]
+    fn autosaved_session_restores_curves_and_reruns_the_comparison() {
+        let lang = Lang::load(Language::ENGLISH);
+        let mut state = test_state();
+        state.add_single_curve(test_curve("a.wav", -20.0));
+        state.compare_a = Some(test_curve("ref.wav", -18.0));
+        state.compare_b = Some(test_curve("mix.wav", -16.0));
+        state.compare_offset = 0.25;
+        let key = state.session_key();
+
+        let text = ron::to_string(&Versioned::new(state.snapshot())).unwrap();
+        let session: Versioned<SessionSnapshot> = ron::from_str(&text).unwrap();
+        assert_eq!(session.data.curve_count(), 3);
+
+        let mut restored = test_state();
+        restored.restore(session.data, &lang);
+        assert_eq!(restored.single_files.lock().unwrap()[0].name, "a.wav");
+        assert_eq!(restored.compare_offset, 0.25);
+        assert!(restored.compare_result.is_some());
+        // 没有变化时会话键不变，自动保存据此跳过
+        assert_eq!(state.session_key(), key);
+        state.add_single_curve(test_curve("b.wav", -20.0));
+        assert_ne!(state.session_key(), key);
+    }
+
+    /// 导出时的连续写入在稳定 WATCH_DEBOUNCE 后只报告一次，文件被删除后不报告
+    #[test]
+    fn watcher_reports_a_burst_of_writes_once_after_it_settles() {
+        let start = Instant::now();
+        let at = |ms: u64| start + Duration::from_millis(ms);
+        let signature = |len: u64| Some((len, std::time::SystemTime::UNIX_EPOCH));
+        let mut file = WatchedFile::new(signature(10));
+        assert!(!file.poll(signature(10), at(250)));
+
+        // 导出期间文件连续变化 (中途短暂消失)，稳定 WATCH_DEBOUNCE 后才报告一次
+        for (ms, len) in [(500, 20), (750, 30), (1000, 40)] {
+            assert!(!file.poll(signature(len), at(ms)));
+        }
+        assert!(!file.poll(None, at(1250)));
+        assert!(!file.poll(signature(50), at(1500)));
+        assert!(!file.poll(signature(50), at(2000)));
+        assert!(file.poll(signature(50), at(2300)));
+        assert!(!file.poll(signature(50), at(3000)));
+
+        // 文件被删除后不报告
+        assert!(!file.poll(None, at(3250)));
+        assert!(!file.poll(None, at(5000)));
+    }
+
+    /// 通过 notify 发现磁盘上的改写 (先写临时文件再改名)，写入完成后报告一次
+    #[test]
+    fn watcher_reports_a_file_replaced_on_disk() {
+        let dir = TempDir::new("watch");
+        let path = dir.join("mix.wav");
+        std::fs::write(&path, b"first").unwrap();
+        let logger = Logger::new();
+        let mut watcher = FileWatcher::start(egui::Context::default(), &logger);
+        watcher.set_watched(BTreeSet::from([path.clone()]), &logger);
+        thread::sleep(Duration::from_millis(100));
+
+        let temp = dir.join("mix.wav.tmp");
+        std::fs::write(&temp, b"second, longer").unwrap();
+        std::fs::rename(&temp, &path).unwrap();
+        assert_eq!(watcher.changes.recv_timeout(Duration::from_secs(10)).ok(), Some(path));
+        assert!(watcher.changes.recv_timeout(WATCH_DEBOUNCE * 2).is_err());
+    }
+
+    /// 实时输入的块经环形缓冲组成与文件分析相同的 RMS 窗口，缓冲满时丢弃并计数
+    #[test]
+    fn live_blocks_form_the_same_rms_windows_as_file_analysis() {
+        // 10 帧立体声为一块，幅度 0.5 的直流信号均方值为 0.25 (-6.02 dBFS)
+        let ring = BlockRing::new(4);
+        let mut block = BlockAccumulator::new(10, 2);
+        for _ in 0..10 * 2 * 5 {
+            if let Some(mean_square) = block.push(0.5) {
+                ring.push(mean_square);
+            }
+        }
+        // 缓冲只有 4 块，第 5 块被丢弃
+        assert_eq!(ring.dropped.load(Ordering::Relaxed), 1);
+
+        // 默认 400 ms 窗口、100 ms 步进：凑满 4 块才有第一个点，时间为窗口中心
+        let mut windows = LiveWindows::new(&AnalysisSettings::default());
+        let points: Vec<[f64; 2]> = std::iter::from_fn(|| ring.pop()).filter_map(|ms| windows.push(ms)).collect();
+        assert_eq!(points.len(), 1);
+        assert!((points[0][0] - 0.2).abs() < 1e-9);
+        assert!((points[0][1] - 20.0 * 0.5f64.log10()).abs() < 1e-9);
+        assert!(ring.pop().is_none());
+
+        // 静音窗口记为 SILENCE_FLOOR_DBFS
+        let next = (0..4).filter_map(|_| windows.push(0.0)).last().unwrap();
+        assert!((next[0] - 0.6).abs() < 1e-9);
+        assert_eq!(next[1], SILENCE_FLOOR_DBFS);
+    }
+}
@@ -0,0 +1,188 @@
+//! 可插拔的曲线指标：每个指标实现 [`CurveMetric`]，由 [`MetricRegistry`] 在分析流程末尾统一运行，
+//! 结果按名称存入 [`AudioCurve::metrics`](crate::AudioCurve::metrics)。统计表、CSV 导出和对比报告
+//! 按名称显示曲线上已有的全部指标，新增指标不需要修改 `parse_wav` 或 `AudioCurve`。
+//!
+//! 添加指标：实现 [`CurveMetric`]，内置指标加入 [`MetricRegistry::builtin`]，
+//! 其余的在调用 [`parse_wav_with_metrics`](crate::parse_wav_with_metrics) 前注册：
+//!
+//! ```no_run
+//! use wav_lufs_curve::metrics::{AnalysisContext, CurveMetric, MetricRegistry, MetricValue};
+//! use wav_lufs_curve::{parse_wav_with_metrics, AnalysisSettings, NoLog};
+//!
+//! /// 最响与最安静窗口的电平差
+//! struct WindowRange;
+//!
+//! impl CurveMetric for WindowRange {
+//!     fn name(&self) -> &str {
+//!         "window_range_db"
+//!     }
+//!
+//!     fn compute(&self, ctx: &AnalysisContext) -> MetricValue {
+//!         let levels = ctx.points.iter().map(|p| p[1]);
+//!         let max = levels.clone().fold(f64::NEG_INFINITY, f64::max);
+//!         let min = levels.fold(f64::INFINITY, f64::min);
+//!         if ctx.points.is_empty() { MetricValue::Unavailable } else { MetricValue::Db(max - min) }
+//!     }
+//! }
+//!
+//! let mut metrics = MetricRegistry::builtin();
+//! metrics.register(WindowRange);
+//! let curve = parse_wav_with_metrics("mix.wav".into(), &AnalysisSettings::default(), &metrics, &NoLog, &mut |_| {})?;
+//! println!("{:?}", curve.metric("window_range_db"));
+//! # Ok::<(), Box<dyn std::error::Error + Send + Sync>>(())
+//! ```
+
+use crate::AnalysisSettings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// 平均电平指标的名称，[`AudioCurve::average_dbfs`](crate::AudioCurve::average_dbfs) 取自此指标
+pub const AVERAGE_METRIC: &str = "average_dbfs";
+/// 最响窗口指标的名称
+pub const PEAK_WINDOW_METRIC: &str = "peak_window_dbfs";
+
+/// 一个指标的计算结果
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+pub enum MetricValue {
+    /// 绝对电平 (dBFS)
+    Dbfs(f64),
+    /// 某一时刻的电平 (如最响窗口)
+    DbfsAt {
+        /// 时间 (秒)
+        time: f64,
+        /// 电平 (dBFS)
+        dbfs: f64,
+    },
+    /// 相对电平 (dB)，如两个电平之差
+    Db(f64),
+    /// 无单位的数值
+    Number(f64),
+    /// 无法计算 (如曲线没有点、指标需要音频而曲线来自 CSV)
+    Unavailable,
+}
+
+impl MetricValue {
+    /// 用于排序和对比的数值；无法计算时为 None
+    pub fn value(&self) -> Option<f64> {
+        match *self {
+            MetricValue::Dbfs(v) | MetricValue::Db(v) | MetricValue::Number(v) => Some(v),
+            MetricValue::DbfsAt { dbfs, .. } => Some(dbfs),
+            MetricValue::Unavailable => None,
+        }
+    }
+}
+
+impl std::fmt::Display for MetricValue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MetricValue::Dbfs(v) => write!(f, "{:.2} dBFS", v),
+            MetricValue::DbfsAt { time, dbfs } => write!(f, "{:.2} dBFS @ {:.2}s", dbfs, time),
+            MetricValue::Db(v) => write!(f, "{:.2} dB", v),
+            MetricValue::Number(v) => write!(f, "{:.4}", v),
+            MetricValue::Unavailable => write!(f, "-"),
+        }
+    }
+}
+
+/// 指标计算时可用的分析结果
+pub struct AnalysisContext<'a> {
+    /// 窗口曲线点 (时间, dBFS)，低于噪声门限的窗口已记为 SILENCE_FLOOR_DBFS
+    pub points: &'a [[f64; 2]],
+    /// 计算曲线时使用的分析参数
+    pub analysis: &'a AnalysisSettings,
+    /// WAV 格式；没有音频 (CSV 曲线) 时为 None
+    pub spec: Option<hound::WavSpec>,
+    samples: &'a [f64],
+    channels: usize,
+}
+
+impl<'a> AnalysisContext<'a> {
+    /// 只有曲线点、没有音频的上下文 (CSV 导入的曲线)
+    pub fn from_points(points: &'a [[f64; 2]], analysis: &'a AnalysisSettings) -> Self {
+        Self { points, analysis, spec: None, samples: &[], channels: 1 }
+    }
+
+    /// 带分析范围内样本的上下文；`samples` 按 `channels` 个一帧交错存放
+    pub fn with_samples(points: &'a [[f64; 2]], analysis: &'a AnalysisSettings, spec: hound::WavSpec, samples: &'a [f64], channels: usize) -> Self {
+        Self { points, analysis, spec: Some(spec), samples, channels: channels.max(1) }
+    }
+
+    /// 是否有音频样本
+    pub fn has_audio(&self) -> bool {
+        !self.samples.is_empty()
+    }
+
+    /// 逐帧遍历分析范围内的样本 (借用，不复制)，即计算曲线的样本：已按分析参数计权，
+    /// 下混时每帧一个样本 (BS.1770 下混为各声道加权能量和的平方根)，否则为交错的各声道；没有音频时为空
+    pub fn frames(&self) -> impl Iterator<Item = &'a [f64]> + 'a {
+        self.samples.chunks_exact(self.channels)
+    }
+}
+
+/// 一项曲线指标
+pub trait CurveMetric: Send + Sync {
+    /// 指标名称，作为 [`AudioCurve::metrics`](crate::AudioCurve::metrics) 的键及表格列名、CSV 字段名
+    fn name(&self) -> &str;
+    /// 按分析结果计算指标
+    fn compute(&self, ctx: &AnalysisContext) -> MetricValue;
+}
+
+/// 分析流程运行的指标列表
+#[derive(Default)]
+pub struct MetricRegistry {
+    metrics: Vec<Box<dyn CurveMetric>>,
+}
+
+impl MetricRegistry {
+    /// 内置指标：平均电平、最响窗口
+    pub fn builtin() -> Self {
+        let mut registry = Self::default();
+        registry.register(AverageLevel);
+        registry.register(PeakWindow);
+        registry
+    }
+
+    /// 添加一项指标；与已有指标同名时后注册的结果覆盖先注册的
+    pub fn register(&mut self, metric: impl CurveMetric + 'static) {
+        self.metrics.push(Box::new(metric));
+    }
+
+    /// 已注册的指标名称 (按注册顺序)
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.metrics.iter().map(|metric| metric.name())
+    }
+
+    /// 依次计算全部指标
+    pub fn run(&self, ctx: &AnalysisContext) -> BTreeMap<String, MetricValue> {
+        self.metrics.iter().map(|metric| (metric.name().to_string(), metric.compute(ctx))).collect()
+    }
+}
+
+/// 平均电平：按分析参数中的平均方式 (功率平均或 dB 值平均) 计算各窗口的平均值
+pub struct AverageLevel;
+
+impl CurveMetric for AverageLevel {
+    fn name(&self) -> &str {
+        AVERAGE_METRIC
+    }
+
+    fn compute(&self, ctx: &AnalysisContext) -> MetricValue {
+        MetricValue::Dbfs(ctx.analysis.average.average(ctx.points))
+    }
+}
+
+/// 最响窗口的时间和电平
+pub struct PeakWindow;
+
+impl CurveMetric for PeakWindow {
+    fn name(&self) -> &str {
+        PEAK_WINDOW_METRIC
+    }
+
+    fn compute(&self, ctx: &AnalysisContext) -> MetricValue {
+        match ctx.points.iter().max_by(|a, b| a[1].total_cmp(&b[1])) {
+            Some(&[time, dbfs]) => MetricValue::DbfsAt { time, dbfs },
+            None => MetricValue::Unavailable,
+        }
+    }
+}
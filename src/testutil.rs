@@ -0,0 +1,94 @@
+//! 测试用的合成 WAV：在独立的临时目录中按指定格式写入正弦、静音、白噪声和两级阶跃信号。
+
+use std::path::{Path, PathBuf};
+
+/// 测试结束 (drop) 时删除的临时目录，目录名含进程号和用途，并行测试互不干扰
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(purpose: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("wav_lufs_curve_{}_{}", purpose, std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+
+    pub fn join(&self, name: &str) -> PathBuf {
+        self.0.join(name)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// 测试信号；电平均为峰值 dBFS (满幅正弦为 0 dBFS 峰值，即 RMS 约 -3.01 dBFS)
+#[derive(Clone, Copy, Debug)]
+pub enum Signal {
+    Sine { freq: f64, peak_dbfs: f64 },
+    Silence,
+    /// 均匀分布白噪声，RMS 为峰值的 1/√3
+    WhiteNoise { peak_dbfs: f64 },
+    /// 前 switch_sec 秒为 first_dbfs，之后为 second_dbfs 的 1 kHz 正弦
+    Step { first_dbfs: f64, second_dbfs: f64, switch_sec: f64 },
+    /// 方波，RMS 等于峰值 (满幅时即 0 dBFS)；整数格式的正半周为最大值，负半周为最小值
+    Square { freq: f64, peak_dbfs: f64 },
+}
+
+/// 16/24/32-bit PCM 和 32-bit Float，即 parse_wav 支持的全部格式
+pub const ALL_FORMATS: [(hound::SampleFormat, u16); 4] = [
+    (hound::SampleFormat::Int, 16),
+    (hound::SampleFormat::Int, 24),
+    (hound::SampleFormat::Int, 32),
+    (hound::SampleFormat::Float, 32),
+];
+
+fn db_to_gain(dbfs: f64) -> f64 {
+    10f64.powf(dbfs / 20.0)
+}
+
+impl Signal {
+    /// 第 n 个样本 (每个声道相同)，范围 [-1.0, 1.0]
+    fn sample(&self, n: usize, sample_rate: u32, noise: &mut u64) -> f64 {
+        let t = n as f64 / sample_rate as f64;
+        let sine = |freq: f64, dbfs: f64| db_to_gain(dbfs) * (2.0 * std::f64::consts::PI * freq * t).sin();
+        match *self {
+            Signal::Sine { freq, peak_dbfs } => sine(freq, peak_dbfs),
+            Signal::Silence => 0.0,
+            Signal::WhiteNoise { peak_dbfs } => {
+                // 固定种子的 xorshift，结果可复现
+                *noise ^= *noise << 13;
+                *noise ^= *noise >> 7;
+                *noise ^= *noise << 17;
+                db_to_gain(peak_dbfs) * ((*noise >> 11) as f64 / (1u64 << 53) as f64 * 2.0 - 1.0)
+            }
+            Signal::Step { first_dbfs, second_dbfs, switch_sec } => sine(1000.0, if t < switch_sec { first_dbfs } else { second_dbfs }),
+            // 按样本序号取半周期，避免正弦过零点处的符号误差
+            Signal::Square { freq, peak_dbfs } => {
+                let half_periods = (n as f64 * freq * 2.0 / sample_rate as f64).floor() as u64;
+                db_to_gain(peak_dbfs) * if half_periods.is_multiple_of(2) { 1.0 } else { -1.0 }
+            }
+        }
+    }
+}
+
+/// 写一个 seconds 秒的测试文件，所有声道内容相同
+pub fn write_wav(path: &Path, signal: Signal, format: (hound::SampleFormat, u16), sample_rate: u32, channels: u16, seconds: f64) {
+    let (sample_format, bits_per_sample) = format;
+    let spec = hound::WavSpec { channels, sample_rate, bits_per_sample, sample_format };
+    let mut writer = hound::WavWriter::create(path, spec).unwrap();
+    // 与 for_each_wav_sample 的归一化一致：整数样本除以 2^(bits-1)
+    let scale = (1i64 << (bits_per_sample - 1)) as f64;
+    let mut noise = 0x9e37_79b9_7f4a_7c15u64;
+    for n in 0..(seconds * sample_rate as f64).round() as usize {
+        let x = signal.sample(n, sample_rate, &mut noise);
+        for _ in 0..channels {
+            match sample_format {
+                hound::SampleFormat::Float => writer.write_sample(x as f32).unwrap(),
+                hound::SampleFormat::Int => writer.write_sample((x * scale).round().clamp(-scale, scale - 1.0) as i32).unwrap(),
+            }
+        }
+    }
+    writer.finalize().unwrap();
+}